@@ -1,21 +1,65 @@
 #include <assert.h>
+#include <stdint.h>
 #include <stdio.h>
 
-void rtml_systolic_array_weight_stationary(int hardware_id, float *out,
-                                           float *activations, float *weights,
-                                           int input_vector_size,
-                                           int output_vector_size, int batch) {
-  fprintf(stderr, "Running systolic array, hardware id %d\n", hardware_id);
+// bfloat16 values are stored as the upper 16 bits of a float32.
+static float rtml_bf16_to_float(uint16_t value) {
+  union {
+    float f;
+    uint32_t u;
+  } bits;
+  bits.u = ((uint32_t)value) << 16;
+  return bits.f;
+}
+
+#define RTML_IDENTITY(x) (x)
 
-  int batch_i;
-  for (batch_i = 0; batch_i < batch; ++batch_i) {
-    int col;
-    for (col = 0; col < output_vector_size; ++col) {
-      out[batch_i*output_vector_size + col] = 0.0;
-      int row;
-      for (row = 0; row < input_vector_size; ++row) {
-        out[batch_i*output_vector_size + col] += activations[batch_i*input_vector_size + row] * weights[row * output_vector_size + col];
-      }
-    }
+// Defines a reference systolic array which reads activations and weights of
+// type T and accumulates (and writes its outputs) in type ACC_T. TO_ACC
+// converts a value of type T to type ACC_T.
+#define DEFINE_RTML_SYSTOLIC_ARRAY_WEIGHT_STATIONARY(NAME, T, ACC_T, TO_ACC)   \
+  void NAME(int hardware_id, ACC_T *out, T *activations, T *weights,           \
+            int input_vector_size, int output_vector_size, int batch) {        \
+    fprintf(stderr, "Running systolic array, hardware id %d\n", hardware_id); \
+                                                                               \
+    int batch_i;                                                               \
+    for (batch_i = 0; batch_i < batch; ++batch_i) {                            \
+      int col;                                                                 \
+      for (col = 0; col < output_vector_size; ++col) {                         \
+        out[batch_i * output_vector_size + col] = 0;                           \
+        int row;                                                               \
+        for (row = 0; row < input_vector_size; ++row) {                        \
+          out[batch_i * output_vector_size + col] +=                           \
+              (ACC_T)TO_ACC(activations[batch_i * input_vector_size + row]) *  \
+              (ACC_T)TO_ACC(weights[row * output_vector_size + col]);          \
+        }                                                                      \
+      }                                                                        \
+    }                                                                          \
   }
-}
+
+DEFINE_RTML_SYSTOLIC_ARRAY_WEIGHT_STATIONARY(
+    rtml_systolic_array_weight_stationary, float, float, RTML_IDENTITY)
+DEFINE_RTML_SYSTOLIC_ARRAY_WEIGHT_STATIONARY(
+    rtml_systolic_array_weight_stationary_fp64, double, double, RTML_IDENTITY)
+DEFINE_RTML_SYSTOLIC_ARRAY_WEIGHT_STATIONARY(
+    rtml_systolic_array_weight_stationary_fp16, _Float16, float, RTML_IDENTITY)
+DEFINE_RTML_SYSTOLIC_ARRAY_WEIGHT_STATIONARY(
+    rtml_systolic_array_weight_stationary_bf16, uint16_t, float,
+    rtml_bf16_to_float)
+DEFINE_RTML_SYSTOLIC_ARRAY_WEIGHT_STATIONARY(
+    rtml_systolic_array_weight_stationary_int8, int8_t, int32_t, RTML_IDENTITY)
+DEFINE_RTML_SYSTOLIC_ARRAY_WEIGHT_STATIONARY(
+    rtml_systolic_array_weight_stationary_int16, int16_t, int32_t,
+    RTML_IDENTITY)
+DEFINE_RTML_SYSTOLIC_ARRAY_WEIGHT_STATIONARY(
+    rtml_systolic_array_weight_stationary_int32, int32_t, int32_t,
+    RTML_IDENTITY)
+DEFINE_RTML_SYSTOLIC_ARRAY_WEIGHT_STATIONARY(
+    rtml_systolic_array_weight_stationary_uint8, uint8_t, uint32_t,
+    RTML_IDENTITY)
+DEFINE_RTML_SYSTOLIC_ARRAY_WEIGHT_STATIONARY(
+    rtml_systolic_array_weight_stationary_uint16, uint16_t, uint32_t,
+    RTML_IDENTITY)
+DEFINE_RTML_SYSTOLIC_ARRAY_WEIGHT_STATIONARY(
+    rtml_systolic_array_weight_stationary_uint32, uint32_t, uint32_t,
+    RTML_IDENTITY)
@@ -0,0 +1,65 @@
+"""Generates the ResNet-18 fixtures for tests/resnet18_relay_to_glenside.rs.
+
+Writes one .npy file per input of the network (the image, `data`, and the
+weights) and result.npy, the output of running the checked-in resnet18.relay
+on those inputs with Relay, to OUT_DIR. result.npy is written last, so the
+fixtures are complete once it exists. The test runs this script itself when
+its fixtures are missing.
+
+resnet18.relay is ResNet-18 simplified for inference (so batch norms are
+removed). It's only regenerated when --regenerate-relay is passed.
+
+Usage:
+    python3 resnet18_helper.py OUT_DIR [--regenerate-relay]
+"""
+import argparse
+import os
+import tvm
+from tvm import relay
+from tvm.relay.testing import resnet
+import numpy as np
+
+parser = argparse.ArgumentParser()
+parser.add_argument('out_dir')
+parser.add_argument('--regenerate-relay',
+                    action='store_true',
+                    help='Overwrite the checked-in resnet18.relay')
+args = parser.parse_args()
+
+relay_filepath = os.path.join(os.path.dirname(os.path.abspath(__file__)),
+                              'resnet18.relay')
+
+np.random.seed(0)
+
+image_shape = (3, 224, 224)
+
+model, params = resnet.get_workload(batch_size=1,
+                                    num_classes=1000,
+                                    num_layers=18,
+                                    image_shape=image_shape,
+                                    dtype='float32')
+
+if args.regenerate_relay:
+    model = tvm.transform.Sequential(
+        [relay.transform.InferType(),
+         relay.transform.SimplifyInference()])(model)
+    with open(relay_filepath, 'w') as file:
+        file.write(model.astext())
+else:
+    with open(relay_filepath) as file:
+        model = tvm.parser.fromtext(file.read())
+
+os.makedirs(args.out_dir, exist_ok=True)
+
+image = np.random.rand(1, *image_shape).astype('float32')
+
+inputs = {'data': image}
+inputs.update({name: value.asnumpy() for name, value in params.items()})
+for name, value in inputs.items():
+    with open(os.path.join(args.out_dir, name + '.npy'), 'wb') as file:
+        np.save(file, value)
+
+ex = relay.create_executor(mod=model)
+result = ex.evaluate()(**inputs).asnumpy()
+with open(os.path.join(args.out_dir, 'result.npy'), 'wb') as file:
+    np.save(file, result)
@@ -1,29 +1,207 @@
 use crate::hw_design_language::*;
 use crate::language::MyAnalysis;
 use crate::language::MyAnalysisData;
-use crate::language::{Language, PadType};
+use crate::language::{ComputeType, Language, PadType};
 use egg::EGraph;
 use egg::Id;
+use egg::Language as LanguageTrait;
 use itertools::Itertools;
 use ndarray::Dimension;
 use ndarray::IxDyn;
-use rand::Rng;
 use std::collections::{HashMap, HashSet};
 use std::iter::FromIterator;
+use std::ops::Range;
 
 type Expr = EGraph<Language, MyAnalysis>;
 
-static SYSTOLIC_ARRAY_SIGNATURE: &str = "
-extern void rtml_systolic_array_weight_stationary(
+/// Gives the name of the systolic array function for the given datatype. The
+/// float32 systolic array keeps the original, unsuffixed name.
+/// ```
+/// use glenside::codegen::systolic_array_function_name;
+/// use glenside::hw_design_language::DType;
+/// assert_eq!(
+///     systolic_array_function_name(DType::Fp32),
+///     "rtml_systolic_array_weight_stationary"
+/// );
+/// assert_eq!(
+///     systolic_array_function_name(DType::Int8),
+///     "rtml_systolic_array_weight_stationary_int8"
+/// );
+/// ```
+pub fn systolic_array_function_name(dtype: DType) -> String {
+    match dtype {
+        DType::Fp32 => "rtml_systolic_array_weight_stationary".to_string(),
+        _ => format!("rtml_systolic_array_weight_stationary_{}", dtype),
+    }
+}
+
+/// Gives the declaration of the systolic array function for the given
+/// datatype. The systolic array reads activations and weights of type `dtype`
+/// and writes its outputs in `dtype`'s accumulator type.
+fn systolic_array_signature(dtype: DType) -> String {
+    format!(
+        "
+extern void {name}(
   int hardware_id,
-  float * out,
-  float * activations,
-  float * weights,
+  {acc_t} * out,
+  {t} * activations,
+  {t} * weights,
   int input_vector_size,
   int output_vector_size,
   int batch);
+",
+        name = systolic_array_function_name(dtype),
+        acc_t = dtype.accumulator_dtype().to_c_type_string(),
+        t = dtype.to_c_type_string()
+    )
+}
+
+/// Helpers for converting to and from bfloat16, which C doesn't have a type
+/// for. Conversion to bfloat16 truncates (rounds toward zero).
+static BF16_HELPERS: &str = "
+static inline uint16_t glenside_float_to_bf16(float value) {
+  union {
+    float f;
+    uint32_t u;
+  } bits;
+  bits.f = value;
+  return (uint16_t)(bits.u >> 16);
+}
+
+static inline float glenside_bf16_to_float(uint16_t value) {
+  union {
+    float f;
+    uint32_t u;
+  } bits;
+  bits.u = ((uint32_t)value) << 16;
+  return bits.f;
+}
 ";
 
+/// Gives a C expression converting `value`, which is of `dtype`'s accumulator
+/// type, to `dtype`.
+fn c_narrowing_string(value: &str, dtype: DType) -> String {
+    match dtype {
+        DType::Bf16 => format!("glenside_float_to_bf16({})", value),
+        _ => format!("({}){}", dtype.to_c_type_string(), value),
+    }
+}
+
+/// Gives a C expression converting `value`, which is of type `dtype`, to
+/// `dtype`'s accumulator type.
+fn c_widening_string(value: &str, dtype: DType) -> String {
+    match dtype {
+        DType::Bf16 => format!("glenside_bf16_to_float({})", value),
+        _ => format!(
+            "({}){}",
+            dtype.accumulator_dtype().to_c_type_string(),
+            value
+        ),
+    }
+}
+
+/// Gives a C expression reading element `index` of the buffer `var_name`,
+/// which holds values of type `dtype`, converted to `dtype`'s accumulator type.
+fn c_read_string(var_name: &str, index: &str, dtype: DType) -> String {
+    c_widening_string(
+        format!("(({}*){})[{}]", dtype.to_c_type_string(), var_name, index).as_str(),
+        dtype,
+    )
+}
+
+/// Gives a C statement writing `value`, which is of `dtype`'s accumulator
+/// type, to element `index` of the buffer `var_name`, which holds values of
+/// type `dtype`.
+fn c_write_string(var_name: &str, index: &str, value: &str, dtype: DType) -> String {
+    format!(
+        "(({}*){})[{}] = {};",
+        dtype.to_c_type_string(),
+        var_name,
+        index,
+        c_narrowing_string(format!("({})", value).as_str(), dtype)
+    )
+}
+
+/// Gives a C literal of type `dtype` for `value`. Panics if `dtype` is an
+/// integer type which can't represent `value` exactly. bfloat16 values are
+/// given as their raw bits, truncating the float32 value.
+/// ```
+/// use glenside::hw_design_language::DType;
+/// assert_eq!(glenside::codegen::c_literal_string(1.5, DType::Fp32), "1.5");
+/// assert_eq!(glenside::codegen::c_literal_string(-2.0, DType::Int8), "-2");
+/// assert_eq!(glenside::codegen::c_literal_string(1.0, DType::Bf16), "0x3f80");
+/// ```
+pub fn c_literal_string(value: f64, dtype: DType) -> String {
+    match dtype {
+        DType::Int8
+        | DType::Int16
+        | DType::Int32
+        | DType::Uint8
+        | DType::Uint16
+        | DType::Uint32 => {
+            let (min, max): (f64, f64) = match dtype {
+                DType::Int8 => (i8::MIN.into(), i8::MAX.into()),
+                DType::Int16 => (i16::MIN.into(), i16::MAX.into()),
+                DType::Int32 => (i32::MIN.into(), i32::MAX.into()),
+                DType::Uint8 => (0.0, u8::MAX.into()),
+                DType::Uint16 => (0.0, u16::MAX.into()),
+                DType::Uint32 => (0.0, u32::MAX.into()),
+                _ => unreachable!(),
+            };
+            assert!(
+                value.fract() == 0.0 && value >= min && value <= max,
+                "{} can't be represented exactly in {}",
+                value,
+                dtype
+            );
+            format!("{}", value as i64)
+        }
+        DType::Bf16 => format!("{:#06x}", (value as f32).to_bits() >> 16),
+        // Debug formatting always gives a decimal point or an exponent, and
+        // never writes out huge or tiny values in full.
+        DType::Fp16 | DType::Fp32 | DType::Fp64 => format!("{:?}", value),
+    }
+}
+
+/// Gives a C literal for the smallest value of `dtype`, which is what
+/// [`PadType::MinPadding`] pads with.
+fn c_min_value_string(dtype: DType) -> String {
+    match dtype {
+        DType::Int8 => c_literal_string(i8::MIN.into(), dtype),
+        DType::Int16 => c_literal_string(i16::MIN.into(), dtype),
+        DType::Int32 => c_literal_string(i32::MIN.into(), dtype),
+        DType::Uint8 | DType::Uint16 | DType::Uint32 => c_literal_string(0.0, dtype),
+        // The smallest finite bfloat16.
+        DType::Bf16 => "0xff7f".to_string(),
+        DType::Fp16 => c_literal_string(-65504.0, dtype),
+        DType::Fp32 => format!("{:e}", f32::MIN),
+        DType::Fp64 => format!("{:e}", f64::MIN),
+    }
+}
+
+/// Gives a C expression indexing into the flattened, row-major tensor of the
+/// given shape, where `indices` are C expressions indexing each dimension.
+/// ```
+/// assert_eq!(
+///     glenside::codegen::c_flat_index_string(
+///         &["i".to_string(), "j".to_string(), "k".to_string()],
+///         &[2, 3, 4]
+///     ),
+///     "(i)*12 + (j)*4 + (k)*1"
+/// );
+/// ```
+pub fn c_flat_index_string(indices: &[String], shape: &[usize]) -> String {
+    assert_eq!(indices.len(), shape.len());
+    if indices.is_empty() {
+        return "0".to_string();
+    }
+    indices
+        .iter()
+        .enumerate()
+        .map(|(i, index)| format!("({})*{}", index, shape[i + 1..].iter().product::<usize>()))
+        .join(" + ")
+}
+
 /// Gives the signature of a C array given the datatype, name, and shape. Useful
 /// for declaring arrays and array-type function arguments.
 /// ```
@@ -37,7 +215,6 @@ extern void rtml_systolic_array_weight_stationary(
 /// );
 /// ```
 pub fn c_array_string(name: &str, shape: &[usize], dtype: DType) -> String {
-    assert_eq!(DType::Fp32.to_c_type_string(), "float");
     format!(
         "{} {}{}",
         dtype.to_c_type_string(),
@@ -64,7 +241,8 @@ pub fn c_allocation_string(prefix: &str, name: &str, shape: &[usize], dtype: DTy
 }
 
 /// Creates a representation (currently just a string with a C definition) of
-/// an allocation and an assignment.
+/// an allocation and an assignment. Each value is written as a literal of type
+/// `dtype`, using [`c_literal_string`].
 /// ```
 /// assert_eq!(
 ///     glenside::codegen::c_assignment_string(
@@ -75,11 +253,20 @@ pub fn c_allocation_string(prefix: &str, name: &str, shape: &[usize], dtype: DTy
 ///             .unwrap()
 ///             .view()
 ///     ),
-///     "my_prefix float a[2][3] = {{0, 1, 2}, {3, 4, 5}};"
+///     "my_prefix float a[2][3] = {{0.0, 1.0, 2.0}, {3.0, 4.0, 5.0}};"
+/// );
+/// assert_eq!(
+///     glenside::codegen::c_assignment_string(
+///         "",
+///         "b",
+///         glenside::hw_design_language::DType::Bf16,
+///         &ndarray::arr1(&[1f32, -2f32]).into_dyn().view()
+///     ),
+///     " uint16_t b[2] = {0x3f80, 0xc000};"
 /// );
 ///
 /// ```
-pub fn c_assignment_string<A: std::fmt::Display>(
+pub fn c_assignment_string<A: num_traits::AsPrimitive<f64>>(
     prefix: &str,
     name: &str,
     dtype: DType,
@@ -94,21 +281,28 @@ pub fn c_assignment_string<A: std::fmt::Display>(
     // Cut off the ;
     allocation_string.truncate(allocation_string.len() - 1);
 
-    fn recursive_helper<A: std::fmt::Display>(array: &ndarray::ArrayViewD<A>) -> String {
+    fn recursive_helper<A: num_traits::AsPrimitive<f64>>(
+        array: &ndarray::ArrayViewD<A>,
+        dtype: DType,
+    ) -> String {
         if array.ndim() == 0 {
-            array.to_string()
+            c_literal_string(array.first().unwrap().as_(), dtype)
         } else {
             format!(
                 "{{{}}}",
                 array
                     .axis_iter(ndarray::Axis(0))
-                    .map(|a| recursive_helper(&a))
+                    .map(|a| recursive_helper(&a, dtype))
                     .join(", ")
             )
         }
     }
 
-    format!("{} = {};", allocation_string, recursive_helper(array))
+    format!(
+        "{} = {};",
+        allocation_string,
+        recursive_helper(array, dtype)
+    )
 }
 
 // TODO(@gussmith23) Turn on rustdoc lints
@@ -153,7 +347,10 @@ pub fn c_assignment_string<A: std::fmt::Display>(
 /// map.insert("t2".to_string(), vec![32, 32]);
 /// map.insert("t3".to_string(), vec![32, 32]);
 ///
-/// let mut egraph = EGraph::new(MyAnalysis { name_to_shape: map });
+/// let mut egraph = EGraph::new(MyAnalysis {
+///     name_to_shape: map,
+///     name_to_dtype: HashMap::default(),
+/// });
 /// egraph.add_expr(&expr);
 ///
 /// let (hw_map, hw_design) = create_hardware_design_monolithic(&egraph, (32, 32));
@@ -184,16 +381,9 @@ pub fn create_hardware_design_monolithic(
 ) -> (HashMap<Id, usize>, Vec<Atom>) {
     let hw_id = 0;
     let mut map = HashMap::new();
-    let atoms = vec![Atom {
-        name: format!("systolic_array_{}", hw_id),
-        id: hw_id,
-        config: AtomConfig::SystolicArrayWeightStationary(SystolicArrayWeightStationaryParams {
-            // TODO(@gussmith23) hardcoded datatype
-            dtype: DType::Fp32,
-            rows: row,
-            cols: col,
-        }),
-    }];
+    // The dtype of the single systolic array. Every systolic array in the
+    // expression must agree on it.
+    let mut dtype = None;
 
     for eclass in expr.classes() {
         assert_eq!(eclass.nodes.len(), 1);
@@ -224,12 +414,30 @@ pub fn create_hardware_design_monolithic(
                     _ => panic!(),
                 };
 
+                let this_dtype = MyAnalysis::get_dtype(eclass.id, expr);
+                assert_eq!(
+                    *dtype.get_or_insert(this_dtype),
+                    this_dtype,
+                    "Found systolic arrays of different dtypes in a monolithic design"
+                );
+
                 map.insert(eclass.id, hw_id);
             }
             _ => (),
         }
     }
 
+    let atoms = vec![Atom {
+        name: format!("systolic_array_{}", hw_id),
+        id: hw_id,
+        config: AtomConfig::SystolicArrayWeightStationary(SystolicArrayWeightStationaryParams {
+            // If there are no systolic arrays, the dtype doesn't matter.
+            dtype: dtype.unwrap_or(DType::Fp32),
+            rows: row,
+            cols: col,
+        }),
+    }];
+
     (map, atoms)
 }
 
@@ -270,8 +478,7 @@ pub fn create_hardware_design_no_sharing(expr: &Expr) -> (HashMap<Id, usize>, Ve
                     id: hw_id,
                     config: AtomConfig::SystolicArrayWeightStationary(
                         SystolicArrayWeightStationaryParams {
-                            // TODO(@gussmith23) hardcoded datatype
-                            dtype: DType::Fp32,
+                            dtype: MyAnalysis::get_dtype(eclass.id, expr),
                             rows: *row,
                             cols: *col,
                         },
@@ -287,7 +494,14 @@ pub fn create_hardware_design_no_sharing(expr: &Expr) -> (HashMap<Id, usize>, Ve
 
 /// Finds all symbols in a program, and return their names.
 pub fn find_vars(expr: &Expr, id: Id) -> Vec<String> {
-    fn find_vars_recursive_helper(set: &mut HashSet<String>, expr: &Expr, id: Id) {
+    let mut set = HashSet::default();
+    // Programs can be too deep to search recursively, so we keep a worklist.
+    let mut visited = HashSet::new();
+    let mut worklist = vec![id];
+    while let Some(id) = worklist.pop() {
+        if !visited.insert(id) {
+            continue;
+        }
         match {
             assert_eq!(expr[id].nodes.len(), 1);
             &expr[id].nodes[0]
@@ -296,85 +510,89 @@ pub fn find_vars(expr: &Expr, id: Id) -> Vec<String> {
                 set.insert(s.to_string());
             }
             // Id
-            &Language::AccessTensor(id) | &Language::AccessFlatten(id) => {
-                find_vars_recursive_helper(set, expr, id);
+            &Language::AccessTensor(id)
+            | &Language::AccessFlatten(id)
+            | &Language::AccessShiftRight(id)
+            | &Language::MapDotProduct(id) => {
+                worklist.push(id);
             }
             // Box<[Id]>
             Language::List(ids) | Language::Shape(ids) => {
                 for id in ids.iter() {
-                    find_vars_recursive_helper(set, expr, *id);
+                    worklist.push(*id);
                 }
             }
             // [Id; 1]
             &Language::ShapeOf(ids) => {
                 for id in ids.iter() {
-                    find_vars_recursive_helper(set, expr, *id);
+                    worklist.push(*id);
                 }
             }
             // [Id; 2]
             &Language::Access(ids)
             | &Language::AccessTranspose(ids)
             | &Language::AccessReshape(ids)
+            | &Language::AccessPair(ids)
+            | &Language::AccessCartesianProduct(ids)
+            | &Language::AccessInsertAxis(ids)
+            | &Language::AccessBroadcast(ids)
+            | &Language::Compute(ids)
             | &Language::ShapeInsertAxis(ids)
             | &Language::ShapeRemoveAxis(ids)
             | &Language::AccessShape(ids)
-            | &Language::AccessSqueeze(ids) => {
+            | &Language::AccessSqueeze(ids)
+            | &Language::SliceShape(ids)
+            | &Language::CartesianProduct(ids)
+            | &Language::ElementwiseAdd(ids) => {
                 for id in ids.iter() {
-                    find_vars_recursive_helper(set, expr, *id);
+                    worklist.push(*id);
                 }
             }
             // [Id; 3]
-            &Language::AccessConcatenate(ids) | &Language::AccessWindows(ids) => {
+            &Language::AccessConcatenate(ids)
+            | &Language::AccessWindows(ids)
+            | &Language::MoveAxis(ids)
+            | &Language::Concatenate(ids) => {
                 for id in ids.iter() {
-                    find_vars_recursive_helper(set, expr, *id);
+                    worklist.push(*id);
                 }
             }
             // [Id; 4]
             &Language::SystolicArray(ids)
             | &Language::SystolicArrayWithBlocking(ids)
-            | &Language::AccessSlice(ids) => {
+            | &Language::AccessSlice(ids)
+            | &Language::AccessWindowsDilated(ids)
+            | &Language::Slice(ids)
+            | &Language::BsgSystolicArray(ids) => {
                 for id in ids.iter() {
-                    find_vars_recursive_helper(set, expr, *id);
+                    worklist.push(*id);
                 }
             }
             // [Id; 5]
             &Language::AccessPad(ids) => {
                 for id in ids.iter() {
-                    find_vars_recursive_helper(set, expr, *id);
+                    worklist.push(*id);
                 }
             }
-            &Language::Usize(_) | &Language::PadType(_) => (),
-            &Language::Literal(_)
-            | &Language::AccessLiteral(_)
-            | &Language::NotNanFloat64(_)
-            | &Language::AccessBroadcast(_)
-            | &Language::AccessInsertAxis(_)
-            | &Language::AccessPair(_)
+            &Language::Usize(_)
+            | &Language::PadType(_)
+            | &Language::DataType(_)
             | Language::ComputeType(_)
-            | &Language::Compute(_)
-            | &Language::AccessCartesianProduct(_)
-            | &Language::SliceShape(_)
-            | &Language::MoveAxis(_)
-            | &Language::CartesianProduct(_)
-            | &Language::MapDotProduct(_)
-            | &Language::Slice(_)
-            | &Language::Concatenate(_)
-            | &Language::ElementwiseAdd(_)
-            | &Language::BsgSystolicArray(_)
-            | &Language::AccessShiftRight(_) => panic!("{:#?} not implemented", expr[id].nodes[0]),
+            | &Language::Clip(_)
+            | &Language::LeakyReLU(_)
+            | &Language::Literal(_)
+            | &Language::AccessLiteral(_)
+            | &Language::NotNanFloat64(_) => (),
         }
     }
 
-    let mut set = HashSet::default();
-    find_vars_recursive_helper(&mut set, expr, id);
-
     Vec::from_iter(set.drain())
 }
 
 /// Returns c code.
-/// args: The signature will be `void <function_name>(float * out, float * <arg0>...)`
+/// args: The signature will be `void <function_name>(<dtype> * out, <dtype> * <arg0>...)`,
+/// where each dtype comes from the analysis.
 // TODO(@gussmith23) Does not reason about ordering on hardware.
-// TODO(@gussmith23) Hardcoded to float32
 pub fn codegen(
     expr: &Expr,
     id: Id,
@@ -383,17 +601,17 @@ pub fn codegen(
     allocations_prefix: &str,
     args: &Vec<&str>,
 ) -> String {
-    let mut declarations = String::default();
+    let mut allocations = Allocations::default();
     let mut code = String::default();
-    let out_symbol = codegen_recursive_helper(
-        expr,
-        id,
-        id,
-        allocations_prefix,
-        &mut declarations,
-        &mut code,
-        hw_map,
-    );
+    // Generate each access before anything which uses it. Each expression is
+    // generated only once, so by the time we generate an expression, its
+    // children are already done, and codegen never has to recurse deeply.
+    // (Programs like Mobilenet are deep enough to overflow the stack
+    // otherwise.)
+    for access_id in accesses_in_post_order(expr, id) {
+        codegen_recursive_helper(expr, access_id, id, &mut allocations, &mut code, hw_map);
+    }
+    let out_symbol = allocations.generated[&id].clone();
 
     let found_vars = find_vars(expr, id);
     for found_var in found_vars.iter() {
@@ -409,13 +627,8 @@ pub fn codegen(
     signature.push_str(
         c_array_string(
             "out",
-            match &expr[id].data {
-                MyAnalysisData::AccessPattern(a) => a.as_vec(),
-                _ => panic!("Assuming output is a tensor for now"),
-            }
-            .as_slice(),
-            // TODO(@gussmith23) Assuming float32 output.
-            DType::Fp32,
+            data_shape(expr, id).as_slice(),
+            MyAnalysis::get_dtype(id, expr),
         )
         .as_str(),
     );
@@ -428,7 +641,7 @@ pub fn codegen(
                     c_array_string(
                         var,
                         expr.analysis.name_to_shape[*var].as_slice(),
-                        DType::Fp32,
+                        expr.analysis.get_input_dtype(var),
                     )
                 )
             })
@@ -439,54 +652,469 @@ pub fn codegen(
 
     let mut out = String::default();
 
-    out.push_str(SYSTOLIC_ARRAY_SIGNATURE);
-    out.push_str("\n");
-
-    out.push_str(declarations.as_str());
-    out.push_str("\n");
+    out.push_str("#include <stdint.h>\n");
+    // Some software fallbacks for compute statements use libm.
+    if expr.classes().any(|eclass| {
+        eclass.nodes.iter().any(|node| match node {
+            Language::ComputeType(ComputeType::Sqrt)
+            | Language::ComputeType(ComputeType::Softmax)
+            | Language::ComputeType(ComputeType::Sigmoid)
+            | Language::ComputeType(ComputeType::Tanh)
+            | Language::ComputeType(ComputeType::Exp)
+            | Language::ComputeType(ComputeType::Gelu) => true,
+            _ => false,
+        })
+    }) {
+        out.push_str("#include <math.h>\n");
+    }
 
-    out.push_str(signature.as_str());
-    out.push_str("{");
+    // Declare a systolic array function for each datatype used on systolic
+    // arrays.
+    let mut systolic_array_dtypes = expr
+        .classes()
+        .filter(|eclass| {
+            eclass.nodes.iter().any(|node| match node {
+                Language::SystolicArray(_) | Language::SystolicArrayWithBlocking(_) => true,
+                _ => false,
+            })
+        })
+        .map(|eclass| MyAnalysis::get_dtype(eclass.id, expr))
+        .collect::<Vec<_>>();
+    systolic_array_dtypes.sort_by_key(|dtype| *dtype as usize);
+    systolic_array_dtypes.dedup();
+    for dtype in systolic_array_dtypes.iter() {
+        out.push_str(systolic_array_signature(*dtype).as_str());
+    }
+    // Software fallbacks for compute statements also need to convert bfloat16
+    // values.
+    let bf16_computes = expr.classes().any(|eclass| {
+        eclass.nodes.iter().any(|node| match node {
+            Language::Compute(_)
+            | Language::MapDotProduct(_)
+            | Language::ElementwiseAdd(_)
+            | Language::BsgSystolicArray(_) => {
+                MyAnalysis::get_dtype(eclass.id, expr) == DType::Bf16
+            }
+            _ => false,
+        })
+    });
+    if systolic_array_dtypes.contains(&DType::Bf16) || bf16_computes {
+        out.push_str(BF16_HELPERS);
+    }
     out.push_str("\n");
 
-    out.push_str(code.as_str());
-
     // Copy value into "out" variable
     // Get length of array
-    let length = match &expr[id].data {
-        MyAnalysisData::AccessPattern(a) => a
-            .shape
-            .slice()
-            .iter()
-            .chain(a.item_shape.slice().iter())
-            .product::<usize>(),
-        _ => panic!(),
-    };
-    out.push_str(
+    let length = data_shape(expr, id).iter().product::<usize>();
+    code.push_str(
         format!(
             "
 for (int i = 0; i < {}; i++) {{
-  ((float*)out)[i] = ((float*){})[i];
+  (({c_type}*)out)[i] = (({c_type}*){})[i];
 }}
 ",
-            length, out_symbol
+            length,
+            out_symbol,
+            c_type = MyAnalysis::get_dtype(id, expr).to_c_type_string()
+        )
+        .as_str(),
+    );
+    // The result is read by the copy into "out".
+    allocations.record_use(&out_symbol, code.len(), code.len());
+
+    let Allocations {
+        allocations,
+        lifetimes,
+        ..
+    } = allocations;
+
+    // Pack all intermediates into a single arena.
+    let arena_name = format!("{}_arena", function_name);
+    let plan = plan_buffers(
+        allocations
+            .iter()
+            .map(|allocation| {
+                (
+                    allocation.name.clone(),
+                    allocation.size_in_bytes(),
+                    lifetimes[&allocation.name].clone(),
+                )
+            })
+            .collect::<Vec<_>>()
+            .as_slice(),
+    );
+    out.push_str(
+        format!(
+            "// Peak memory used by intermediates: {} bytes ({} bytes without buffer sharing)\n",
+            plan.arena_size, plan.unshared_size
         )
         .as_str(),
     );
+    if !allocations.is_empty() {
+        out.push_str(
+            format!(
+                "{} uint8_t {}[{}] __attribute__ ((aligned ({})));\n",
+                allocations_prefix, arena_name, plan.arena_size, BUFFER_ALIGNMENT
+            )
+            .as_str(),
+        );
+    }
+    for allocation in allocations.iter() {
+        out.push_str(
+            format!(
+                "#define {name} (*({c_type} (*){dims})({arena} + {offset}))\n",
+                name = allocation.name,
+                c_type = allocation.dtype.to_c_type_string(),
+                dims = allocation
+                    .shape
+                    .iter()
+                    .map(|dim| format!("[{}]", dim))
+                    .collect::<String>(),
+                arena = arena_name,
+                offset = plan.offsets[&allocation.name],
+            )
+            .as_str(),
+        );
+    }
+    out.push_str("\n");
+
+    out.push_str(signature.as_str());
+    out.push_str("{");
+    out.push_str("\n");
+
+    out.push_str(code.as_str());
 
     out.push_str("}");
     out.push_str("\n");
 
+    for allocation in allocations.iter() {
+        out.push_str(format!("#undef {}\n", allocation.name).as_str());
+    }
+
     out
 }
 
-/// allocations_prefix: string to prefix all allocations/declarations with
+/// Returns C code for a `main()` which tests a function generated by
+/// [`codegen()`]. The harness `#include`s the generated code from
+/// `code_filepath`, defines each of the `inputs` (given in the same order as
+/// the function's arguments), calls the function `function_name`, and checks
+/// each value of the output against `expected`, within `tolerance`. It prints
+/// any mismatches and exits with a nonzero status if there were any.
+///
+/// Values are given as `f32`s, and converted to each tensor's `DType`.
+pub fn c_test_harness(
+    code_filepath: &str,
+    function_name: &str,
+    inputs: &[(&str, DType, ndarray::ArrayViewD<f32>)],
+    out_dtype: DType,
+    expected: &ndarray::ArrayViewD<f32>,
+    tolerance: f64,
+) -> String {
+    let to_double = |value: &str| match out_dtype {
+        DType::Bf16 => format!("glenside_test_harness_bf16_to_double({})", value),
+        _ => format!("(double){}", value),
+    };
+
+    format!(
+        "#include \"{code_filepath}\"
+#include <stdint.h>
+#include <stdio.h>
+{bf16_helper}
+{inputs}
+{expected}
+{out}
+
+int main() {{
+  {function_name}(out{args});
+
+  int mismatches = 0;
+  for (int i = 0; i < {length}; i++) {{
+    double expected_value = {expected_value};
+    double actual_value = {actual_value};
+    double diff = expected_value - actual_value;
+    // Written so that a NaN on either side counts as a mismatch.
+    if (!(diff <= {tolerance} && diff >= -{tolerance})) {{
+      fprintf(stderr, \"Mismatch at index %d: expected %f, got %f\\n\",
+              i, expected_value, actual_value);
+      mismatches++;
+    }}
+  }}
+
+  if (mismatches > 0) {{
+    fprintf(stderr, \"%d of {length} values did not match\\n\", mismatches);
+    return 1;
+  }}
+
+  printf(\"All {length} values matched\\n\");
+  return 0;
+}}
+",
+        code_filepath = code_filepath,
+        bf16_helper = if out_dtype == DType::Bf16 {
+            "
+static double glenside_test_harness_bf16_to_double(uint16_t value) {
+  union {
+    float f;
+    uint32_t u;
+  } bits;
+  bits.u = ((uint32_t)value) << 16;
+  return (double)bits.f;
+}
+"
+        } else {
+            ""
+        },
+        inputs = inputs
+            .iter()
+            .map(|(name, dtype, value)| c_assignment_string("", name, *dtype, value))
+            .join("\n"),
+        expected = c_assignment_string("", "expected", out_dtype, expected),
+        out = c_allocation_string("", "out", expected.shape(), out_dtype),
+        function_name = function_name,
+        args = inputs
+            .iter()
+            .map(|(name, _, _)| format!(", {}", name))
+            .collect::<String>(),
+        length = expected.len(),
+        expected_value =
+            to_double(format!("(({}*)expected)[i]", out_dtype.to_c_type_string()).as_str()),
+        actual_value = to_double(format!("(({}*)out)[i]", out_dtype.to_c_type_string()).as_str()),
+        tolerance = tolerance,
+    )
+}
+
+/// An intermediate buffer needed by the generated code.
+struct Allocation {
+    name: String,
+    shape: Vec<usize>,
+    dtype: DType,
+}
+
+impl Allocation {
+    fn size_in_bytes(&self) -> usize {
+        self.shape.iter().product::<usize>() * self.dtype.size_in_bytes()
+    }
+}
+
+/// The intermediate buffers needed by the generated code, along with when
+/// each is live.
+#[derive(Default)]
+struct Allocations {
+    allocations: Vec<Allocation>,
+    /// The span of the generated code, in bytes, over which each buffer is
+    /// live: from the start of the code which writes it to the end of the code
+    /// of the last expression which reads it.
+    lifetimes: HashMap<String, Range<usize>>,
+    /// For each expression currently being generated, the buffers returned by
+    /// the children generated so far, and the span of each child's code.
+    children: Vec<Vec<(String, Range<usize>)>>,
+    /// For each expression currently being generated, the buffers it has
+    /// allocated itself.
+    allocated: Vec<Vec<String>>,
+    /// The buffer holding the result of each e-class generated so far.
+    generated: HashMap<Id, String>,
+}
+
+impl Allocations {
+    fn push(&mut self, allocation: Allocation) {
+        if let Some(allocated) = self.allocated.last_mut() {
+            allocated.push(allocation.name.clone());
+        }
+        self.allocations.push(allocation);
+    }
+
+    /// Records that `name` is live from `start` (or earlier) to `end`.
+    fn record_use(&mut self, name: &str, start: usize, end: usize) {
+        let lifetime = self.lifetimes.entry(name.to_string()).or_insert(start..end);
+        lifetime.start = std::cmp::min(lifetime.start, start);
+        lifetime.end = std::cmp::max(lifetime.end, end);
+    }
+}
+
+/// Alignment, in bytes, of every buffer in the arena.
+const BUFFER_ALIGNMENT: usize = 64;
+
+/// Where each intermediate buffer lives in the arena.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferPlan {
+    /// The offset of each buffer in the arena, in bytes.
+    pub offsets: HashMap<String, usize>,
+    /// The size of the arena in bytes, i.e. the peak memory used by
+    /// intermediates.
+    pub arena_size: usize,
+    /// The memory the buffers would use if each had its own storage.
+    pub unshared_size: usize,
+}
+
+/// Plans the storage of the intermediate buffers used by generated C code.
+///
+/// Each buffer in `buffers` is given as its name, its size in bytes, and its
+/// lifetime: the range of positions in the generated code over which it is
+/// live. Buffers whose lifetimes don't overlap can share storage; we pack the
+/// buffers into a single arena greedily, largest first.
+/// ```
+/// use glenside::codegen::plan_buffers;
+///
+/// let plan = plan_buffers(&[
+///     ("b".to_string(), 16, 0..2),
+///     ("c".to_string(), 16, 1..3),
+///     ("d".to_string(), 16, 2..3),
+/// ]);
+/// // b is dead by the time d is written, so they can share storage.
+/// assert_eq!(plan.offsets["b"], plan.offsets["d"]);
+/// assert_ne!(plan.offsets["b"], plan.offsets["c"]);
+/// assert_eq!(plan.arena_size, 128);
+/// assert_eq!(plan.unshared_size, 192);
+/// ```
+pub fn plan_buffers(buffers: &[(String, usize, Range<usize>)]) -> BufferPlan {
+    let aligned = |size: usize| (size + BUFFER_ALIGNMENT - 1) / BUFFER_ALIGNMENT * BUFFER_ALIGNMENT;
+
+    let mut order = (0..buffers.len()).collect::<Vec<_>>();
+    order.sort_by_key(|i| std::cmp::Reverse(buffers[*i].1));
+
+    // (offset, size, index) of each placed buffer.
+    let mut placed: Vec<(usize, usize, usize)> = Vec::default();
+    let mut offsets = HashMap::default();
+    for &i in order.iter() {
+        let size = aligned(buffers[i].1);
+        let lifetime = &buffers[i].2;
+
+        // Buffers which are live at the same time as this one, by offset.
+        let mut conflicts = placed
+            .iter()
+            .filter(|(_, _, j)| {
+                let other_lifetime = &buffers[*j].2;
+                lifetime.start < other_lifetime.end && other_lifetime.start < lifetime.end
+            })
+            .map(|(offset, size, _)| (*offset, *size))
+            .collect::<Vec<_>>();
+        conflicts.sort();
+
+        // Take the first gap big enough to fit this buffer.
+        let mut offset = 0;
+        for (other_offset, other_size) in conflicts {
+            if offset + size <= other_offset {
+                break;
+            }
+            offset = std::cmp::max(offset, other_offset + other_size);
+        }
+
+        placed.push((offset, size, i));
+        offsets.insert(buffers[i].0.clone(), offset);
+    }
+
+    BufferPlan {
+        offsets,
+        arena_size: placed
+            .iter()
+            .map(|(offset, size, _)| offset + size)
+            .max()
+            .unwrap_or(0),
+        unshared_size: buffers.iter().map(|(_, size, _)| aligned(*size)).sum(),
+    }
+}
+
+/// Generates the code for `id`, recording the lifetimes of the buffers it
+/// uses.
+/// allocations: the intermediate buffers needed by `code`
 fn codegen_recursive_helper(
     expr: &Expr,
     id: Id,
     top_level_id: Id,
-    allocations_prefix: &str,
-    declarations: &mut String,
+    allocations: &mut Allocations,
+    code: &mut String,
+    hw_map: &HashMap<Id, usize>,
+) -> String {
+    let start = code.len();
+
+    if let Some(out) = allocations.generated.get(&id).cloned() {
+        if let Some(siblings) = allocations.children.last_mut() {
+            siblings.push((out.clone(), start..start));
+        }
+        return out;
+    }
+
+    allocations.children.push(Vec::default());
+    allocations.allocated.push(Vec::default());
+
+    let out = codegen_node(expr, id, top_level_id, allocations, code, hw_map);
+
+    let end = code.len();
+    let children = allocations.children.pop().unwrap();
+    // The children's results are read by this expression's code.
+    for (child, _) in children.iter() {
+        allocations.record_use(child, end, end);
+    }
+    // This expression's own code starts at the first point not covered by
+    // one of its children. The buffers it allocates aren't live before then,
+    // so they can share storage with its children's intermediates.
+    let mut own_start = start;
+    for (_, span) in children.iter() {
+        if span.start > own_start {
+            break;
+        }
+        own_start = std::cmp::max(own_start, span.end);
+    }
+    for name in allocations.allocated.pop().unwrap() {
+        allocations.record_use(&name, own_start, end);
+    }
+    allocations.record_use(&out, own_start, end);
+    if let Some(siblings) = allocations.children.last_mut() {
+        siblings.push((out.clone(), start..end));
+    }
+    allocations.generated.insert(id, out.clone());
+
+    out
+}
+
+/// The shape of the data at `id`: either an access pattern, or a tensor
+/// produced by one of the legacy constructs.
+fn data_shape(expr: &Expr, id: Id) -> Vec<usize> {
+    match &expr[id].data {
+        MyAnalysisData::AccessPattern(a) => a.as_vec(),
+        MyAnalysisData::Legacy(_) => MyAnalysis::get_shape(id, expr).slice().to_vec(),
+        _ => panic!("Expected an access pattern or a tensor"),
+    }
+}
+
+/// Returns the e-classes holding data (accesses, or the tensors produced by
+/// the legacy constructs) in the program rooted at `id`, each after all of its
+/// children which hold data. These are the e-classes
+/// [`codegen_recursive_helper()`] generates code for, in an order in which it
+/// doesn't need to recurse.
+fn accesses_in_post_order(expr: &Expr, id: Id) -> Vec<Id> {
+    let mut order = Vec::default();
+    let mut visited = HashSet::new();
+    // Each entry is an e-class, and whether its children have been visited.
+    let mut stack = vec![(id, false)];
+    while let Some((id, children_visited)) = stack.pop() {
+        if children_visited {
+            order.push(id);
+            continue;
+        }
+        if !visited.insert(id) {
+            continue;
+        }
+        stack.push((id, true));
+        assert_eq!(expr[id].nodes.len(), 1);
+        for child in expr[id].nodes[0].children().iter().rev() {
+            match &expr[*child].data {
+                MyAnalysisData::AccessPattern(_) => stack.push((*child, false)),
+                MyAnalysisData::Legacy(l) if l.shape.is_some() => stack.push((*child, false)),
+                _ => (),
+            }
+        }
+    }
+    order
+}
+
+/// Generates the code for `id`, returning the name of the buffer holding its
+/// result.
+fn codegen_node(
+    expr: &Expr,
+    id: Id,
+    top_level_id: Id,
+    allocations: &mut Allocations,
     code: &mut String,
     hw_map: &HashMap<Id, usize>,
 ) -> String {
@@ -494,22 +1122,30 @@ fn codegen_recursive_helper(
         assert_eq!(expr[id].nodes.len(), 1);
         &expr[id].nodes[0]
     } {
-        &Language::AccessWindows([access_id, filters_shape_id, stride_shape_id]) => {
+        &Language::AccessWindows([access_id, filters_shape_id, stride_shape_id])
+        | &Language::AccessWindowsDilated([access_id, filters_shape_id, stride_shape_id, _]) => {
             let access = match &expr[access_id].data {
                 MyAnalysisData::AccessPattern(a) => a,
                 _ => panic!(),
             };
             let filters_shape = MyAnalysis::get_shape_of_value(filters_shape_id, expr);
             let stride_shape = MyAnalysis::get_shape_of_value(stride_shape_id, expr);
+            let dilation_shape = match &expr[id].nodes[0] {
+                &Language::AccessWindowsDilated([_, _, _, dilation_shape_id]) => {
+                    MyAnalysis::get_shape_of_value(dilation_shape_id, expr).clone()
+                }
+                _ => IxDyn(vec![1; stride_shape.ndim()].as_slice()),
+            };
 
             // TODO(@gussmith23) Generalize AccessWindows to other accesses
             // Right now we expect item shape to be a scalar.
             assert_eq!(access.item_shape.ndim(), 0);
 
-            let access_windows_shape = crate::language::access_windows_resulting_shape(
+            let access_windows_shape = crate::language::access_windows_dilated_resulting_shape(
                 &access.shape,
                 &filters_shape,
                 &stride_shape,
+                &dilation_shape,
             );
 
             let access_windows_item_shape = filters_shape.clone();
@@ -518,42 +1154,22 @@ fn codegen_recursive_helper(
             assert_eq!(stride_shape.ndim(), access_windows_shape.len());
 
             let access_windows_out_var_name: String = {
-                // TODO(@gussmith23) Find a different way to name intermediates
-                // Currently generating random strings. Not great IMO.
-                let out = format!(
-                    "access_windows_out_{}",
-                    rand::thread_rng()
-                        .sample_iter(&rand::distributions::Alphanumeric)
-                        .take(30)
-                        .collect::<String>()
-                );
-                declarations.push_str(
-                    c_allocation_string(
-                        allocations_prefix,
-                        out.as_str(),
-                        access_windows_shape
-                            .iter()
-                            .chain(access_windows_item_shape.slice().iter())
-                            .cloned()
-                            .collect::<Vec<_>>()
-                            .as_slice(),
-                        DType::Fp32,
-                    )
-                    .as_str(),
-                );
+                let out = format!("access_windows_eclass_{}_out", id);
+                allocations.push(Allocation {
+                    name: out.to_string(),
+                    shape: access_windows_shape
+                        .iter()
+                        .chain(access_windows_item_shape.slice().iter())
+                        .cloned()
+                        .collect::<Vec<_>>(),
+                    dtype: MyAnalysis::get_dtype(id, expr),
+                });
                 out
             };
 
             // TODO(@gussmith23) It would make our lives easier if we
-            let access_var_name = codegen_recursive_helper(
-                expr,
-                access_id,
-                top_level_id,
-                allocations_prefix,
-                declarations,
-                code,
-                hw_map,
-            );
+            let access_var_name =
+                codegen_recursive_helper(expr, access_id, top_level_id, allocations, code, hw_map);
 
             // Create a for loop for every dimension in the result shape.
             for (dim_index, dim_len) in access_windows_shape.iter().enumerate() {
@@ -602,10 +1218,11 @@ for (int {index_var_name} = 0; {index_var_name} < {dim_len}; {index_var_name}++)
                     in_name = access_var_name,
                     in_index = (0..access_windows_shape.len())
                         .map(|i| format!(
-                            "[{shape_index}*{stride} + {item_shape_index}]",
+                            "[{shape_index}*{stride} + {item_shape_index}*{dilation}]",
                             shape_index = format!("shape_index_{}", i),
                             item_shape_index = format!("item_shape_index_{}", i),
                             stride = stride_shape[i],
+                            dilation = dilation_shape[i],
                         ))
                         .collect::<Vec<_>>()
                         .join("")
@@ -620,48 +1237,31 @@ for (int {index_var_name} = 0; {index_var_name} < {dim_len}; {index_var_name}++)
 
             access_windows_out_var_name
         }
-        &Language::AccessSlice([access_id, axis_id, low_id, high_id]) => {
-            let original_shape = match &expr[access_id].data {
-                MyAnalysisData::AccessPattern(a) => a.as_vec(),
-                _ => panic!(),
-            };
+        &Language::AccessSlice([access_id, axis_id, low_id, high_id])
+        | &Language::Slice([access_id, axis_id, low_id, high_id]) => {
+            let original_shape = data_shape(expr, access_id);
             let axis = MyAnalysis::get_usize(axis_id, expr);
             let low = MyAnalysis::get_usize(low_id, expr);
             let _high = MyAnalysis::get_usize(high_id, expr);
-            let new_shape = match &expr[id].data {
-                MyAnalysisData::AccessPattern(a) => a.as_vec(),
-                _ => panic!(),
-            };
+            let new_shape = data_shape(expr, id);
 
-            let access_var_name = codegen_recursive_helper(
-                expr,
-                access_id,
-                top_level_id,
-                allocations_prefix,
-                declarations,
-                code,
-                hw_map,
-            );
+            let access_var_name =
+                codegen_recursive_helper(expr, access_id, top_level_id, allocations, code, hw_map);
 
             let slice_out_var_name: String = {
-                // TODO(@gussmith23) Find a different way to name intermediates
-                // Currently generating random strings. Not great IMO.
                 let out = format!(
-                    "access_slice_out_{}",
-                    rand::thread_rng()
-                        .sample_iter(&rand::distributions::Alphanumeric)
-                        .take(30)
-                        .collect::<String>()
-                );
-                declarations.push_str(
-                    c_allocation_string(
-                        allocations_prefix,
-                        out.as_str(),
-                        new_shape.as_slice(),
-                        DType::Fp32,
-                    )
-                    .as_str(),
+                    "{}_eclass_{}_out",
+                    match &expr[id].nodes[0] {
+                        Language::AccessSlice(_) => "access_slice",
+                        _ => "slice",
+                    },
+                    id
                 );
+                allocations.push(Allocation {
+                    name: out.to_string(),
+                    shape: new_shape.to_vec(),
+                    dtype: MyAnalysis::get_dtype(id, expr),
+                });
                 out
             };
 
@@ -710,26 +1310,22 @@ for (int i{i} = 0; i{i} < {limit}; i{i}++) {{",
         }
         Language::Symbol(s) => s.clone(),
         &Language::AccessTensor(symbol_id) => {
-            let symbol = codegen_recursive_helper(
-                expr,
-                symbol_id,
-                top_level_id,
-                allocations_prefix,
-                declarations,
-                code,
-                hw_map,
-            );
+            let symbol =
+                codegen_recursive_helper(expr, symbol_id, top_level_id, allocations, code, hw_map);
             symbol
         }
         &Language::Access([access_tensor_id, _axis_id]) => codegen_recursive_helper(
             expr,
             access_tensor_id,
             top_level_id,
-            allocations_prefix,
-            declarations,
+            allocations,
             code,
             hw_map,
         ),
+        // Shifting the access axis doesn't change the data or its layout.
+        &Language::AccessShiftRight(access_id) => {
+            codegen_recursive_helper(expr, access_id, top_level_id, allocations, code, hw_map)
+        }
         &Language::SystolicArray([rows_id, cols_id, a0_id, a1_id])
         | &Language::SystolicArrayWithBlocking([rows_id, cols_id, a0_id, a1_id]) => {
             let rows = MyAnalysis::get_usize(rows_id, expr);
@@ -775,24 +1371,8 @@ for (int i{i} = 0; i{i} < {limit}; i{i}++) {{",
             assert!(this_access.shape.ndim() == 1 || this_access.shape.ndim() == 2);
             assert_eq!(this_access.item_shape.ndim(), 0);
 
-            let s0 = codegen_recursive_helper(
-                expr,
-                a0_id,
-                top_level_id,
-                allocations_prefix,
-                declarations,
-                code,
-                hw_map,
-            );
-            let s1 = codegen_recursive_helper(
-                expr,
-                a1_id,
-                top_level_id,
-                allocations_prefix,
-                declarations,
-                code,
-                hw_map,
-            );
+            let s0 = codegen_recursive_helper(expr, a0_id, top_level_id, allocations, code, hw_map);
+            let s1 = codegen_recursive_helper(expr, a1_id, top_level_id, allocations, code, hw_map);
 
             let out_var_name = match {
                 assert_eq!(expr[id].nodes.len(), 1);
@@ -811,38 +1391,56 @@ for (int i{i} = 0; i{i} < {limit}; i{i}++) {{",
                 _ => unreachable!(),
             };
 
-            // TODO(@gussmith23) how to assign unique names to each usage?
-            // TODO(@gussmith23) Allocations should not be done ad-hoc
-            declarations.push_str(
-                c_allocation_string(
-                    allocations_prefix,
-                    out_var_name.as_str(),
-                    this_access
-                        .shape
-                        .slice()
-                        .iter()
-                        .chain(this_access.item_shape.slice().iter())
-                        .cloned()
-                        .collect::<Vec<_>>()
-                        .as_slice(),
-                    // TODO(@gussmith23) Datatype assumption
-                    DType::Fp32,
-                )
-                .as_str(),
-            );
+            let dtype = this_access.dtype;
+            let accumulator_dtype = dtype.accumulator_dtype();
+            let out_shape = this_access
+                .shape
+                .slice()
+                .iter()
+                .chain(this_access.item_shape.slice().iter())
+                .cloned()
+                .collect::<Vec<_>>();
+
+            // TODO(@gussmith23) how to assign unique names to each usage?
+            // TODO(@gussmith23) Allocations should not be done ad-hoc
+            allocations.push(Allocation {
+                name: out_var_name.to_string(),
+                shape: out_shape.to_vec(),
+                dtype,
+            });
+
+            // The systolic array writes its results in the accumulator type.
+            // If that differs from the output type, we accumulate into a
+            // separate buffer and convert afterwards.
+            let accumulator_var_name = if accumulator_dtype != dtype {
+                let accumulator_var_name = format!("{}_accumulator", out_var_name);
+                allocations.push(Allocation {
+                    name: accumulator_var_name.to_string(),
+                    shape: out_shape.to_vec(),
+                    dtype: accumulator_dtype,
+                });
+                accumulator_var_name
+            } else {
+                out_var_name.clone()
+            };
 
             code.push_str(
                 format!(
-                    "rtml_systolic_array_weight_stationary(
+                    "{}(
                        {}, {}, {}, {}, {}, {}, {});\n",
+                    systolic_array_function_name(dtype),
                     // Hardware ID
                     hw_map.get(&id).unwrap(),
                     // Pointer to output
-                    format!("(float*){}", out_var_name,),
+                    format!(
+                        "({}*){}",
+                        accumulator_dtype.to_c_type_string(),
+                        accumulator_var_name,
+                    ),
                     // Pointer to input vector
-                    format!("(float*){}", s0,),
+                    format!("({}*){}", dtype.to_c_type_string(), s0,),
                     // Pointer to input matrix
-                    format!("(float*){}", s1,),
+                    format!("({}*){}", dtype.to_c_type_string(), s1,),
                     // Length of input vector/size of input matrix dim 0
                     a1.item_shape.slice()[0],
                     // Size of input matrix dim 1/length of output vector
@@ -859,6 +1457,31 @@ for (int i{i} = 0; i{i} < {limit}; i{i}++) {{",
                 .as_str(),
             );
 
+            if accumulator_dtype != dtype {
+                code.push_str(
+                    format!(
+                        "
+for (int i = 0; i < {limit}; i++) {{
+  (({c_type}*){out_var_name})[i] = {narrowed};
+}}
+",
+                        limit = out_shape.iter().product::<usize>(),
+                        c_type = dtype.to_c_type_string(),
+                        out_var_name = out_var_name,
+                        narrowed = c_narrowing_string(
+                            format!(
+                                "(({}*){})[i]",
+                                accumulator_dtype.to_c_type_string(),
+                                accumulator_var_name
+                            )
+                            .as_str(),
+                            dtype
+                        ),
+                    )
+                    .as_str(),
+                );
+            }
+
             out_var_name
         }
         &Language::Usize(u) => format!("{}", u),
@@ -895,35 +1518,16 @@ for (int i{i} = 0; i{i} < {limit}; i{i}++) {{",
                 _ => panic!(),
             };
 
-            let access_var_name = codegen_recursive_helper(
-                expr,
-                access_id,
-                top_level_id,
-                allocations_prefix,
-                declarations,
-                code,
-                hw_map,
-            );
+            let access_var_name =
+                codegen_recursive_helper(expr, access_id, top_level_id, allocations, code, hw_map);
 
             let pad_out_var_name: String = {
-                // TODO(@gussmith23) Find a different way to name intermediates
-                // Currently generating random strings. Not great IMO.
-                let out = format!(
-                    "pad_out_{}",
-                    rand::thread_rng()
-                        .sample_iter(&rand::distributions::Alphanumeric)
-                        .take(30)
-                        .collect::<String>()
-                );
-                declarations.push_str(
-                    c_allocation_string(
-                        allocations_prefix,
-                        out.as_str(),
-                        new_shape.as_slice(),
-                        DType::Fp32,
-                    )
-                    .as_str(),
-                );
+                let out = format!("pad_eclass_{}_out", id);
+                allocations.push(Allocation {
+                    name: out.to_string(),
+                    shape: new_shape.to_vec(),
+                    dtype: MyAnalysis::get_dtype(id, expr),
+                });
                 out
             };
 
@@ -954,9 +1558,10 @@ for (int {i} = 0; {i} < {limit}; {i}++) {{",
 if (i{pad_axis} < {pad_before_index} || i{pad_axis} >= {pad_after_index}) {{
   {out_name}{out_index} = {pad_value};
 }} else {{
-  {out_name}{out_index} = ((float*){in_name})[{in_index}];
+  {out_name}{out_index} = (({c_type}*){in_name})[{in_index}];
 }}
 ",
+                    c_type = MyAnalysis::get_dtype(id, expr).to_c_type_string(),
                     pad_axis = axis,
                     pad_before_index = pad_before,
                     pad_after_index = pad_before + original_shape[axis],
@@ -966,8 +1571,8 @@ if (i{pad_axis} < {pad_before_index} || i{pad_axis} >= {pad_after_index}) {{
                         .collect::<Vec<_>>()
                         .join(""),
                     pad_value = match pad_type {
-                        PadType::ZeroPadding => "0",
-                        PadType::MinPadding => todo!(),
+                        PadType::ZeroPadding => "0".to_string(),
+                        PadType::MinPadding => c_min_value_string(MyAnalysis::get_dtype(id, expr)),
                     },
                     in_name = access_var_name,
                     in_index = (0..new_shape.len())
@@ -1023,34 +1628,15 @@ if (i{pad_axis} < {pad_before_index} || i{pad_axis} >= {pad_after_index}) {{
 
             assert_eq!(original_shape.len(), new_axis_order.len());
 
-            let access_var_name = codegen_recursive_helper(
-                expr,
-                access_id,
-                top_level_id,
-                allocations_prefix,
-                declarations,
-                code,
-                hw_map,
-            );
+            let access_var_name =
+                codegen_recursive_helper(expr, access_id, top_level_id, allocations, code, hw_map);
             let transpose_out_var_name: String = {
-                // TODO(@gussmith23) Find a different way to name intermediates
-                // Currently generating random strings. Not great IMO.
-                let out = format!(
-                    "transpose_out_{}",
-                    rand::thread_rng()
-                        .sample_iter(&rand::distributions::Alphanumeric)
-                        .take(30)
-                        .collect::<String>()
-                );
-                declarations.push_str(
-                    c_allocation_string(
-                        allocations_prefix,
-                        out.as_str(),
-                        new_shape.as_slice(),
-                        DType::Fp32,
-                    )
-                    .as_str(),
-                );
+                let out = format!("transpose_eclass_{}_out", id);
+                allocations.push(Allocation {
+                    name: out.to_string(),
+                    shape: new_shape.to_vec(),
+                    dtype: MyAnalysis::get_dtype(id, expr),
+                });
                 out
             };
 
@@ -1102,43 +1688,25 @@ for (int {i} = 0; {i} < {limit}; {i}++) {{",
                 _ => panic!(),
             };
             let out_var_name: String = {
-                // TODO(@gussmith23) Find a different way to name intermediates
-                // Currently generating random strings. Not great IMO.
-                let out = format!(
-                    "access_flatten_out_{}",
-                    rand::thread_rng()
-                        .sample_iter(&rand::distributions::Alphanumeric)
-                        .take(30)
-                        .collect::<String>()
-                );
-                declarations.push_str(
-                    c_allocation_string(
-                        allocations_prefix,
-                        out.as_str(),
-                        out_shape.as_slice(),
-                        DType::Fp32,
-                    )
-                    .as_str(),
-                );
+                let out = format!("access_flatten_eclass_{}_out", id);
+                allocations.push(Allocation {
+                    name: out.to_string(),
+                    shape: out_shape.to_vec(),
+                    dtype: MyAnalysis::get_dtype(id, expr),
+                });
                 out
             };
 
-            let in_var_name = codegen_recursive_helper(
-                expr,
-                access_id,
-                top_level_id,
-                allocations_prefix,
-                declarations,
-                code,
-                hw_map,
-            );
+            let in_var_name =
+                codegen_recursive_helper(expr, access_id, top_level_id, allocations, code, hw_map);
 
             code.push_str(
                 format!(
                     "
 for (int i = 0; i < {limit}; ++i) {{
-  ((float*){out_var_name})[i] = ((float*){in_var_name})[i];
+  (({c_type}*){out_var_name})[i] = (({c_type}*){in_var_name})[i];
 }}",
+                    c_type = MyAnalysis::get_dtype(id, expr).to_c_type_string(),
                     out_var_name = out_var_name,
                     in_var_name = in_var_name,
                     limit = out_shape.iter().product::<usize>(),
@@ -1156,43 +1724,25 @@ for (int i = 0; i < {limit}; ++i) {{
                 _ => panic!(),
             };
             let out_var_name: String = {
-                // TODO(@gussmith23) Find a different way to name intermediates
-                // Currently generating random strings. Not great IMO.
-                let out = format!(
-                    "access_reshape_out_{}",
-                    rand::thread_rng()
-                        .sample_iter(&rand::distributions::Alphanumeric)
-                        .take(30)
-                        .collect::<String>()
-                );
-                declarations.push_str(
-                    c_allocation_string(
-                        allocations_prefix,
-                        out.as_str(),
-                        out_shape.as_slice(),
-                        DType::Fp32,
-                    )
-                    .as_str(),
-                );
+                let out = format!("access_reshape_eclass_{}_out", id);
+                allocations.push(Allocation {
+                    name: out.to_string(),
+                    shape: out_shape.to_vec(),
+                    dtype: MyAnalysis::get_dtype(id, expr),
+                });
                 out
             };
 
-            let in_var_name = codegen_recursive_helper(
-                expr,
-                access_id,
-                top_level_id,
-                allocations_prefix,
-                declarations,
-                code,
-                hw_map,
-            );
+            let in_var_name =
+                codegen_recursive_helper(expr, access_id, top_level_id, allocations, code, hw_map);
 
             code.push_str(
                 format!(
                     "
 for (int i = 0; i < {limit}; ++i) {{
-  ((float*){out_var_name})[i] = ((float*){in_var_name})[i];
+  (({c_type}*){out_var_name})[i] = (({c_type}*){in_var_name})[i];
 }}",
+                    c_type = MyAnalysis::get_dtype(id, expr).to_c_type_string(),
                     out_var_name = out_var_name,
                     in_var_name = in_var_name,
                     limit = out_shape.iter().product::<usize>(),
@@ -1210,43 +1760,25 @@ for (int i = 0; i < {limit}; ++i) {{
                 _ => panic!(),
             };
             let out_var_name: String = {
-                // TODO(@gussmith23) Find a different way to name intermediates
-                // Currently generating random strings. Not great IMO.
-                let out = format!(
-                    "access_squeeze_out_{}",
-                    rand::thread_rng()
-                        .sample_iter(&rand::distributions::Alphanumeric)
-                        .take(30)
-                        .collect::<String>()
-                );
-                declarations.push_str(
-                    c_allocation_string(
-                        allocations_prefix,
-                        out.as_str(),
-                        out_shape.as_slice(),
-                        DType::Fp32,
-                    )
-                    .as_str(),
-                );
+                let out = format!("access_squeeze_eclass_{}_out", id);
+                allocations.push(Allocation {
+                    name: out.to_string(),
+                    shape: out_shape.to_vec(),
+                    dtype: MyAnalysis::get_dtype(id, expr),
+                });
                 out
             };
 
-            let in_var_name = codegen_recursive_helper(
-                expr,
-                access_id,
-                top_level_id,
-                allocations_prefix,
-                declarations,
-                code,
-                hw_map,
-            );
+            let in_var_name =
+                codegen_recursive_helper(expr, access_id, top_level_id, allocations, code, hw_map);
 
             code.push_str(
                 format!(
                     "
 for (int i = 0; i < {limit}; ++i) {{
-  ((float*){out_var_name})[i] = ((float*){in_var_name})[i];
+  (({c_type}*){out_var_name})[i] = (({c_type}*){in_var_name})[i];
 }}",
+                    c_type = MyAnalysis::get_dtype(id, expr).to_c_type_string(),
                     out_var_name = out_var_name,
                     in_var_name = in_var_name,
                     limit = out_shape.iter().product::<usize>(),
@@ -1256,68 +1788,24 @@ for (int i = 0; i < {limit}; ++i) {{
 
             out_var_name
         }
-        &Language::AccessConcatenate([a0_id, a1_id, axis_id]) => {
+        &Language::AccessConcatenate([a0_id, a1_id, axis_id])
+        | &Language::Concatenate([a0_id, a1_id, axis_id]) => {
             let axis = MyAnalysis::get_usize(axis_id, expr);
-            let (a0, _a1) = match (&expr[a0_id].data, &expr[a1_id].data) {
-                (MyAnalysisData::AccessPattern(a0), MyAnalysisData::AccessPattern(a1)) => (a0, a1),
-                _ => panic!(),
-            };
-            let arg_0_name = codegen_recursive_helper(
-                expr,
-                a0_id,
-                top_level_id,
-                allocations_prefix,
-                declarations,
-                code,
-                hw_map,
-            );
-            let arg_1_name = codegen_recursive_helper(
-                expr,
-                a1_id,
-                top_level_id,
-                allocations_prefix,
-                declarations,
-                code,
-                hw_map,
-            );
+            let arg_0_name =
+                codegen_recursive_helper(expr, a0_id, top_level_id, allocations, code, hw_map);
+            let arg_1_name =
+                codegen_recursive_helper(expr, a1_id, top_level_id, allocations, code, hw_map);
 
-            let concat_shape = match &expr[id].data {
-                MyAnalysisData::AccessPattern(a) => a
-                    .shape
-                    .slice()
-                    .iter()
-                    .chain(a.item_shape.slice().iter())
-                    .cloned()
-                    .collect::<Vec<_>>(),
-                _ => panic!("Expected access pattern"),
-            };
-            let a0_shape = a0
-                .shape
-                .slice()
-                .iter()
-                .chain(a0.item_shape.slice().iter())
-                .cloned()
-                .collect::<Vec<_>>();
+            let concat_shape = data_shape(expr, id);
+            let a0_shape = data_shape(expr, a0_id);
 
             let out_var_name: String = {
-                // TODO(@gussmith23) Find a different way to name intermediates
-                // Currently generating random strings. Not great IMO.
-                let out = format!(
-                    "concat_out_{}",
-                    rand::thread_rng()
-                        .sample_iter(&rand::distributions::Alphanumeric)
-                        .take(30)
-                        .collect::<String>()
-                );
-                declarations.push_str(
-                    c_allocation_string(
-                        allocations_prefix,
-                        out.as_str(),
-                        concat_shape.as_slice(),
-                        DType::Fp32,
-                    )
-                    .as_str(),
-                );
+                let out = format!("concat_eclass_{}_out", id);
+                allocations.push(Allocation {
+                    name: out.to_string(),
+                    shape: concat_shape.to_vec(),
+                    dtype: MyAnalysis::get_dtype(id, expr),
+                });
                 out
             };
 
@@ -1345,7 +1833,7 @@ if (i{i} < {dim_len}) {{
 }}
 ",
                     i = axis,
-                    dim_len = a0[axis],
+                    dim_len = a0_shape[axis],
                     out_var_name = out_var_name,
                     out_indices = (0..(concat_shape.len()))
                         .map(|i| format!("[i{}]", i,))
@@ -1372,196 +1860,782 @@ if (i{i} < {dim_len}) {{
 
             out_var_name
         }
-        &Language::Literal(_)
-        | &Language::NotNanFloat64(_)
-        | Language::List(_)
-        | &Language::AccessBroadcast(_)
-        | &Language::AccessInsertAxis(_)
-        | &Language::AccessPair(_)
-        | Language::PadType(_)
-        | Language::ComputeType(_)
-        | &Language::Compute(_)
-        | &Language::AccessCartesianProduct(_)
-        | Language::Shape(_)
-        | &Language::SliceShape(_)
-        | &Language::ShapeInsertAxis(_)
-        | &Language::ShapeRemoveAxis(_)
-        | &Language::ShapeOf(_)
-        | &Language::MoveAxis(_)
-        | &Language::CartesianProduct(_)
-        | &Language::MapDotProduct(_)
-        | &Language::Slice(_)
-        | &Language::Concatenate(_)
-        | &Language::ElementwiseAdd(_)
-        | &Language::BsgSystolicArray(_)
-        | &Language::AccessShape(_)
-        | &Language::AccessLiteral(_)
-        | &Language::AccessShiftRight(_) => panic!("{:#?} not implemented", expr[id].nodes[0]),
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use egg::RecExpr;
-    use ndarray::{SliceInfo, SliceOrIndex};
-    use std::fs::File;
-    use std::io::Write;
-    use std::iter::FromIterator;
-    use std::path::PathBuf;
-    use std::process::Command;
-    use std::str::FromStr;
+        // Inserting an axis doesn't change the data or its layout.
+        &Language::AccessInsertAxis([access_id, _axis_id]) => {
+            codegen_recursive_helper(expr, access_id, top_level_id, allocations, code, hw_map)
+        }
+        &Language::AccessLiteral([literal_id, _dtype_id]) => {
+            let literal = match &expr[literal_id].data {
+                MyAnalysisData::Literal(t) => t,
+                _ => panic!(),
+            };
+            let dtype = MyAnalysis::get_dtype(id, expr);
 
-    #[test]
-    fn transpose() {
-        let shape = vec![1, 20, 300, 3];
-        let permutation = vec![3, 1, 0, 2];
-        let input = ndarray::ArrayD::from_shape_vec(
-            shape.clone(),
-            (0..shape.iter().product::<usize>()).collect(),
-        )
-        .unwrap();
-        let input_transposed = input.clone().permuted_axes(permutation.clone());
+            let out_var_name: String = {
+                let out = format!("access_literal_eclass_{}_out", id);
+                allocations.push(Allocation {
+                    name: out.clone(),
+                    // Scalars are stored as one-element arrays, so that they
+                    // can be indexed like any other buffer.
+                    shape: if literal.ndim() == 0 {
+                        vec![1]
+                    } else {
+                        literal.shape().to_vec()
+                    },
+                    dtype,
+                });
+                out
+            };
 
-        let expr = RecExpr::from_str(
-            format!(
-                "
-(access-transpose (access-tensor t) (list {}))",
-                permutation.iter().map(|x| x.to_string()).join(" ")
-            )
-            .as_str(),
-        )
-        .unwrap();
+            for (i, value) in literal.iter().enumerate() {
+                code.push_str(
+                    format!(
+                        "
+(({c_type}*){out_var_name})[{i}] = {value};",
+                        c_type = dtype.to_c_type_string(),
+                        out_var_name = out_var_name,
+                        i = i,
+                        value = c_literal_string(*value, dtype),
+                    )
+                    .as_str(),
+                );
+            }
 
-        let mut map = HashMap::default();
-        map.insert("t".to_string(), shape.clone());
+            out_var_name
+        }
+        &Language::AccessBroadcast([access_id, _access_shape_id]) => {
+            let access = match &expr[access_id].data {
+                MyAnalysisData::AccessPattern(a) => a,
+                _ => panic!(),
+            };
+            let in_shape = access.as_vec();
+            let out_shape = match &expr[id].data {
+                MyAnalysisData::AccessPattern(a) => a.as_vec(),
+                _ => panic!(),
+            };
+            assert_eq!(in_shape.len(), out_shape.len());
 
-        let mut egraph = EGraph::new(MyAnalysis { name_to_shape: map });
-        let id = egraph.add_expr(&expr);
+            let in_var_name =
+                codegen_recursive_helper(expr, access_id, top_level_id, allocations, code, hw_map);
 
-        let code = codegen(
-            &egraph,
-            id,
-            &HashMap::default(),
-            "transpose",
-            "",
-            &vec!["t"],
-        );
+            let out_var_name: String = {
+                let out = format!("access_broadcast_eclass_{}_out", id);
+                allocations.push(Allocation {
+                    name: out.to_string(),
+                    shape: out_shape.to_vec(),
+                    dtype: MyAnalysis::get_dtype(id, expr),
+                });
+                out
+            };
 
-        let main_code = format!(
-            "
-#include <assert.h>
+            for (dim_index, dim_len) in out_shape.iter().enumerate() {
+                code.push_str(
+                    format!(
+                        "
+for (int {i} = 0; {i} < {limit}; {i}++) {{",
+                        i = format!("i{}", dim_index),
+                        limit = dim_len
+                    )
+                    .as_str(),
+                );
+            }
 
-{}
-{}
-{}
-{}
+            // Dimensions of size 1 are broadcast, so we always read index 0.
+            code.push_str(
+                format!(
+                    "
+(({c_type}*){out_var_name})[{out_index}] = (({c_type}*){in_var_name})[{in_index}];",
+                    c_type = MyAnalysis::get_dtype(id, expr).to_c_type_string(),
+                    out_var_name = out_var_name,
+                    out_index = c_flat_index_string(
+                        &(0..out_shape.len())
+                            .map(|i| format!("i{}", i))
+                            .collect::<Vec<_>>(),
+                        &out_shape
+                    ),
+                    in_var_name = in_var_name,
+                    in_index = c_flat_index_string(
+                        &in_shape
+                            .iter()
+                            .enumerate()
+                            .map(|(i, dim_len)| if *dim_len == 1 {
+                                "0".to_string()
+                            } else {
+                                format!("i{}", i)
+                            })
+                            .collect::<Vec<_>>(),
+                        &in_shape
+                    ),
+                )
+                .as_str(),
+            );
 
-int main() {{
-  transpose(out, a);
+            for _ in out_shape.iter() {
+                code.push_str("\n}");
+            }
 
-  for (int i = 0; i < {}; i++) {{
-    assert(((float*)a_t)[i] == ((float*)out)[i]);
-  }}
-}}
-",
-            c_assignment_string("", "a", DType::Fp32, &input.view()),
-            c_assignment_string("", "a_t", DType::Fp32, &input_transposed.view()),
-            c_assignment_string(
-                "",
-                "out",
-                DType::Fp32,
-                &ndarray::ArrayD::<f32>::zeros(input_transposed.shape()).view()
-            ),
-            code,
-            shape.iter().product::<usize>()
-        );
+            out_var_name
+        }
+        &Language::AccessPair([a0_id, a1_id])
+        | &Language::AccessCartesianProduct([a0_id, a1_id])
+        | &Language::CartesianProduct([a0_id, a1_id]) => {
+            let out_shape = data_shape(expr, id);
+
+            // An access pair pairs up corresponding items, while a cartesian
+            // product pairs up every item of a0 with every item of a1.
+            let (a0_num_items, a1_num_items, item_size) = match &expr[id].nodes[0] {
+                Language::CartesianProduct(_) => {
+                    // The items of the legacy cartesian product are the
+                    // vectors along the last axis of each tensor.
+                    let (a0_shape, a1_shape) = (data_shape(expr, a0_id), data_shape(expr, a1_id));
+                    let item_size = a0_shape[a0_shape.len() - 1];
+                    (
+                        a0_shape.iter().product::<usize>() / item_size,
+                        a1_shape.iter().product::<usize>() / item_size,
+                        item_size,
+                    )
+                }
+                node => {
+                    let (a0, a1) = match (&expr[a0_id].data, &expr[a1_id].data) {
+                        (MyAnalysisData::AccessPattern(a0), MyAnalysisData::AccessPattern(a1)) => {
+                            (a0, a1)
+                        }
+                        _ => panic!(),
+                    };
+                    (
+                        a0.shape.slice().iter().product::<usize>(),
+                        match node {
+                            Language::AccessPair(_) => 1,
+                            _ => a1.shape.slice().iter().product::<usize>(),
+                        },
+                        a0.item_shape.slice().iter().product::<usize>(),
+                    )
+                }
+            };
 
-        let main_c_filepath = std::env::temp_dir().with_file_name(format!(
-            "transpose-test-{}.c",
-            std::time::SystemTime::now().elapsed().unwrap().as_nanos()
-        ));
+            let a0_var_name =
+                codegen_recursive_helper(expr, a0_id, top_level_id, allocations, code, hw_map);
+            let a1_var_name =
+                codegen_recursive_helper(expr, a1_id, top_level_id, allocations, code, hw_map);
 
-        let binary_filepath = std::env::temp_dir().with_file_name(format!(
-            "transpose-test-{}",
-            std::time::SystemTime::now().elapsed().unwrap().as_nanos()
-        ));
-        println!("{}", binary_filepath.to_string_lossy());
+            let out_var_name: String = {
+                let out = format!(
+                    "{}_eclass_{}_out",
+                    match &expr[id].nodes[0] {
+                        Language::AccessPair(_) => "access_pair",
+                        Language::AccessCartesianProduct(_) => "access_cartesian_product",
+                        _ => "cartesian_product",
+                    },
+                    id,
+                );
+                allocations.push(Allocation {
+                    name: out.to_string(),
+                    shape: out_shape.to_vec(),
+                    dtype: MyAnalysis::get_dtype(id, expr),
+                });
+                out
+            };
 
-        File::create(&main_c_filepath)
-            .unwrap()
-            .write_all(main_code.as_bytes())
-            .unwrap();
+            code.push_str(
+                format!(
+                    "
+for (int i0 = 0; i0 < {a0_num_items}; i0++) {{
+  for (int i1 = 0; i1 < {a1_num_items}; i1++) {{
+    for (int j = 0; j < {item_size}; j++) {{
+      (({c_type}*){out})[((i0*{a1_num_items} + i1)*2 + 0)*{item_size} + j] = (({c_type}*){a0})[{a0_index}];
+      (({c_type}*){out})[((i0*{a1_num_items} + i1)*2 + 1)*{item_size} + j] = (({c_type}*){a1})[{a1_index}];
+    }}
+  }}
+}}",
+                    a0_num_items = a0_num_items,
+                    a1_num_items = a1_num_items,
+                    item_size = item_size,
+                    c_type = MyAnalysis::get_dtype(id, expr).to_c_type_string(),
+                    out = out_var_name,
+                    a0 = a0_var_name,
+                    a1 = a1_var_name,
+                    a0_index = format!("i0*{} + j", item_size),
+                    a1_index = match &expr[id].nodes[0] {
+                        Language::AccessPair(_) => format!("i0*{} + j", item_size),
+                        _ => format!("i1*{} + j", item_size),
+                    },
+                )
+                .as_str(),
+            );
 
-        let result = Command::new("gcc")
-            .arg("-Werror")
-            .arg("-g")
-            .arg("-o")
-            .arg(&binary_filepath)
-            .arg(&main_c_filepath)
-            .output()
-            .unwrap();
+            out_var_name
+        }
+        &Language::MoveAxis([tensor_id, src_axis_id, dest_axis_id]) => {
+            let original_shape = data_shape(expr, tensor_id);
+            let src_axis = MyAnalysis::get_usize(src_axis_id, expr);
+            let dest_axis = MyAnalysis::get_usize(dest_axis_id, expr);
+            let new_shape = data_shape(expr, id);
+
+            let tensor_var_name =
+                codegen_recursive_helper(expr, tensor_id, top_level_id, allocations, code, hw_map);
+            let out_var_name: String = {
+                let out = format!("move_axis_eclass_{}_out", id);
+                allocations.push(Allocation {
+                    name: out.to_string(),
+                    shape: new_shape.to_vec(),
+                    dtype: MyAnalysis::get_dtype(id, expr),
+                });
+                out
+            };
 
-        assert!(
-            result.status.success(),
-            "{}",
-            std::str::from_utf8(result.stderr.as_slice())
-                .expect("Could not convert stderr to UTF8")
-        );
+            // Create a for loop for every dimension in the input.
+            for (i, dim_len) in original_shape.iter().enumerate() {
+                code.push_str(
+                    format!(
+                        "
+for (int i{i} = 0; i{i} < {limit}; i{i}++) {{",
+                        i = i,
+                        limit = dim_len,
+                    )
+                    .as_str(),
+                );
+            }
 
-        let result = Command::new(&binary_filepath).output().unwrap();
+            // Despite its name, move-axis swaps the two axes; see the
+            // analysis.
+            code.push_str(
+                format!(
+                    "
+{out_name}{out_index} = {in_name}{in_index};
+",
+                    out_name = out_var_name,
+                    out_index = (0..new_shape.len())
+                        .map(|i| format!(
+                            "[i{}]",
+                            if i == src_axis {
+                                dest_axis
+                            } else if i == dest_axis {
+                                src_axis
+                            } else {
+                                i
+                            }
+                        ))
+                        .collect::<String>(),
+                    in_name = tensor_var_name,
+                    in_index = (0..original_shape.len())
+                        .map(|i| format!("[i{}]", i))
+                        .collect::<String>(),
+                )
+                .as_str(),
+            );
 
-        assert!(
-            result.status.success(),
-            "{}",
-            std::str::from_utf8(result.stderr.as_slice())
-                .expect("Could not convert stderr to UTF8")
-        );
+            // Close each for loop
+            for _ in original_shape.iter() {
+                code.push_str("}");
+            }
+
+            out_var_name
+        }
+        &Language::MapDotProduct(tensor_id) => {
+            let shape = data_shape(expr, tensor_id);
+            let vector_len = shape[shape.len() - 1];
+            let num_pairs = shape.iter().product::<usize>() / (2 * vector_len);
+            let dtype = MyAnalysis::get_dtype(id, expr);
+
+            let in_var_name =
+                codegen_recursive_helper(expr, tensor_id, top_level_id, allocations, code, hw_map);
+            let out_var_name: String = {
+                let out = format!("map_dot_product_eclass_{}_out", id);
+                allocations.push(Allocation {
+                    name: out.to_string(),
+                    shape: data_shape(expr, id),
+                    dtype,
+                });
+                out
+            };
+
+            code.push_str(
+                format!(
+                    "
+for (int i = 0; i < {num_pairs}; i++) {{
+  {acc_t} sum = 0;
+  for (int k = 0; k < {vector_len}; k++) {{
+    sum = sum + {read_0} * {read_1};
+  }}
+  {write}
+}}",
+                    num_pairs = num_pairs,
+                    vector_len = vector_len,
+                    acc_t = dtype.accumulator_dtype().to_c_type_string(),
+                    read_0 = c_read_string(
+                        in_var_name.as_str(),
+                        format!("(i*2 + 0)*{} + k", vector_len).as_str(),
+                        dtype
+                    ),
+                    read_1 = c_read_string(
+                        in_var_name.as_str(),
+                        format!("(i*2 + 1)*{} + k", vector_len).as_str(),
+                        dtype
+                    ),
+                    write = c_write_string(out_var_name.as_str(), "i", "sum", dtype),
+                )
+                .as_str(),
+            );
+
+            out_var_name
+        }
+        &Language::ElementwiseAdd([t0_id, t1_id]) => {
+            let shape = data_shape(expr, id);
+            assert_eq!(shape, data_shape(expr, t1_id));
+            let dtype = MyAnalysis::get_dtype(id, expr);
+
+            let t0_var_name =
+                codegen_recursive_helper(expr, t0_id, top_level_id, allocations, code, hw_map);
+            let t1_var_name =
+                codegen_recursive_helper(expr, t1_id, top_level_id, allocations, code, hw_map);
+            let out_var_name: String = {
+                let out = format!("elementwise_add_eclass_{}_out", id);
+                allocations.push(Allocation {
+                    name: out.to_string(),
+                    shape: shape.to_vec(),
+                    dtype,
+                });
+                out
+            };
+
+            code.push_str(
+                format!(
+                    "
+for (int i = 0; i < {limit}; i++) {{
+  {write}
+}}",
+                    limit = shape.iter().product::<usize>(),
+                    write = c_write_string(
+                        out_var_name.as_str(),
+                        "i",
+                        format!(
+                            "{} + {}",
+                            c_read_string(t0_var_name.as_str(), "i", dtype),
+                            c_read_string(t1_var_name.as_str(), "i", dtype)
+                        )
+                        .as_str(),
+                        dtype
+                    ),
+                )
+                .as_str(),
+            );
+
+            out_var_name
+        }
+        // There's no C interface to the BSG systolic array, so it's run in
+        // software.
+        &Language::BsgSystolicArray([rows_id, cols_id, t0_id, t1_id]) => {
+            let rows = MyAnalysis::get_usize(rows_id, expr);
+            let cols = MyAnalysis::get_usize(cols_id, expr);
+            let t0_shape = data_shape(expr, t0_id);
+            assert_eq!(t0_shape[t0_shape.len() - 1], rows);
+            assert_eq!(data_shape(expr, t1_id), vec![rows, cols]);
+            let batch = t0_shape.iter().product::<usize>() / rows;
+            let dtype = MyAnalysis::get_dtype(id, expr);
+
+            let t0_var_name =
+                codegen_recursive_helper(expr, t0_id, top_level_id, allocations, code, hw_map);
+            let t1_var_name =
+                codegen_recursive_helper(expr, t1_id, top_level_id, allocations, code, hw_map);
+            let out_var_name: String = {
+                let out = format!("bsg_systolic_array_eclass_{}_out", id);
+                allocations.push(Allocation {
+                    name: out.to_string(),
+                    shape: data_shape(expr, id),
+                    dtype,
+                });
+                out
+            };
+
+            code.push_str(
+                format!(
+                    "
+for (int i = 0; i < {batch}; i++) {{
+  for (int j = 0; j < {cols}; j++) {{
+    {acc_t} sum = 0;
+    for (int k = 0; k < {rows}; k++) {{
+      sum = sum + {read_0} * {read_1};
+    }}
+    {write}
+  }}
+}}",
+                    batch = batch,
+                    rows = rows,
+                    cols = cols,
+                    acc_t = dtype.accumulator_dtype().to_c_type_string(),
+                    read_0 = c_read_string(
+                        t0_var_name.as_str(),
+                        format!("i*{} + k", rows).as_str(),
+                        dtype
+                    ),
+                    read_1 = c_read_string(
+                        t1_var_name.as_str(),
+                        format!("k*{} + j", cols).as_str(),
+                        dtype
+                    ),
+                    write = c_write_string(
+                        out_var_name.as_str(),
+                        format!("i*{} + j", cols).as_str(),
+                        "sum",
+                        dtype
+                    ),
+                )
+                .as_str(),
+            );
+
+            out_var_name
+        }
+        // Software fallback for compute statements which aren't mapped to
+        // hardware. All arithmetic happens in the accumulator type of the
+        // input's dtype.
+        &Language::Compute([compute_type_id, access_id]) => {
+            let compute_type = match &expr[compute_type_id].data {
+                MyAnalysisData::ComputeType(t) => t.clone(),
+                _ => panic!(),
+            };
+            let access = match &expr[access_id].data {
+                MyAnalysisData::AccessPattern(a) => a,
+                _ => panic!(),
+            };
+            let out_shape = match &expr[id].data {
+                MyAnalysisData::AccessPattern(a) => a.as_vec(),
+                _ => panic!(),
+            };
+            let dtype = access.dtype;
+            // Usually the same as the input's dtype, but e.g. argmax produces
+            // integer indices.
+            let out_dtype = MyAnalysis::get_dtype(id, expr);
+
+            let in_var_name =
+                codegen_recursive_helper(expr, access_id, top_level_id, allocations, code, hw_map);
+
+            let out_var_name: String = {
+                let out = format!("compute_eclass_{}_out", id);
+                allocations.push(Allocation {
+                    name: out.to_string(),
+                    shape: out_shape.to_vec(),
+                    dtype: out_dtype,
+                });
+                out
+            };
+
+            let num_items = access.shape.slice().iter().product::<usize>();
+            let item_size = access.item_shape.slice().iter().product::<usize>();
+            let acc_t = dtype.accumulator_dtype().to_c_type_string();
+            let read = |index: &str| {
+                c_widening_string(
+                    format!(
+                        "(({}*){})[{}]",
+                        dtype.to_c_type_string(),
+                        in_var_name,
+                        index
+                    )
+                    .as_str(),
+                    dtype,
+                )
+            };
+            let write = |index: &str, value: &str| {
+                format!(
+                    "(({}*){})[{}] = {};",
+                    out_dtype.to_c_type_string(),
+                    out_var_name,
+                    index,
+                    c_narrowing_string(format!("({})", value).as_str(), out_dtype)
+                )
+            };
+
+            code.push_str(
+                match compute_type {
+                    ComputeType::ReLU
+                    | ComputeType::Sqrt
+                    | ComputeType::Negative
+                    | ComputeType::Sigmoid
+                    | ComputeType::Tanh
+                    | ComputeType::Exp
+                    | ComputeType::Clip { .. }
+                    | ComputeType::LeakyReLU { .. }
+                    | ComputeType::Gelu => format!(
+                        "
+for (int i = 0; i < {limit}; i++) {{
+  {acc_t} value = {read};
+  {write}
+}}",
+                        limit = num_items * item_size,
+                        acc_t = acc_t,
+                        read = read("i"),
+                        write = write(
+                            "i",
+                            match compute_type {
+                                ComputeType::ReLU => "value > 0 ? value : 0".to_string(),
+                                ComputeType::Sqrt => format!("({})sqrt((double)value)", acc_t),
+                                ComputeType::Negative => "-value".to_string(),
+                                ComputeType::Sigmoid =>
+                                    format!("({})(1 / (1 + exp(-(double)value)))", acc_t),
+                                ComputeType::Tanh => format!("({})tanh((double)value)", acc_t),
+                                ComputeType::Exp => format!("({})exp((double)value)", acc_t),
+                                ComputeType::Clip { min, max } => {
+                                    // C has no literal for infinity, and an
+                                    // infinite bound doesn't clamp anything.
+                                    let (min, max) = (min.into_inner(), max.into_inner());
+                                    assert!(
+                                        min != std::f64::INFINITY && max != std::f64::NEG_INFINITY,
+                                        "Can't clip everything to infinity"
+                                    );
+                                    let mut clamped = "value".to_string();
+                                    if max != std::f64::INFINITY {
+                                        clamped = format!(
+                                            "value > {max:?} ? ({acc_t}){max:?} : {clamped}",
+                                            max = max,
+                                            acc_t = acc_t,
+                                            clamped = clamped
+                                        );
+                                    }
+                                    if min != std::f64::NEG_INFINITY {
+                                        clamped = format!(
+                                            "value < {min:?} ? ({acc_t}){min:?} : {clamped}",
+                                            min = min,
+                                            acc_t = acc_t,
+                                            clamped = clamped
+                                        );
+                                    }
+                                    clamped
+                                }
+                                ComputeType::LeakyReLU { alpha } => format!(
+                                    "value >= 0 ? value : ({})({:?} * value)",
+                                    acc_t,
+                                    alpha.into_inner()
+                                ),
+                                ComputeType::Gelu => format!(
+                                    "({})(0.5 * (double)value * (1 + tanh({:?} * ((double)value + 0.044715 * (double)value * (double)value * (double)value))))",
+                                    acc_t,
+                                    (2.0 / std::f64::consts::PI).sqrt()
+                                ),
+                                _ => unreachable!(),
+                            }
+                            .as_str()
+                        ),
+                    ),
+                    ComputeType::ReduceSum
+                    | ComputeType::ReduceMax
+                    | ComputeType::ReduceMin
+                    | ComputeType::ReduceMean => {
+                        format!(
+                            "
+for (int i = 0; i < {num_items}; i++) {{
+  {acc_t} acc = {read_first};
+  for (int j = 1; j < {item_size}; j++) {{
+    {acc_t} value = {read};
+    {combine}
+  }}
+  {write}
+}}",
+                            num_items = num_items,
+                            item_size = item_size,
+                            acc_t = acc_t,
+                            read_first = read(format!("i*{}", item_size).as_str()),
+                            read = read(format!("i*{} + j", item_size).as_str()),
+                            combine = match compute_type {
+                                ComputeType::ReduceSum | ComputeType::ReduceMean =>
+                                    "acc = acc + value;",
+                                ComputeType::ReduceMax => "acc = value > acc ? value : acc;",
+                                ComputeType::ReduceMin => "acc = value < acc ? value : acc;",
+                                _ => unreachable!(),
+                            },
+                            write = write(
+                                "i",
+                                match compute_type {
+                                    ComputeType::ReduceMean => format!("acc / {}", item_size),
+                                    _ => "acc".to_string(),
+                                }
+                                .as_str()
+                            ),
+                        )
+                    }
+                    ComputeType::ArgMax => format!(
+                        "
+for (int i = 0; i < {num_items}; i++) {{
+  {acc_t} max = {read_first};
+  int argmax = 0;
+  for (int j = 1; j < {item_size}; j++) {{
+    {acc_t} value = {read};
+    if (value > max) {{
+      max = value;
+      argmax = j;
+    }}
+  }}
+  {write}
+}}",
+                        num_items = num_items,
+                        item_size = item_size,
+                        acc_t = acc_t,
+                        read_first = read(format!("i*{}", item_size).as_str()),
+                        read = read(format!("i*{} + j", item_size).as_str()),
+                        write = write("i", "argmax"),
+                    ),
+                    ComputeType::ElementwiseAdd
+                    | ComputeType::ElementwiseMul
+                    | ComputeType::ElementwiseDiv
+                    | ComputeType::DotProduct => {
+                        // Items are of shape [num_operands, ...]; we combine
+                        // the num_operands tensors elementwise.
+                        assert!(access.item_shape.ndim() >= 1);
+                        let num_operands = access.item_shape[0];
+                        let operand_size = item_size / num_operands;
+                        let op = match compute_type {
+                            ComputeType::ElementwiseAdd => "+",
+                            ComputeType::ElementwiseDiv => "/",
+                            ComputeType::ElementwiseMul | ComputeType::DotProduct => "*",
+                            _ => unreachable!(),
+                        };
+                        let combine_operands = format!(
+                            "
+    {acc_t} acc = {read_first};
+    for (int j = 1; j < {num_operands}; j++) {{
+      acc = acc {op} {read};
+    }}",
+                            acc_t = acc_t,
+                            num_operands = num_operands,
+                            op = op,
+                            read_first = read(format!("i*{} + k", item_size).as_str()),
+                            read =
+                                read(format!("i*{} + j*{} + k", item_size, operand_size).as_str()),
+                        );
+                        match compute_type {
+                            // A dot product then sums the products.
+                            ComputeType::DotProduct => format!(
+                                "
+for (int i = 0; i < {num_items}; i++) {{
+  {acc_t} sum = 0;
+  for (int k = 0; k < {operand_size}; k++) {{{combine_operands}
+    sum = sum + acc;
+  }}
+  {write}
+}}",
+                                num_items = num_items,
+                                acc_t = acc_t,
+                                operand_size = operand_size,
+                                combine_operands = combine_operands,
+                                write = write("i", "sum"),
+                            ),
+                            _ => format!(
+                                "
+for (int i = 0; i < {num_items}; i++) {{
+  for (int k = 0; k < {operand_size}; k++) {{{combine_operands}
+    {write}
+  }}
+}}",
+                                num_items = num_items,
+                                operand_size = operand_size,
+                                combine_operands = combine_operands,
+                                write = write(format!("i*{} + k", operand_size).as_str(), "acc"),
+                            ),
+                        }
+                    }
+                    ComputeType::Softmax => {
+                        assert_eq!(
+                            access.item_shape.ndim(),
+                            1,
+                            "Softmax is only implemented for axis=-1"
+                        );
+                        // Subtract the max before exponentiating, for
+                        // numerical stability.
+                        format!(
+                            "
+for (int i = 0; i < {num_items}; i++) {{
+  {acc_t} max = {read_first};
+  for (int j = 1; j < {item_size}; j++) {{
+    {acc_t} value = {read};
+    max = value > max ? value : max;
+  }}
+  {acc_t} sum = 0;
+  for (int j = 0; j < {item_size}; j++) {{
+    sum = sum + ({acc_t})exp((double)({read} - max));
+  }}
+  for (int j = 0; j < {item_size}; j++) {{
+    {acc_t} value = ({acc_t})exp((double)({read} - max)) / sum;
+    {write}
+  }}
+}}",
+                            num_items = num_items,
+                            item_size = item_size,
+                            acc_t = acc_t,
+                            read_first = read(format!("i*{}", item_size).as_str()),
+                            read = read(format!("i*{} + j", item_size).as_str()),
+                            write = write(format!("i*{} + j", item_size).as_str(), "value"),
+                        )
+                    }
+                }
+                .as_str(),
+            );
+
+            out_var_name
+        }
+        &Language::Literal(_)
+        | &Language::NotNanFloat64(_)
+        | Language::List(_)
+        | Language::PadType(_)
+        | &Language::DataType(_)
+        | Language::ComputeType(_)
+        | &Language::Clip(_)
+        | &Language::LeakyReLU(_)
+        | Language::Shape(_)
+        | &Language::SliceShape(_)
+        | &Language::ShapeInsertAxis(_)
+        | &Language::ShapeRemoveAxis(_)
+        | &Language::ShapeOf(_)
+        | &Language::AccessShape(_) => panic!("{:#?} not implemented", expr[id].nodes[0]),
     }
+}
 
-    #[test]
-    fn concat() {
-        let shape0 = vec![2, 10, 50, 3];
-        let shape1 = vec![2, 3, 50, 3];
-        let concat_axis = 1;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egg::RecExpr;
+    use ndarray::{SliceInfo, SliceOrIndex};
+    use std::fs::File;
+    use std::io::Write;
+    use std::iter::FromIterator;
+    use std::path::PathBuf;
+    use std::process::Command;
+    use std::str::FromStr;
 
-        let input0 = ndarray::ArrayD::from_shape_vec(
-            shape0.clone(),
-            (0..shape0.iter().product::<usize>()).collect(),
-        )
-        .unwrap();
-        let input1 = ndarray::ArrayD::from_shape_vec(
-            shape1.clone(),
-            (0..shape1.iter().product::<usize>()).collect(),
+    #[test]
+    fn transpose() {
+        let shape = vec![1, 20, 300, 3];
+        let permutation = vec![3, 1, 0, 2];
+        let input = ndarray::ArrayD::from_shape_vec(
+            shape.clone(),
+            (0..shape.iter().product::<usize>()).collect(),
         )
         .unwrap();
-        let concatted =
-            ndarray::stack(ndarray::Axis(concat_axis), &[input0.view(), input1.view()]).unwrap();
+        let input_transposed = input.clone().permuted_axes(permutation.clone());
 
         let expr = RecExpr::from_str(
             format!(
                 "
-(access-concatenate (access-tensor t0) (access-tensor t1) {})",
-                concat_axis
+(access-transpose (access-tensor t) (list {}))",
+                permutation.iter().map(|x| x.to_string()).join(" ")
             )
             .as_str(),
         )
         .unwrap();
 
         let mut map = HashMap::default();
-        map.insert("t0".to_string(), shape0.clone());
-        map.insert("t1".to_string(), shape1.clone());
+        map.insert("t".to_string(), shape.clone());
 
-        let mut egraph = EGraph::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&expr);
 
         let code = codegen(
             &egraph,
             id,
             &HashMap::default(),
-            "concatenate",
+            "transpose",
             "",
-            &vec!["t0", "t1"],
+            &vec!["t"],
         );
 
         let main_code = format!(
@@ -1572,37 +2646,34 @@ int main() {{
 {}
 {}
 {}
-{}
 
 int main() {{
-  concatenate(out, t0, t1);
+  transpose(out, a);
 
   for (int i = 0; i < {}; i++) {{
     assert(((float*)a_t)[i] == ((float*)out)[i]);
   }}
 }}
 ",
-            c_assignment_string("", "t0", DType::Fp32, &input0.view()),
-            c_assignment_string("", "t1", DType::Fp32, &input1.view()),
-            c_assignment_string("", "a_t", DType::Fp32, &concatted.view()),
+            c_assignment_string("", "a", DType::Fp32, &input.view()),
+            c_assignment_string("", "a_t", DType::Fp32, &input_transposed.view()),
             c_assignment_string(
                 "",
                 "out",
                 DType::Fp32,
-                &ndarray::ArrayD::<f32>::zeros(concatted.shape()).view()
+                &ndarray::ArrayD::<f32>::zeros(input_transposed.shape()).view()
             ),
             code,
-            concatted.shape().iter().product::<usize>()
+            shape.iter().product::<usize>()
         );
 
         let main_c_filepath = std::env::temp_dir().with_file_name(format!(
-            "concatenate-test-{}.c",
+            "transpose-test-{}.c",
             std::time::SystemTime::now().elapsed().unwrap().as_nanos()
         ));
-        println!("{}", main_c_filepath.to_string_lossy());
 
         let binary_filepath = std::env::temp_dir().with_file_name(format!(
-            "concatenate-test-{}",
+            "transpose-test-{}",
             std::time::SystemTime::now().elapsed().unwrap().as_nanos()
         ));
         println!("{}", binary_filepath.to_string_lossy());
@@ -1639,32 +2710,31 @@ int main() {{
     }
 
     #[test]
-    fn systolic_array() {
-        let shape0 = vec![2, 10];
-        let shape1 = vec![10, 15];
+    fn concat() {
+        let shape0 = vec![2, 10, 50, 3];
+        let shape1 = vec![2, 3, 50, 3];
+        let concat_axis = 1;
 
         let input0 = ndarray::ArrayD::from_shape_vec(
             shape0.clone(),
             (0..shape0.iter().product::<usize>()).collect(),
         )
-        .unwrap()
-        .into_dimensionality::<ndarray::Ix2>()
         .unwrap();
         let input1 = ndarray::ArrayD::from_shape_vec(
             shape1.clone(),
             (0..shape1.iter().product::<usize>()).collect(),
         )
-        .unwrap()
-        .into_dimensionality::<ndarray::Ix2>()
         .unwrap();
-        let multiplied = input0.dot(&input1).into_dyn();
+        let concatted =
+            ndarray::stack(ndarray::Axis(concat_axis), &[input0.view(), input1.view()]).unwrap();
 
         let expr = RecExpr::from_str(
-            "
-(systolic-array 10 15
- (access (access-tensor t0) 1)
- (access (access-tensor t1) 0)
-)",
+            format!(
+                "
+(access-concatenate (access-tensor t0) (access-tensor t1) {})",
+                concat_axis
+            )
+            .as_str(),
         )
         .unwrap();
 
@@ -1672,17 +2742,17 @@ int main() {{
         map.insert("t0".to_string(), shape0.clone());
         map.insert("t1".to_string(), shape1.clone());
 
-        let mut egraph = EGraph::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&expr);
 
-        let mut hw_map = HashMap::default();
-        hw_map.insert(id, 0);
-
         let code = codegen(
             &egraph,
             id,
-            &hw_map,
-            "systolic_array",
+            &HashMap::default(),
+            "concatenate",
             "",
             &vec!["t0", "t1"],
         );
@@ -1690,7 +2760,6 @@ int main() {{
         let main_code = format!(
             "
 #include <assert.h>
-#include \"{}\"
 
 {}
 {}
@@ -1699,46 +2768,34 @@ int main() {{
 {}
 
 int main() {{
-  systolic_array(out, t0, t1);
+  concatenate(out, t0, t1);
 
   for (int i = 0; i < {}; i++) {{
-    assert(((float*)result)[i] == ((float*)out)[i]);
+    assert(((float*)a_t)[i] == ((float*)out)[i]);
   }}
 }}
 ",
-            PathBuf::from_str(
-                format!(
-                    "{}/{}/{}/{}",
-                    env!("CARGO_MANIFEST_DIR"),
-                    "data",
-                    "codegen-mlp",
-                    "rtml_systolic_array_weight_stationary.c"
-                )
-                .as_str()
-            )
-            .unwrap()
-            .to_string_lossy(),
-            c_assignment_string("", "t0", DType::Fp32, &input0.into_dyn().view()),
-            c_assignment_string("", "t1", DType::Fp32, &input1.into_dyn().view()),
-            c_assignment_string("", "result", DType::Fp32, &multiplied.view()),
+            c_assignment_string("", "t0", DType::Fp32, &input0.view()),
+            c_assignment_string("", "t1", DType::Fp32, &input1.view()),
+            c_assignment_string("", "a_t", DType::Fp32, &concatted.view()),
             c_assignment_string(
                 "",
                 "out",
                 DType::Fp32,
-                &ndarray::ArrayD::<f32>::zeros(multiplied.shape()).view()
+                &ndarray::ArrayD::<f32>::zeros(concatted.shape()).view()
             ),
             code,
-            multiplied.shape().iter().product::<usize>()
+            concatted.shape().iter().product::<usize>()
         );
 
         let main_c_filepath = std::env::temp_dir().with_file_name(format!(
-            "systolic-array-test-{}.c",
+            "concatenate-test-{}.c",
             std::time::SystemTime::now().elapsed().unwrap().as_nanos()
         ));
         println!("{}", main_c_filepath.to_string_lossy());
 
         let binary_filepath = std::env::temp_dir().with_file_name(format!(
-            "systolic-array-test-{}",
+            "concatenate-test-{}",
             std::time::SystemTime::now().elapsed().unwrap().as_nanos()
         ));
         println!("{}", binary_filepath.to_string_lossy());
@@ -1775,90 +2832,109 @@ int main() {{
     }
 
     #[test]
-    fn pad() {
-        let shape = vec![10, 20, 3, 45];
-        let pad_axis = 2;
-        let pad_before = 2;
-        let pad_after = 3;
-        let pad_type = PadType::ZeroPadding;
-
-        let mut pad_before_shape = shape.clone();
-        pad_before_shape[pad_axis] = pad_before;
-        let mut pad_after_shape = shape.clone();
-        pad_after_shape[pad_axis] = pad_after;
+    fn systolic_array() {
+        let shape0 = vec![2, 10];
+        let shape1 = vec![10, 15];
 
-        let input = ndarray::ArrayD::from_shape_vec(
-            shape.clone(),
-            (0..shape.iter().product::<usize>()).collect(),
+        let input0 = ndarray::ArrayD::from_shape_vec(
+            shape0.clone(),
+            (0..shape0.iter().product::<usize>()).collect(),
         )
+        .unwrap()
+        .into_dimensionality::<ndarray::Ix2>()
         .unwrap();
-
-        assert!(pad_type == PadType::ZeroPadding);
-
-        let padded = ndarray::stack(
-            ndarray::Axis(pad_axis),
-            &[
-                ndarray::ArrayD::zeros(pad_before_shape).view(),
-                input.view(),
-                ndarray::ArrayD::zeros(pad_after_shape).view(),
-            ],
+        let input1 = ndarray::ArrayD::from_shape_vec(
+            shape1.clone(),
+            (0..shape1.iter().product::<usize>()).collect(),
         )
+        .unwrap()
+        .into_dimensionality::<ndarray::Ix2>()
         .unwrap();
+        let multiplied = input0.dot(&input1).into_dyn();
 
         let expr = RecExpr::from_str(
-            format!(
-                "
-(access-pad (access-tensor t) {} {} {} {})",
-                pad_type, pad_axis, pad_before, pad_after
-            )
-            .as_str(),
+            "
+(systolic-array 10 15
+ (access (access-tensor t0) 1)
+ (access (access-tensor t1) 0)
+)",
         )
         .unwrap();
 
         let mut map = HashMap::default();
-        map.insert("t".to_string(), shape.clone());
+        map.insert("t0".to_string(), shape0.clone());
+        map.insert("t1".to_string(), shape1.clone());
 
-        let mut egraph = EGraph::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&expr);
 
-        let code = codegen(&egraph, id, &HashMap::default(), "pad", "", &vec!["t"]);
+        let mut hw_map = HashMap::default();
+        hw_map.insert(id, 0);
+
+        let code = codegen(
+            &egraph,
+            id,
+            &hw_map,
+            "systolic_array",
+            "",
+            &vec!["t0", "t1"],
+        );
 
         let main_code = format!(
             "
 #include <assert.h>
+#include \"{}\"
 
 {}
 {}
 {}
 {}
+{}
 
 int main() {{
-  pad(out, a);
+  systolic_array(out, t0, t1);
 
   for (int i = 0; i < {}; i++) {{
-    assert(((float*)a_pad)[i] == ((float*)out)[i]);
+    assert(((float*)result)[i] == ((float*)out)[i]);
   }}
 }}
 ",
-            c_assignment_string("", "a", DType::Fp32, &input.view()),
-            c_assignment_string("", "a_pad", DType::Fp32, &padded.view()),
+            PathBuf::from_str(
+                format!(
+                    "{}/{}/{}/{}",
+                    env!("CARGO_MANIFEST_DIR"),
+                    "data",
+                    "codegen-mlp",
+                    "rtml_systolic_array_weight_stationary.c"
+                )
+                .as_str()
+            )
+            .unwrap()
+            .to_string_lossy(),
+            c_assignment_string("", "t0", DType::Fp32, &input0.into_dyn().view()),
+            c_assignment_string("", "t1", DType::Fp32, &input1.into_dyn().view()),
+            c_assignment_string("", "result", DType::Fp32, &multiplied.view()),
             c_assignment_string(
                 "",
                 "out",
                 DType::Fp32,
-                &ndarray::ArrayD::<f32>::zeros(padded.shape()).view()
+                &ndarray::ArrayD::<f32>::zeros(multiplied.shape()).view()
             ),
             code,
-            shape.iter().product::<usize>()
+            multiplied.shape().iter().product::<usize>()
         );
 
         let main_c_filepath = std::env::temp_dir().with_file_name(format!(
-            "pad-test-{}.c",
+            "systolic-array-test-{}.c",
             std::time::SystemTime::now().elapsed().unwrap().as_nanos()
         ));
+        println!("{}", main_c_filepath.to_string_lossy());
 
         let binary_filepath = std::env::temp_dir().with_file_name(format!(
-            "pad-test-{}",
+            "systolic-array-test-{}",
             std::time::SystemTime::now().elapsed().unwrap().as_nanos()
         ));
         println!("{}", binary_filepath.to_string_lossy());
@@ -1895,91 +2971,120 @@ int main() {{
     }
 
     #[test]
-    fn slice() {
-        let shape = vec![32, 7, 100, 3];
-        let slice_axis = 2;
-        let low = 5;
-        let high = 83;
+    fn systolic_array_int8() {
+        let shape0 = vec![2, 10];
+        let shape1 = vec![10, 15];
 
-        let input = ndarray::ArrayD::from_shape_vec(
-            shape.clone(),
-            (0..shape.iter().product::<usize>()).collect(),
+        // Keep values small so that the results fit in an int8.
+        let input0 = ndarray::ArrayD::from_shape_vec(
+            shape0.clone(),
+            (0..shape0.iter().product::<usize>())
+                .map(|i| (i % 3) as i32)
+                .collect(),
         )
+        .unwrap()
+        .into_dimensionality::<ndarray::Ix2>()
         .unwrap();
-
-        let mut slices = Vec::from_iter(
-            std::iter::repeat(SliceOrIndex::Slice {
-                start: 0,
-                end: None,
-                step: 1,
-            })
-            .take(shape.len()),
-        );
-        slices[slice_axis] = SliceOrIndex::Slice {
-            start: low,
-            end: Some(high),
-            step: 1,
-        };
-        let sliced = input.slice(
-            &SliceInfo::<std::vec::Vec<ndarray::SliceOrIndex>, ndarray::IxDyn>::new(slices)
-                .unwrap()
-                .as_ref(),
-        );
+        let input1 = ndarray::ArrayD::from_shape_vec(
+            shape1.clone(),
+            (0..shape1.iter().product::<usize>())
+                .map(|i| (i % 3) as i32 - 1)
+                .collect(),
+        )
+        .unwrap()
+        .into_dimensionality::<ndarray::Ix2>()
+        .unwrap();
+        let multiplied = input0.dot(&input1).into_dyn();
 
         let expr = RecExpr::from_str(
-            format!(
-                "
-(access-slice (access-tensor t) {} {} {})",
-                slice_axis, low, high
-            )
-            .as_str(),
+            "
+(systolic-array 10 15
+ (access (access-tensor t0) 1)
+ (access (access-tensor t1) 0)
+)",
         )
         .unwrap();
 
         let mut map = HashMap::default();
-        map.insert("t".to_string(), shape.clone());
-
-        let mut egraph = EGraph::new(MyAnalysis { name_to_shape: map });
+        map.insert("t0".to_string(), shape0.clone());
+        map.insert("t1".to_string(), shape1.clone());
+        let mut dtypes = HashMap::default();
+        dtypes.insert("t0".to_string(), DType::Int8);
+        dtypes.insert("t1".to_string(), DType::Int8);
+
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: dtypes,
+        });
         let id = egraph.add_expr(&expr);
 
-        let code = codegen(&egraph, id, &HashMap::default(), "slice", "", &vec!["t"]);
+        let mut hw_map = HashMap::default();
+        hw_map.insert(id, 0);
+
+        let code = codegen(
+            &egraph,
+            id,
+            &hw_map,
+            "systolic_array",
+            "",
+            &vec!["t0", "t1"],
+        );
+
+        assert!(code.contains("rtml_systolic_array_weight_stationary_int8("));
+        assert!(code.contains("int32_t"));
 
         let main_code = format!(
             "
 #include <assert.h>
+#include \"{}\"
 
 {}
 {}
 {}
 {}
+{}
 
 int main() {{
-  slice(out, a);
+  systolic_array(out, t0, t1);
 
   for (int i = 0; i < {}; i++) {{
-    assert(((float*)a_sliced)[i] == ((float*)out)[i]);
+    assert(((int8_t*)result)[i] == ((int8_t*)out)[i]);
   }}
 }}
 ",
-            c_assignment_string("", "a", DType::Fp32, &input.view()),
-            c_assignment_string("", "a_sliced", DType::Fp32, &sliced.view()),
+            PathBuf::from_str(
+                format!(
+                    "{}/{}/{}/{}",
+                    env!("CARGO_MANIFEST_DIR"),
+                    "data",
+                    "codegen-mlp",
+                    "rtml_systolic_array_weight_stationary.c"
+                )
+                .as_str()
+            )
+            .unwrap()
+            .to_string_lossy(),
+            c_assignment_string("", "t0", DType::Int8, &input0.into_dyn().view()),
+            c_assignment_string("", "t1", DType::Int8, &input1.into_dyn().view()),
+            c_assignment_string("", "result", DType::Int8, &multiplied.view()),
             c_assignment_string(
                 "",
                 "out",
-                DType::Fp32,
-                &ndarray::ArrayD::<f32>::zeros(sliced.shape()).view()
+                DType::Int8,
+                &ndarray::ArrayD::<i32>::zeros(multiplied.shape()).view()
             ),
             code,
-            sliced.shape().iter().product::<usize>()
+            multiplied.shape().iter().product::<usize>()
         );
 
         let main_c_filepath = std::env::temp_dir().with_file_name(format!(
-            "slice-test-{}.c",
+            "systolic-array-int8-test-{}.c",
             std::time::SystemTime::now().elapsed().unwrap().as_nanos()
         ));
+        println!("{}", main_c_filepath.to_string_lossy());
 
         let binary_filepath = std::env::temp_dir().with_file_name(format!(
-            "slice-test-{}",
+            "systolic-array-int8-test-{}",
             std::time::SystemTime::now().elapsed().unwrap().as_nanos()
         ));
         println!("{}", binary_filepath.to_string_lossy());
@@ -2016,44 +3121,37 @@ int main() {{
     }
 
     #[test]
-    fn access_windows() {
-        let shape = vec![3, 50, 27, 4];
-        let filters_shape = vec![2, 9, 22, 3];
-        let stride = vec![1, 10, 3, 1];
+    fn access_literal() {
+        let shape = vec![2, 3];
 
         let input = ndarray::ArrayD::from_shape_vec(
             shape.clone(),
-            (0i64..shape.iter().map(|v| *v as i64).product::<i64>()).collect(),
+            (0..shape.iter().product::<usize>())
+                .map(|i| i as f64)
+                .collect(),
         )
         .unwrap();
 
         let expr = RecExpr::from_str(
-            format!(
-                "
-(access-windows
- (access (access-tensor t) {access_axis})
- (shape {filter_shapes})
- (shape {strides})
+            "
+(compute elementwise-mul
+ (access-pair
+  (access (access-tensor t) 2)
+  (access
+   (access-broadcast
+    (access-insert-axis
+     (access-insert-axis (access-literal (literal 0.5) float32) 0)
+     0
+    )
+    (access-shape (shape 2 3) (shape))
+   )
+   2
+  )
+ )
 )",
-                access_axis = shape.len(),
-                filter_shapes = filters_shape
-                    .iter()
-                    .map(|i| i.to_string())
-                    .collect::<Vec<_>>()
-                    .join(" "),
-                strides = stride
-                    .iter()
-                    .map(|i| i.to_string())
-                    .collect::<Vec<_>>()
-                    .join(" "),
-            )
-            .as_str(),
         )
         .unwrap();
 
-        let mut map = HashMap::default();
-        map.insert("t".to_string(), shape.clone());
-
         let mut env = HashMap::default();
         env.insert("t", input.clone());
         let out =
@@ -2062,14 +3160,19 @@ int main() {{
                 _ => panic!(),
             };
 
-        let mut egraph = EGraph::new(MyAnalysis { name_to_shape: map });
+        let mut map = HashMap::default();
+        map.insert("t".to_string(), shape.clone());
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&expr);
 
         let code = codegen(
             &egraph,
             id,
             &HashMap::default(),
-            "access_windows",
+            "access_literal",
             "",
             &vec!["t"],
         );
@@ -2084,15 +3187,15 @@ int main() {{
 {}
 
 int main() {{
-  access_windows(out, a);
+  access_literal(out, a);
 
   for (int i = 0; i < {}; i++) {{
-    assert(((float*)a_windows)[i] == ((float*)out)[i]);
+    assert(((float*)expected)[i] == ((float*)out)[i]);
   }}
 }}
 ",
             c_assignment_string("", "a", DType::Fp32, &input.view()),
-            c_assignment_string("", "a_windows", DType::Fp32, &out.tensor.view()),
+            c_assignment_string("", "expected", DType::Fp32, &out.tensor.view()),
             c_assignment_string(
                 "",
                 "out",
@@ -2100,16 +3203,16 @@ int main() {{
                 &ndarray::ArrayD::<f32>::zeros(out.tensor.shape()).view()
             ),
             code,
-            out.tensor.shape().iter().product::<usize>()
+            out.tensor.len()
         );
 
         let main_c_filepath = std::env::temp_dir().with_file_name(format!(
-            "access-windows-test-{}.c",
+            "access-literal-test-{}.c",
             std::time::SystemTime::now().elapsed().unwrap().as_nanos()
         ));
 
         let binary_filepath = std::env::temp_dir().with_file_name(format!(
-            "access-windows-test-{}",
+            "access-literal-test-{}",
             std::time::SystemTime::now().elapsed().unwrap().as_nanos()
         ));
         println!("{}", binary_filepath.to_string_lossy());
@@ -2146,23 +3249,41 @@ int main() {{
     }
 
     #[test]
-    fn access_flatten() {
-        let shape = vec![3, 50, 27, 4];
-        let access_axis = 2;
+    fn pad_min_int8() {
+        let shape = vec![3, 4, 5];
+        let pad_axis = 1;
+        let pad_before = 2;
+        let pad_after = 1;
+        let pad_type = PadType::MinPadding;
+
+        let mut pad_before_shape = shape.clone();
+        pad_before_shape[pad_axis] = pad_before;
+        let mut pad_after_shape = shape.clone();
+        pad_after_shape[pad_axis] = pad_after;
 
         let input = ndarray::ArrayD::from_shape_vec(
             shape.clone(),
-            (0..shape.iter().product::<usize>()).collect(),
+            (0..shape.iter().product::<usize>())
+                .map(|i| i as i8 - 30)
+                .collect(),
+        )
+        .unwrap();
+
+        let padded = ndarray::stack(
+            ndarray::Axis(pad_axis),
+            &[
+                ndarray::ArrayD::from_elem(pad_before_shape, i8::MIN).view(),
+                input.view(),
+                ndarray::ArrayD::from_elem(pad_after_shape, i8::MIN).view(),
+            ],
         )
         .unwrap();
 
         let expr = RecExpr::from_str(
             format!(
                 "
-(access-flatten
- (access (access-tensor t) {access_axis})
-)",
-                access_axis = access_axis
+(access-pad (access-tensor t) {} {} {} {})",
+                pad_type, pad_axis, pad_before, pad_after
             )
             .as_str(),
         )
@@ -2170,27 +3291,16 @@ int main() {{
 
         let mut map = HashMap::default();
         map.insert("t".to_string(), shape.clone());
+        let mut dtypes = HashMap::default();
+        dtypes.insert("t".to_string(), DType::Int8);
 
-        let out = input
-            .view()
-            .into_shape((
-                shape[0..access_axis].iter().product::<usize>(),
-                shape[access_axis..].iter().product::<usize>(),
-            ))
-            .unwrap()
-            .into_dyn();
-
-        let mut egraph = EGraph::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: dtypes,
+        });
         let id = egraph.add_expr(&expr);
 
-        let code = codegen(
-            &egraph,
-            id,
-            &HashMap::default(),
-            "access_flatten",
-            "",
-            &vec!["t"],
-        );
+        let code = codegen(&egraph, id, &HashMap::default(), "pad", "", &vec!["t"]);
 
         let main_code = format!(
             "
@@ -2202,32 +3312,32 @@ int main() {{
 {}
 
 int main() {{
-  access_flatten(out, a);
+  pad(out, a);
 
   for (int i = 0; i < {}; i++) {{
-    assert(((float*)a_flattened)[i] == ((float*)out)[i]);
+    assert(((int8_t*)a_pad)[i] == ((int8_t*)out)[i]);
   }}
 }}
 ",
-            c_assignment_string("", "a", DType::Fp32, &input.view()),
-            c_assignment_string("", "a_flattened", DType::Fp32, &out.view()),
+            c_assignment_string("", "a", DType::Int8, &input.view()),
+            c_assignment_string("", "a_pad", DType::Int8, &padded.view()),
             c_assignment_string(
                 "",
                 "out",
-                DType::Fp32,
-                &ndarray::ArrayD::<f32>::zeros(out.shape()).view()
+                DType::Int8,
+                &ndarray::ArrayD::<i8>::zeros(padded.shape()).view()
             ),
             code,
-            out.shape().iter().product::<usize>()
+            padded.len()
         );
 
         let main_c_filepath = std::env::temp_dir().with_file_name(format!(
-            "access-flatten-test-{}.c",
+            "pad-min-int8-test-{}.c",
             std::time::SystemTime::now().elapsed().unwrap().as_nanos()
         ));
 
         let binary_filepath = std::env::temp_dir().with_file_name(format!(
-            "access-flatten-test-{}",
+            "pad-min-int8-test-{}",
             std::time::SystemTime::now().elapsed().unwrap().as_nanos()
         ));
         println!("{}", binary_filepath.to_string_lossy());
@@ -2264,142 +3374,93 @@ int main() {{
     }
 
     #[test]
-    #[should_panic]
-    fn extract_monolithic_panic() {
-        let expr = RecExpr::from_str(
-            "
-(systolic-array 32 32
- (access
-  (systolic-array 64 32
-   (access (access-tensor t0) 1)
-   (access (access-tensor t1) 0)
-  )
-  1
- )
- (access
-  (systolic-array 32 32
-   (access (access-tensor t2) 1)
-   (access (access-tensor t3) 0)
-  )
-  0
- )
-)",
-        )
-        .unwrap();
-
-        let mut map = HashMap::default();
-        map.insert("t0".to_string(), vec![32, 64]);
-        map.insert("t1".to_string(), vec![64, 32]);
-        map.insert("t2".to_string(), vec![32, 32]);
-        map.insert("t3".to_string(), vec![32, 32]);
-
-        let mut egraph = EGraph::new(MyAnalysis { name_to_shape: map });
-        egraph.add_expr(&expr);
-
-        let (_hw_map, _hw_design) = create_hardware_design_monolithic(&egraph, (32, 32));
-    }
+    fn pad() {
+        let shape = vec![10, 20, 3, 45];
+        let pad_axis = 2;
+        let pad_before = 2;
+        let pad_after = 3;
+        let pad_type = PadType::ZeroPadding;
 
-    #[test]
-    fn systolic_array_with_blocking() {
-        let shape0 = vec![2, 10];
-        let shape1 = vec![10, 15];
+        let mut pad_before_shape = shape.clone();
+        pad_before_shape[pad_axis] = pad_before;
+        let mut pad_after_shape = shape.clone();
+        pad_after_shape[pad_axis] = pad_after;
 
-        let input0 = ndarray::ArrayD::from_shape_vec(
-            shape0.clone(),
-            (0..shape0.iter().product::<usize>()).collect(),
+        let input = ndarray::ArrayD::from_shape_vec(
+            shape.clone(),
+            (0..shape.iter().product::<usize>()).collect(),
         )
-        .unwrap()
-        .into_dimensionality::<ndarray::Ix2>()
         .unwrap();
-        let input1 = ndarray::ArrayD::from_shape_vec(
-            shape1.clone(),
-            (0..shape1.iter().product::<usize>()).collect(),
+
+        assert!(pad_type == PadType::ZeroPadding);
+
+        let padded = ndarray::stack(
+            ndarray::Axis(pad_axis),
+            &[
+                ndarray::ArrayD::zeros(pad_before_shape).view(),
+                input.view(),
+                ndarray::ArrayD::zeros(pad_after_shape).view(),
+            ],
         )
-        .unwrap()
-        .into_dimensionality::<ndarray::Ix2>()
         .unwrap();
-        let multiplied = input0.dot(&input1).into_dyn();
 
         let expr = RecExpr::from_str(
-            "
-(systolic-array-with-blocking 2 5
- (access (access-tensor t0) 1)
- (access (access-tensor t1) 0)
-)",
+            format!(
+                "
+(access-pad (access-tensor t) {} {} {} {})",
+                pad_type, pad_axis, pad_before, pad_after
+            )
+            .as_str(),
         )
         .unwrap();
 
         let mut map = HashMap::default();
-        map.insert("t0".to_string(), shape0.clone());
-        map.insert("t1".to_string(), shape1.clone());
+        map.insert("t".to_string(), shape.clone());
 
-        let mut egraph = EGraph::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&expr);
 
-        let mut hw_map = HashMap::default();
-        hw_map.insert(id, 0);
-
-        let code = codegen(
-            &egraph,
-            id,
-            &hw_map,
-            "systolic_array_with_blocking",
-            "",
-            &vec!["t0", "t1"],
-        );
+        let code = codegen(&egraph, id, &HashMap::default(), "pad", "", &vec!["t"]);
 
         let main_code = format!(
             "
 #include <assert.h>
-#include \"{}\"
 
 {}
 {}
 {}
 {}
-{}
 
 int main() {{
-  systolic_array_with_blocking(out, t0, t1);
+  pad(out, a);
 
   for (int i = 0; i < {}; i++) {{
-    assert(((float*)result)[i] == ((float*)out)[i]);
+    assert(((float*)a_pad)[i] == ((float*)out)[i]);
   }}
 }}
 ",
-            PathBuf::from_str(
-                format!(
-                    "{}/{}/{}/{}",
-                    env!("CARGO_MANIFEST_DIR"),
-                    "data",
-                    "codegen-mlp",
-                    "rtml_systolic_array_weight_stationary.c"
-                )
-                .as_str()
-            )
-            .unwrap()
-            .to_string_lossy(),
-            c_assignment_string("", "t0", DType::Fp32, &input0.into_dyn().view()),
-            c_assignment_string("", "t1", DType::Fp32, &input1.into_dyn().view()),
-            c_assignment_string("", "result", DType::Fp32, &multiplied.view()),
+            c_assignment_string("", "a", DType::Fp32, &input.view()),
+            c_assignment_string("", "a_pad", DType::Fp32, &padded.view()),
             c_assignment_string(
                 "",
                 "out",
                 DType::Fp32,
-                &ndarray::ArrayD::<f32>::zeros(multiplied.shape()).view()
+                &ndarray::ArrayD::<f32>::zeros(padded.shape()).view()
             ),
             code,
-            multiplied.shape().iter().product::<usize>()
+            shape.iter().product::<usize>()
         );
 
         let main_c_filepath = std::env::temp_dir().with_file_name(format!(
-            "systolic-array-with-blocking-test-{}.c",
+            "pad-test-{}.c",
             std::time::SystemTime::now().elapsed().unwrap().as_nanos()
         ));
-        println!("{}", main_c_filepath.to_string_lossy());
 
         let binary_filepath = std::env::temp_dir().with_file_name(format!(
-            "systolic-array-with-blocking-test-{}",
+            "pad-test-{}",
             std::time::SystemTime::now().elapsed().unwrap().as_nanos()
         ));
         println!("{}", binary_filepath.to_string_lossy());
@@ -2434,4 +3495,1436 @@ int main() {{
                 .expect("Could not convert stderr to UTF8")
         );
     }
+
+    #[test]
+    fn slice() {
+        let shape = vec![32, 7, 100, 3];
+        let slice_axis = 2;
+        let low = 5;
+        let high = 83;
+
+        let input = ndarray::ArrayD::from_shape_vec(
+            shape.clone(),
+            (0..shape.iter().product::<usize>()).collect(),
+        )
+        .unwrap();
+
+        let mut slices = Vec::from_iter(
+            std::iter::repeat(SliceOrIndex::Slice {
+                start: 0,
+                end: None,
+                step: 1,
+            })
+            .take(shape.len()),
+        );
+        slices[slice_axis] = SliceOrIndex::Slice {
+            start: low,
+            end: Some(high),
+            step: 1,
+        };
+        let sliced = input.slice(
+            &SliceInfo::<std::vec::Vec<ndarray::SliceOrIndex>, ndarray::IxDyn>::new(slices)
+                .unwrap()
+                .as_ref(),
+        );
+
+        let expr = RecExpr::from_str(
+            format!(
+                "
+(access-slice (access-tensor t) {} {} {})",
+                slice_axis, low, high
+            )
+            .as_str(),
+        )
+        .unwrap();
+
+        let mut map = HashMap::default();
+        map.insert("t".to_string(), shape.clone());
+
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&expr);
+
+        let code = codegen(&egraph, id, &HashMap::default(), "slice", "", &vec!["t"]);
+
+        let main_code = format!(
+            "
+#include <assert.h>
+
+{}
+{}
+{}
+{}
+
+int main() {{
+  slice(out, a);
+
+  for (int i = 0; i < {}; i++) {{
+    assert(((float*)a_sliced)[i] == ((float*)out)[i]);
+  }}
+}}
+",
+            c_assignment_string("", "a", DType::Fp32, &input.view()),
+            c_assignment_string("", "a_sliced", DType::Fp32, &sliced.view()),
+            c_assignment_string(
+                "",
+                "out",
+                DType::Fp32,
+                &ndarray::ArrayD::<f32>::zeros(sliced.shape()).view()
+            ),
+            code,
+            sliced.shape().iter().product::<usize>()
+        );
+
+        let main_c_filepath = std::env::temp_dir().with_file_name(format!(
+            "slice-test-{}.c",
+            std::time::SystemTime::now().elapsed().unwrap().as_nanos()
+        ));
+
+        let binary_filepath = std::env::temp_dir().with_file_name(format!(
+            "slice-test-{}",
+            std::time::SystemTime::now().elapsed().unwrap().as_nanos()
+        ));
+        println!("{}", binary_filepath.to_string_lossy());
+
+        File::create(&main_c_filepath)
+            .unwrap()
+            .write_all(main_code.as_bytes())
+            .unwrap();
+
+        let result = Command::new("gcc")
+            .arg("-Werror")
+            .arg("-g")
+            .arg("-o")
+            .arg(&binary_filepath)
+            .arg(&main_c_filepath)
+            .output()
+            .unwrap();
+
+        assert!(
+            result.status.success(),
+            "{}",
+            std::str::from_utf8(result.stderr.as_slice())
+                .expect("Could not convert stderr to UTF8")
+        );
+
+        let result = Command::new(&binary_filepath).output().unwrap();
+
+        assert!(
+            result.status.success(),
+            "{}",
+            std::str::from_utf8(result.stderr.as_slice())
+                .expect("Could not convert stderr to UTF8")
+        );
+    }
+
+    #[test]
+    fn access_windows() {
+        let shape = vec![3, 50, 27, 4];
+        let filters_shape = vec![2, 9, 22, 3];
+        let stride = vec![1, 10, 3, 1];
+
+        let input = ndarray::ArrayD::from_shape_vec(
+            shape.clone(),
+            (0i64..shape.iter().map(|v| *v as i64).product::<i64>()).collect(),
+        )
+        .unwrap();
+
+        let expr = RecExpr::from_str(
+            format!(
+                "
+(access-windows
+ (access (access-tensor t) {access_axis})
+ (shape {filter_shapes})
+ (shape {strides})
+)",
+                access_axis = shape.len(),
+                filter_shapes = filters_shape
+                    .iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                strides = stride
+                    .iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            )
+            .as_str(),
+        )
+        .unwrap();
+
+        let mut map = HashMap::default();
+        map.insert("t".to_string(), shape.clone());
+
+        let mut env = HashMap::default();
+        env.insert("t", input.clone());
+        let out =
+            match crate::language::interpreter::interpret(&expr, expr.as_ref().len() - 1, &env) {
+                crate::language::interpreter::Value::Access(a) => a,
+                _ => panic!(),
+            };
+
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&expr);
+
+        let code = codegen(
+            &egraph,
+            id,
+            &HashMap::default(),
+            "access_windows",
+            "",
+            &vec!["t"],
+        );
+
+        let main_code = format!(
+            "
+#include <assert.h>
+
+{}
+{}
+{}
+{}
+
+int main() {{
+  access_windows(out, a);
+
+  for (int i = 0; i < {}; i++) {{
+    assert(((float*)a_windows)[i] == ((float*)out)[i]);
+  }}
+}}
+",
+            c_assignment_string("", "a", DType::Fp32, &input.view()),
+            c_assignment_string("", "a_windows", DType::Fp32, &out.tensor.view()),
+            c_assignment_string(
+                "",
+                "out",
+                DType::Fp32,
+                &ndarray::ArrayD::<f32>::zeros(out.tensor.shape()).view()
+            ),
+            code,
+            out.tensor.shape().iter().product::<usize>()
+        );
+
+        let main_c_filepath = std::env::temp_dir().with_file_name(format!(
+            "access-windows-test-{}.c",
+            std::time::SystemTime::now().elapsed().unwrap().as_nanos()
+        ));
+
+        let binary_filepath = std::env::temp_dir().with_file_name(format!(
+            "access-windows-test-{}",
+            std::time::SystemTime::now().elapsed().unwrap().as_nanos()
+        ));
+        println!("{}", binary_filepath.to_string_lossy());
+
+        File::create(&main_c_filepath)
+            .unwrap()
+            .write_all(main_code.as_bytes())
+            .unwrap();
+
+        let result = Command::new("gcc")
+            .arg("-Werror")
+            .arg("-g")
+            .arg("-o")
+            .arg(&binary_filepath)
+            .arg(&main_c_filepath)
+            .output()
+            .unwrap();
+
+        assert!(
+            result.status.success(),
+            "{}",
+            std::str::from_utf8(result.stderr.as_slice())
+                .expect("Could not convert stderr to UTF8")
+        );
+
+        let result = Command::new(&binary_filepath).output().unwrap();
+
+        assert!(
+            result.status.success(),
+            "{}",
+            std::str::from_utf8(result.stderr.as_slice())
+                .expect("Could not convert stderr to UTF8")
+        );
+    }
+
+    #[test]
+    fn access_flatten() {
+        let shape = vec![3, 50, 27, 4];
+        let access_axis = 2;
+
+        let input = ndarray::ArrayD::from_shape_vec(
+            shape.clone(),
+            (0..shape.iter().product::<usize>()).collect(),
+        )
+        .unwrap();
+
+        let expr = RecExpr::from_str(
+            format!(
+                "
+(access-flatten
+ (access (access-tensor t) {access_axis})
+)",
+                access_axis = access_axis
+            )
+            .as_str(),
+        )
+        .unwrap();
+
+        let mut map = HashMap::default();
+        map.insert("t".to_string(), shape.clone());
+
+        let out = input
+            .view()
+            .into_shape((
+                shape[0..access_axis].iter().product::<usize>(),
+                shape[access_axis..].iter().product::<usize>(),
+            ))
+            .unwrap()
+            .into_dyn();
+
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&expr);
+
+        let code = codegen(
+            &egraph,
+            id,
+            &HashMap::default(),
+            "access_flatten",
+            "",
+            &vec!["t"],
+        );
+
+        let main_code = format!(
+            "
+#include <assert.h>
+
+{}
+{}
+{}
+{}
+
+int main() {{
+  access_flatten(out, a);
+
+  for (int i = 0; i < {}; i++) {{
+    assert(((float*)a_flattened)[i] == ((float*)out)[i]);
+  }}
+}}
+",
+            c_assignment_string("", "a", DType::Fp32, &input.view()),
+            c_assignment_string("", "a_flattened", DType::Fp32, &out.view()),
+            c_assignment_string(
+                "",
+                "out",
+                DType::Fp32,
+                &ndarray::ArrayD::<f32>::zeros(out.shape()).view()
+            ),
+            code,
+            out.shape().iter().product::<usize>()
+        );
+
+        let main_c_filepath = std::env::temp_dir().with_file_name(format!(
+            "access-flatten-test-{}.c",
+            std::time::SystemTime::now().elapsed().unwrap().as_nanos()
+        ));
+
+        let binary_filepath = std::env::temp_dir().with_file_name(format!(
+            "access-flatten-test-{}",
+            std::time::SystemTime::now().elapsed().unwrap().as_nanos()
+        ));
+        println!("{}", binary_filepath.to_string_lossy());
+
+        File::create(&main_c_filepath)
+            .unwrap()
+            .write_all(main_code.as_bytes())
+            .unwrap();
+
+        let result = Command::new("gcc")
+            .arg("-Werror")
+            .arg("-g")
+            .arg("-o")
+            .arg(&binary_filepath)
+            .arg(&main_c_filepath)
+            .output()
+            .unwrap();
+
+        assert!(
+            result.status.success(),
+            "{}",
+            std::str::from_utf8(result.stderr.as_slice())
+                .expect("Could not convert stderr to UTF8")
+        );
+
+        let result = Command::new(&binary_filepath).output().unwrap();
+
+        assert!(
+            result.status.success(),
+            "{}",
+            std::str::from_utf8(result.stderr.as_slice())
+                .expect("Could not convert stderr to UTF8")
+        );
+    }
+
+    #[test]
+    fn access_reshape() {
+        let shape = vec![3, 8, 5];
+
+        let input = ndarray::ArrayD::from_shape_vec(
+            shape.clone(),
+            (0..shape.iter().product::<usize>())
+                .map(|v| v as f32)
+                .collect(),
+        )
+        .unwrap();
+
+        let expr = RecExpr::from_str(
+            "
+(access-reshape
+ (access-transpose (access (access-tensor t) 1) (list 1 0 2))
+ (access-shape (shape 4 6) (shape 5))
+)",
+        )
+        .unwrap();
+
+        // Get the expected result from the interpreter.
+        let mut env = HashMap::default();
+        env.insert("t", input.clone());
+        let out =
+            match crate::language::interpreter::interpret(&expr, expr.as_ref().len() - 1, &env) {
+                crate::language::interpreter::Value::Access(a) => a.tensor,
+                _ => panic!(),
+            };
+
+        let mut map = HashMap::default();
+        map.insert("t".to_string(), shape.clone());
+
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&expr);
+
+        let code = codegen(
+            &egraph,
+            id,
+            &HashMap::default(),
+            "access_reshape",
+            "",
+            &vec!["t"],
+        );
+
+        let main_code = format!(
+            "
+#include <assert.h>
+
+{}
+{}
+{}
+{}
+
+int main() {{
+  access_reshape(out, a);
+
+  for (int i = 0; i < {}; i++) {{
+    assert(((float*)expected)[i] == ((float*)out)[i]);
+  }}
+}}
+",
+            c_assignment_string("", "a", DType::Fp32, &input.view()),
+            c_assignment_string("", "expected", DType::Fp32, &out.view()),
+            c_assignment_string(
+                "",
+                "out",
+                DType::Fp32,
+                &ndarray::ArrayD::<f32>::zeros(out.shape()).view()
+            ),
+            code,
+            out.shape().iter().product::<usize>()
+        );
+
+        let main_c_filepath = std::env::temp_dir().with_file_name(format!(
+            "access-reshape-test-{}.c",
+            std::time::SystemTime::now().elapsed().unwrap().as_nanos()
+        ));
+
+        let binary_filepath = std::env::temp_dir().with_file_name(format!(
+            "access-reshape-test-{}",
+            std::time::SystemTime::now().elapsed().unwrap().as_nanos()
+        ));
+        println!("{}", binary_filepath.to_string_lossy());
+
+        File::create(&main_c_filepath)
+            .unwrap()
+            .write_all(main_code.as_bytes())
+            .unwrap();
+
+        let result = Command::new("gcc")
+            .arg("-Werror")
+            .arg("-g")
+            .arg("-o")
+            .arg(&binary_filepath)
+            .arg(&main_c_filepath)
+            .output()
+            .unwrap();
+
+        assert!(
+            result.status.success(),
+            "{}",
+            std::str::from_utf8(result.stderr.as_slice())
+                .expect("Could not convert stderr to UTF8")
+        );
+
+        let result = Command::new(&binary_filepath).output().unwrap();
+
+        assert!(
+            result.status.success(),
+            "{}",
+            std::str::from_utf8(result.stderr.as_slice())
+                .expect("Could not convert stderr to UTF8")
+        );
+    }
+
+    #[test]
+    fn access_shift_right() {
+        let shape = vec![3, 8, 5];
+
+        let input = ndarray::ArrayD::from_shape_vec(
+            shape.clone(),
+            (0..shape.iter().product::<usize>())
+                .map(|v| v as f32)
+                .collect(),
+        )
+        .unwrap();
+
+        let expr = RecExpr::from_str(
+            "
+(access-transpose
+ (access-shift-right (access (access-tensor t) 2))
+ (list 2 0 1)
+)",
+        )
+        .unwrap();
+
+        // Get the expected result from the interpreter.
+        let mut env = HashMap::default();
+        env.insert("t", input.clone());
+        let out =
+            match crate::language::interpreter::interpret(&expr, expr.as_ref().len() - 1, &env) {
+                crate::language::interpreter::Value::Access(a) => a.tensor,
+                _ => panic!(),
+            };
+
+        let mut map = HashMap::default();
+        map.insert("t".to_string(), shape.clone());
+
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&expr);
+
+        let code = codegen(
+            &egraph,
+            id,
+            &HashMap::default(),
+            "access_shift_right",
+            "",
+            &vec!["t"],
+        );
+
+        let main_code = format!(
+            "
+#include <assert.h>
+
+{}
+{}
+{}
+{}
+
+int main() {{
+  access_shift_right(out, a);
+
+  for (int i = 0; i < {}; i++) {{
+    assert(((float*)expected)[i] == ((float*)out)[i]);
+  }}
+}}
+",
+            c_assignment_string("", "a", DType::Fp32, &input.view()),
+            c_assignment_string("", "expected", DType::Fp32, &out.view()),
+            c_assignment_string(
+                "",
+                "out",
+                DType::Fp32,
+                &ndarray::ArrayD::<f32>::zeros(out.shape()).view()
+            ),
+            code,
+            out.shape().iter().product::<usize>()
+        );
+
+        let main_c_filepath = std::env::temp_dir().with_file_name(format!(
+            "access-shift-right-test-{}.c",
+            std::time::SystemTime::now().elapsed().unwrap().as_nanos()
+        ));
+
+        let binary_filepath = std::env::temp_dir().with_file_name(format!(
+            "access-shift-right-test-{}",
+            std::time::SystemTime::now().elapsed().unwrap().as_nanos()
+        ));
+        println!("{}", binary_filepath.to_string_lossy());
+
+        File::create(&main_c_filepath)
+            .unwrap()
+            .write_all(main_code.as_bytes())
+            .unwrap();
+
+        let result = Command::new("gcc")
+            .arg("-Werror")
+            .arg("-g")
+            .arg("-o")
+            .arg(&binary_filepath)
+            .arg(&main_c_filepath)
+            .output()
+            .unwrap();
+
+        assert!(
+            result.status.success(),
+            "{}",
+            std::str::from_utf8(result.stderr.as_slice())
+                .expect("Could not convert stderr to UTF8")
+        );
+
+        let result = Command::new(&binary_filepath).output().unwrap();
+
+        assert!(
+            result.status.success(),
+            "{}",
+            std::str::from_utf8(result.stderr.as_slice())
+                .expect("Could not convert stderr to UTF8")
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn extract_monolithic_panic() {
+        let expr = RecExpr::from_str(
+            "
+(systolic-array 32 32
+ (access
+  (systolic-array 64 32
+   (access (access-tensor t0) 1)
+   (access (access-tensor t1) 0)
+  )
+  1
+ )
+ (access
+  (systolic-array 32 32
+   (access (access-tensor t2) 1)
+   (access (access-tensor t3) 0)
+  )
+  0
+ )
+)",
+        )
+        .unwrap();
+
+        let mut map = HashMap::default();
+        map.insert("t0".to_string(), vec![32, 64]);
+        map.insert("t1".to_string(), vec![64, 32]);
+        map.insert("t2".to_string(), vec![32, 32]);
+        map.insert("t3".to_string(), vec![32, 32]);
+
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        egraph.add_expr(&expr);
+
+        let (_hw_map, _hw_design) = create_hardware_design_monolithic(&egraph, (32, 32));
+    }
+
+    #[test]
+    fn systolic_array_with_blocking() {
+        let shape0 = vec![2, 10];
+        let shape1 = vec![10, 15];
+
+        let input0 = ndarray::ArrayD::from_shape_vec(
+            shape0.clone(),
+            (0..shape0.iter().product::<usize>()).collect(),
+        )
+        .unwrap()
+        .into_dimensionality::<ndarray::Ix2>()
+        .unwrap();
+        let input1 = ndarray::ArrayD::from_shape_vec(
+            shape1.clone(),
+            (0..shape1.iter().product::<usize>()).collect(),
+        )
+        .unwrap()
+        .into_dimensionality::<ndarray::Ix2>()
+        .unwrap();
+        let multiplied = input0.dot(&input1).into_dyn();
+
+        let expr = RecExpr::from_str(
+            "
+(systolic-array-with-blocking 2 5
+ (access (access-tensor t0) 1)
+ (access (access-tensor t1) 0)
+)",
+        )
+        .unwrap();
+
+        let mut map = HashMap::default();
+        map.insert("t0".to_string(), shape0.clone());
+        map.insert("t1".to_string(), shape1.clone());
+
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&expr);
+
+        let mut hw_map = HashMap::default();
+        hw_map.insert(id, 0);
+
+        let code = codegen(
+            &egraph,
+            id,
+            &hw_map,
+            "systolic_array_with_blocking",
+            "",
+            &vec!["t0", "t1"],
+        );
+
+        let main_code = format!(
+            "
+#include <assert.h>
+#include \"{}\"
+
+{}
+{}
+{}
+{}
+{}
+
+int main() {{
+  systolic_array_with_blocking(out, t0, t1);
+
+  for (int i = 0; i < {}; i++) {{
+    assert(((float*)result)[i] == ((float*)out)[i]);
+  }}
+}}
+",
+            PathBuf::from_str(
+                format!(
+                    "{}/{}/{}/{}",
+                    env!("CARGO_MANIFEST_DIR"),
+                    "data",
+                    "codegen-mlp",
+                    "rtml_systolic_array_weight_stationary.c"
+                )
+                .as_str()
+            )
+            .unwrap()
+            .to_string_lossy(),
+            c_assignment_string("", "t0", DType::Fp32, &input0.into_dyn().view()),
+            c_assignment_string("", "t1", DType::Fp32, &input1.into_dyn().view()),
+            c_assignment_string("", "result", DType::Fp32, &multiplied.view()),
+            c_assignment_string(
+                "",
+                "out",
+                DType::Fp32,
+                &ndarray::ArrayD::<f32>::zeros(multiplied.shape()).view()
+            ),
+            code,
+            multiplied.shape().iter().product::<usize>()
+        );
+
+        let main_c_filepath = std::env::temp_dir().with_file_name(format!(
+            "systolic-array-with-blocking-test-{}.c",
+            std::time::SystemTime::now().elapsed().unwrap().as_nanos()
+        ));
+        println!("{}", main_c_filepath.to_string_lossy());
+
+        let binary_filepath = std::env::temp_dir().with_file_name(format!(
+            "systolic-array-with-blocking-test-{}",
+            std::time::SystemTime::now().elapsed().unwrap().as_nanos()
+        ));
+        println!("{}", binary_filepath.to_string_lossy());
+
+        File::create(&main_c_filepath)
+            .unwrap()
+            .write_all(main_code.as_bytes())
+            .unwrap();
+
+        let result = Command::new("gcc")
+            .arg("-Werror")
+            .arg("-g")
+            .arg("-o")
+            .arg(&binary_filepath)
+            .arg(&main_c_filepath)
+            .output()
+            .unwrap();
+
+        assert!(
+            result.status.success(),
+            "{}",
+            std::str::from_utf8(result.stderr.as_slice())
+                .expect("Could not convert stderr to UTF8")
+        );
+
+        let result = Command::new(&binary_filepath).output().unwrap();
+
+        assert!(
+            result.status.success(),
+            "{}",
+            std::str::from_utf8(result.stderr.as_slice())
+                .expect("Could not convert stderr to UTF8")
+        );
+    }
+
+    /// Generates C for `program`, runs it on `inputs`, and checks its output
+    /// against the interpreter's.
+    fn compare_with_interpreter(
+        test_name: &str,
+        program: &str,
+        inputs: &[(&str, ndarray::ArrayD<f32>)],
+        tolerance: f32,
+    ) {
+        let expr = RecExpr::from_str(program).unwrap();
+
+        let mut env = HashMap::default();
+        let mut map = HashMap::default();
+        for (name, value) in inputs.iter() {
+            env.insert(*name, value.clone());
+            map.insert(name.to_string(), value.shape().to_vec());
+        }
+        let expected =
+            match crate::language::interpreter::interpret(&expr, expr.as_ref().len() - 1, &env) {
+                crate::language::interpreter::Value::Access(a) => a.tensor,
+                crate::language::interpreter::Value::Tensor(t) => t,
+                _ => panic!(),
+            };
+
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&expr);
+
+        let args = inputs.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        let code = codegen(&egraph, id, &HashMap::default(), test_name, "", &args);
+
+        let main_code = format!(
+            "
+#include <assert.h>
+
+{inputs}
+{expected}
+{out}
+{code}
+
+int main() {{
+  {test_name}(out, {args});
+
+  for (int i = 0; i < {length}; i++) {{
+    float diff = ((float*)expected)[i] - ((float*)out)[i];
+    assert(diff <= {tolerance} && diff >= -{tolerance});
+  }}
+}}
+",
+            inputs = inputs
+                .iter()
+                .map(|(name, value)| c_assignment_string("", name, DType::Fp32, &value.view()))
+                .join("\n"),
+            expected = c_assignment_string("", "expected", DType::Fp32, &expected.view()),
+            out = c_assignment_string(
+                "",
+                "out",
+                DType::Fp32,
+                &ndarray::ArrayD::<f32>::zeros(expected.shape()).view()
+            ),
+            code = code,
+            test_name = test_name,
+            args = args.join(", "),
+            length = expected.len(),
+            tolerance = tolerance,
+        );
+
+        let main_c_filepath = std::env::temp_dir().with_file_name(format!(
+            "{}-test-{}.c",
+            test_name,
+            std::time::SystemTime::now().elapsed().unwrap().as_nanos()
+        ));
+
+        let binary_filepath = std::env::temp_dir().with_file_name(format!(
+            "{}-test-{}",
+            test_name,
+            std::time::SystemTime::now().elapsed().unwrap().as_nanos()
+        ));
+        println!("{}", binary_filepath.to_string_lossy());
+
+        File::create(&main_c_filepath)
+            .unwrap()
+            .write_all(main_code.as_bytes())
+            .unwrap();
+
+        let result = Command::new("gcc")
+            .arg("-Werror")
+            .arg("-g")
+            .arg("-o")
+            .arg(&binary_filepath)
+            .arg(&main_c_filepath)
+            .arg("-lm")
+            .output()
+            .unwrap();
+
+        assert!(
+            result.status.success(),
+            "{}",
+            std::str::from_utf8(result.stderr.as_slice())
+                .expect("Could not convert stderr to UTF8")
+        );
+
+        let result = Command::new(&binary_filepath).output().unwrap();
+
+        assert!(
+            result.status.success(),
+            "{}",
+            std::str::from_utf8(result.stderr.as_slice())
+                .expect("Could not convert stderr to UTF8")
+        );
+    }
+
+    /// Random values in [-1, 1), so that relu, max, etc. are exercised.
+    fn random_input(shape: &[usize]) -> ndarray::ArrayD<f32> {
+        use ndarray_rand::{rand_distr::Uniform, RandomExt};
+        ndarray::ArrayD::random(shape.to_vec(), Uniform::new(-1f32, 1f32))
+    }
+
+    #[test]
+    fn compute_relu() {
+        compare_with_interpreter(
+            "compute_relu",
+            "(compute relu (access (access-tensor t) 1))",
+            &[("t", random_input(&[3, 4, 5]))],
+            0.0,
+        );
+    }
+
+    #[test]
+    fn compute_negative() {
+        compare_with_interpreter(
+            "compute_negative",
+            "(compute negative (access (access-tensor t) 2))",
+            &[("t", random_input(&[3, 4, 5]))],
+            0.0,
+        );
+    }
+
+    #[test]
+    fn compute_sqrt() {
+        compare_with_interpreter(
+            "compute_sqrt",
+            "(compute sqrt (access (access-tensor t) 0))",
+            &[("t", random_input(&[3, 4, 5]).mapv(f32::abs))],
+            1e-6,
+        );
+    }
+
+    #[test]
+    fn compute_clip() {
+        compare_with_interpreter(
+            "compute_clip",
+            "(compute (clip -0.5 0.25) (access (access-tensor t) 1))",
+            &[("t", random_input(&[3, 4, 5]))],
+            0.0,
+        );
+    }
+
+    #[test]
+    fn compute_clip_unbounded() {
+        compare_with_interpreter(
+            "compute_clip_unbounded_below",
+            "(compute (clip -inf 0.25) (access (access-tensor t) 1))",
+            &[("t", random_input(&[3, 4, 5]))],
+            0.0,
+        );
+        compare_with_interpreter(
+            "compute_clip_unbounded_above",
+            "(compute (clip -0.5 inf) (access (access-tensor t) 1))",
+            &[("t", random_input(&[3, 4, 5]))],
+            0.0,
+        );
+    }
+
+    #[test]
+    fn compute_reduce_sum() {
+        compare_with_interpreter(
+            "compute_reduce_sum",
+            "(compute reduce-sum (access (access-tensor t) 1))",
+            &[("t", random_input(&[3, 4, 5]))],
+            1e-5,
+        );
+    }
+
+    #[test]
+    fn compute_reduce_max() {
+        compare_with_interpreter(
+            "compute_reduce_max",
+            "(compute reduce-max (access (access-tensor t) 2))",
+            &[("t", random_input(&[3, 4, 5]))],
+            0.0,
+        );
+    }
+
+    #[test]
+    fn compute_reduce_mean() {
+        compare_with_interpreter(
+            "compute_reduce_mean",
+            "(compute reduce-mean (access (access-tensor t) 1))",
+            &[("t", random_input(&[3, 4, 5]))],
+            1e-5,
+        );
+    }
+
+    #[test]
+    fn compute_softmax() {
+        compare_with_interpreter(
+            "compute_softmax",
+            "(compute softmax (access (access-tensor t) 2))",
+            &[("t", random_input(&[3, 4, 5]))],
+            1e-6,
+        );
+    }
+
+    #[test]
+    fn compute_elementwise_add() {
+        compare_with_interpreter(
+            "compute_elementwise_add",
+            "(compute elementwise-add
+              (access-pair (access (access-tensor a) 1) (access (access-tensor b) 1))
+             )",
+            &[
+                ("a", random_input(&[3, 4, 5])),
+                ("b", random_input(&[3, 4, 5])),
+            ],
+            0.0,
+        );
+    }
+
+    #[test]
+    fn compute_elementwise_mul() {
+        compare_with_interpreter(
+            "compute_elementwise_mul",
+            "(compute elementwise-mul
+              (access-pair (access (access-tensor a) 0) (access (access-tensor b) 0))
+             )",
+            &[
+                ("a", random_input(&[3, 4, 5])),
+                ("b", random_input(&[3, 4, 5])),
+            ],
+            0.0,
+        );
+    }
+
+    #[test]
+    fn compute_elementwise_div() {
+        compare_with_interpreter(
+            "compute_elementwise_div",
+            "(compute elementwise-div
+              (access-pair (access (access-tensor a) 2) (access (access-tensor b) 2))
+             )",
+            &[
+                ("a", random_input(&[3, 4, 5])),
+                ("b", random_input(&[3, 4, 5]).mapv(|v| v.abs() + 1.0)),
+            ],
+            1e-6,
+        );
+    }
+
+    #[test]
+    fn compute_dot_product() {
+        // A matrix multiplication, without any hardware.
+        compare_with_interpreter(
+            "compute_dot_product",
+            "(compute dot-product
+              (access-cartesian-product
+               (access (access-tensor a) 1)
+               (access (access-transpose (access-tensor b) (list 1 0)) 1)
+              )
+             )",
+            &[("a", random_input(&[6, 4])), ("b", random_input(&[4, 7]))],
+            1e-5,
+        );
+    }
+
+    #[test]
+    fn access_broadcast() {
+        compare_with_interpreter(
+            "access_broadcast",
+            "(compute elementwise-add
+              (access-pair
+               (access (access-tensor a) 1)
+               (access-broadcast
+                (access-insert-axis (access (access-tensor b) 0) 0)
+                (access-shape (shape 3) (shape 5))
+               )
+              )
+             )",
+            &[("a", random_input(&[3, 5])), ("b", random_input(&[5]))],
+            0.0,
+        );
+    }
+
+    #[test]
+    fn plan_buffers_overlapping_lifetimes() {
+        // a and b are both written before c, which reads them.
+        let plan = plan_buffers(&[
+            ("a".to_string(), 16, 0..3),
+            ("b".to_string(), 100, 1..3),
+            ("c".to_string(), 16, 2..3),
+        ]);
+        // All three buffers are live at once.
+        assert_eq!(plan.offsets["b"], 0);
+        assert_eq!(plan.offsets["a"], 128);
+        assert_eq!(plan.offsets["c"], 192);
+        assert_eq!(plan.arena_size, 256);
+        assert_eq!(plan.unshared_size, 256);
+    }
+
+    #[test]
+    fn plan_buffers_adjacent_lifetimes() {
+        // Lifetimes are half-open, so a buffer can reuse the storage of one
+        // which dies where it starts.
+        let plan = plan_buffers(&[
+            ("a".to_string(), 4, 0..1),
+            ("b".to_string(), 4, 1..2),
+            ("c".to_string(), 4, 1..3),
+            ("d".to_string(), 4, 2..3),
+        ]);
+        assert_eq!(plan.offsets["a"], plan.offsets["b"]);
+        assert_ne!(plan.offsets["b"], plan.offsets["c"]);
+        assert_eq!(plan.offsets["b"], plan.offsets["d"]);
+        assert_eq!(plan.arena_size, 128);
+        assert_eq!(plan.unshared_size, 256);
+    }
+
+    #[test]
+    fn buffer_sharing() {
+        let program = "
+         (compute relu
+          (compute negative
+           (compute relu
+            (compute negative (access (access-tensor t) 1))
+           )
+          )
+         )";
+
+        let expr = RecExpr::from_str(program).unwrap();
+        let mut map = HashMap::default();
+        map.insert("t".to_string(), vec![3, 4, 5]);
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&expr);
+        let code = codegen(
+            &egraph,
+            id,
+            &HashMap::default(),
+            "buffer_sharing",
+            "",
+            &vec!["t"],
+        );
+
+        // Each of the four intermediates takes 240 bytes, padded to 256. Only
+        // two are ever live at once.
+        assert!(code.contains(
+            "// Peak memory used by intermediates: 512 bytes (1024 bytes without buffer sharing)"
+        ));
+        assert!(code.contains("uint8_t buffer_sharing_arena[512]"));
+
+        compare_with_interpreter(
+            "buffer_sharing",
+            program,
+            &[("t", random_input(&[3, 4, 5]))],
+            0.0,
+        );
+    }
+
+    #[test]
+    fn legacy_move_axis() {
+        compare_with_interpreter(
+            "legacy_move_axis",
+            "(move-axis t 0 2)",
+            &[("t", random_input(&[3, 4, 5]))],
+            0.0,
+        );
+    }
+
+    #[test]
+    fn legacy_slice() {
+        compare_with_interpreter(
+            "legacy_slice",
+            "(slice t 1 1 3)",
+            &[("t", random_input(&[3, 4, 5]))],
+            0.0,
+        );
+    }
+
+    #[test]
+    fn legacy_concatenate() {
+        compare_with_interpreter(
+            "legacy_concatenate",
+            "(concatenate a b 1)",
+            &[
+                ("a", random_input(&[3, 4, 5])),
+                ("b", random_input(&[3, 2, 5])),
+            ],
+            0.0,
+        );
+    }
+
+    #[test]
+    fn legacy_elementwise_add() {
+        compare_with_interpreter(
+            "legacy_elementwise_add",
+            "(elementwise-add a b)",
+            &[("a", random_input(&[3, 4])), ("b", random_input(&[3, 4]))],
+            0.0,
+        );
+    }
+
+    #[test]
+    fn legacy_cartesian_product_map_dot_product() {
+        compare_with_interpreter(
+            "legacy_cartesian_product",
+            "(cartesian-product a b)",
+            &[
+                ("a", random_input(&[2, 3, 4])),
+                ("b", random_input(&[5, 4])),
+            ],
+            0.0,
+        );
+        compare_with_interpreter(
+            "legacy_map_dot_product",
+            "(map-dot-product (cartesian-product a b))",
+            &[
+                ("a", random_input(&[2, 3, 4])),
+                ("b", random_input(&[5, 4])),
+            ],
+            1e-6,
+        );
+    }
+
+    #[test]
+    fn legacy_bsg_systolic_array() {
+        compare_with_interpreter(
+            "legacy_bsg_systolic_array",
+            "(bsg-systolic-array 4 5 a b)",
+            &[("a", random_input(&[3, 4])), ("b", random_input(&[4, 5]))],
+            1e-6,
+        );
+        compare_with_interpreter(
+            "legacy_bsg_systolic_array_vector",
+            "(bsg-systolic-array 4 5 a b)",
+            &[("a", random_input(&[4])), ("b", random_input(&[4, 5]))],
+            1e-6,
+        );
+    }
+
+    #[test]
+    fn deep_program() {
+        // Deep enough to overflow the stack if codegen recursed over it. We
+        // build the e-graph node by node, as parsing the program would
+        // recurse, too.
+        let mut map = HashMap::default();
+        map.insert("t".to_string(), vec![2]);
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let t = egraph.add(Language::Symbol("t".to_string()));
+        let t = egraph.add(Language::AccessTensor(t));
+        let zero = egraph.add(Language::Usize(0));
+        let negative = egraph.add(Language::ComputeType(ComputeType::Negative));
+        let mut id = egraph.add(Language::Access([t, zero]));
+        for _ in 0..5000 {
+            id = egraph.add(Language::Compute([negative, id]));
+        }
+
+        let code = codegen(
+            &egraph,
+            id,
+            &HashMap::default(),
+            "deep_program",
+            "",
+            &vec!["t"],
+        );
+
+        // Only two intermediates are ever live at once.
+        assert!(code.contains("uint8_t deep_program_arena[128]"));
+    }
+
+    /// Generates `program` and a test harness for it, then compiles and runs
+    /// the harness, returning whether it succeeded.
+    fn run_test_harness(
+        test_name: &str,
+        program: &str,
+        inputs: &[(&str, ndarray::ArrayD<f32>)],
+        expected: &ndarray::ArrayD<f32>,
+    ) -> bool {
+        let expr = RecExpr::from_str(program).unwrap();
+        let mut map = HashMap::default();
+        for (name, value) in inputs.iter() {
+            map.insert(name.to_string(), value.shape().to_vec());
+        }
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&expr);
+
+        let args = inputs.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        let code = codegen(&egraph, id, &HashMap::default(), test_name, "", &args);
+
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let code_filepath = std::env::temp_dir().join(format!("{}-{}.c", test_name, nanos));
+        let harness_filepath =
+            std::env::temp_dir().join(format!("{}-harness-{}.c", test_name, nanos));
+        let binary_filepath = std::env::temp_dir().join(format!("{}-harness-{}", test_name, nanos));
+
+        File::create(&code_filepath)
+            .unwrap()
+            .write_all(code.as_bytes())
+            .unwrap();
+        File::create(&harness_filepath)
+            .unwrap()
+            .write_all(
+                c_test_harness(
+                    code_filepath.to_str().unwrap(),
+                    test_name,
+                    &inputs
+                        .iter()
+                        .map(|(name, value)| (*name, DType::Fp32, value.view()))
+                        .collect::<Vec<_>>(),
+                    MyAnalysis::get_dtype(id, &egraph),
+                    &expected.view(),
+                    1e-5,
+                )
+                .as_bytes(),
+            )
+            .unwrap();
+
+        let result = Command::new("gcc")
+            .arg("-Werror")
+            .arg("-o")
+            .arg(&binary_filepath)
+            .arg(&harness_filepath)
+            .arg("-lm")
+            .output()
+            .unwrap();
+        assert!(
+            result.status.success(),
+            "{}",
+            std::str::from_utf8(result.stderr.as_slice())
+                .expect("Could not convert stderr to UTF8")
+        );
+
+        Command::new(&binary_filepath)
+            .output()
+            .unwrap()
+            .status
+            .success()
+    }
+
+    #[test]
+    fn test_harness() {
+        let input =
+            ndarray::ArrayD::from_shape_vec(vec![2, 3], vec![-1., 2., -3., 4., -5., 6.]).unwrap();
+        let expected =
+            ndarray::ArrayD::from_shape_vec(vec![2, 3], vec![0., 2., 0., 4., 0., 6.]).unwrap();
+        assert!(run_test_harness(
+            "test_harness",
+            "(compute relu (access (access-tensor t) 1))",
+            &[("t", input)],
+            &expected,
+        ));
+    }
+
+    #[test]
+    fn test_harness_mismatch() {
+        let input =
+            ndarray::ArrayD::from_shape_vec(vec![2, 3], vec![-1., 2., -3., 4., -5., 6.]).unwrap();
+        // The second value is wrong.
+        let expected =
+            ndarray::ArrayD::from_shape_vec(vec![2, 3], vec![0., 3., 0., 4., 0., 6.]).unwrap();
+        assert!(!run_test_harness(
+            "test_harness_mismatch",
+            "(compute relu (access (access-tensor t) 1))",
+            &[("t", input)],
+            &expected,
+        ));
+    }
+
+    #[test]
+    fn test_harness_nan() {
+        // The square root of -1 is NaN, which should never match.
+        let input = ndarray::ArrayD::from_shape_vec(vec![2], vec![-1., 4.]).unwrap();
+        let expected = ndarray::ArrayD::from_shape_vec(vec![2], vec![1., 2.]).unwrap();
+        assert!(!run_test_harness(
+            "test_harness_nan",
+            "(compute sqrt (access (access-tensor t) 0))",
+            &[("t", input)],
+            &expected,
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "128 can't be represented exactly in int8")]
+    fn c_literal_out_of_range() {
+        c_literal_string(128.0, DType::Int8);
+    }
+
+    #[test]
+    #[should_panic(expected = "-1 can't be represented exactly in uint8")]
+    fn c_literal_negative_unsigned() {
+        c_literal_string(-1.0, DType::Uint8);
+    }
+
+    #[test]
+    #[should_panic(expected = "2.5 can't be represented exactly in int32")]
+    fn c_literal_not_integral() {
+        c_literal_string(2.5, DType::Int32);
+    }
+
+    /// argmax writes int32 indices, so the harness compares against an int32
+    /// output.
+    #[test]
+    fn compute_argmax() {
+        // The last item has a tie, which goes to the lowest index.
+        let input = ndarray::ArrayD::from_shape_vec(
+            vec![3, 4],
+            vec![0.5, -1., 2., 1., -3., -2., -4., -2.5, 1., 3., 0., 3.],
+        )
+        .unwrap();
+        let expected = ndarray::ArrayD::from_shape_vec(vec![3], vec![2., 1., 1.]).unwrap();
+        assert!(run_test_harness(
+            "compute_argmax",
+            "(compute argmax (access (access-tensor t) 1))",
+            &[("t", input)],
+            &expected,
+        ));
+    }
 }
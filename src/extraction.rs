@@ -1,6 +1,6 @@
-use crate::language::{Language, MyAnalysis};
-use egg::{CostFunction, EGraph, Id, Language as LanguageTrait, Pattern, Searcher};
-use std::collections::HashSet;
+use crate::language::{ComputeType, Language, MyAnalysis, MyAnalysisData};
+use egg::{CostFunction, EGraph, Id, Language as LanguageTrait, Pattern, RecExpr, Searcher};
+use std::collections::{HashMap, HashSet};
 
 pub fn find_all_systolic_array_configurations(
     egraph: &EGraph<Language, MyAnalysis>,
@@ -23,6 +23,114 @@ pub fn find_all_systolic_array_configurations(
     systolic_arrays
 }
 
+/// Extracts the cheapest program rooted at `id` under `cost_function`, like
+/// [`egg::Extractor::find_best()`]. Unlike egg's extractor, this doesn't
+/// recurse over the extracted program, so programs as deep as Mobilenet can be
+/// extracted without overflowing the stack.
+pub fn find_best<CF>(
+    egraph: &EGraph<Language, MyAnalysis>,
+    id: Id,
+    mut cost_function: CF,
+) -> (CF::Cost, RecExpr<Language>)
+where
+    CF: CostFunction<Language>,
+{
+    // Find the cheapest node in each eclass, iterating until nothing changes.
+    let mut best: HashMap<Id, (CF::Cost, Language)> = HashMap::default();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for eclass in egraph.classes() {
+            let eclass_id = egraph.find(eclass.id);
+            for node in eclass.nodes.iter() {
+                if !node
+                    .children()
+                    .iter()
+                    .all(|child| best.contains_key(&egraph.find(*child)))
+                {
+                    continue;
+                }
+                let cost = cost_function.cost(node, |child| best[&egraph.find(child)].0.clone());
+                let better = match best.get(&eclass_id) {
+                    Some((best_cost, _)) => cost < *best_cost,
+                    None => true,
+                };
+                if better {
+                    best.insert(eclass_id, (cost, node.clone()));
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    // Build the program, adding each eclass's node after its children.
+    let root = egraph.find(id);
+    let mut expr = RecExpr::default();
+    let mut expr_ids: HashMap<Id, Id> = HashMap::default();
+    let mut stack = vec![root];
+    while let Some(&eclass_id) = stack.last() {
+        if expr_ids.contains_key(&eclass_id) {
+            stack.pop();
+            continue;
+        }
+        let (_, node) = best
+            .get(&eclass_id)
+            .unwrap_or_else(|| panic!("Failed to extract from eclass {:?}", eclass_id));
+        let unbuilt_children = node
+            .children()
+            .iter()
+            .map(|child| egraph.find(*child))
+            .filter(|child| !expr_ids.contains_key(child))
+            .collect::<Vec<_>>();
+        if unbuilt_children.is_empty() {
+            let expr_id = expr.add(
+                node.clone()
+                    .map_children(|child| expr_ids[&egraph.find(child)]),
+            );
+            expr_ids.insert(eclass_id, expr_id);
+            stack.pop();
+        } else {
+            stack.extend(unbuilt_children);
+        }
+    }
+
+    (best[&root].0.clone(), expr)
+}
+
+/// Adds `expr` to `egraph`, like [`egg::EGraph::add_expr()`], but without
+/// recursing, so that deep programs (like those extracted by [`find_best()`])
+/// can be added without overflowing the stack.
+pub fn add_expr(egraph: &mut EGraph<Language, MyAnalysis>, expr: &RecExpr<Language>) -> Id {
+    let mut ids: Vec<Id> = Vec::with_capacity(expr.as_ref().len());
+    for node in expr.as_ref() {
+        let id = egraph.add(node.clone().map_children(|child| ids[usize::from(child)]));
+        ids.push(id);
+    }
+    *ids.last().unwrap()
+}
+
+/// Whether `id` is a pair of two broadcast accesses, as in the batched matrix
+/// multiplies imported from Relay's `nn.batch_matmul`, which
+/// [`crate::language::rewrites::systolic_array_batch_matmul()`] maps onto
+/// systolic arrays.
+fn is_pair_of_broadcasts(egraph: &EGraph<Language, MyAnalysis>, id: Id) -> bool {
+    let is_broadcast = |id: Id| {
+        egraph[id].nodes.iter().any(|node| match node {
+            &Language::Access([access_id, _]) => {
+                egraph[access_id].nodes.iter().any(|node| match node {
+                    Language::AccessBroadcast(_) => true,
+                    _ => false,
+                })
+            }
+            _ => false,
+        })
+    };
+    egraph[id].nodes.iter().any(|node| match node {
+        &Language::AccessPair([a_id, b_id]) => is_broadcast(a_id) && is_broadcast(b_id),
+        _ => false,
+    })
+}
+
 /// A cost function to extract a design using a single size of systolic array.
 ///
 /// `INFINITY_VALUE` represents constructs with infinite cost, i.e., constructs
@@ -50,6 +158,23 @@ impl egg::CostFunction<Language> for MonolithicCostFunction<'_> {
         C: FnMut(Id) -> Self::Cost,
     {
         let base_cost = match enode {
+            // Dot products over cartesian products should be tensorized into
+            // systolic arrays, as should batched matrix multiplies, which are
+            // dot products over pairs of broadcasts. Dot products over other
+            // pairs of accesses (e.g. from depthwise convolutions) can't be,
+            // so we let them fall back to software.
+            &Language::Compute([compute_type_id, access_id])
+                if match &self.egraph[compute_type_id].data {
+                    MyAnalysisData::ComputeType(ComputeType::DotProduct) => true,
+                    _ => false,
+                } && (!self.egraph[access_id].nodes.iter().any(|node| match node {
+                    Language::AccessPair(_) => true,
+                    _ => false,
+                }) || is_pair_of_broadcasts(self.egraph, access_id)) =>
+            {
+                Self::INFINITY_VALUE
+            }
+
             &Language::SystolicArray([rows_id, cols_id, _tensor_0_id, _tensor_1_id])
             | &Language::SystolicArrayWithBlocking([rows_id, cols_id, _tensor_0_id, _tensor_1_id])
                 if (
@@ -71,7 +196,9 @@ impl egg::CostFunction<Language> for MonolithicCostFunction<'_> {
             | Language::AccessConcatenate(_)
             | Language::AccessPad(_)
             | Language::AccessWindows(_)
+            | Language::AccessWindowsDilated(_)
             | Language::PadType(_)
+            | Language::DataType(_)
             | Language::Access(_)
             | Language::AccessTensor(_)
             | Language::ShapeOf(_)
@@ -89,19 +216,23 @@ impl egg::CostFunction<Language> for MonolithicCostFunction<'_> {
             | Language::List(_)
             | Language::SliceShape(_)
             | Language::AccessPair(_)
-            // We don't penalize Compute, though we don't want to extract
-            // compute statements. Instead, we penalize most ComputeTypes, and
-            // let some types pass through until we've implemented some other
+            // We don't penalize most Computes, though we don't want to extract
+            // compute statements. Instead, we penalize dot products above, and
+            // let other types pass through until we've implemented some other
             // way to handle them.
             // TODO(@gussmith23) We shouldn't have to extract ANY computes!
             | Language::Compute(_)
+            | Language::Clip(_)
+            | Language::LeakyReLU(_)
             | Language::AccessTranspose(_) => 1,
 
             // Penalaize specific compute types. In the future, these constructs
             // shouldn't be extractable at all.
             // TODO(@gussmith23) We shouldn't have to extract ANY computes!
             Language::ComputeType(t) => match t {
-                crate::language::ComputeType::DotProduct => Self::INFINITY_VALUE,
+                // Penalized by the Compute case above, depending on what it's
+                // computing over.
+                crate::language::ComputeType::DotProduct => 1,
                 crate::language::ComputeType::ReduceSum => 1,
                 crate::language::ComputeType::ReLU => 1,
                 crate::language::ComputeType::Sqrt => 1,
@@ -112,6 +243,14 @@ impl egg::CostFunction<Language> for MonolithicCostFunction<'_> {
                 crate::language::ComputeType::ReduceMax => 1,
                 crate::language::ComputeType::Softmax => 1,
                 crate::language::ComputeType::ReduceMean => 1,
+                crate::language::ComputeType::Sigmoid => 1,
+                crate::language::ComputeType::Tanh => 1,
+                crate::language::ComputeType::Exp => 1,
+                crate::language::ComputeType::Clip { .. } => 1,
+                crate::language::ComputeType::LeakyReLU { .. } => 1,
+                crate::language::ComputeType::Gelu => 1,
+                crate::language::ComputeType::ReduceMin => 1,
+                crate::language::ComputeType::ArgMax => 1,
             }
 
             // Old constructs.
@@ -129,19 +268,25 @@ impl egg::CostFunction<Language> for MonolithicCostFunction<'_> {
 }
 
 /// This cost function applies the bare minimum amount of logic to produce a
-/// valid hardware/software program. Most importantly, it blocks Compute nodes
-/// from being extracted, as these nodes should be replaced by hardware atoms
-/// (or, perhaps in the future, kernel calls). It also filters out old Glenside
-/// constructs.
+/// valid hardware/software program. Most importantly, it discourages Compute
+/// nodes from being extracted, as these nodes should be replaced by hardware
+/// atoms where possible. It also filters out old Glenside constructs.
 pub struct SimpleCostFunction {
     /// Whether to prioritize systolic-array or systolic-array-with-blocking
     // TODO(@gussmith23) This needs to be tested
     pub prefer_systolic_arrays_with_blocking: bool,
+    /// The cost of extracting a Compute node, which will be compiled to a
+    /// software loop nest by [`crate::codegen::codegen()`]. By default this
+    /// is [`usize::MAX`], meaning compute can't be extracted at all and must
+    /// be lowered to an atom. Set it to something lower (but still high) to
+    /// fall back to software for anything not mapped to hardware.
+    pub compute_cost: usize,
 }
 impl Default for SimpleCostFunction {
     fn default() -> Self {
         SimpleCostFunction {
             prefer_systolic_arrays_with_blocking: false,
+            compute_cost: std::usize::MAX,
         }
     }
 }
@@ -154,8 +299,8 @@ impl CostFunction<Language> for SimpleCostFunction {
     {
         use crate::language::Language::*;
         let base_cost = match enode {
-            // Cannot extract compute: compute must be lowered to an atom.
-            Compute(_) => std::usize::MAX,
+            // Compute should be lowered to an atom, if possible.
+            Compute(_) => self.compute_cost,
             // Extracting hardware atoms is encouraged
             SystolicArray(_) => {
                 if !self.prefer_systolic_arrays_with_blocking {
@@ -173,6 +318,7 @@ impl CostFunction<Language> for SimpleCostFunction {
             }
             // Extracting various access patterns is essential.
             AccessWindows(_)
+            | AccessWindowsDilated(_)
             | Access(_)
             | AccessLiteral(_)
             | AccessTranspose(_)
@@ -190,8 +336,8 @@ impl CostFunction<Language> for SimpleCostFunction {
             | AccessBroadcast(_) => 1,
             // Other glenside constructs that are necessary.
             Shape(_) | ShapeOf(_) | SliceShape(_) | ShapeInsertAxis(_) | ShapeRemoveAxis(_)
-            | List(_) | AccessShape(_) | Usize(_) | PadType(_) | ComputeType(_) | Symbol(_)
-            | Literal(_) | NotNanFloat64(_) => 1,
+            | List(_) | AccessShape(_) | Usize(_) | PadType(_) | DataType(_) | ComputeType(_)
+            | Clip(_) | LeakyReLU(_) | Symbol(_) | Literal(_) | NotNanFloat64(_) => 1,
             // Old constructs that are no longer used
             MoveAxis(_) | CartesianProduct(_) | MapDotProduct(_) | Slice(_) | Concatenate(_)
             | ElementwiseAdd(_) | BsgSystolicArray(_) => std::usize::MAX,
@@ -316,6 +462,76 @@ mod tests {
         assert!(cost >= MonolithicCostFunction::INFINITY_VALUE);
     }
 
+    #[test]
+    fn find_best_matches_extractor() {
+        let program = "
+         (systolic-array 32 32
+          (access
+           (systolic-array 32 32
+            (access (access-tensor v-32) 0)
+            (access (access-tensor t-32-32) 0)
+           )
+           0
+          )
+          (access (compute relu (access (access-tensor t-32-32) 0)) 0)
+         )
+         "
+        .parse()
+        .unwrap();
+
+        let mut egraph = EGraph::new(MyAnalysis::default());
+        let id = egraph.add_expr(&program);
+        egraph.rebuild();
+
+        let cost_function = || MonolithicCostFunction {
+            egraph: &egraph,
+            systolic_array_configuration: (32, 32),
+            prefer_systolic_arrays_with_blocking: false,
+        };
+        let (expected_cost, expected_expr) = Extractor::new(&egraph, cost_function()).find_best(id);
+        let (cost, expr) = find_best(&egraph, id, cost_function());
+        assert_eq!(cost, expected_cost);
+        assert_eq!(expr.pretty(80), expected_expr.pretty(80));
+    }
+
+    #[test]
+    fn find_best_deep_program() {
+        // Deep enough to overflow the stack if extraction recursed over it.
+        // We build the e-graph node by node, as adding a parsed program would
+        // recurse, too.
+        let mut map = HashMap::default();
+        map.insert("t".to_string(), vec![2]);
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let t = egraph.add(Language::Symbol("t".to_string()));
+        let t = egraph.add(Language::AccessTensor(t));
+        let zero = egraph.add(Language::Usize(0));
+        let negative = egraph.add(Language::ComputeType(ComputeType::Negative));
+        let mut id = egraph.add(Language::Access([t, zero]));
+        for _ in 0..5000 {
+            id = egraph.add(Language::Compute([negative, id]));
+        }
+        egraph.rebuild();
+
+        let (cost, expr) = find_best(&egraph, id, SimpleCostFunction::default());
+        // The symbol, access-tensor, 0, access and negative, plus the computes.
+        assert_eq!(expr.as_ref().len(), 5 + 5000);
+        assert_eq!(cost, std::usize::MAX);
+
+        let mut extracted_egraph = EGraph::new(MyAnalysis {
+            name_to_shape: egraph.analysis.name_to_shape.clone(),
+            name_to_dtype: HashMap::default(),
+        });
+        let extracted_id = add_expr(&mut extracted_egraph, &expr);
+        assert_eq!(extracted_egraph.number_of_classes(), 5 + 5000);
+        assert!(match &extracted_egraph[extracted_id].nodes[0] {
+            Language::Compute(_) => true,
+            _ => false,
+        });
+    }
+
     #[test]
     fn extract_1() {
         let program = "
@@ -380,6 +596,239 @@ mod tests {
         );
     }
 
+    #[test]
+    fn monolithic_cost_function_dot_product_cartesian_product() {
+        let mut map = HashMap::default();
+        map.insert("a".to_string(), vec![32, 16]);
+        map.insert("b".to_string(), vec![64, 16]);
+        let program = "
+         (compute dot-product
+          (access-cartesian-product
+           (access (access-tensor a) 1)
+           (access (access-tensor b) 1)
+          )
+         )
+         "
+        .parse()
+        .unwrap();
+
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&program);
+        egraph.rebuild();
+
+        let mut ex = Extractor::new(
+            &egraph,
+            MonolithicCostFunction {
+                egraph: &egraph,
+                systolic_array_configuration: (16, 64),
+                prefer_systolic_arrays_with_blocking: false,
+            },
+        );
+
+        let (cost, _) = ex.find_best(id);
+        assert!(cost >= MonolithicCostFunction::INFINITY_VALUE);
+    }
+
+    #[test]
+    fn monolithic_cost_function_prefers_systolic_array_to_dot_product() {
+        let mut map = HashMap::default();
+        map.insert("a".to_string(), vec![32, 16]);
+        map.insert("b".to_string(), vec![64, 16]);
+        let program = "
+         (compute dot-product
+          (access-cartesian-product
+           (access (access-tensor a) 1)
+           (access (access-tensor b) 1)
+          )
+         )
+         "
+        .parse()
+        .unwrap();
+
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&program);
+        let runner = egg::Runner::<_, _, ()>::new(MyAnalysis::default())
+            .with_egraph(egraph)
+            .run(&[crate::language::rewrites::systolic_array()]);
+
+        let (cost, expr) = Extractor::new(
+            &runner.egraph,
+            MonolithicCostFunction {
+                egraph: &runner.egraph,
+                systolic_array_configuration: (16, 64),
+                prefer_systolic_arrays_with_blocking: false,
+            },
+        )
+        .find_best(id);
+        assert!(cost < MonolithicCostFunction::INFINITY_VALUE);
+        let extracted = expr.pretty(80);
+        assert!(extracted.contains("systolic-array"));
+        assert!(!extracted.contains("dot-product"));
+    }
+
+    #[test]
+    fn monolithic_cost_function_dot_product_pair() {
+        let mut map = HashMap::default();
+        map.insert("a".to_string(), vec![32, 3, 3]);
+        map.insert("b".to_string(), vec![32, 3, 3]);
+        let program = "
+         (compute dot-product
+          (access-pair
+           (access (access-tensor a) 1)
+           (access (access-tensor b) 1)
+          )
+         )
+         "
+        .parse()
+        .unwrap();
+
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&program);
+        egraph.rebuild();
+
+        let mut ex = Extractor::new(
+            &egraph,
+            MonolithicCostFunction {
+                egraph: &egraph,
+                systolic_array_configuration: (16, 64),
+                prefer_systolic_arrays_with_blocking: false,
+            },
+        );
+
+        let (cost, _) = ex.find_best(id);
+        assert!(cost < MonolithicCostFunction::INFINITY_VALUE);
+    }
+
+    /// Depthwise convolutions (e.g. in MobileNet) are dot products over pairs
+    /// of windows and broadcast kernels, which can't be mapped onto systolic
+    /// arrays. Monolithic extraction has to leave them in software, or it
+    /// can't extract these programs at all.
+    #[test]
+    fn monolithic_cost_function_extracts_depthwise_convolutions() {
+        let mut map = HashMap::default();
+        map.insert("data".to_string(), vec![1, 3, 8, 8]);
+        map.insert("weight".to_string(), vec![3, 1, 3, 3]);
+        let program = "
+         (compute dot-product
+          (access-pair
+           (access-squeeze
+            (access-squeeze
+             (access-windows
+              (access (access-tensor data) 4)
+              (shape 1 1 3 3)
+              (shape 1 1 1 1)
+             )
+             4
+            )
+            4
+           )
+           (access-broadcast
+            (access-insert-axis
+             (access-insert-axis
+              (access-insert-axis
+               (access-squeeze (access (access-tensor weight) 1) 1)
+               0
+              )
+              2
+             )
+             3
+            )
+            (access-shape (shape 1 3 6 6) (shape 3 3))
+           )
+          )
+         )
+         "
+        .parse()
+        .unwrap();
+
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&program);
+        let runner = egg::Runner::<_, _, ()>::new(MyAnalysis::default())
+            .with_egraph(egraph)
+            .run(&[
+                crate::language::rewrites::systolic_array(),
+                crate::language::rewrites::systolic_array_batch_matmul(),
+            ]);
+
+        let (cost, expr) = Extractor::new(
+            &runner.egraph,
+            MonolithicCostFunction {
+                egraph: &runner.egraph,
+                systolic_array_configuration: (3, 3),
+                prefer_systolic_arrays_with_blocking: false,
+            },
+        )
+        .find_best(id);
+        assert!(cost < MonolithicCostFunction::INFINITY_VALUE);
+        let extracted = expr.pretty(80);
+        assert!(extracted.contains("(compute dot-product"));
+        assert!(!extracted.contains("systolic-array"));
+    }
+
+    #[test]
+    fn monolithic_cost_function_prefers_systolic_arrays_to_batch_matmul() {
+        let mut map = HashMap::default();
+        map.insert("a".to_string(), vec![2, 3, 4]);
+        map.insert("b".to_string(), vec![2, 5, 4]);
+        let program = "
+         (compute dot-product
+          (access-pair
+           (access
+            (access-broadcast
+             (access-insert-axis (access-tensor a) 2)
+             (access-shape (shape 2 3 5 4) (shape))
+            )
+            3
+           )
+           (access
+            (access-broadcast
+             (access-insert-axis (access-tensor b) 1)
+             (access-shape (shape 2 3 5 4) (shape))
+            )
+            3
+           )
+          )
+         )
+         "
+        .parse()
+        .unwrap();
+
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&program);
+        let runner = egg::Runner::<_, _, ()>::new(MyAnalysis::default())
+            .with_egraph(egraph)
+            .run(&[crate::language::rewrites::systolic_array_batch_matmul()]);
+
+        let (cost, expr) = Extractor::new(
+            &runner.egraph,
+            MonolithicCostFunction {
+                egraph: &runner.egraph,
+                systolic_array_configuration: (4, 5),
+                prefer_systolic_arrays_with_blocking: false,
+            },
+        )
+        .find_best(id);
+        assert!(cost < MonolithicCostFunction::INFINITY_VALUE);
+        let extracted = expr.pretty(80);
+        assert_eq!(extracted.matches("(systolic-array").count(), 2);
+        assert!(!extracted.contains("dot-product"));
+    }
+
     #[test]
     fn simple_cost_function_0() {
         let mut map = HashMap::default();
@@ -408,7 +857,10 @@ mod tests {
         .parse()
         .unwrap();
 
-        let mut egraph = EGraph::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         egraph.rebuild();
 
@@ -442,4 +894,40 @@ mod tests {
   (access (access-tensor weight2) 0))"
         );
     }
+
+    #[test]
+    fn simple_cost_function_compute() {
+        let mut map = HashMap::default();
+        map.insert("t".to_string(), vec![32, 64]);
+        let program = "(compute relu (access (access-tensor t) 0))"
+            .parse()
+            .unwrap();
+
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&program);
+        egraph.rebuild();
+
+        // By default, compute can't be extracted.
+        let (cost, _) = Extractor::new(&egraph, SimpleCostFunction::default()).find_best(id);
+        assert_eq!(cost, std::usize::MAX);
+
+        // With a software fallback, it can.
+        let (cost, best) = Extractor::new(
+            &egraph,
+            SimpleCostFunction {
+                compute_cost: 1000,
+                ..Default::default()
+            },
+        )
+        .find_best(id);
+        assert!(cost < std::usize::MAX);
+        assert!(cost >= 1000);
+        assert_eq!(
+            best.pretty(80),
+            "(compute relu (access (access-tensor t) 0))"
+        );
+    }
 }
@@ -1,8 +1,10 @@
 use serde::Serialize;
 use serde_json::map::Map;
 use serde_json::{json, Value};
+use std::fmt::Display;
+use std::str::FromStr;
 
-#[derive(Serialize)]
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum DType {
     Int8 = 0,
@@ -19,17 +21,103 @@ pub enum DType {
 
 impl DType {
     /// Return the equivalent C type name for a given `dtype`, as a String.
+    /// Integer types use the fixed-width types from `stdint.h`. C has no
+    /// bfloat16 type, so bfloat16 values are stored as their raw bits.
     /// ```
     /// use glenside::hw_design_language::*;
     /// assert_eq!(DType::Fp32.to_c_type_string(), "float");
+    /// assert_eq!(DType::Int8.to_c_type_string(), "int8_t");
+    /// assert_eq!(DType::Fp16.to_c_type_string(), "_Float16");
+    /// assert_eq!(DType::Bf16.to_c_type_string(), "uint16_t");
     /// ```
     pub fn to_c_type_string(&self) -> String {
         match &self {
+            &DType::Int8 => "int8_t",
+            &DType::Int16 => "int16_t",
+            &DType::Int32 => "int32_t",
+            &DType::Uint8 => "uint8_t",
+            &DType::Uint16 => "uint16_t",
+            &DType::Uint32 => "uint32_t",
+            &DType::Bf16 => "uint16_t",
+            &DType::Fp16 => "_Float16",
             &DType::Fp32 => "float",
-            _ => panic!(),
+            &DType::Fp64 => "double",
         }
         .to_string()
     }
+
+    /// The datatype used to accumulate dot products of values of this
+    /// datatype.
+    /// ```
+    /// use glenside::hw_design_language::*;
+    /// assert_eq!(DType::Int8.accumulator_dtype(), DType::Int32);
+    /// assert_eq!(DType::Bf16.accumulator_dtype(), DType::Fp32);
+    /// assert_eq!(DType::Fp32.accumulator_dtype(), DType::Fp32);
+    /// ```
+    pub fn accumulator_dtype(&self) -> DType {
+        match &self {
+            &DType::Int8 | &DType::Int16 | &DType::Int32 => DType::Int32,
+            &DType::Uint8 | &DType::Uint16 | &DType::Uint32 => DType::Uint32,
+            &DType::Bf16 | &DType::Fp16 | &DType::Fp32 => DType::Fp32,
+            &DType::Fp64 => DType::Fp64,
+        }
+    }
+
+    /// The size of a value of this datatype, in bytes.
+    /// ```
+    /// use glenside::hw_design_language::*;
+    /// assert_eq!(DType::Int8.size_in_bytes(), 1);
+    /// assert_eq!(DType::Bf16.size_in_bytes(), 2);
+    /// assert_eq!(DType::Fp64.size_in_bytes(), 8);
+    /// ```
+    pub fn size_in_bytes(&self) -> usize {
+        match &self {
+            &DType::Int8 | &DType::Uint8 => 1,
+            &DType::Int16 | &DType::Uint16 | &DType::Bf16 | &DType::Fp16 => 2,
+            &DType::Int32 | &DType::Uint32 | &DType::Fp32 => 4,
+            &DType::Fp64 => 8,
+        }
+    }
+}
+
+impl FromStr for DType {
+    type Err = ();
+    fn from_str(input: &str) -> Result<DType, Self::Err> {
+        match input {
+            "int8" => Ok(DType::Int8),
+            "int16" => Ok(DType::Int16),
+            "int32" => Ok(DType::Int32),
+            "uint8" => Ok(DType::Uint8),
+            "uint16" => Ok(DType::Uint16),
+            "uint32" => Ok(DType::Uint32),
+            "bf16" | "bfloat16" => Ok(DType::Bf16),
+            "fp16" | "float16" => Ok(DType::Fp16),
+            "fp32" | "float32" => Ok(DType::Fp32),
+            "fp64" | "float64" => Ok(DType::Fp64),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for DType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                DType::Int8 => "int8",
+                DType::Int16 => "int16",
+                DType::Int32 => "int32",
+                DType::Uint8 => "uint8",
+                DType::Uint16 => "uint16",
+                DType::Uint32 => "uint32",
+                DType::Bf16 => "bf16",
+                DType::Fp16 => "fp16",
+                DType::Fp32 => "fp32",
+                DType::Fp64 => "fp64",
+            }
+        )
+    }
 }
 
 pub struct SystolicArrayWeightStationaryParams {
@@ -87,6 +175,27 @@ pub fn atom_to_json(atom: &Atom) -> Value {
 mod tests {
     use super::*;
 
+    #[test]
+    fn dtype_from_str() {
+        for dtype in vec![
+            DType::Int8,
+            DType::Int16,
+            DType::Int32,
+            DType::Uint8,
+            DType::Uint16,
+            DType::Uint32,
+            DType::Bf16,
+            DType::Fp16,
+            DType::Fp32,
+            DType::Fp64,
+        ] {
+            assert_eq!(DType::from_str(&dtype.to_string()), Ok(dtype));
+            // The string representation should match the serialized
+            // representation used in hardware designs.
+            assert_eq!(json!(dtype), json!(dtype.to_string()));
+        }
+    }
+
     #[test]
     fn serialize() {
         let design = HardwareDesign {
@@ -50,7 +50,8 @@ def _recursive_helper(expr):
         return "(access-tensor {})".format(expr.name_hint)
     elif isinstance(expr, tvm.relay.Constant):
         assert expr.data.shape == ()
-        return '(access-literal (literal {}))' \
+        assert expr.data.dtype == 'float32'
+        return '(access-literal (literal {}) float32)' \
             .format(float(expr.data.asnumpy()))
 
     elif isinstance(expr, tvm.relay.Call):
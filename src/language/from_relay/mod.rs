@@ -1,11 +1,12 @@
 // TODO(@gussmith23) Make sure TVM feature flag is getting tested in CI
 #![cfg(feature = "tvm")]
 
+use crate::hw_design_language::DType;
 use crate::language::Language;
 use egg::{Id, RecExpr};
 use ordered_float::NotNan;
-use std::collections::HashMap;
-use std::convert::TryInto;
+use std::collections::{HashMap, HashSet};
+use std::convert::{TryFrom, TryInto};
 use tvm::ir::module::*;
 use tvm::ir::relay::*;
 use tvm::ir::tir::*;
@@ -63,7 +64,6 @@ pub fn conv2d(
         "OIHW and HWIO are the only layouts supported at the moment"
     );
 
-    assert_eq!(dilation, [1, 1]);
     assert_eq!(out_layout, "");
 
     // Transpose to NCHW
@@ -125,6 +125,14 @@ pub fn conv2d(
     stride_list.push(expr.add(Language::Usize(strides[1])));
     let stride_shape_id = expr.add(Language::Shape(Box::from(stride_list.as_slice())));
 
+    // Only use access-windows-dilated when we actually need to, so that
+    // undilated convolutions still match the existing rewrites.
+    let dilation_shape_id = if dilation == [1, 1] {
+        None
+    } else {
+        Some(shape(expr, vec![1, 1, dilation[0], dilation[1]]))
+    };
+
     let in_channels = data_shape[1];
 
     let data_id = match groups as usize {
@@ -141,11 +149,13 @@ pub fn conv2d(
                 usize_kw_id,
             ])));
 
-            let data_id = expr.add(Language::AccessWindows([
+            let data_id = access_windows(
+                expr,
                 data_id,
                 weights_shape_id,
                 stride_shape_id,
-            ]));
+                dilation_shape_id,
+            );
             // Result is [batch 1 new_h new_w] [1 in_channel kw kh]
 
             // Squeeze the 4th dimension so it matches kernel shapes
@@ -172,83 +182,415 @@ pub fn conv2d(
         // If groups = num input channels (ie in depthwise separable mobilenet convs)
         // TODO(@gussmith23) Layout assumption
         n if n == in_channels => {
-            // Kernel size is the same for each group. Each
-            // kernel's shape is (1,1,kH,kW) where the first 1
-            // lines up with batch and the second lines up with
-            // input channels. The fact that the kernel's
-            // channel size is 1 is what makes this grouped with
-            // groups=in_channels.
-            // TODO(@gussmith23) Layout assumption.
-            let mut list = Vec::default();
-            list.push(expr.add(Language::Usize(1)));
-            list.push(expr.add(Language::Usize(1)));
-            for v in weights_shape[2..].iter() {
-                list.push(expr.add(Language::Usize(*v as usize)));
-            }
-            let weights_shape_id = expr.add(Language::Shape(Box::from(list.as_slice())));
-
-            let mut to_be_concatted = Vec::default();
-
-            for channel_idx in 0..in_channels {
-                // Get this group's input channel
-                // TODO(@gussmith23) layout assumption
-                let data_id = access_slice(
-                    expr,
-                    data_id,
-                    1,
-                    channel_idx.try_into().unwrap(),
-                    (channel_idx + 1).try_into().unwrap(),
-                );
-                let data_id = expr.add(Language::AccessWindows([
-                    data_id,
-                    weights_shape_id,
-                    stride_shape_id,
-                ]));
-                let data_id = access(expr, data_id, 4);
-                // Result should be
-                // [1 1 new_H new_W] [1 1 kernel_H kernel_W]
-
-                // Get this group's kernel
-                // TODO(@gussmith23) layout assumption
-                let weights_id = access_slice(
-                    expr,
-                    weights_id,
-                    0,
-                    channel_idx.try_into().unwrap(),
-                    (channel_idx + 1).try_into().unwrap(),
-                );
-                let weights_id = access(expr, weights_id, 0);
+            // Each output channel is computed from only its corresponding
+            // input channel. Rather than slicing out each channel and
+            // concatenating the results (which produces a program whose size
+            // grows with the number of channels), we form windows over each
+            // channel separately, broadcast the kernels to line up with the
+            // windows, and pair them up.
+            assert_eq!(
+                weights_shape[0], in_channels,
+                "Depthwise convolutions with channel multipliers not implemented"
+            );
+            assert_eq!(weights_shape[1], 1);
+
+            let padded_shape = [
+                data_shape[2] + padding[0] + padding[2],
+                data_shape[3] + padding[1] + padding[3],
+            ];
+            let out_shape = vec![
+                data_shape[0],
+                in_channels,
+                (padded_shape[0] - ((weights_shape[2] - 1) * dilation[0] + 1)) / strides[0] + 1,
+                (padded_shape[1] - ((weights_shape[3] - 1) * dilation[1] + 1)) / strides[1] + 1,
+            ];
+
+            // Kernel size is the same for each group. Each kernel's shape is
+            // (1,1,kH,kW) where the first 1 lines up with batch and the second
+            // lines up with input channels.
+            let windows_shape_id = shape(expr, vec![1, 1, weights_shape[2], weights_shape[3]]);
+            let data_id = access_windows(
+                expr,
+                data_id,
+                windows_shape_id,
+                stride_shape_id,
+                dilation_shape_id,
+            );
+            // Result is [batch in_channels new_H new_W] [1 1 kernel_H kernel_W]
+
+            let squeeze_axis_id = expr.add(Language::Usize(4));
+            let data_id = expr.add(Language::AccessSqueeze([data_id, squeeze_axis_id]));
+            let squeeze_axis_id = expr.add(Language::Usize(4));
+            let data_id = expr.add(Language::AccessSqueeze([data_id, squeeze_axis_id]));
+            // Result is [batch in_channels new_H new_W] [kernel_H kernel_W]
 
-                let data_id = expr.add(Language::AccessCartesianProduct([weights_id, data_id]));
-                // Results should be
-                // [1 1 new_H new_W] [2 1 1 kernel_H kernel_W]
+            let weights_id = access(expr, weights_id, 1);
+            let squeeze_axis_id = expr.add(Language::Usize(1));
+            let weights_id = expr.add(Language::AccessSqueeze([weights_id, squeeze_axis_id]));
+            let weights_id = access_insert_axis(expr, weights_id, 0);
+            let weights_id = access_insert_axis(expr, weights_id, 2);
+            let weights_id = access_insert_axis(expr, weights_id, 3);
+            // Result is [1 in_channels 1 1] [kernel_H kernel_W]
+            let access_shape_id =
+                access_shape(expr, &out_shape, &[weights_shape[2], weights_shape[3]]);
+            let weights_id = expr.add(Language::AccessBroadcast([weights_id, access_shape_id]));
+            // Result is [batch in_channels new_H new_W] [kernel_H kernel_W]
+
+            let data_id = expr.add(Language::AccessPair([data_id, weights_id]));
+            // Result is [batch in_channels new_H new_W] [2 kernel_H kernel_W]
+
+            compute(expr, ComputeType::DotProduct, data_id)
+            // Result is [batch in_channels new_H new_W]
+        }
+        _ => panic!("Groups not implemented for groups={}", groups),
+    };
 
-                let data_id = compute(expr, ComputeType::DotProduct, data_id);
-                // Results should be
-                // [1 1 new_H new_W]
+    // Transpose from NCHW to original layout
+    match data_layout {
+        "NCHW" => data_id,
+        "NHWC" => access_transpose(expr, data_id, &[0, 2, 3, 1]),
+        _ => unreachable!(),
+    }
+}
 
-                to_be_concatted.push(data_id);
-            }
+/// Form windows over an access, dilating the windows if a dilation shape is
+/// given.
+fn access_windows(
+    expr: &mut RecExpr<Language>,
+    data_id: Id,
+    filters_shape_id: Id,
+    stride_shape_id: Id,
+    dilation_shape_id: Option<Id>,
+) -> Id {
+    match dilation_shape_id {
+        None => expr.add(Language::AccessWindows([
+            data_id,
+            filters_shape_id,
+            stride_shape_id,
+        ])),
+        Some(dilation_shape_id) => expr.add(Language::AccessWindowsDilated([
+            data_id,
+            filters_shape_id,
+            stride_shape_id,
+            dilation_shape_id,
+        ])),
+    }
+}
+
+/// Pools over the spatial axes of a 4D tensor, taking the max
+/// ([`ComputeType::ReduceMax`]) or mean ([`ComputeType::ReduceMean`]) of
+/// each window. `padding` is `[top, left, bottom, right]`, as in Relay.
+///
+/// Padded values are never the max of a window. When averaging,
+/// `count_include_pad` determines whether padded values count towards the
+/// size of each window (as zeros), or are left out entirely.
+pub fn pool2d(
+    expr: &mut RecExpr<Language>,
+    data_id: Id,
+    data_shape: &[usize],
+    compute_type: ComputeType,
+    pool_size: &[usize],
+    strides: &[usize],
+    padding: &[usize],
+    count_include_pad: bool,
+    layout: &str,
+) -> Id {
+    assert_eq!(data_shape.len(), 4);
+    assert_eq!(pool_size.len(), 2);
+    assert_eq!(strides.len(), 2);
+    assert_eq!(padding.len(), 4);
+    assert!(
+        &["NCHW", "NHWC"].contains(&layout),
+        "NCHW and NHWC are the only layouts supported at the moment"
+    );
+
+    // Transpose to NCHW
+    let (data_id, data_shape) = match layout {
+        "NCHW" => (data_id, Vec::from(data_shape)),
+        "NHWC" => (
+            access_transpose(expr, data_id, &[0, 3, 1, 2]),
+            vec![data_shape[0], data_shape[3], data_shape[1], data_shape[2]],
+        ),
+        _ => unreachable!(),
+    };
 
-            let mut concatted_id = to_be_concatted[0];
-            for to_be_concatted_id in to_be_concatted[1..].iter() {
-                // TODO(@gussmith23) Layout assumption
-                concatted_id = access_concatenate(expr, concatted_id, *to_be_concatted_id, 1);
+    // Forms the windows over a tensor in NCHW layout, padding it with
+    // `pad_type` first.
+    let windows = |expr: &mut RecExpr<Language>, data_id: Id, pad_type: PadType| {
+        let data_id = access_pad(expr, data_id, pad_type, 2, padding[0], padding[2]);
+        let data_id = access_pad(expr, data_id, pad_type, 3, padding[1], padding[3]);
+        let data_id = access(expr, data_id, 4);
+        let pool_window_shape_id = shape(expr, vec![1, 1, pool_size[0], pool_size[1]]);
+        let stride_shape_id = shape(expr, vec![1, 1, strides[0], strides[1]]);
+        let data_id = expr.add(Language::AccessWindows([
+            data_id,
+            pool_window_shape_id,
+            stride_shape_id,
+        ]));
+        access(expr, data_id, 4)
+    };
+
+    let data_id = match compute_type {
+        ComputeType::ReduceMax => {
+            let data_id = windows(expr, data_id, PadType::MinPadding);
+            compute(expr, ComputeType::ReduceMax, data_id)
+        }
+        ComputeType::ReduceMean if count_include_pad || padding.iter().all(|&p| p == 0) => {
+            let data_id = windows(expr, data_id, PadType::ZeroPadding);
+            compute(expr, ComputeType::ReduceMean, data_id)
+        }
+        ComputeType::ReduceMean => {
+            // Windows which overlap the padding average over fewer values.
+            // We count the values in each window by summing the same windows
+            // over a tensor of ones, and divide each window's sum by its
+            // count. The counts don't depend on the data, but Glenside has no
+            // tensor literals to hold them, so they're computed in the
+            // program.
+            let sum_id = windows(expr, data_id, PadType::ZeroPadding);
+            let sum_id = compute(expr, ComputeType::ReduceSum, sum_id);
+
+            let one_id = expr.add(Language::NotNanFloat64(NotNan::new(1.0).unwrap()));
+            let one_id = expr.add(Language::Literal(one_id));
+            let dtype_id = expr.add(Language::DataType(DType::Fp32));
+            let mut ones_id = expr.add(Language::AccessLiteral([one_id, dtype_id]));
+            for _ in 0..4 {
+                ones_id = access_insert_axis(expr, ones_id, 0);
             }
+            let access_shape_id = access_shape(expr, &data_shape, &[]);
+            let ones_id = expr.add(Language::AccessBroadcast([ones_id, access_shape_id]));
+            let count_id = windows(expr, ones_id, PadType::ZeroPadding);
+            let count_id = compute(expr, ComputeType::ReduceSum, count_id);
 
-            concatted_id
+            let pair_id = access_pair(expr, sum_id, count_id, 4);
+            compute(expr, ComputeType::ElementwiseDiv, pair_id)
         }
-        _ => panic!("Groups not implemented for groups={}", groups),
+        _ => panic!("Can't pool with compute type {}", compute_type),
     };
 
     // Transpose from NCHW to original layout
-    match data_layout {
+    match layout {
         "NCHW" => data_id,
         "NHWC" => access_transpose(expr, data_id, &[0, 2, 3, 1]),
         _ => unreachable!(),
     }
 }
 
+/// Pools over the entire spatial extent of a 4D tensor, taking the max
+/// ([`ComputeType::ReduceMax`]) or mean ([`ComputeType::ReduceMean`]) of
+/// each channel.
+pub fn global_pool2d(
+    expr: &mut RecExpr<Language>,
+    data_id: Id,
+    compute_type: ComputeType,
+    layout: &str,
+) -> Id {
+    assert!(
+        compute_type == ComputeType::ReduceMax || compute_type == ComputeType::ReduceMean,
+        "Can't pool with compute type {}",
+        compute_type
+    );
+    match layout {
+        "NCHW" => {
+            let data_id = access(expr, data_id, 2);
+            let data_id = compute(expr, compute_type, data_id);
+            let data_id = access_insert_axis(expr, data_id, 2);
+            let data_id = access_insert_axis(expr, data_id, 3);
+            access(expr, data_id, 2)
+        }
+        "NHWC" => {
+            let data_id = access_transpose(expr, data_id, &[0, 3, 1, 2]);
+            let data_id = access(expr, data_id, 2);
+            let data_id = compute(expr, compute_type, data_id);
+            let data_id = access_insert_axis(expr, data_id, 1);
+            let data_id = access_insert_axis(expr, data_id, 2);
+            access(expr, data_id, 3)
+        }
+        other @ _ => panic!("layout {} not supported", other),
+    }
+}
+
+/// Reduces a tensor with `ndim` dimensions over `axes`, using a reduce
+/// compute type. The reduced axes are transposed to the end of the tensor, so
+/// that they form the item shape. If `keepdims`, the reduced axes are kept,
+/// with size 1.
+///
+/// ```
+/// use egg::{EGraph, RecExpr};
+/// use glenside::language::from_relay::reduce;
+/// use glenside::language::{ComputeType, MyAnalysis, MyAnalysisData};
+/// use std::collections::HashMap;
+/// use std::str::FromStr;
+///
+/// let mut expr = RecExpr::from_str("(access-tensor a)").unwrap();
+/// reduce(&mut expr, 1.into(), ComputeType::ReduceSum, 3, &[0, 2], true);
+///
+/// let mut map = HashMap::default();
+/// map.insert("a".to_string(), vec![2, 3, 4]);
+/// let mut egraph = EGraph::new(MyAnalysis {
+///     name_to_shape: map,
+///     name_to_dtype: HashMap::default(),
+/// });
+/// let id = egraph.add_expr(&expr);
+/// match &egraph[id].data {
+///     MyAnalysisData::AccessPattern(a) => assert_eq!(a.as_vec(), vec![1, 3, 1]),
+///     _ => panic!(),
+/// }
+/// ```
+pub fn reduce(
+    expr: &mut RecExpr<Language>,
+    data_id: Id,
+    compute_type: ComputeType,
+    ndim: usize,
+    axes: &[usize],
+    keepdims: bool,
+) -> Id {
+    let mut axes = axes.to_vec();
+    axes.sort();
+    axes.dedup();
+    assert!(axes.iter().all(|&axis| axis < ndim));
+
+    let transpose_list = (0..ndim)
+        .filter(|axis| !axes.contains(axis))
+        .chain(axes.iter().cloned())
+        .collect::<Vec<_>>();
+    let data_id = if transpose_list.iter().cloned().eq(0..ndim) {
+        data_id
+    } else {
+        access_transpose(expr, data_id, &transpose_list)
+    };
+    let data_id = access(expr, data_id, ndim - axes.len());
+    let mut data_id = compute(expr, compute_type, data_id);
+
+    if keepdims {
+        for &axis in &axes {
+            data_id = access_insert_axis(expr, data_id, axis);
+        }
+    }
+
+    data_id
+}
+
+/// Multiplies each matrix in a batch `[batch, m, k]` by the transpose of the
+/// corresponding matrix in a batch `[batch, n, k]`, as Relay's
+/// `nn.batch_matmul` does. Each row of `a` is paired with each row of `b`
+/// from the same batch (a cartesian product within each batch), by
+/// broadcasting both to `[batch, m, n, k]`.
+///
+/// ```
+/// use egg::{EGraph, RecExpr};
+/// use glenside::language::from_relay::batch_matmul;
+/// use glenside::language::{Language, MyAnalysis, MyAnalysisData};
+/// use std::collections::HashMap;
+///
+/// let mut expr = RecExpr::default();
+/// let a_id = expr.add(Language::Symbol("a".to_string()));
+/// let a_id = expr.add(Language::AccessTensor(a_id));
+/// let b_id = expr.add(Language::Symbol("b".to_string()));
+/// let b_id = expr.add(Language::AccessTensor(b_id));
+/// batch_matmul(&mut expr, a_id, &[2, 3, 4], b_id, &[2, 5, 4]);
+///
+/// let mut map = HashMap::default();
+/// map.insert("a".to_string(), vec![2, 3, 4]);
+/// map.insert("b".to_string(), vec![2, 5, 4]);
+/// let mut egraph = EGraph::new(MyAnalysis {
+///     name_to_shape: map,
+///     name_to_dtype: HashMap::default(),
+/// });
+/// let id = egraph.add_expr(&expr);
+/// match &egraph[id].data {
+///     MyAnalysisData::AccessPattern(a) => assert_eq!(a.as_vec(), vec![2, 3, 5]),
+///     _ => panic!(),
+/// }
+/// ```
+pub fn batch_matmul(
+    expr: &mut RecExpr<Language>,
+    a_id: Id,
+    a_shape: &[usize],
+    b_id: Id,
+    b_shape: &[usize],
+) -> Id {
+    assert_eq!(a_shape.len(), 3);
+    assert_eq!(b_shape.len(), 3);
+    assert_eq!(a_shape[2], b_shape[2]);
+    assert!(
+        a_shape[0] == b_shape[0] || a_shape[0] == 1 || b_shape[0] == 1,
+        "Batch dimensions {} and {} can't be broadcast together",
+        a_shape[0],
+        b_shape[0]
+    );
+
+    let out_shape = [
+        std::cmp::max(a_shape[0], b_shape[0]),
+        a_shape[1],
+        b_shape[1],
+        a_shape[2],
+    ];
+
+    let a_id = access_insert_axis(expr, a_id, 2);
+    let access_shape_id = access_shape(expr, &out_shape, &[]);
+    let a_id = expr.add(Language::AccessBroadcast([a_id, access_shape_id]));
+    // Result is [batch m n k]
+
+    let b_id = access_insert_axis(expr, b_id, 1);
+    let access_shape_id = access_shape(expr, &out_shape, &[]);
+    let b_id = expr.add(Language::AccessBroadcast([b_id, access_shape_id]));
+    // Result is [batch m n k]
+
+    let pair_id = access_pair(expr, a_id, b_id, 3);
+    compute(expr, ComputeType::DotProduct, pair_id)
+}
+
+/// Reads a list of integer attributes, such as a pooling operator's padding.
+fn usize_list(list: &tvm::runtime::array::Array<tvm::ir::expr::PrimExpr>) -> Vec<usize> {
+    (0..list.len())
+        .map(|i| {
+            list.get(i as isize)
+                .unwrap()
+                .downcast::<IntImm>()
+                .unwrap()
+                .value as usize
+        })
+        .collect()
+}
+
+/// Reads the attribute `name` out of a call's `attrs` through TVM's
+/// reflection. This version of TVM's Rust bindings only has typed attributes
+/// for some operators; this reads the attributes of the rest.
+fn attr<T>(attrs: &tvm::ir::attrs::Attrs, name: &str) -> T
+where
+    T: TryFrom<tvm::runtime::RetValue>,
+    T::Error: std::fmt::Debug,
+{
+    let key = tvm::runtime::String::from(name.to_string());
+    let value = tvm::runtime::Function::get("node.NodeGetAttr")
+        .unwrap()
+        .invoke(vec![attrs.into(), (&key).into()])
+        .unwrap_or_else(|e| panic!("Could not read attribute {}: {}", name, e));
+    T::try_from(value)
+        .unwrap_or_else(|e| panic!("Attribute {} has an unexpected type: {:?}", name, e))
+}
+
+/// Reads a list of integer attributes, such as transpose's axes. An unset
+/// list reads as empty.
+fn int_list_attr(attrs: &tvm::ir::attrs::Attrs, name: &str) -> Vec<i64> {
+    match attr::<tvm::runtime::RetValue>(attrs, name) {
+        tvm::runtime::RetValue::Null => Vec::default(),
+        value => {
+            let list =
+                tvm::runtime::array::Array::<tvm::ir::expr::PrimExpr>::try_from(value).unwrap();
+            (0..list.len())
+                .map(|i| {
+                    list.get(i as isize)
+                        .unwrap()
+                        .downcast::<IntImm>()
+                        .unwrap()
+                        .value
+                })
+                .collect()
+        }
+    }
+}
+
 /// Create access shape literal
 ///
 /// ```
@@ -402,6 +744,47 @@ pub fn access_insert_axis(expr: &mut RecExpr<Language>, id: Id, axis: usize) ->
     expr.add(Language::AccessInsertAxis([id, axis_id]))
 }
 
+/// Reshape an access, giving it the access shape `shape` and item shape
+/// `item_shape`
+///
+/// ```
+/// use std::str::FromStr;
+/// use glenside::language::from_relay::access_reshape;
+/// use egg::RecExpr;
+///
+/// let mut expr = RecExpr::from_str("(access (access-tensor a) 0)").unwrap();
+/// let id = access_reshape(&mut expr, 3.into(), &[], &[2, 3]);
+/// assert_eq!(
+///     expr.pretty(80),
+///     "(access-reshape (access (access-tensor a) 0) (access-shape (shape) (shape 2 3)))"
+/// );
+/// ```
+pub fn access_reshape(
+    expr: &mut RecExpr<Language>,
+    id: Id,
+    shape: &[usize],
+    item_shape: &[usize],
+) -> Id {
+    let access_shape_id = access_shape(expr, shape, item_shape);
+    expr.add(Language::AccessReshape([id, access_shape_id]))
+}
+
+/// Squeeze an axis of size 1 out of an access
+///
+/// ```
+/// use std::str::FromStr;
+/// use glenside::language::from_relay::access_squeeze;
+/// use egg::RecExpr;
+///
+/// let mut expr = RecExpr::from_str("(access-tensor a)").unwrap();
+/// let id = access_squeeze(&mut expr, 1.into(), 2);
+/// assert_eq!(expr.pretty(80), "(access-squeeze (access-tensor a) 2)");
+/// ```
+pub fn access_squeeze(expr: &mut RecExpr<Language>, id: Id, axis: usize) -> Id {
+    let axis_id = expr.add(Language::Usize(axis));
+    expr.add(Language::AccessSqueeze([id, axis_id]))
+}
+
 /// Given the input access and compute type, add compute expression
 ///
 /// ```
@@ -485,6 +868,29 @@ pub fn shape_from_type(t: tvm::ir::ty::Type) -> Vec<usize> {
     shape
 }
 
+/// Converts a possibly-negative Relay axis into an index into a shape with
+/// `ndim` dimensions.
+fn normalize_axis(axis: i64, ndim: usize) -> usize {
+    let normalized = if axis < 0 { axis + ndim as i64 } else { axis };
+    assert!(
+        normalized >= 0 && (normalized as usize) < ndim,
+        "Axis {} out of range for a tensor with {} dimensions",
+        axis,
+        ndim
+    );
+    normalized as usize
+}
+
+/// Get the name of the primitive operator `call` calls, if it calls one.
+fn primitive_op_name(call: &Call) -> Option<String> {
+    call.op
+        .clone()
+        .upcast::<tvm::ir::expr::BaseExpr>()
+        .downcast::<tvm::ir::op::Op>()
+        .ok()
+        .map(|op| op.name.as_str().unwrap().to_string())
+}
+
 /// Convert Relay IRModule to Glenside RecExpr.
 ///
 /// Returns the RecExpr, along with a Vec mapping symbols to their shapes.
@@ -518,6 +924,129 @@ pub fn from_relay(module: &IRModule) -> (RecExpr<Language>, Vec<(String, Vec<usi
     (glenside_expr, names_and_shapes)
 }
 
+/// The Relay operators which [`from_relay`] can compile. Keep in sync with
+/// [`compile_expression`]; the `supported_operators_import` test checks that
+/// each one imports.
+pub static SUPPORTED_OPERATORS: &[&str] = &[
+    "nn.softmax",
+    "sum",
+    "mean",
+    "max",
+    "min",
+    "argmax",
+    "nn.relu",
+    "sqrt",
+    "negative",
+    "sigmoid",
+    "tanh",
+    "exp",
+    "clip",
+    "nn.leaky_relu",
+    "nn.max_pool2d",
+    "nn.avg_pool2d",
+    "nn.global_avg_pool2d",
+    "nn.global_max_pool2d",
+    "expand_dims",
+    "nn.dense",
+    "nn.batch_matmul",
+    "add",
+    "multiply",
+    "divide",
+    "nn.batch_flatten",
+    "nn.bias_add",
+    "nn.conv2d",
+    "reshape",
+    "transpose",
+    "squeeze",
+    "concatenate",
+    "split",
+    "strided_slice",
+];
+
+/// Finds the first construct in `module`'s main function, in evaluation
+/// order, which [`from_relay`] can't compile. Returns a description of it
+/// (for operators, just the operator's name), or `None` if the whole module
+/// is supported.
+pub fn find_unsupported_operator(module: &IRModule) -> Option<String> {
+    fn helper(relay_expr: Expr, visited: &mut HashSet<Expr>) -> Option<String> {
+        // Subexpressions can be shared; only check each once.
+        if !visited.insert(relay_expr.clone()) {
+            return None;
+        }
+        if relay_expr.clone().downcast::<tvm::ir::relay::Var>().is_ok()
+            || relay_expr
+                .clone()
+                .downcast::<tvm::ir::relay::Constant>()
+                .is_ok()
+        {
+            None
+        } else if let Ok(tuple) = relay_expr.clone().downcast::<tvm::ir::relay::Tuple>() {
+            for i in 0..tuple.fields.len() {
+                if let Some(unsupported) =
+                    helper(tuple.fields.get(i.try_into().unwrap()).unwrap(), visited)
+                {
+                    return Some(unsupported);
+                }
+            }
+            None
+        } else if let Ok(tuple_get_item) = relay_expr
+            .clone()
+            .downcast::<tvm::ir::relay::TupleGetItem>()
+        {
+            if !is_compilable_tuple(&tuple_get_item.tuple) {
+                return Some(
+                    "projection out of a tuple which isn't written out in the program or a split"
+                        .to_string(),
+                );
+            }
+            helper(tuple_get_item.tuple.clone(), visited)
+        } else if let Ok(call) = relay_expr.clone().downcast::<tvm::ir::relay::Call>() {
+            for i in 0..call.args.len() {
+                if let Some(unsupported) =
+                    helper(call.args.get(i.try_into().unwrap()).unwrap(), visited)
+                {
+                    return Some(unsupported);
+                }
+            }
+            if primitive_op_name(&call).as_deref() == Some("concatenate")
+                && !is_compilable_tuple(&call.args.get(0).unwrap())
+            {
+                return Some(
+                    "concatenate of a tuple which isn't written out in the program or a split"
+                        .to_string(),
+                );
+            }
+            match call
+                .op
+                .clone()
+                .upcast::<tvm::ir::expr::BaseExpr>()
+                .downcast::<tvm::ir::op::Op>()
+            {
+                Ok(primitive_op) => {
+                    let name = primitive_op.name.as_str().unwrap();
+                    if SUPPORTED_OPERATORS.contains(&name) {
+                        None
+                    } else {
+                        Some(name.to_string())
+                    }
+                }
+                Err(_) => Some("call to a non-primitive function".to_string()),
+            }
+        } else {
+            Some(format!(
+                "expression {}",
+                tvm::ir::expr::as_text(relay_expr.clone())
+            ))
+        }
+    }
+
+    let main = module
+        .lookup(module.get_global_var("main".to_string().into()).unwrap())
+        .unwrap();
+    let func = main.downcast::<tvm::ir::relay::Function>().unwrap();
+    helper(func.body.clone(), &mut HashSet::new())
+}
+
 /// Generates an ordered list of Relay expressions to compile.
 ///
 /// Compiling large Relay expressions with naive recursion overflows the stack,
@@ -530,15 +1059,41 @@ fn create_worklist(relay_expr: Expr, worklist: &mut Vec<Expr>) {
             worklist.push(expr.clone());
         }
     }
+    fn add_tuple_to_worklist(tuple: Expr, worklist: &mut Vec<Expr>) {
+        match tuple.clone().downcast::<tvm::ir::relay::Call>() {
+            // Fields of a split are compiled as slices of the split's input,
+            // so the split itself is never compiled.
+            Ok(call) if primitive_op_name(&call).as_deref() == Some("split") => {
+                for i in 0..call.args.len() {
+                    create_worklist(call.args.get(i.try_into().unwrap()).unwrap(), worklist);
+                }
+            }
+            _ => create_worklist(tuple, worklist),
+        }
+    }
     if let Ok(_var) = relay_expr.clone().downcast::<tvm::ir::relay::Var>() {
         add_to_worklist(relay_expr.clone(), worklist);
     } else if let Ok(_constant) = relay_expr.clone().downcast::<tvm::ir::relay::Constant>() {
         add_to_worklist(relay_expr.clone(), worklist);
     } else if let Ok(call) = relay_expr.clone().downcast::<tvm::ir::relay::Call>() {
         for i in 0..call.args.len() {
-            // Recursively add children (and their dependencies) to the worklist
-            create_worklist(call.args.get(i.try_into().unwrap()).unwrap(), worklist);
+            // Recursively add children (and their dependencies) to the
+            // worklist. A split can only be an argument of an operator which
+            // takes a tuple, e.g. concatenate.
+            add_tuple_to_worklist(call.args.get(i.try_into().unwrap()).unwrap(), worklist);
+        }
+        add_to_worklist(relay_expr.clone(), worklist);
+    } else if let Ok(tuple) = relay_expr.clone().downcast::<tvm::ir::relay::Tuple>() {
+        // Tuples aren't compiled themselves; the operators which take tuples
+        // (e.g. concatenate) use the tuple's fields directly.
+        for i in 0..tuple.fields.len() {
+            create_worklist(tuple.fields.get(i.try_into().unwrap()).unwrap(), worklist);
         }
+    } else if let Ok(tuple_get_item) = relay_expr
+        .clone()
+        .downcast::<tvm::ir::relay::TupleGetItem>()
+    {
+        add_tuple_to_worklist(tuple_get_item.tuple.clone(), worklist);
         add_to_worklist(relay_expr.clone(), worklist);
     } else {
         todo!()
@@ -604,7 +1159,8 @@ fn compile_expression(
             NotNan::<f64>::new(value as f64).unwrap(),
         ));
         let literal_id = glenside_expr.add(Language::Literal(literal_id));
-        let access_literal_id = glenside_expr.add(Language::AccessLiteral(literal_id));
+        let dtype_id = glenside_expr.add(Language::DataType(DType::Fp32));
+        let access_literal_id = glenside_expr.add(Language::AccessLiteral([literal_id, dtype_id]));
         access_literal_id
     } else if let Ok(call) = relay_expr.clone().downcast::<tvm::ir::relay::Call>() {
         if let Ok(primitive_op) = call
@@ -622,31 +1178,71 @@ fn compile_expression(
                         .clone()
                         .downcast::<tvm::ir::relay::attrs::nn::SoftmaxAttrs>()
                         .unwrap();
-                    match attrs.axis {
-                        -1 => {
-                            let data_id = access(
-                                glenside_expr,
-                                data_id,
-                                (call
-                                    .args
-                                    .get(0)
-                                    .unwrap()
-                                    .checked_type
-                                    .clone()
-                                    .downcast::<TensorType>()
-                                    .unwrap()
-                                    .shape
-                                    .len()
-                                    - 1)
-                                .try_into()
-                                .unwrap(),
-                            );
-                            compute(glenside_expr, ComputeType::Softmax, data_id)
-                        }
-                        other @ _ => todo!("Softmax with axis value {} not yet supported", other),
+                    let ndim =
+                        shape_from_type(call.args.get(0).unwrap().checked_type.clone()).len();
+                    let axis = normalize_axis(attrs.axis.into(), ndim);
+                    if axis == ndim - 1 {
+                        let data_id = access(glenside_expr, data_id, ndim - 1);
+                        compute(glenside_expr, ComputeType::Softmax, data_id)
+                    } else {
+                        // Softmax is computed over the last axis, so move the
+                        // softmax axis to the end and back again.
+                        let transpose_list = (0..ndim)
+                            .filter(|&i| i != axis)
+                            .chain(std::iter::once(axis))
+                            .collect::<Vec<_>>();
+                        let data_id = access_transpose(glenside_expr, data_id, &transpose_list);
+                        let data_id = access(glenside_expr, data_id, ndim - 1);
+                        let data_id = compute(glenside_expr, ComputeType::Softmax, data_id);
+                        let inverse_list = (0..ndim)
+                            .map(|i| transpose_list.iter().position(|&j| j == i).unwrap())
+                            .collect::<Vec<_>>();
+                        access_transpose(glenside_expr, data_id, &inverse_list)
                     }
                 }
-                "nn.relu" | "sqrt" | "negative" => {
+                "sum" | "mean" | "max" | "min" | "argmax" => {
+                    assert_eq!(call.args.len(), 1);
+                    // In this version of TVM, argmax takes ReduceAttrs, which
+                    // has no select_last_index: ties always go to the first
+                    // index, as they do in ComputeType::ArgMax.
+                    let ndim =
+                        shape_from_type(call.args.get(0).unwrap().checked_type.clone()).len();
+
+                    // An unspecified axis list reduces over every axis.
+                    let axes = int_list_attr(&call.attrs, "axis");
+                    let axes = if axes.is_empty() {
+                        (0..ndim).collect::<Vec<_>>()
+                    } else {
+                        axes.into_iter()
+                            .map(|axis| normalize_axis(axis, ndim))
+                            .collect::<Vec<_>>()
+                    };
+                    let axes = if attr::<i64>(&call.attrs, "exclude") != 0 {
+                        (0..ndim)
+                            .filter(|axis| !axes.contains(axis))
+                            .collect::<Vec<_>>()
+                    } else {
+                        axes
+                    };
+
+                    let data_id = get_compiled_expression(call.args.get(0).unwrap());
+                    reduce(
+                        glenside_expr,
+                        data_id,
+                        match primitive_op.name.as_str().unwrap() {
+                            "sum" => ComputeType::ReduceSum,
+                            "mean" => ComputeType::ReduceMean,
+                            "max" => ComputeType::ReduceMax,
+                            "min" => ComputeType::ReduceMin,
+                            "argmax" => ComputeType::ArgMax,
+                            _ => unreachable!(),
+                        },
+                        ndim,
+                        &axes,
+                        attr::<i64>(&call.attrs, "keepdims") != 0,
+                    )
+                }
+                "nn.relu" | "sqrt" | "negative" | "sigmoid" | "tanh" | "exp" => {
                     assert_eq!(call.args.len(), 1);
                     let data_id = get_compiled_expression(call.args.get(0).unwrap());
                     compute(
@@ -655,11 +1251,35 @@ fn compile_expression(
                             "nn.relu" => ComputeType::ReLU,
                             "sqrt" => ComputeType::Sqrt,
                             "negative" => ComputeType::Negative,
+                            "sigmoid" => ComputeType::Sigmoid,
+                            "tanh" => ComputeType::Tanh,
+                            "exp" => ComputeType::Exp,
                             _ => unreachable!(),
                         },
                         data_id,
                     )
                 }
+                "clip" => {
+                    assert_eq!(call.args.len(), 1);
+                    let a_min = attr::<f64>(&call.attrs, "a_min");
+                    let a_max = attr::<f64>(&call.attrs, "a_max");
+                    let data_id = get_compiled_expression(call.args.get(0).unwrap());
+                    let min_id =
+                        glenside_expr.add(Language::NotNanFloat64(NotNan::new(a_min).unwrap()));
+                    let max_id =
+                        glenside_expr.add(Language::NotNanFloat64(NotNan::new(a_max).unwrap()));
+                    let clip_id = glenside_expr.add(Language::Clip([min_id, max_id]));
+                    glenside_expr.add(Language::Compute([clip_id, data_id]))
+                }
+                "nn.leaky_relu" => {
+                    assert_eq!(call.args.len(), 1);
+                    let alpha = attr::<f64>(&call.attrs, "alpha");
+                    let data_id = get_compiled_expression(call.args.get(0).unwrap());
+                    let alpha_id =
+                        glenside_expr.add(Language::NotNanFloat64(NotNan::new(alpha).unwrap()));
+                    let leaky_relu_id = glenside_expr.add(Language::LeakyReLU(alpha_id));
+                    glenside_expr.add(Language::Compute([leaky_relu_id, data_id]))
+                }
                 "nn.max_pool2d" => {
                     assert_eq!(call.args.len(), 1);
                     let attrs = call
@@ -667,151 +1287,64 @@ fn compile_expression(
                         .clone()
                         .downcast::<tvm::ir::relay::attrs::nn::MaxPool2DAttrs>()
                         .unwrap();
-                    assert_eq!(
-                        call.args
-                            .get(0)
-                            .unwrap()
-                            .checked_type
-                            .clone()
-                            .downcast::<TensorType>()
-                            .unwrap()
-                            .shape
-                            .len(),
-                        4
-                    );
-                    assert_eq!(attrs.pool_size.len(), 2);
-                    assert_eq!(attrs.padding.len(), 4);
-                    assert_eq!(attrs.strides.len(), 2);
                     assert_eq!(attrs.ceil_mode, false);
 
-                    match attrs.layout.as_str().unwrap() {
-                        "NCHW" => {
-                            let data_id = get_compiled_expression(call.args.get(0).unwrap());
-                            let data_id = access_pad(
-                                glenside_expr,
-                                data_id,
-                                PadType::MinPadding,
-                                2,
-                                attrs
-                                    .padding
-                                    .get(0)
-                                    .unwrap()
-                                    .downcast::<IntImm>()
-                                    .unwrap()
-                                    .value as usize,
-                                attrs
-                                    .padding
-                                    .get(2)
-                                    .unwrap()
-                                    .downcast::<IntImm>()
-                                    .unwrap()
-                                    .value as usize,
-                            );
-                            let data_id = access_pad(
-                                glenside_expr,
-                                data_id,
-                                PadType::MinPadding,
-                                3,
-                                attrs
-                                    .padding
-                                    .get(1)
-                                    .unwrap()
-                                    .downcast::<IntImm>()
-                                    .unwrap()
-                                    .value as usize,
-                                attrs
-                                    .padding
-                                    .get(3)
-                                    .unwrap()
-                                    .downcast::<IntImm>()
-                                    .unwrap()
-                                    .value as usize,
-                            );
-                            let data_id = access(glenside_expr, data_id, 4);
-
-                            let stride_shape_id = shape(
-                                glenside_expr,
-                                vec![
-                                    1,
-                                    1,
-                                    attrs
-                                        .strides
-                                        .get(0)
-                                        .unwrap()
-                                        .downcast::<IntImm>()
-                                        .unwrap()
-                                        .value as usize,
-                                    attrs
-                                        .strides
-                                        .get(1)
-                                        .unwrap()
-                                        .downcast::<IntImm>()
-                                        .unwrap()
-                                        .value as usize,
-                                ],
-                            );
-                            let pool_window_shape_id = shape(
-                                glenside_expr,
-                                vec![
-                                    1,
-                                    1,
-                                    attrs
-                                        .pool_size
-                                        .get(0)
-                                        .unwrap()
-                                        .downcast::<IntImm>()
-                                        .unwrap()
-                                        .value as usize,
-                                    attrs
-                                        .pool_size
-                                        .get(1)
-                                        .unwrap()
-                                        .downcast::<IntImm>()
-                                        .unwrap()
-                                        .value as usize,
-                                ],
-                            );
-
-                            let data_id = glenside_expr.add(Language::AccessWindows([
-                                data_id,
-                                pool_window_shape_id,
-                                stride_shape_id,
-                            ]));
-
-                            let data_id = access(glenside_expr, data_id, 4);
-
-                            let data_id = compute(glenside_expr, ComputeType::ReduceMax, data_id);
+                    let data_id = get_compiled_expression(call.args.get(0).unwrap());
+                    pool2d(
+                        glenside_expr,
+                        data_id,
+                        &shape_from_type(call.args.get(0).unwrap().checked_type.clone()),
+                        ComputeType::ReduceMax,
+                        &usize_list(&attrs.pool_size),
+                        &usize_list(&attrs.strides),
+                        &usize_list(&attrs.padding),
+                        false,
+                        attrs.layout.as_str().unwrap(),
+                    )
+                }
+                "nn.avg_pool2d" => {
+                    assert_eq!(call.args.len(), 1);
+                    assert_eq!(attr::<i64>(&call.attrs, "ceil_mode"), 0);
+                    let usize_list_attr = |name: &str| {
+                        int_list_attr(&call.attrs, name)
+                            .into_iter()
+                            .map(|i| i as usize)
+                            .collect::<Vec<_>>()
+                    };
 
-                            data_id
-                        }
-                        other @ _ => todo!("layout {} not supported", other),
-                    }
+                    let data_id = get_compiled_expression(call.args.get(0).unwrap());
+                    pool2d(
+                        glenside_expr,
+                        data_id,
+                        &shape_from_type(call.args.get(0).unwrap().checked_type.clone()),
+                        ComputeType::ReduceMean,
+                        &usize_list_attr("pool_size"),
+                        &usize_list_attr("strides"),
+                        &usize_list_attr("padding"),
+                        attr::<i64>(&call.attrs, "count_include_pad") != 0,
+                        &attr::<String>(&call.attrs, "layout"),
+                    )
                 }
-                "nn.global_avg_pool2d" => {
+                "nn.global_avg_pool2d" | "nn.global_max_pool2d" => {
+                    assert_eq!(call.args.len(), 1);
                     let attrs = call
                         .attrs
                         .clone()
                         .downcast::<tvm::ir::relay::attrs::nn::GlobalPool2DAttrs>()
                         .unwrap();
-                    assert_eq!(call.args.len(), 1);
-                    assert_eq!(
-                        attrs.layout, "NCHW",
-                        "NCHW is the only layout currently supported"
-                    );
 
-                    match attrs.layout.as_str().unwrap() {
-                        "NCHW" => {
-                            let data_id = get_compiled_expression(call.args.get(0).unwrap());
-                            let data_id = access(glenside_expr, data_id, 2);
-                            let data_id = compute(glenside_expr, ComputeType::ReduceMean, data_id);
-                            let data_id = access_insert_axis(glenside_expr, data_id, 2);
-                            let data_id = access_insert_axis(glenside_expr, data_id, 3);
-                            let data_id = access(glenside_expr, data_id, 2);
-                            data_id
-                        }
-                        _ => todo!("layout not currently supported"),
-                    }
-                }
+                    let data_id = get_compiled_expression(call.args.get(0).unwrap());
+                    global_pool2d(
+                        glenside_expr,
+                        data_id,
+                        match primitive_op.name.as_str().unwrap() {
+                            "nn.global_avg_pool2d" => ComputeType::ReduceMean,
+                            "nn.global_max_pool2d" => ComputeType::ReduceMax,
+                            _ => unreachable!(),
+                        },
+                        attrs.layout.as_str().unwrap(),
+                    )
+                }
                 "expand_dims" => {
                     let attrs = call
                         .attrs
@@ -882,6 +1415,15 @@ fn compile_expression(
                         glenside_expr.add(Language::AccessCartesianProduct([data_id, weights_id]));
                     compute(glenside_expr, ComputeType::DotProduct, data_id)
                 }
+                "nn.batch_matmul" => {
+                    assert_eq!(call.args.len(), 2);
+                    let a_id = get_compiled_expression(call.args.get(0).unwrap());
+                    let a_shape = shape_from_type(call.args.get(0).unwrap().checked_type.clone());
+                    let b_id = get_compiled_expression(call.args.get(1).unwrap());
+                    let b_shape = shape_from_type(call.args.get(1).unwrap().checked_type.clone());
+
+                    batch_matmul(glenside_expr, a_id, &a_shape, b_id, &b_shape)
+                }
                 "add" | "multiply" | "divide" => {
                     assert_eq!(call.args.len(), 2);
                     let mut a_id = get_compiled_expression(call.args.get(0).unwrap());
@@ -1151,16 +1693,218 @@ fn compile_expression(
                         attrs.out_layout.as_str().unwrap(),
                     )
                 }
+                "reshape" => {
+                    assert_eq!(call.args.len(), 1);
+                    // Relay's newshape can contain special values (e.g. -1),
+                    // so we take the new shape from the call's type instead.
+                    let new_shape = shape_from_type(relay_expr.checked_type.clone());
+
+                    let data_id = get_compiled_expression(call.args.get(0).unwrap());
+                    let data_id = access(glenside_expr, data_id, 0);
+                    access_reshape(glenside_expr, data_id, &[], &new_shape)
+                }
+                "transpose" => {
+                    assert_eq!(call.args.len(), 1);
+                    let ndim =
+                        shape_from_type(call.args.get(0).unwrap().checked_type.clone()).len();
+
+                    // With no axes given, transpose reverses the axes.
+                    let axes = int_list_attr(&call.attrs, "axes");
+                    let axes = if axes.is_empty() {
+                        (0..ndim).rev().collect::<Vec<_>>()
+                    } else {
+                        axes.into_iter()
+                            .map(|axis| normalize_axis(axis, ndim))
+                            .collect::<Vec<_>>()
+                    };
+
+                    let data_id = get_compiled_expression(call.args.get(0).unwrap());
+                    access_transpose(glenside_expr, data_id, &axes)
+                }
+                "squeeze" => {
+                    assert_eq!(call.args.len(), 1);
+                    let data_shape =
+                        shape_from_type(call.args.get(0).unwrap().checked_type.clone());
+                    let out_shape = shape_from_type(relay_expr.checked_type.clone());
+
+                    // Find the axes which were squeezed by lining the input
+                    // shape up against the output shape. When there's a
+                    // choice of which axis of size 1 to squeeze, the result
+                    // is the same either way.
+                    let mut squeezed_axes = Vec::default();
+                    let mut out_axis = 0;
+                    for (axis, dim) in data_shape.iter().enumerate() {
+                        if out_axis < out_shape.len() && out_shape[out_axis] == *dim {
+                            out_axis += 1;
+                        } else {
+                            assert_eq!(*dim, 1, "Can only squeeze axes of size 1");
+                            squeezed_axes.push(axis);
+                        }
+                    }
+                    assert_eq!(out_axis, out_shape.len());
+
+                    let mut data_id = get_compiled_expression(call.args.get(0).unwrap());
+                    // Squeeze from the back, so that the axes we haven't
+                    // squeezed yet keep their indices.
+                    for axis in squeezed_axes.into_iter().rev() {
+                        data_id = access_squeeze(glenside_expr, data_id, axis);
+                    }
+                    data_id
+                }
+                "concatenate" => {
+                    assert_eq!(call.args.len(), 1);
+                    let tuple = call.args.get(0).unwrap();
+                    let num_fields: usize = tuple
+                        .checked_type
+                        .clone()
+                        .downcast::<TupleType>()
+                        .unwrap()
+                        .fields
+                        .len()
+                        .try_into()
+                        .unwrap();
+                    assert!(num_fields >= 1);
+                    let ndim = shape_from_type(relay_expr.checked_type.clone()).len();
+                    let axis = normalize_axis(attr::<i64>(&call.attrs, "axis"), ndim);
+
+                    // The accesses being concatenated must have the same
+                    // access axis, so we re-access them all at 0.
+                    let mut data_id = compile_tuple_field(
+                        tuple.clone(),
+                        0,
+                        glenside_expr,
+                        &get_compiled_expression,
+                    );
+                    data_id = access(glenside_expr, data_id, 0);
+                    for i in 1..num_fields {
+                        let field_id = compile_tuple_field(
+                            tuple.clone(),
+                            i,
+                            glenside_expr,
+                            &get_compiled_expression,
+                        );
+                        let field_id = access(glenside_expr, field_id, 0);
+                        data_id = access_concatenate(glenside_expr, data_id, field_id, axis);
+                    }
+                    data_id
+                }
+                "strided_slice" => {
+                    assert_eq!(call.args.len(), 1);
+                    // In "size" mode, `end` gives the size of each slice
+                    // rather than where it ends, and the strides are ignored.
+                    let size_mode = attr::<String>(&call.attrs, "slice_mode") == "size";
+                    if !size_mode {
+                        assert!(
+                            int_list_attr(&call.attrs, "strides")
+                                .into_iter()
+                                .all(|stride| stride == 1),
+                            "Only strided slices with strides of 1 are supported"
+                        );
+                    }
+                    let data_shape =
+                        shape_from_type(call.args.get(0).unwrap().checked_type.clone());
+                    // The type tells us how long each sliced axis is, whether
+                    // the slice is given by its ends or by its sizes.
+                    let out_shape = shape_from_type(relay_expr.checked_type.clone());
+                    assert_eq!(data_shape.len(), out_shape.len());
+
+                    let begins = int_list_attr(&call.attrs, "begin");
+                    let mut data_id = get_compiled_expression(call.args.get(0).unwrap());
+                    for axis in 0..data_shape.len() {
+                        let begin = if axis < begins.len() {
+                            let begin = begins[axis];
+                            if begin < 0 {
+                                (begin + data_shape[axis] as i64).max(0) as usize
+                            } else {
+                                (begin as usize).min(data_shape[axis])
+                            }
+                        } else {
+                            0
+                        };
+                        if begin != 0 || out_shape[axis] != data_shape[axis] {
+                            data_id = access_slice(
+                                glenside_expr,
+                                data_id,
+                                axis,
+                                begin,
+                                begin + out_shape[axis],
+                            );
+                        }
+                    }
+                    data_id
+                }
                 _ => todo!(),
             }
         } else {
             todo!()
         }
+    } else if let Ok(tuple_get_item) = relay_expr
+        .clone()
+        .downcast::<tvm::ir::relay::TupleGetItem>()
+    {
+        compile_tuple_field(
+            tuple_get_item.tuple.clone(),
+            tuple_get_item.index.try_into().unwrap(),
+            glenside_expr,
+            &get_compiled_expression,
+        )
     } else {
         todo!()
     }
 }
 
+/// Whether [`compile_tuple_field`] can compile the fields of `tuple`.
+fn is_compilable_tuple(tuple: &Expr) -> bool {
+    tuple.clone().downcast::<tvm::ir::relay::Tuple>().is_ok()
+        || matches!(
+            tuple.clone().downcast::<tvm::ir::relay::Call>(),
+            Ok(call) if primitive_op_name(&call).as_deref() == Some("split")
+        )
+}
+
+/// Compiles field `index` of `tuple`, which must be a tuple written out in the
+/// program or a call to split. Tuples aren't compiled themselves: a written-out
+/// tuple's field is the compiled field expression, and a split's field is a
+/// slice of the split's input.
+fn compile_tuple_field(
+    tuple: Expr,
+    index: usize,
+    glenside_expr: &mut RecExpr<Language>,
+    get_compiled_expression: &impl Fn(Expr) -> Id,
+) -> Id {
+    if let Ok(tuple) = tuple.clone().downcast::<tvm::ir::relay::Tuple>() {
+        get_compiled_expression(tuple.fields.get(index.try_into().unwrap()).unwrap())
+    } else if let Ok(split) = tuple.clone().downcast::<tvm::ir::relay::Call>() {
+        assert_eq!(
+            primitive_op_name(&split).as_deref(),
+            Some("split"),
+            "Only projections out of tuples and splits are supported"
+        );
+        assert_eq!(split.args.len(), 1);
+        let data_shape = shape_from_type(split.args.get(0).unwrap().checked_type.clone());
+        let axis = normalize_axis(attr::<i64>(&split.attrs, "axis"), data_shape.len());
+
+        // The split's type gives the size of each section; the section
+        // we want starts after all of the sections before it.
+        let sections = split
+            .clone()
+            .upcast::<Expr>()
+            .checked_type
+            .clone()
+            .downcast::<TupleType>()
+            .unwrap();
+        let section_length =
+            |i: usize| shape_from_type(sections.fields.get(i.try_into().unwrap()).unwrap())[axis];
+        let low = (0..index).map(section_length).sum::<usize>();
+        let high = low + section_length(index);
+
+        let data_id = get_compiled_expression(split.args.get(0).unwrap());
+        access_slice(glenside_expr, data_id, axis, low, high)
+    } else {
+        panic!("Only projections out of tuples and splits are supported")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::language::interpreter::interpret;
@@ -1234,6 +1978,7 @@ mod tests {
                 // from_relay.py. It can be simpler (e.g. collapsing accesses).
                 let mut egraph = EGraph::new(MyAnalysis {
                     name_to_shape: env.clone(),
+                    name_to_dtype: HashMap::default(),
                 });
                 let id = egraph.add_expr(&expr);
 
@@ -1356,6 +2101,76 @@ def @main(%x: Tensor[(1, 3, 32, 32), float32]) -> Tensor[(1, 3, 32, 32), float32
         Uniform::new(0f32, 1f32)
     );
 
+    test!(
+        sigmoid,
+        1e-6,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(1, 3, 32, 32), float32]) -> Tensor[(1, 3, 32, 32), float32] {
+  sigmoid(%x)
+}
+"#,
+        r#"
+(compute sigmoid (access-tensor x))
+"#
+    );
+
+    test!(
+        tanh,
+        1e-6,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(1, 3, 32, 32), float32]) -> Tensor[(1, 3, 32, 32), float32] {
+  tanh(%x)
+}
+"#,
+        r#"
+(compute tanh (access-tensor x))
+"#
+    );
+
+    test!(
+        exp,
+        1e-6,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(1, 3, 32, 32), float32]) -> Tensor[(1, 3, 32, 32), float32] {
+  exp(%x)
+}
+"#,
+        r#"
+(compute exp (access-tensor x))
+"#
+    );
+
+    test!(
+        clip,
+        1e-60,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(1, 3, 32, 32), float32]) -> Tensor[(1, 3, 32, 32), float32] {
+  clip(%x, a_min=-0.5f, a_max=0.75f)
+}
+"#,
+        r#"
+(compute (clip -0.5 0.75) (access-tensor x))
+"#
+    );
+
+    test!(
+        leaky_relu,
+        1e-7,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(1, 3, 32, 32), float32]) -> Tensor[(1, 3, 32, 32), float32] {
+  nn.leaky_relu(%x, alpha=0.25f)
+}
+"#,
+        r#"
+(compute (leaky-relu 0.25) (access-tensor x))
+"#
+    );
+
     test!(
         constant_0,
         1e-600,
@@ -1366,7 +2181,7 @@ def @main() -> float32 {
 }
 "#,
         r#"
-(access-literal (literal 0.01639530062675476))
+(access-literal (literal 0.01639530062675476) float32)
 "#
     );
 
@@ -1437,6 +2252,185 @@ def @main(%data: Tensor[(1, 3, 32, 32), float32]) -> Tensor[(1, 3, 17, 12), floa
 "#
     );
 
+    test!(
+        max_pool2d_nhwc,
+        1e-60,
+        r#"
+#[version = "0.0.5"]
+def @main(%data: Tensor[(1, 32, 32, 3), float32]) -> Tensor[(1, 17, 12, 3), float32] {
+  nn.max_pool2d(%data, pool_size=[3, 4], strides=[2, 3], padding=[1, 2, 3, 4], layout="NHWC") /* ty=Tensor[(1, 17, 12, 3), float32] */
+}
+"#,
+        r#"
+(access-transpose
+ (compute reduce-max
+  (access
+   (access-windows
+    (access
+     (access-pad
+      (access-pad
+       (access-transpose (access-tensor data) (list 0 3 1 2))
+       min-padding
+       2 1 3
+      )
+      min-padding
+      3 2 4
+     )
+     4
+    )
+    (shape 1 1 3 4)
+    (shape 1 1 2 3)
+   )
+   4
+  )
+ )
+ (list 0 2 3 1)
+)
+"#
+    );
+
+    test!(
+        avg_pool2d,
+        1e-6,
+        r#"
+#[version = "0.0.5"]
+def @main(%data: Tensor[(1, 3, 16, 16), float32]) -> Tensor[(1, 3, 8, 8), float32] {
+  nn.avg_pool2d(%data, pool_size=[3, 3], strides=[2, 2], padding=[1, 1, 1, 1], count_include_pad=True) /* ty=Tensor[(1, 3, 8, 8), float32] */
+}
+"#,
+        r#"
+(compute reduce-mean
+ (access
+  (access-windows
+   (access
+    (access-pad
+     (access-pad (access-tensor data) zero-padding 2 1 1)
+     zero-padding
+     3 1 1
+    )
+    4
+   )
+   (shape 1 1 3 3)
+   (shape 1 1 2 2)
+  )
+  4
+ )
+)
+"#
+    );
+
+    // Without count_include_pad, windows overlapping the padding are
+    // averaged over only their unpadded values.
+    test!(
+        avg_pool2d_exclude_pad,
+        1e-6,
+        r#"
+#[version = "0.0.5"]
+def @main(%data: Tensor[(1, 3, 16, 16), float32]) -> Tensor[(1, 3, 8, 8), float32] {
+  nn.avg_pool2d(%data, pool_size=[3, 3], strides=[2, 2], padding=[1, 1, 1, 1]) /* ty=Tensor[(1, 3, 8, 8), float32] */
+}
+"#,
+        r#"
+(compute elementwise-div
+ (access-pair
+  (access
+   (compute reduce-sum
+    (access
+     (access-windows
+      (access
+       (access-pad
+        (access-pad (access-tensor data) zero-padding 2 1 1)
+        zero-padding
+        3 1 1
+       )
+       4
+      )
+      (shape 1 1 3 3)
+      (shape 1 1 2 2)
+     )
+     4
+    )
+   )
+   4
+  )
+  (access
+   (compute reduce-sum
+    (access
+     (access-windows
+      (access
+       (access-pad
+        (access-pad
+         (access-broadcast
+          (access-insert-axis
+           (access-insert-axis
+            (access-insert-axis
+             (access-insert-axis (access-literal (literal ?one) float32) 0)
+             0
+            )
+            0
+           )
+           0
+          )
+          (access-shape (shape 1 3 16 16) (shape))
+         )
+         zero-padding
+         2 1 1
+        )
+        zero-padding
+        3 1 1
+       )
+       4
+      )
+      (shape 1 1 3 3)
+      (shape 1 1 2 2)
+     )
+     4
+    )
+   )
+   4
+  )
+ )
+)
+"#
+    );
+
+    test!(
+        avg_pool2d_nhwc,
+        1e-6,
+        r#"
+#[version = "0.0.5"]
+def @main(%data: Tensor[(1, 16, 16, 3), float32]) -> Tensor[(1, 8, 8, 3), float32] {
+  nn.avg_pool2d(%data, pool_size=[2, 2], strides=[2, 2], padding=[0, 0, 0, 0], layout="NHWC") /* ty=Tensor[(1, 8, 8, 3), float32] */
+}
+"#,
+        r#"
+(access-transpose
+ (compute reduce-mean
+  (access
+   (access-windows
+    (access
+     (access-pad
+      (access-pad
+       (access-transpose (access-tensor data) (list 0 3 1 2))
+       zero-padding
+       2 0 0
+      )
+      zero-padding
+      3 0 0
+     )
+     4
+    )
+    (shape 1 1 2 2)
+    (shape 1 1 2 2)
+   )
+   4
+  )
+ )
+ (list 0 2 3 1)
+)
+"#
+    );
+
     // The first part of a separable convolution, as seen in Mobilenet.
     test!(
         conv2d_depthwise_separable_stage1,
@@ -1447,9 +2441,41 @@ def @main(%data: Tensor[(1, 3, 32, 32), float32], %weight: Tensor[(3, 1, 3, 3),
   nn.conv2d(%data, %weight, strides=[1, 2], padding=[3, 4, 5, 6], groups=3)
 }
 "#,
-        // TODO(@gussmith23) I'm being lazy here
         r#"
-(access-concatenate ?a ?b ?c)
+(compute dot-product
+ (access-pair
+  (access-squeeze
+   (access-squeeze
+    (access-windows
+     (access
+      (access-pad
+       (access-pad (access-tensor data) zero-padding 2 3 5)
+       zero-padding 3 4 6
+      )
+      4
+     )
+     (shape 1 1 3 3)
+     (shape 1 1 1 2)
+    )
+    4
+   )
+   4
+  )
+  (access-broadcast
+   (access-insert-axis
+    (access-insert-axis
+     (access-insert-axis
+      (access-squeeze (access (access-tensor weight) 1) 1)
+      0
+     )
+     2
+    )
+    3
+   )
+   (access-shape (shape 1 3 38 20) (shape 3 3))
+  )
+ )
+)
 "#
     );
 
@@ -1520,18 +2546,65 @@ def @main(%data: Tensor[(1, 3, 32, 32), float32], %weights: Tensor[(8, 3, 3, 3),
     );
 
     test!(
-        conv2d_nhwc_hwio,
+        conv2d_dilated,
         1e-5,
         r#"
 #[version = "0.0.5"]
-def @main(%data: Tensor[(1, 32, 32, 3), float32], %weights: Tensor[(3, 3, 3, 8), float32]) -> Tensor[(1, 17, 12, 8), float32] {
-  nn.conv2d(%data, %weights, strides=[2, 3], padding=[1, 2, 3, 4], data_layout="NHWC", kernel_layout="HWIO")
+def @main(%data: Tensor[(1, 3, 32, 32), float32], %weights: Tensor[(8, 3, 3, 3), float32]) -> Tensor[(1, 8, 30, 30), float32] {
+  nn.conv2d(%data, %weights, padding=[1, 1, 1, 1], dilation=[2, 2]) /* ty=Tensor[(1, 8, 30, 30), float32] */
 }
 "#,
         r#"
 (access-transpose
- (access-transpose
-  (compute dot-product
+ (compute dot-product
+  (access-cartesian-product
+   (access (access-tensor weights) 1)
+   (access
+    (access-squeeze
+     (access-squeeze
+      (access-windows-dilated
+       (access
+        (access-pad
+         (access-pad
+          (access-tensor data)
+          zero-padding
+          2 1 1
+         )
+         zero-padding
+         3 1 1
+        )
+        4
+       )
+       (shape 1 3 3 3)
+       (shape 1 1 1 1)
+       (shape 1 1 2 2)
+      )
+      4
+     )
+     1
+    )
+    3
+   )
+  )
+ )
+ (list 1 0 2 3)
+)
+"#
+    );
+
+    test!(
+        conv2d_nhwc_hwio,
+        1e-5,
+        r#"
+#[version = "0.0.5"]
+def @main(%data: Tensor[(1, 32, 32, 3), float32], %weights: Tensor[(3, 3, 3, 8), float32]) -> Tensor[(1, 17, 12, 8), float32] {
+  nn.conv2d(%data, %weights, strides=[2, 3], padding=[1, 2, 3, 4], data_layout="NHWC", kernel_layout="HWIO")
+}
+"#,
+        r#"
+(access-transpose
+ (access-transpose
+  (compute dot-product
    (access-cartesian-product
     (access
      (access-transpose (access-tensor weights) (list 3 2 0 1))
@@ -1646,6 +2719,54 @@ def @main(%x: Tensor[(1, 3, 32, 32), float32]) -> Tensor[(1, 3, 1, 1), float32]
 "#
     );
 
+    test!(
+        global_avg_pool2d_nhwc,
+        1e-6,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(1, 32, 32, 3), float32]) -> Tensor[(1, 1, 1, 3), float32] {
+  nn.global_avg_pool2d(%x, layout="NHWC") /* ty=Tensor[(1, 1, 1, 3), float32] */
+}
+"#,
+        r#"
+(access
+ (access-insert-axis
+  (access-insert-axis
+   (compute reduce-mean
+    (access (access-transpose (access-tensor x) (list 0 3 1 2)) 2)
+   )
+   1
+  )
+  2
+ )
+ 3
+)
+"#
+    );
+
+    test!(
+        global_max_pool2d,
+        1e-60,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(1, 3, 32, 32), float32]) -> Tensor[(1, 3, 1, 1), float32] {
+  nn.global_max_pool2d(%x) /* ty=Tensor[(1, 3, 1, 1), float32] */
+}
+"#,
+        r#"
+(access
+ (access-insert-axis
+  (access-insert-axis
+   (compute reduce-max (access (access-tensor x) 2))
+   2
+  )
+  3
+ )
+ 2
+)
+"#
+    );
+
     test!(
         batch_flatten,
         1e-60,
@@ -1808,4 +2929,630 @@ def @main(%x: Tensor[(3), float32]) -> Tensor[(3), float32] {
 (compute softmax (access (compute softmax (access (access-tensor x) 0)) 0))
 "#
     );
+
+    test!(
+        reshape,
+        1e-60,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(2, 3, 4), float32]) -> Tensor[(6, 4), float32] {
+  reshape(%x, newshape=[6, -1]) /* ty=Tensor[(6, 4), float32] */
+}
+"#,
+        r#"
+(access-reshape (access (access-tensor x) 0) (access-shape (shape) (shape 6 4)))
+"#
+    );
+
+    test!(
+        transpose,
+        1e-60,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(2, 3, 4), float32]) -> Tensor[(4, 2, 3), float32] {
+  transpose(%x, axes=[2, 0, -2]) /* ty=Tensor[(4, 2, 3), float32] */
+}
+"#,
+        r#"
+(access-transpose (access-tensor x) (list 2 0 1))
+"#
+    );
+
+    test!(
+        transpose_no_axes,
+        1e-60,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(2, 3, 4), float32]) -> Tensor[(4, 3, 2), float32] {
+  transpose(%x) /* ty=Tensor[(4, 3, 2), float32] */
+}
+"#,
+        r#"
+(access-transpose (access-tensor x) (list 2 1 0))
+"#
+    );
+
+    test!(
+        squeeze,
+        1e-60,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(1, 3, 1, 4), float32]) -> Tensor[(3, 4), float32] {
+  squeeze(%x, axis=[0, 2]) /* ty=Tensor[(3, 4), float32] */
+}
+"#,
+        r#"
+(access-squeeze (access-squeeze (access-tensor x) 2) 0)
+"#
+    );
+
+    test!(
+        concatenate,
+        1e-60,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(2, 3), float32], %y: Tensor[(2, 5), float32]) -> Tensor[(2, 8), float32] {
+  %0 = (%x, %y);
+  concatenate(%0, axis=1) /* ty=Tensor[(2, 8), float32] */
+}
+"#,
+        r#"
+(access-concatenate (access (access-tensor x) 0) (access (access-tensor y) 0) 1)
+"#
+    );
+
+    test!(
+        concatenate_three_negative_axis,
+        1e-60,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(2, 3), float32], %y: Tensor[(4, 3), float32], %z: Tensor[(1, 3), float32]) -> Tensor[(7, 3), float32] {
+  %0 = (%x, %y, %z);
+  concatenate(%0, axis=-2) /* ty=Tensor[(7, 3), float32] */
+}
+"#,
+        r#"
+(access-concatenate
+ (access-concatenate (access (access-tensor x) 0) (access (access-tensor y) 0) 0)
+ (access (access-tensor z) 0)
+ 0
+)
+"#
+    );
+
+    test!(
+        split_sections,
+        1e-60,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(4, 6), float32]) -> Tensor[(4, 2), float32] {
+  %0 = split(%x, indices_or_sections=3, axis=1);
+  %0.1
+}
+"#,
+        r#"
+(access-slice (access-tensor x) 1 2 4)
+"#
+    );
+
+    test!(
+        split_indices,
+        1e-60,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(4, 6), float32]) -> Tensor[(4, 6), float32] {
+  %0 = split(%x, indices_or_sections=[1, 3], axis=0);
+  %1 = %0.2;
+  %2 = %0.0;
+  %3 = %0.1;
+  %4 = (%1, %2, %3);
+  concatenate(%4) /* ty=Tensor[(4, 6), float32] */
+}
+"#,
+        r#"
+(access-concatenate
+ (access-concatenate
+  (access (access-slice (access-tensor x) 0 3 4) 0)
+  (access (access-slice (access-tensor x) 0 0 1) 0)
+  0
+ )
+ (access (access-slice (access-tensor x) 0 1 3) 0)
+ 0
+)
+"#
+    );
+
+    test!(
+        strided_slice,
+        1e-60,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(4, 6, 8), float32]) -> Tensor[(2, 6, 5), float32] {
+  strided_slice(%x, begin=[1, 0, 2], end=[3, 6, 7], strides=[1]) /* ty=Tensor[(2, 6, 5), float32] */
+}
+"#,
+        r#"
+(access-slice (access-slice (access-tensor x) 0 1 3) 2 2 7)
+"#
+    );
+
+    test!(
+        strided_slice_negative_begin,
+        1e-60,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(4, 6), float32]) -> Tensor[(2, 6), float32] {
+  strided_slice(%x, begin=[-3], end=[-1], strides=[1]) /* ty=Tensor[(2, 6), float32] */
+}
+"#,
+        r#"
+(access-slice (access-tensor x) 0 1 3)
+"#
+    );
+
+    test!(
+        strided_slice_size_mode,
+        1e-60,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(4, 6), float32]) -> Tensor[(2, 4), float32] {
+  strided_slice(%x, begin=[1, 2], end=[2, -1], strides=[2], slice_mode="size") /* ty=Tensor[(2, 4), float32] */
+}
+"#,
+        r#"
+(access-slice (access-slice (access-tensor x) 0 1 3) 1 2 6)
+"#
+    );
+
+    test!(
+        concatenate_split,
+        1e-60,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(4, 6), float32]) -> Tensor[(4, 6), float32] {
+  %0 = split(%x, indices_or_sections=2, axis=1);
+  concatenate(%0, axis=1) /* ty=Tensor[(4, 6), float32] */
+}
+"#,
+        r#"
+(access-concatenate
+ (access (access-slice (access-tensor x) 1 0 3) 0)
+ (access (access-slice (access-tensor x) 1 3 6) 0)
+ 1
+)
+"#
+    );
+
+    test!(
+        batch_matmul,
+        1e-6,
+        r#"
+#[version = "0.0.5"]
+def @main(%a: Tensor[(2, 3, 4), float32], %b: Tensor[(2, 5, 4), float32]) -> Tensor[(2, 3, 5), float32] {
+  nn.batch_matmul(%a, %b) /* ty=Tensor[(2, 3, 5), float32] */
+}
+"#,
+        r#"
+(compute dot-product
+ (access-pair
+  (access
+   (access-broadcast
+    (access-insert-axis (access-tensor a) 2)
+    (access-shape (shape 2 3 5 4) (shape))
+   )
+   3
+  )
+  (access
+   (access-broadcast
+    (access-insert-axis (access-tensor b) 1)
+    (access-shape (shape 2 3 5 4) (shape))
+   )
+   3
+  )
+ )
+)
+"#
+    );
+
+    test!(
+        softmax_axis,
+        1e-7,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(3, 4, 5), float32]) -> Tensor[(3, 4, 5), float32] {
+  nn.softmax(%x, axis=1) /* ty=Tensor[(3, 4, 5), float32] */
+}
+"#,
+        r#"
+(access-transpose
+ (compute softmax
+  (access (access-transpose (access-tensor x) (list 0 2 1)) 2)
+ )
+ (list 0 2 1)
+)
+"#
+    );
+
+    test!(
+        sum_keepdims,
+        1e-6,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(3, 4, 5), float32]) -> Tensor[(3, 1, 5), float32] {
+  sum(%x, axis=[1], keepdims=True) /* ty=Tensor[(3, 1, 5), float32] */
+}
+"#,
+        r#"
+(access-insert-axis
+ (compute reduce-sum
+  (access (access-transpose (access-tensor x) (list 0 2 1)) 2)
+ )
+ 1
+)
+"#
+    );
+
+    test!(
+        mean_multiple_axes,
+        1e-6,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(3, 4, 5), float32]) -> Tensor[(4), float32] {
+  mean(%x, axis=[0, 2]) /* ty=Tensor[(4), float32] */
+}
+"#,
+        r#"
+(compute reduce-mean
+ (access (access-transpose (access-tensor x) (list 1 0 2)) 1)
+)
+"#
+    );
+
+    test!(
+        max_exclude,
+        1e-60,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(3, 4, 5), float32]) -> Tensor[(3), float32] {
+  max(%x, axis=[0], exclude=True) /* ty=Tensor[(3), float32] */
+}
+"#,
+        r#"
+(compute reduce-max (access (access-tensor x) 1))
+"#
+    );
+
+    test!(
+        min_negative_axis,
+        1e-60,
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(3, 4, 5), float32]) -> Tensor[(3, 4), float32] {
+  min(%x, axis=[-1]) /* ty=Tensor[(3, 4), float32] */
+}
+"#,
+        r#"
+(compute reduce-min (access (access-tensor x) 2))
+"#
+    );
+
+    /// argmax produces int32 indices, so unlike the tests above, Relay's
+    /// output is read as integers. The input is drawn from a handful of
+    /// values, so that there are ties, which both go to the lowest index.
+    #[test]
+    fn argmax() {
+        let relay = r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(3, 4), float32]) -> Tensor[(3), int32] {
+  argmax(%x, axis=[1]) /* ty=Tensor[(3), int32] */
+}
+"#;
+        let module = tvm::ir::module::IRModule::parse("", relay);
+        let (expr, shapes_vec) = super::from_relay(&module);
+        let mut egraph = EGraph::new(MyAnalysis {
+            name_to_shape: shapes_vec.iter().cloned().collect(),
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&expr);
+        let pattern = "(compute argmax (access (access-tensor x) 1))"
+            .parse::<Pattern<Language>>()
+            .unwrap();
+        assert!(pattern.search_eclass(&egraph, id).is_some());
+        assert_eq!(
+            MyAnalysis::get_dtype(id, &egraph),
+            crate::hw_design_language::DType::Int32
+        );
+
+        let x = ndarray::ArrayD::<f32>::random_using(
+            shapes_vec[0].1.clone(),
+            Uniform::new(0f32, 3f32),
+            &mut SmallRng::seed_from_u64(23),
+        )
+        .mapv(f32::floor);
+        let mut env = HashMap::default();
+        env.insert("x", x.clone());
+
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let x_filepath = std::env::temp_dir().join(format!("argmax-x-{}.npy", nanos));
+        write_npy(&x_filepath, &x).unwrap();
+        let output_filepath = std::env::temp_dir().join(format!("argmax-output-{}.npy", nanos));
+
+        let mut proc = Command::new("python3")
+            .arg(format!(
+                "{}/src/language/from_relay/run_relay.py",
+                env!("CARGO_MANIFEST_DIR")
+            ))
+            .arg(&output_filepath)
+            .arg(&x_filepath)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .expect("Failed to spawn process");
+        proc.stdin
+            .as_mut()
+            .unwrap()
+            .write_all(relay.as_bytes())
+            .unwrap();
+        let output = proc.wait_with_output().unwrap();
+        assert!(
+            output.status.success(),
+            "Running Relay code failed with code {:?}.\nstderr:\n{}",
+            output.status.code(),
+            std::str::from_utf8(output.stderr.as_slice())
+                .expect("Could not convert stderr to UTF8")
+        );
+
+        let relay_output: ndarray::ArrayD<i32> = read_npy(output_filepath).unwrap();
+        let interpreter_output = match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            crate::language::interpreter::Value::Access(a) => a.tensor,
+            _ => panic!(),
+        };
+        assert_eq!(
+            relay_output,
+            interpreter_output.mapv(|index: f32| index as i32),
+            "input:\n{:?}",
+            x
+        );
+    }
+
+    #[test]
+    fn find_unsupported_operator() {
+        let module = tvm::ir::module::IRModule::parse(
+            "",
+            r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(1, 3), float32]) -> Tensor[(1, 3), float32] {
+  %0 = nn.relu(%x);
+  %1 = log(%0);
+  %2 = nn.relu(%1);
+  round(%2)
+}
+"#,
+        );
+        assert_eq!(
+            super::find_unsupported_operator(&module),
+            Some("log".to_string())
+        );
+    }
+
+    #[test]
+    fn find_unsupported_operator_none() {
+        let module = tvm::ir::module::IRModule::parse(
+            "",
+            r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(1, 3), float32]) -> Tensor[(1, 3), float32] {
+  %0 = nn.relu(%x);
+  add(%0, %0)
+}
+"#,
+        );
+        assert_eq!(super::find_unsupported_operator(&module), None);
+    }
+
+    #[test]
+    fn find_unsupported_operator_tuples() {
+        let module = tvm::ir::module::IRModule::parse(
+            "",
+            r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(4, 6), float32]) -> Tensor[(4, 6), float32] {
+  %0 = split(%x, indices_or_sections=2, axis=1);
+  %1 = %0.1;
+  %2 = %0.0;
+  %3 = (%1, %2);
+  concatenate(%3, axis=1)
+}
+"#,
+        );
+        assert_eq!(super::find_unsupported_operator(&module), None);
+    }
+
+    #[test]
+    fn find_unsupported_operator_in_tuple() {
+        let module = tvm::ir::module::IRModule::parse(
+            "",
+            r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(1, 3), float32]) -> Tensor[(2, 3), float32] {
+  %0 = log(%x);
+  %1 = (%x, %0);
+  concatenate(%1)
+}
+"#,
+        );
+        assert_eq!(
+            super::find_unsupported_operator(&module),
+            Some("log".to_string())
+        );
+    }
+
+    #[test]
+    fn find_unsupported_operator_tuple_argument() {
+        let module = tvm::ir::module::IRModule::parse(
+            "",
+            r#"
+#[version = "0.0.5"]
+def @main(%t: (Tensor[(1, 3), float32], Tensor[(2, 3), float32])) -> Tensor[(3, 3), float32] {
+  concatenate(%t)
+}
+"#,
+        );
+        assert_eq!(
+            super::find_unsupported_operator(&module),
+            Some(
+                "concatenate of a tuple which isn't written out in the program or a split"
+                    .to_string()
+            )
+        );
+    }
+
+    /// Gelu is only supported in its tanh approximation, so the erf-based gelu
+    /// which frontends build in Relay isn't imported.
+    #[test]
+    fn find_unsupported_operator_erf_gelu() {
+        let module = tvm::ir::module::IRModule::parse(
+            "",
+            r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(1, 3), float32]) -> Tensor[(1, 3), float32] {
+  %0 = multiply(%x, 0.707107f);
+  %1 = erf(%0);
+  %2 = multiply(%1, 0.5f);
+  %3 = add(0.5f, %2);
+  multiply(%x, %3)
+}
+"#,
+        );
+        assert_eq!(
+            super::find_unsupported_operator(&module),
+            Some("erf".to_string())
+        );
+    }
+
+    /// Imports a small program using each operator in
+    /// [`super::SUPPORTED_OPERATORS`], so the list can't claim an operator
+    /// which the importer doesn't actually handle.
+    #[test]
+    fn supported_operators_import() {
+        let programs: &[(&str, &str)] = &[
+            ("nn.softmax", "(%x: Tensor[(3, 4), float32]) { nn.softmax(%x) }"),
+            ("sum", "(%x: Tensor[(3, 4), float32]) { sum(%x, axis=[1]) }"),
+            ("mean", "(%x: Tensor[(3, 4), float32]) { mean(%x, axis=[1]) }"),
+            ("max", "(%x: Tensor[(3, 4), float32]) { max(%x, axis=[1]) }"),
+            ("min", "(%x: Tensor[(3, 4), float32]) { min(%x, axis=[1]) }"),
+            ("argmax", "(%x: Tensor[(3, 4), float32]) { argmax(%x, axis=[1]) }"),
+            ("nn.relu", "(%x: Tensor[(3, 4), float32]) { nn.relu(%x) }"),
+            ("sqrt", "(%x: Tensor[(3, 4), float32]) { sqrt(%x) }"),
+            ("negative", "(%x: Tensor[(3, 4), float32]) { negative(%x) }"),
+            ("sigmoid", "(%x: Tensor[(3, 4), float32]) { sigmoid(%x) }"),
+            ("tanh", "(%x: Tensor[(3, 4), float32]) { tanh(%x) }"),
+            ("exp", "(%x: Tensor[(3, 4), float32]) { exp(%x) }"),
+            (
+                "clip",
+                "(%x: Tensor[(3, 4), float32]) { clip(%x, a_min=-0.5f, a_max=0.75f) }",
+            ),
+            (
+                "nn.leaky_relu",
+                "(%x: Tensor[(3, 4), float32]) { nn.leaky_relu(%x, alpha=0.25f) }",
+            ),
+            (
+                "nn.max_pool2d",
+                "(%x: Tensor[(1, 3, 8, 8), float32]) { nn.max_pool2d(%x, pool_size=[2, 2], strides=[2, 2], padding=[0, 0, 0, 0]) }",
+            ),
+            (
+                "nn.avg_pool2d",
+                "(%x: Tensor[(1, 3, 8, 8), float32]) { nn.avg_pool2d(%x, pool_size=[2, 2], strides=[2, 2], padding=[0, 0, 0, 0]) }",
+            ),
+            (
+                "nn.global_avg_pool2d",
+                "(%x: Tensor[(1, 3, 8, 8), float32]) { nn.global_avg_pool2d(%x) }",
+            ),
+            (
+                "nn.global_max_pool2d",
+                "(%x: Tensor[(1, 3, 8, 8), float32]) { nn.global_max_pool2d(%x) }",
+            ),
+            (
+                "expand_dims",
+                "(%x: Tensor[(3, 4), float32]) { expand_dims(%x, axis=1) }",
+            ),
+            (
+                "nn.dense",
+                "(%x: Tensor[(3, 4), float32], %w: Tensor[(5, 4), float32]) { nn.dense(%x, %w) }",
+            ),
+            (
+                "nn.batch_matmul",
+                "(%x: Tensor[(2, 3, 4), float32], %w: Tensor[(2, 5, 4), float32]) { nn.batch_matmul(%x, %w) }",
+            ),
+            (
+                "add",
+                "(%x: Tensor[(3, 4), float32], %y: Tensor[(3, 4), float32]) { add(%x, %y) }",
+            ),
+            (
+                "multiply",
+                "(%x: Tensor[(3, 4), float32], %y: Tensor[(3, 4), float32]) { multiply(%x, %y) }",
+            ),
+            (
+                "divide",
+                "(%x: Tensor[(3, 4), float32], %y: Tensor[(3, 4), float32]) { divide(%x, %y) }",
+            ),
+            (
+                "nn.batch_flatten",
+                "(%x: Tensor[(2, 3, 4), float32]) { nn.batch_flatten(%x) }",
+            ),
+            (
+                "nn.bias_add",
+                "(%x: Tensor[(3, 4), float32], %y: Tensor[(4), float32]) { nn.bias_add(%x, %y, axis=1) }",
+            ),
+            (
+                "nn.conv2d",
+                "(%x: Tensor[(1, 3, 8, 8), float32], %w: Tensor[(4, 3, 3, 3), float32]) { nn.conv2d(%x, %w, padding=[1, 1, 1, 1]) }",
+            ),
+            (
+                "reshape",
+                "(%x: Tensor[(3, 4), float32]) { reshape(%x, newshape=[12]) }",
+            ),
+            ("transpose", "(%x: Tensor[(3, 4), float32]) { transpose(%x) }"),
+            (
+                "squeeze",
+                "(%x: Tensor[(3, 1, 4), float32]) { squeeze(%x, axis=[1]) }",
+            ),
+            (
+                "concatenate",
+                "(%x: Tensor[(3, 4), float32], %y: Tensor[(3, 4), float32]) { %0 = (%x, %y); concatenate(%0, axis=1) }",
+            ),
+            (
+                "split",
+                "(%x: Tensor[(4, 6), float32]) { %0 = split(%x, indices_or_sections=2, axis=1); %0.1 }",
+            ),
+            (
+                "strided_slice",
+                "(%x: Tensor[(4, 6), float32]) { strided_slice(%x, begin=[1, 2], end=[3, 6], strides=[1]) }",
+            ),
+        ];
+
+        let mut operators = programs.iter().map(|(op, _)| *op).collect::<Vec<_>>();
+        let mut supported = super::SUPPORTED_OPERATORS.to_vec();
+        operators.sort();
+        supported.sort();
+        assert_eq!(operators, supported);
+
+        for (op, program) in programs.iter() {
+            let module = tvm::ir::module::IRModule::parse(
+                "",
+                format!("#[version = \"0.0.5\"]\ndef @main{}\n", program).as_str(),
+            );
+            assert_eq!(super::find_unsupported_operator(&module), None, "{}", op);
+            let (expr, shapes_vec) = super::from_relay(&module);
+            let mut egraph = EGraph::new(MyAnalysis {
+                name_to_shape: shapes_vec.into_iter().collect(),
+                name_to_dtype: HashMap::default(),
+            });
+            egraph.add_expr(&expr);
+        }
+    }
 }
@@ -1,13 +1,16 @@
 use super::language::{ComputeType, Language, PadType};
-use egg::{Id, RecExpr};
+use crate::hw_design_language::DType;
+use egg::{Id, Language as LanguageTrait, RecExpr};
 use itertools::Itertools;
 use ndarray::{s, Array, ArrayD, Dimension, IxDyn, Zip};
 use num_traits::cast::AsPrimitive;
 use num_traits::Pow;
+use ordered_float::NotNan;
 use std::collections::hash_map::HashMap;
 use std::iter::FromIterator;
 use std::ops::Div;
 
+#[derive(Clone)]
 pub enum Value<DataType> {
     Tensor(ArrayD<DataType>),
     Access(Access<DataType>),
@@ -15,10 +18,12 @@ pub enum Value<DataType> {
     Shape(IxDyn),
     ComputeType(ComputeType),
     PadType(PadType),
+    DType(DType),
     AccessShape(IxDyn, usize),
     List(Vec<usize>),
 }
 
+#[derive(Clone)]
 pub struct Access<DataType> {
     pub tensor: ArrayD<DataType>,
     pub access_axis: usize,
@@ -26,11 +31,14 @@ pub struct Access<DataType> {
 
 pub type Environment<'a, DataType> = HashMap<&'a str, ArrayD<DataType>>;
 
-// TODO(@gussmith23) Interpreter stack overflows on large programs
-// If I want to interpret something like a full resnet, then I will have to
-// figure out a way around the stack overflows.
 /// Interpret a Glenside expression
 ///
+/// The expression is evaluated iteratively, in the topological order given by
+/// the [`RecExpr`], rather than recursively, so large programs (e.g. a full
+/// ResNet) won't overflow the stack. Each node reachable from `index` is
+/// evaluated exactly once, even if it is shared by multiple users, and its
+/// value is freed as soon as its last user has been evaluated.
+///
 /// Generally, `DataType` can be inferred from the environment passed in. If
 /// your expression doesn't actually use any tensor values, and the environment
 /// is empty, you can choose an arbitrary type: e.g. `interpret::<i64>(...)`:
@@ -70,17 +78,111 @@ where
         + num_traits::Bounded
         + Exp
         + Sqrt
+        + Tanh
+        + FromNotNanFloat64Literal
+        + ndarray::ScalarOperand,
+    usize: num_traits::cast::AsPrimitive<DataType>,
+{
+    let nodes = expr.as_ref();
+
+    // Find the nodes reachable from `index`, and count how many times each is
+    // used. Children always come before their parents in a RecExpr, so a
+    // single backwards pass suffices.
+    let mut reachable = vec![false; index + 1];
+    let mut remaining_uses = vec![0usize; index + 1];
+    reachable[index] = true;
+    // The final value is "used" by the caller.
+    remaining_uses[index] = 1;
+    for i in (0..=index).rev() {
+        if !reachable[i] {
+            continue;
+        }
+        for child in nodes[i].children() {
+            let child: usize = (*child).into();
+            assert!(child < i, "RecExpr is not topologically sorted");
+            reachable[child] = true;
+            remaining_uses[child] += 1;
+        }
+    }
+
+    let mut values = Values {
+        values: (0..=index).map(|_| None).collect(),
+        remaining_uses,
+    };
+    for i in 0..=index {
+        if reachable[i] {
+            let value = interpret_node(expr, i, env, &mut values);
+            values.values[i] = Some(value);
+        }
+    }
+
+    values.take(index.into())
+}
+
+/// The values computed so far while interpreting a [`RecExpr`], indexed by
+/// node.
+struct Values<DataType> {
+    values: Vec<Option<Value<DataType>>>,
+    /// The number of uses of each node which haven't been evaluated yet.
+    remaining_uses: Vec<usize>,
+}
+
+impl<DataType: Clone> Values<DataType> {
+    /// Get the value of node `id` for one of its uses. The last use gets the
+    /// value itself, freeing it; earlier uses get a copy.
+    fn take(&mut self, id: Id) -> Value<DataType> {
+        let index: usize = id.into();
+        assert!(
+            self.remaining_uses[index] > 0,
+            "Value of node {} used more times than expected",
+            index
+        );
+        self.remaining_uses[index] -= 1;
+        if self.remaining_uses[index] == 0 {
+            self.values[index]
+                .take()
+                .unwrap_or_else(|| panic!("Node {} has not been evaluated", index))
+        } else {
+            self.values[index]
+                .as_ref()
+                .unwrap_or_else(|| panic!("Node {} has not been evaluated", index))
+                .clone()
+        }
+    }
+}
+
+/// Evaluate the node at `index`, taking the values of its children from
+/// `values`.
+fn interpret_node<DataType: 'static>(
+    expr: &RecExpr<Language>,
+    index: usize,
+    env: &Environment<DataType>,
+    values: &mut Values<DataType>,
+) -> Value<DataType>
+where
+    DataType: Copy
+        + std::ops::Mul<Output = DataType>
+        + std::ops::Div<Output = DataType>
+        + std::ops::Neg<Output = DataType>
+        + std::iter::Sum
+        + num_traits::identities::One
+        + num_traits::identities::Zero
+        + std::cmp::PartialOrd
+        + num_traits::Bounded
+        + Exp
+        + Sqrt
+        + Tanh
         + FromNotNanFloat64Literal
         + ndarray::ScalarOperand,
     usize: num_traits::cast::AsPrimitive<DataType>,
 {
     match &expr.as_ref()[index] {
         &Language::AccessShape([shape_id, item_shape_id]) => {
-            let shape = match interpret(expr, shape_id.into(), env) {
+            let shape = match values.take(shape_id) {
                 Value::Shape(s) => s,
                 _ => panic!(),
             };
-            let item_shape = match interpret(expr, item_shape_id.into(), env) {
+            let item_shape = match values.take(item_shape_id) {
                 Value::Shape(s) => s,
                 _ => panic!(),
             };
@@ -98,19 +200,19 @@ where
             )
         }
         &Language::AccessSlice([access_id, axis_id, low_id, high_id]) => {
-            let mut access = match interpret(expr, access_id.into(), env) {
+            let mut access = match values.take(access_id) {
                 Value::Access(a) => a,
                 _ => panic!(),
             };
-            let axis = match interpret(expr, axis_id.into(), env) {
+            let axis = match values.take(axis_id) {
                 Value::Usize(u) => u,
                 _ => panic!(),
             };
-            let low = match interpret(expr, low_id.into(), env) {
+            let low = match values.take(low_id) {
                 Value::Usize(u) => u,
                 _ => panic!(),
             };
-            let high = match interpret(expr, high_id.into(), env) {
+            let high = match values.take(high_id) {
                 Value::Usize(u) => u,
                 _ => panic!(),
             };
@@ -130,15 +232,15 @@ where
             Value::Access(access)
         }
         &Language::AccessConcatenate([a_id, b_id, axis_id]) => {
-            let a = match interpret(expr, a_id.into(), env) {
+            let a = match values.take(a_id) {
                 Value::Access(a) => a,
                 _ => panic!(),
             };
-            let b = match interpret(expr, b_id.into(), env) {
+            let b = match values.take(b_id) {
                 Value::Access(a) => a,
                 _ => panic!(),
             };
-            let axis = match interpret(expr, axis_id.into(), env) {
+            let axis = match values.take(axis_id) {
                 Value::Usize(u) => u,
                 _ => panic!(),
             };
@@ -150,22 +252,36 @@ where
                 access_axis: a.access_axis,
             })
         }
-        &Language::AccessLiteral(id) => match interpret(expr, id.into(), env) {
-            Value::Tensor(t) => Value::Access(Access {
-                tensor: t,
-                access_axis: 0,
-            }),
-            _ => panic!(),
-        },
-        &Language::Literal(id) => match interpret(expr, id.into(), env) {
+        &Language::AccessLiteral([literal_id, dtype_id]) => {
+            // Values are interpreted as `DataType` regardless of their dtype.
+            match values.take(dtype_id) {
+                Value::DType(_) => (),
+                _ => panic!(),
+            };
+            match values.take(literal_id) {
+                Value::Tensor(t) => Value::Access(Access {
+                    tensor: t,
+                    access_axis: 0,
+                }),
+                _ => panic!(),
+            }
+        }
+        &Language::Literal(id) => match values.take(id) {
             t @ Value::Tensor(_) => t,
+            // Integer-valued literals are parsed as usizes.
+            Value::Usize(u) => Value::Tensor(
+                ndarray::arr0(DataType::from_not_nan_float_64_literal(
+                    NotNan::new(u as f64).unwrap(),
+                ))
+                .into_dyn(),
+            ),
             _ => panic!(),
         },
         &Language::NotNanFloat64(v) => Value::Tensor(
             ndarray::arr0(DataType::from_not_nan_float_64_literal(v.into())).into_dyn(),
         ),
         &Language::AccessFlatten(access_id) => {
-            let mut access = match interpret(expr, access_id.into(), env) {
+            let mut access = match values.take(access_id) {
                 Value::Access(a) => a,
                 _ => panic!(),
             };
@@ -187,33 +303,173 @@ where
 
             Value::Access(access)
         }
+        // Both systolic array constructs compute the same thing: blocking only
+        // changes how the computation is scheduled onto the hardware. Each
+        // vector accessed by `a0` (of length N) is multiplied by the [N, O]
+        // matrix accessed by `a1`, producing a vector of length O. This matches
+        // the reference implementation in
+        // rtml_systolic_array_weight_stationary.c.
+        &Language::SystolicArray([rows_id, cols_id, a0_id, a1_id])
+        | &Language::SystolicArrayWithBlocking([rows_id, cols_id, a0_id, a1_id]) => {
+            let (rows, cols) = match (values.take(rows_id), values.take(cols_id)) {
+                (Value::Usize(rows), Value::Usize(cols)) => (rows, cols),
+                _ => panic!("Expected usizes as the first two arguments to a systolic array"),
+            };
+            let (a0, a1) = match (values.take(a0_id), values.take(a1_id)) {
+                (Value::Access(a0), Value::Access(a1)) => (a0, a1),
+                _ => panic!("Expected accesses as the last two arguments to a systolic array"),
+            };
+
+            assert_eq!(a1.access_axis, 0);
+            assert_eq!(a1.tensor.ndim(), 2);
+            assert!(a0.access_axis == 0 || a0.access_axis == 1);
+            assert_eq!(a0.tensor.ndim(), a0.access_axis + 1);
+
+            let input_vector_size = a1.tensor.shape()[0];
+            let output_vector_size = a1.tensor.shape()[1];
+            assert_eq!(a0.tensor.shape()[a0.access_axis], input_vector_size);
+
+            match &expr.as_ref()[index] {
+                &Language::SystolicArray(_) => {
+                    assert_eq!(rows, input_vector_size);
+                    assert_eq!(cols, output_vector_size);
+                }
+                &Language::SystolicArrayWithBlocking(_) => {
+                    assert_eq!(input_vector_size % rows, 0);
+                    assert_eq!(output_vector_size % cols, 0);
+                }
+                _ => unreachable!(),
+            }
+
+            let batch = a0.tensor.shape()[..a0.access_axis]
+                .iter()
+                .product::<usize>();
+            let activations = Array::from_shape_vec(
+                (batch, input_vector_size),
+                a0.tensor.iter().cloned().collect(),
+            )
+            .unwrap();
+            let weights = a1.tensor;
+
+            let out = Array::from_shape_fn((batch, output_vector_size), |(batch_i, col)| {
+                (0..input_vector_size)
+                    .map(|row| activations[[batch_i, row]] * weights[[row, col]])
+                    .sum::<DataType>()
+            });
+
+            let out_shape = a0.tensor.shape()[..a0.access_axis]
+                .iter()
+                .cloned()
+                .chain(std::iter::once(output_vector_size))
+                .collect::<Vec<_>>();
+
+            Value::Access(Access {
+                access_axis: out_shape.len(),
+                tensor: out.into_shape(out_shape).unwrap().into_dyn(),
+            })
+        }
+        &Language::BsgSystolicArray([rows_id, cols_id, t0_id, t1_id]) => {
+            let (rows, cols) = match (values.take(rows_id), values.take(cols_id)) {
+                (Value::Usize(rows), Value::Usize(cols)) => (rows, cols),
+                _ => panic!("Expected usizes as the first two arguments to a bsg-systolic-array"),
+            };
+            let (t0, t1) = match (values.take(t0_id), values.take(t1_id)) {
+                (Value::Tensor(t0), Value::Tensor(t1)) => (t0, t1),
+                _ => panic!("Expected tensors as the last two arguments to a bsg-systolic-array"),
+            };
+
+            // t0 is a vector of length rows, or a matrix of row vectors; t1 is
+            // a rows x cols matrix.
+            assert!(t0.ndim() == 1 || t0.ndim() == 2);
+            assert_eq!(t0.shape()[t0.ndim() - 1], rows);
+            assert_eq!(t1.shape(), &[rows, cols]);
+
+            let batch = t0.len() / rows;
+            let activations =
+                Array::from_shape_vec((batch, rows), t0.iter().cloned().collect()).unwrap();
+
+            let out = Array::from_shape_fn((batch, cols), |(batch_i, col)| {
+                (0..rows)
+                    .map(|row| activations[[batch_i, row]] * t1[[row, col]])
+                    .sum::<DataType>()
+            });
+
+            let out_shape = t0.shape()[..t0.ndim() - 1]
+                .iter()
+                .cloned()
+                .chain(std::iter::once(cols))
+                .collect::<Vec<_>>();
+
+            Value::Tensor(out.into_shape(out_shape).unwrap().into_dyn())
+        }
+        &Language::AccessReshape([access_id, access_shape_id]) => {
+            let mut access = match values.take(access_id) {
+                Value::Access(a) => a,
+                _ => panic!("Expected an access as the first argument to access-reshape"),
+            };
+            let (shape, access_axis) = match values.take(access_shape_id) {
+                Value::AccessShape(shape, access_axis) => (shape, access_axis),
+                _ => panic!("Expected an access shape as the second argument to access-reshape"),
+            };
+
+            assert_eq!(
+                access.tensor.shape().iter().product::<usize>(),
+                shape.slice().iter().product::<usize>(),
+                "access-reshape cannot change the number of elements"
+            );
+
+            // Iterate in logical (row-major) order, so that reshaping works
+            // regardless of the memory layout of the tensor (e.g. after a
+            // transpose).
+            access.tensor =
+                ArrayD::from_shape_vec(shape, access.tensor.iter().cloned().collect()).unwrap();
+            access.access_axis = access_axis;
+
+            Value::Access(access)
+        }
+        &Language::AccessShiftRight(access_id) => {
+            let mut access = match values.take(access_id) {
+                Value::Access(a) => a,
+                _ => panic!(),
+            };
+
+            access.access_axis = access.access_axis.saturating_sub(1);
+
+            Value::Access(access)
+        }
         &Language::AccessTranspose([access_id, list_id]) => {
-            let mut access = match interpret(expr, access_id.into(), env) {
+            let mut access = match values.take(access_id) {
                 Value::Access(a) => a,
                 _ => panic!(),
             };
-            let list = match interpret(expr, list_id.into(), env) {
+            let list = match values.take(list_id) {
                 Value::List(l) => l,
                 _ => panic!(),
             };
 
-            access.tensor = access.tensor.permuted_axes(list);
+            // Other operators reshape their inputs, which needs a standard
+            // layout.
+            access.tensor = access
+                .tensor
+                .permuted_axes(list)
+                .as_standard_layout()
+                .into_owned();
             Value::Access(access)
         }
         Language::List(list) => Value::List(
             list.iter()
-                .map(|id: &Id| match interpret(expr, (*id).into(), env) {
+                .map(|id: &Id| match values.take(*id) {
                     Value::Usize(u) => u,
                     _ => panic!(),
                 })
                 .collect::<Vec<_>>(),
         ),
         &Language::AccessBroadcast([access_id, shape_id]) => {
-            let mut access = match interpret(expr, access_id.into(), env) {
+            let mut access = match values.take(access_id) {
                 Value::Access(a) => a,
                 _ => panic!(),
             };
-            let shape = match interpret(expr, shape_id.into(), env) {
+            let shape = match values.take(shape_id) {
                 Value::AccessShape(s, _) => s,
                 _ => panic!("Expected access shape as second argument to access-broadcast"),
             };
@@ -230,11 +486,11 @@ where
             Value::Access(access)
         }
         &Language::AccessInsertAxis([access_id, axis_id]) => {
-            let mut access = match interpret(expr, access_id.into(), env) {
+            let mut access = match values.take(access_id) {
                 Value::Access(a) => a,
                 _ => panic!(),
             };
-            let axis = match interpret(expr, axis_id.into(), env) {
+            let axis = match values.take(axis_id) {
                 Value::Usize(u) => u,
                 _ => panic!(),
             };
@@ -249,10 +505,7 @@ where
             Value::Access(access)
         }
         &Language::AccessPair([a0_id, a1_id]) => {
-            let (a0, a1) = match (
-                interpret(expr, a0_id.into(), env),
-                interpret(expr, a1_id.into(), env),
-            ) {
+            let (a0, a1) = match (values.take(a0_id), values.take(a1_id)) {
                 (Value::Access(a0), Value::Access(a1)) => (a0, a1),
                 _ => panic!("Expected both arguments to access-pair to be accesses"),
             };
@@ -282,11 +535,11 @@ where
             })
         }
         &Language::AccessSqueeze([access_id, axis_id]) => {
-            let mut access = match interpret(expr, access_id.into(), env) {
+            let mut access = match values.take(access_id) {
                 Value::Access(a) => a,
                 _ => panic!(),
             };
-            let axis = match interpret(expr, axis_id.into(), env) {
+            let axis = match values.take(axis_id) {
                 Value::Usize(u) => u,
                 _ => panic!(),
             };
@@ -305,24 +558,25 @@ where
             Value::Access(access)
         }
         Language::PadType(t) => Value::PadType(*t),
+        Language::DataType(t) => Value::DType(*t),
         &Language::AccessPad([access_id, pad_type_id, axis_id, pad_before_id, pad_after_id]) => {
-            let access = match interpret(expr, access_id.into(), env) {
+            let access = match values.take(access_id) {
                 Value::Access(a) => a,
                 _ => panic!(),
             };
-            let pad_type = match interpret(expr, pad_type_id.into(), env) {
+            let pad_type = match values.take(pad_type_id) {
                 Value::PadType(t) => t,
                 _ => panic!(),
             };
-            let axis = match interpret(expr, axis_id.into(), env) {
+            let axis = match values.take(axis_id) {
                 Value::Usize(u) => u,
                 _ => panic!(),
             };
-            let pad_before = match interpret(expr, pad_before_id.into(), env) {
+            let pad_before = match values.take(pad_before_id) {
                 Value::Usize(u) => u,
                 _ => panic!(),
             };
-            let pad_after = match interpret(expr, pad_after_id.into(), env) {
+            let pad_after = match values.take(pad_after_id) {
                 Value::Usize(u) => u,
                 _ => panic!(),
             };
@@ -363,12 +617,26 @@ where
             })
         }
         Language::ComputeType(t) => Value::ComputeType(t.clone()),
+        &Language::Clip([min_id, max_id]) => {
+            values.take(min_id);
+            values.take(max_id);
+            Value::ComputeType(ComputeType::Clip {
+                min: number(expr, min_id),
+                max: number(expr, max_id),
+            })
+        }
+        &Language::LeakyReLU(alpha_id) => {
+            values.take(alpha_id);
+            Value::ComputeType(ComputeType::LeakyReLU {
+                alpha: number(expr, alpha_id),
+            })
+        }
         &Language::Compute([compute_type_id, access_id]) => {
-            let compute_type = match interpret(expr, compute_type_id.into(), env) {
+            let compute_type = match values.take(compute_type_id) {
                 Value::ComputeType(t) => t,
                 _ => panic!(),
             };
-            let access = match interpret(expr, access_id.into(), env) {
+            let access = match values.take(access_id) {
                 Value::Access(a) => a,
                 _ => panic!(),
             };
@@ -554,6 +822,65 @@ where
                     tensor: access.tensor.mapv(|v| v.sqrt()),
                     access_axis: access.access_axis,
                 }),
+                ComputeType::Sigmoid => Value::Access(Access {
+                    tensor: access
+                        .tensor
+                        .mapv(|v| DataType::one() / (DataType::one() + (-v).exp())),
+                    access_axis: access.access_axis,
+                }),
+                ComputeType::Tanh => Value::Access(Access {
+                    tensor: access.tensor.mapv(|v| v.tanh()),
+                    access_axis: access.access_axis,
+                }),
+                ComputeType::Exp => Value::Access(Access {
+                    tensor: access.tensor.mapv(|v| v.exp()),
+                    access_axis: access.access_axis,
+                }),
+                ComputeType::Clip { min, max } => {
+                    let min = DataType::from_not_nan_float_64_literal(min);
+                    let max = DataType::from_not_nan_float_64_literal(max);
+                    Value::Access(Access {
+                        tensor: access.tensor.mapv(|v| {
+                            if v < min {
+                                min
+                            } else if v > max {
+                                max
+                            } else {
+                                v
+                            }
+                        }),
+                        access_axis: access.access_axis,
+                    })
+                }
+                ComputeType::LeakyReLU { alpha } => {
+                    let alpha = DataType::from_not_nan_float_64_literal(alpha);
+                    Value::Access(Access {
+                        tensor: access.tensor.mapv(|v| {
+                            if v >= DataType::zero() {
+                                v
+                            } else {
+                                alpha * v
+                            }
+                        }),
+                        access_axis: access.access_axis,
+                    })
+                }
+                ComputeType::Gelu => {
+                    let half = DataType::from_not_nan_float_64_literal(NotNan::new(0.5).unwrap());
+                    let sqrt_2_over_pi = DataType::from_not_nan_float_64_literal(
+                        NotNan::new((2.0 / std::f64::consts::PI).sqrt()).unwrap(),
+                    );
+                    let coefficient =
+                        DataType::from_not_nan_float_64_literal(NotNan::new(0.044715).unwrap());
+                    Value::Access(Access {
+                        tensor: access.tensor.mapv(|v| {
+                            half * v
+                                * (DataType::one()
+                                    + (sqrt_2_over_pi * (v + coefficient * v * v * v)).tanh())
+                        }),
+                        access_axis: access.access_axis,
+                    })
+                }
                 ComputeType::ReLU => Value::Access(Access {
                     tensor: access.tensor.mapv(|v| {
                         if v >= DataType::zero() {
@@ -611,13 +938,65 @@ where
                         }),
                     access_axis: access.access_axis,
                 }),
+                ComputeType::ReduceMin => Value::Access(Access {
+                    tensor: access
+                        .tensor
+                        .clone()
+                        .into_shape(
+                            access.tensor.shape()[..access.access_axis]
+                                .iter()
+                                .cloned()
+                                .chain(std::iter::once(
+                                    access.tensor.shape()[access.access_axis..]
+                                        .iter()
+                                        .cloned()
+                                        .product(),
+                                ))
+                                .collect::<Vec<_>>()
+                                .as_slice(),
+                        )
+                        .unwrap()
+                        .map_axis(ndarray::Axis(access.access_axis), |t| {
+                            t.iter().fold(
+                                DataType::max_value(),
+                                |acc, v| if *v < acc { *v } else { acc },
+                            )
+                        }),
+                    access_axis: access.access_axis,
+                }),
+                ComputeType::ArgMax => Value::Access(Access {
+                    tensor: access
+                        .tensor
+                        .clone()
+                        .into_shape(
+                            access.tensor.shape()[..access.access_axis]
+                                .iter()
+                                .cloned()
+                                .chain(std::iter::once(
+                                    access.tensor.shape()[access.access_axis..]
+                                        .iter()
+                                        .cloned()
+                                        .product(),
+                                ))
+                                .collect::<Vec<_>>()
+                                .as_slice(),
+                        )
+                        .unwrap()
+                        .map_axis(ndarray::Axis(access.access_axis), |t| {
+                            let mut argmax = 0;
+                            for (i, v) in t.iter().enumerate() {
+                                if *v > t[argmax] {
+                                    argmax = i;
+                                }
+                            }
+                            argmax.as_()
+                        }),
+                    access_axis: access.access_axis,
+                }),
             }
         }
         &Language::AccessCartesianProduct([a0_id, a1_id]) => {
-            let (a0, a1) = match (
-                interpret(expr, a0_id.into(), env),
-                interpret(expr, a1_id.into(), env),
-            ) {
+            let (a0, a1) = match (values.take(a0_id), values.take(a1_id)) {
                 (Value::Access(a0), Value::Access(a1)) => (a0, a1),
                 _ => panic!(),
             };
@@ -701,11 +1080,11 @@ where
             })
         }
         &Language::Access([access_id, dim_id]) => {
-            let access = match interpret(expr, access_id.into(), env) {
+            let access = match values.take(access_id) {
                 Value::Access(a) => a,
                 _ => panic!(),
             };
-            let dim = match interpret(expr, dim_id.into(), env) {
+            let dim = match values.take(dim_id) {
                 Value::Usize(u) => u,
                 _ => panic!(),
             };
@@ -718,19 +1097,29 @@ where
                 access_axis: dim,
             })
         }
-        &Language::AccessWindows([access_id, filters_shape_id, stride_shape_id]) => {
-            let access = match interpret(expr, access_id.into(), env) {
+        &Language::AccessWindows([access_id, filters_shape_id, stride_shape_id])
+        | &Language::AccessWindowsDilated([access_id, filters_shape_id, stride_shape_id, _]) => {
+            let access = match values.take(access_id) {
                 Value::Access(a) => a,
                 _ => panic!(),
             };
-            let filters_shape = match interpret(expr, filters_shape_id.into(), env) {
+            let filters_shape = match values.take(filters_shape_id) {
                 Value::Shape(s) => s,
                 _ => panic!(),
             };
-            let stride_shape = match interpret(expr, stride_shape_id.into(), env) {
+            let stride_shape = match values.take(stride_shape_id) {
                 Value::Shape(s) => s,
                 _ => panic!(),
             };
+            let dilation_shape = match &expr.as_ref()[index] {
+                &Language::AccessWindowsDilated([_, _, _, dilation_shape_id]) => {
+                    match values.take(dilation_shape_id) {
+                        Value::Shape(s) => s,
+                        _ => panic!(),
+                    }
+                }
+                _ => IxDyn(vec![1; stride_shape.ndim()].as_slice()),
+            };
 
             assert_eq!(
                 access.access_axis,
@@ -748,7 +1137,13 @@ where
                 "access-windows filters ndims should match stride ndims"
             );
 
-            let out_shape = super::access_windows_resulting_shape(
+            assert_eq!(
+                dilation_shape.ndim(),
+                stride_shape.ndim(),
+                "access-windows dilation ndims should match stride ndims"
+            );
+
+            let out_shape = super::access_windows_dilated_resulting_shape(
                 &IxDyn(access.tensor.shape()),
                 &filters_shape,
                 // Ignore striding for now; we will stride after we get the result
@@ -759,8 +1154,34 @@ where
                         .collect::<Vec<_>>()
                         .as_slice(),
                 ),
+                &dilation_shape,
             );
 
+            // Dilated windows are formed by taking windows which span the
+            // dilated filter, and then stepping through each window by the
+            // dilation.
+            let dilated_filters_shape = IxDyn(
+                filters_shape
+                    .slice()
+                    .iter()
+                    .zip(dilation_shape.slice().iter())
+                    .map(|(&k, &d)| (k - 1) * d + 1)
+                    .collect::<Vec<_>>()
+                    .as_slice(),
+            );
+            let dilation_slice = ndarray::SliceInfo::<_, IxDyn>::new(
+                dilation_shape
+                    .slice()
+                    .iter()
+                    .map(|&d| ndarray::SliceOrIndex::Slice {
+                        start: 0,
+                        end: None,
+                        step: d as isize,
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .unwrap();
+
             let mut result = ArrayD::<DataType>::zeros(
                 out_shape
                     .iter()
@@ -770,9 +1191,11 @@ where
             );
 
             Zip::from(result.genrows_mut())
-                .and(access.tensor.windows(filters_shape.clone()))
+                .and(access.tensor.windows(dilated_filters_shape))
                 .apply(|mut result, windows| {
-                    result.assign(&Array::from_iter(windows.iter().cloned()))
+                    result.assign(&Array::from_iter(
+                        windows.slice(dilation_slice.as_ref()).iter().cloned(),
+                    ))
                 });
 
             let mut result = result
@@ -805,72 +1228,66 @@ where
 
             Value::Access(Access {
                 tensor: result,
-                // TODO(@gussmith23) Hardcoded
-                // This already bit me. I forgot to update it when I changed the
-                // access-windows semantics, and it took me a bit to find the
-                // bug.
-                // Actually, at this point, I'm pretty sure this is just wrong.
-                access_axis: 3,
+                // The windows are accessed as items, so the access axis comes
+                // right after the (one per input dimension) window positions.
+                access_axis: out_shape.len(),
             })
         }
         Language::Shape(list) => Value::Shape(IxDyn(
             list.iter()
-                .map(|id: &Id| match interpret(expr, (*id).into(), env) {
+                .map(|id: &Id| match values.take(*id) {
                     Value::Usize(u) => u,
                     _ => panic!(),
                 })
                 .collect::<Vec<_>>()
                 .as_slice(),
         )),
-        &Language::SliceShape([shape_id, slice_axis_id]) => match (
-            interpret(expr, shape_id.into(), env),
-            interpret(expr, slice_axis_id.into(), env),
-        ) {
-            (Value::Shape(s), Value::Usize(u)) => {
-                Value::Shape(IxDyn(s.as_array_view().slice(s![u..]).to_slice().unwrap()))
+        &Language::SliceShape([shape_id, slice_axis_id]) => {
+            match (values.take(shape_id), values.take(slice_axis_id)) {
+                (Value::Shape(s), Value::Usize(u)) => {
+                    Value::Shape(IxDyn(s.as_array_view().slice(s![u..]).to_slice().unwrap()))
+                }
+                _ => panic!(),
             }
-            _ => panic!(),
-        },
-        &Language::ShapeInsertAxis([shape_id, axis_id]) => match (
-            interpret(expr, shape_id.into(), env),
-            interpret(expr, axis_id.into(), env),
-        ) {
-            (Value::Shape(s), Value::Usize(u)) => {
-                assert!(u <= s.ndim());
-                Value::Shape(IxDyn(
-                    s.slice()[..u]
-                        .iter()
-                        .chain(std::iter::once(&1))
-                        .chain(s.slice()[u..].iter())
-                        .cloned()
-                        .collect::<Vec<_>>()
-                        .as_slice(),
-                ))
+        }
+        &Language::ShapeInsertAxis([shape_id, axis_id]) => {
+            match (values.take(shape_id), values.take(axis_id)) {
+                (Value::Shape(s), Value::Usize(u)) => {
+                    assert!(u <= s.ndim());
+                    Value::Shape(IxDyn(
+                        s.slice()[..u]
+                            .iter()
+                            .chain(std::iter::once(&1))
+                            .chain(s.slice()[u..].iter())
+                            .cloned()
+                            .collect::<Vec<_>>()
+                            .as_slice(),
+                    ))
+                }
+                _ => panic!(),
             }
-            _ => panic!(),
-        },
-        &Language::ShapeRemoveAxis([shape_id, axis_id]) => match (
-            interpret(expr, shape_id.into(), env),
-            interpret(expr, axis_id.into(), env),
-        ) {
-            (Value::Shape(s), Value::Usize(u)) => {
-                assert!(u < s.ndim(), "Invalid axis in shape-remove-axis");
-                Value::Shape(IxDyn(
-                    s.slice()[..u]
-                        .iter()
-                        .chain(s.slice()[u + 1..].iter())
-                        .cloned()
-                        .collect::<Vec<_>>()
-                        .as_slice(),
-                ))
+        }
+        &Language::ShapeRemoveAxis([shape_id, axis_id]) => {
+            match (values.take(shape_id), values.take(axis_id)) {
+                (Value::Shape(s), Value::Usize(u)) => {
+                    assert!(u < s.ndim(), "Invalid axis in shape-remove-axis");
+                    Value::Shape(IxDyn(
+                        s.slice()[..u]
+                            .iter()
+                            .chain(s.slice()[u + 1..].iter())
+                            .cloned()
+                            .collect::<Vec<_>>()
+                            .as_slice(),
+                    ))
+                }
+                _ => panic!(),
             }
-            _ => panic!(),
-        },
-        &Language::ShapeOf([tensor_id]) => match interpret(expr, tensor_id.into(), env) {
+        }
+        &Language::ShapeOf([tensor_id]) => match values.take(tensor_id) {
             Value::Tensor(t) => Value::Shape(IxDyn(t.shape())),
             _ => panic!(),
         },
-        &Language::AccessTensor(tensor_id) => match interpret(expr, tensor_id.into(), env) {
+        &Language::AccessTensor(tensor_id) => match values.take(tensor_id) {
             Value::Tensor(t) => Value::Access(Access {
                 tensor: t,
                 // TODO(@gussmith) Arbitrarily picked default access axis
@@ -885,17 +1302,134 @@ where
         ),
         &Language::Usize(u) => Value::Usize(u),
 
-        &Language::MoveAxis(_)
-        | &Language::CartesianProduct(_)
-        | &Language::MapDotProduct(_)
-        | &Language::Slice(_)
-        | &Language::Concatenate(_)
-        | &Language::ElementwiseAdd(_)
-        | &Language::BsgSystolicArray(_)
-        | &Language::SystolicArray(_)
-        | &Language::SystolicArrayWithBlocking(_)
-        | &Language::AccessReshape(_)
-        | &Language::AccessShiftRight(_) => todo!("{:?}", &expr.as_ref()[index]),
+        &Language::MoveAxis([tensor_id, src_axis_id, dest_axis_id]) => {
+            let (mut t, src_axis, dest_axis) = match (
+                values.take(tensor_id),
+                values.take(src_axis_id),
+                values.take(dest_axis_id),
+            ) {
+                (Value::Tensor(t), Value::Usize(src_axis), Value::Usize(dest_axis)) => {
+                    (t, src_axis, dest_axis)
+                }
+                _ => panic!("Expected a tensor and two usizes as arguments to move-axis"),
+            };
+
+            // Despite the name, move-axis swaps the two axes; see the
+            // analysis.
+            t.swap_axes(src_axis, dest_axis);
+
+            Value::Tensor(t.as_standard_layout().into_owned())
+        }
+        &Language::CartesianProduct([t0_id, t1_id]) => {
+            let (t0, t1) = match (values.take(t0_id), values.take(t1_id)) {
+                (Value::Tensor(t0), Value::Tensor(t1)) => (t0, t1),
+                _ => panic!("Expected both arguments to cartesian-product to be tensors"),
+            };
+
+            let vector_len = t0.shape()[t0.ndim() - 1];
+            assert_eq!(vector_len, t1.shape()[t1.ndim() - 1]);
+            let t0_num_vectors = t0.len() / vector_len;
+            let t1_num_vectors = t1.len() / vector_len;
+            let t0_vectors =
+                Array::from_shape_vec((t0_num_vectors, vector_len), t0.iter().cloned().collect())
+                    .unwrap();
+            let t1_vectors =
+                Array::from_shape_vec((t1_num_vectors, vector_len), t1.iter().cloned().collect())
+                    .unwrap();
+
+            let out = Array::from_shape_fn(
+                (t0_num_vectors, t1_num_vectors, 2, vector_len),
+                |(i0, i1, which, j)| {
+                    if which == 0 {
+                        t0_vectors[[i0, j]]
+                    } else {
+                        t1_vectors[[i1, j]]
+                    }
+                },
+            );
+
+            let out_shape = t0.shape()[..t0.ndim() - 1]
+                .iter()
+                .chain(t1.shape()[..t1.ndim() - 1].iter())
+                .cloned()
+                .chain(vec![2, vector_len])
+                .collect::<Vec<_>>();
+
+            Value::Tensor(out.into_shape(out_shape).unwrap().into_dyn())
+        }
+        &Language::MapDotProduct(tensor_id) => {
+            let t = match values.take(tensor_id) {
+                Value::Tensor(t) => t,
+                _ => panic!("Expected a tensor as the argument to map-dot-product"),
+            };
+
+            assert!(t.ndim() >= 2);
+            assert_eq!(t.shape()[t.ndim() - 2], 2);
+            let vector_len = t.shape()[t.ndim() - 1];
+            let num_pairs = t.len() / (2 * vector_len);
+            let pairs =
+                Array::from_shape_vec((num_pairs, 2, vector_len), t.iter().cloned().collect())
+                    .unwrap();
+
+            let out = Array::from_shape_fn(num_pairs, |i| {
+                (0..vector_len)
+                    .map(|j| pairs[[i, 0, j]] * pairs[[i, 1, j]])
+                    .sum::<DataType>()
+            });
+
+            Value::Tensor(
+                out.into_shape(&t.shape()[..t.ndim() - 2])
+                    .unwrap()
+                    .into_dyn(),
+            )
+        }
+        &Language::Slice([tensor_id, axis_id, low_id, high_id]) => {
+            let (t, axis, low, high) = match (
+                values.take(tensor_id),
+                values.take(axis_id),
+                values.take(low_id),
+                values.take(high_id),
+            ) {
+                (Value::Tensor(t), Value::Usize(axis), Value::Usize(low), Value::Usize(high)) => {
+                    (t, axis, low, high)
+                }
+                _ => panic!("Expected a tensor and three usizes as arguments to slice"),
+            };
+
+            Value::Tensor(
+                t.slice_axis(ndarray::Axis(axis), ndarray::Slice::from(low..high))
+                    .to_owned(),
+            )
+        }
+        &Language::Concatenate([t0_id, t1_id, axis_id]) => {
+            let (t0, t1, axis) =
+                match (values.take(t0_id), values.take(t1_id), values.take(axis_id)) {
+                    (Value::Tensor(t0), Value::Tensor(t1), Value::Usize(axis)) => (t0, t1, axis),
+                    _ => panic!("Expected two tensors and a usize as arguments to concatenate"),
+                };
+
+            Value::Tensor(ndarray::stack![ndarray::Axis(axis), t0, t1].into_dyn())
+        }
+        &Language::ElementwiseAdd([t0_id, t1_id]) => {
+            let (t0, t1) = match (values.take(t0_id), values.take(t1_id)) {
+                (Value::Tensor(t0), Value::Tensor(t1)) => (t0, t1),
+                _ => panic!("Expected both arguments to elementwise-add to be tensors"),
+            };
+
+            assert_eq!(t0.shape(), t1.shape());
+
+            Value::Tensor(t0 + t1)
+        }
+    }
+}
+
+/// The value of the number at `id` in `expr`, which may have been parsed as
+/// either a usize or a float.
+fn number(expr: &RecExpr<Language>, id: Id) -> NotNan<f64> {
+    match &expr.as_ref()[usize::from(id)] {
+        &Language::Usize(u) => NotNan::new(u as f64).unwrap(),
+        &Language::NotNanFloat64(v) => v,
+        other => panic!("Expected a number, found {:?}", other),
     }
 }
 
@@ -1020,7 +1554,43 @@ impl Sqrt for i64 {
     }
 }
 
-extern crate test;
+/// Trait for types which implement the hyperbolic tangent.
+pub trait Tanh {
+    /// Calculate hyperbolic tangent.
+    fn tanh(self) -> Self;
+}
+
+impl Tanh for f64 {
+    /// ```
+    /// use glenside::language::interpreter::Tanh;
+    /// assert!((Tanh::tanh(0.5f64) - 0.46211715726000974).abs() < 1e-15);
+    /// ```
+    fn tanh(self) -> Self {
+        f64::tanh(self)
+    }
+}
+
+impl Tanh for f32 {
+    /// ```
+    /// use glenside::language::interpreter::Tanh;
+    /// assert!((Tanh::tanh(0.5f32) - 0.46211716).abs() < 1e-6);
+    /// ```
+    fn tanh(self) -> Self {
+        f32::tanh(self)
+    }
+}
+
+impl Tanh for i64 {
+    /// ```should_panic
+    /// use glenside::language::interpreter::Tanh;
+    /// Tanh::tanh(5i64);
+    /// ```
+    fn tanh(self) -> Self {
+        panic!()
+    }
+}
+
+extern crate test;
 
 #[cfg(test)]
 mod tests {
@@ -1473,6 +2043,43 @@ mod tests {
         }
     );
 
+    benchmark_test!(
+        access_windows_dilated,
+        "(access-windows-dilated
+            (access (access-tensor t) 3)
+            (shape 1 2 2)
+            (shape 1 1 1)
+            (shape 1 2 2)
+        )",
+        vec![(
+            "t",
+            array![[
+                [1., 2., 3., 4., 5.],
+                [6., 7., 8., 9., 10.],
+                [11., 12., 13., 14., 15.],
+                [16., 17., 18., 19., 20.],
+                [21., 22., 23., 24., 25.],
+            ]]
+            .into_dyn()
+        )],
+        |value| {
+            match value {
+                Value::Access(a) => {
+                    assert_eq!(a.tensor.shape(), &[1, 3, 3, 1, 2, 2]);
+                    assert_eq!(
+                        a.tensor.slice(s![0, 0, 0, .., .., ..]),
+                        array![[[1., 3.], [11., 13.]]]
+                    );
+                    assert_eq!(
+                        a.tensor.slice(s![0, 1, 2, .., .., ..]),
+                        array![[[8., 10.], [18., 20.]]]
+                    );
+                }
+                _ => panic!(),
+            }
+        }
+    );
+
     #[test]
     fn shape() {
         let expr = RecExpr::<Language>::from_str("(shape 1 2 3)").unwrap();
@@ -1798,6 +2405,84 @@ mod tests {
         }
     }
 
+    #[test]
+    fn compute_reduce_min() {
+        let mut env = Environment::new();
+        env.insert(
+            "t",
+            array![[[1, -2], [3, 0]], [[-5, 6], [0, 8]], [[-9, 10], [11, 12]],].into_dyn(),
+        );
+
+        let expr = RecExpr::<Language>::from_str(
+            "(compute reduce-min
+              (access (access-tensor t) 1)
+             )",
+        )
+        .unwrap();
+
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(access_axis, 1);
+                assert_eq!(tensor, array![-2, -5, -9].into_dyn());
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn compute_argmax() {
+        let mut env = Environment::new();
+        env.insert(
+            "t",
+            array![[[1, -2], [3, 0]], [[-5, 6], [0, 8]], [[-9, 10], [11, 12]],].into_dyn(),
+        );
+
+        let expr = RecExpr::<Language>::from_str(
+            "(compute argmax
+              (access (access-tensor t) 1)
+             )",
+        )
+        .unwrap();
+
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(access_axis, 1);
+                assert_eq!(tensor, array![2, 3, 3].into_dyn());
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn compute_argmax_ties() {
+        let mut env = Environment::new();
+        env.insert("t", array![[4, 7, 7], [5, 5, 1]].into_dyn());
+
+        let expr = RecExpr::<Language>::from_str(
+            "(compute argmax
+              (access (access-tensor t) 1)
+             )",
+        )
+        .unwrap();
+
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(access_axis, 1);
+                assert_eq!(tensor, array![1, 0].into_dyn());
+            }
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn compute_reduce_max_3() {
         let mut env = Environment::new();
@@ -2356,209 +3041,502 @@ mod tests {
     }
 
     #[test]
-    fn compute_reduce_mean_0() {
-        let mut env = Environment::new();
-        env.insert(
-            "t",
-            array![[[1f64, 2f64], [3f64, 4f64]], [[5f64, 6f64], [7f64, 8f64]]].into_dyn(),
-        );
+    fn deep_program() {
+        // A recursive interpreter would overflow the stack on this program.
+        let mut expr = RecExpr::default();
+        let t_id = expr.add(Language::Symbol("t".to_string()));
+        let mut id = expr.add(Language::AccessTensor(t_id));
+        let relu_id = expr.add(Language::ComputeType(ComputeType::ReLU));
+        for _ in 0..100000 {
+            id = expr.add(Language::Compute([relu_id, id]));
+        }
 
-        let expr =
-            RecExpr::<Language>::from_str("(compute reduce-mean (access (access-tensor t) 0))")
-                .unwrap();
+        let mut env = Environment::new();
+        env.insert("t", array![-1f64, 2f64].into_dyn());
 
-        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+        match interpret(&expr, usize::from(id), &env) {
             Value::Access(Access {
                 tensor,
-                access_axis,
+                access_axis: _,
             }) => {
-                assert_eq!(
-                    tensor,
-                    ndarray::arr0((1f64 + 2f64 + 3f64 + 4f64 + 5f64 + 6f64 + 7f64 + 8f64) / 8f64)
-                        .into_dyn(),
-                );
-                assert_eq!(access_axis, 0);
+                assert_eq!(tensor, array![0f64, 2f64].into_dyn());
             }
             _ => panic!(),
         }
     }
 
     #[test]
-    fn compute_reduce_mean_1() {
-        let mut env = Environment::new();
-        env.insert(
-            "t",
-            array![[[1f64, 2f64], [3f64, 4f64]], [[5f64, 6f64], [7f64, 8f64]]].into_dyn(),
-        );
+    fn shared_subexpressions() {
+        // Each node is used twice by the next node, so this would take 2^64
+        // evaluations if shared subexpressions were evaluated more than once.
+        let mut expr = RecExpr::default();
+        let t_id = expr.add(Language::Symbol("t".to_string()));
+        let t_id = expr.add(Language::AccessTensor(t_id));
+        let axis_id = expr.add(Language::Usize(0));
+        let mut id = expr.add(Language::Access([t_id, axis_id]));
+        let add_id = expr.add(Language::ComputeType(ComputeType::ElementwiseAdd));
+        for _ in 0..64 {
+            let pair_id = expr.add(Language::AccessPair([id, id]));
+            id = expr.add(Language::Compute([add_id, pair_id]));
+        }
 
-        let expr =
-            RecExpr::<Language>::from_str("(compute reduce-mean (access (access-tensor t) 1))")
-                .unwrap();
+        let mut env = Environment::new();
+        env.insert("t", array![1f64, 3f64].into_dyn());
 
-        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+        match interpret(&expr, usize::from(id), &env) {
             Value::Access(Access {
                 tensor,
-                access_axis,
+                access_axis: _,
             }) => {
                 assert_eq!(
                     tensor,
-                    array![
-                        (1f64 + 2f64 + 3f64 + 4f64) / 4f64,
-                        (5f64 + 6f64 + 7f64 + 8f64) / 4f64
-                    ]
-                    .into_dyn(),
+                    array![2f64.powi(64), 3f64 * 2f64.powi(64)].into_dyn()
                 );
-                assert_eq!(access_axis, 1);
             }
             _ => panic!(),
         }
     }
 
     #[test]
-    fn compute_reduce_mean_2() {
+    fn unreachable_nodes_not_evaluated() {
+        // `u` isn't in the environment, but it's not needed to compute the
+        // node we ask for.
+        let expr = RecExpr::<Language>::from_str(
+            "(access-pair (access (access-tensor t) 0) (access (access-tensor u) 0))",
+        )
+        .unwrap();
         let mut env = Environment::new();
-        env.insert(
-            "t",
-            array![[[1f64, 2f64], [3f64, 4f64]], [[5f64, 6f64], [7f64, 8f64]]].into_dyn(),
-        );
-
-        let expr =
-            RecExpr::<Language>::from_str("(compute reduce-mean (access (access-tensor t) 2))")
-                .unwrap();
+        env.insert("t", array![1, 2].into_dyn());
 
-        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+        match interpret(&expr, 3, &env) {
             Value::Access(Access {
                 tensor,
                 access_axis,
             }) => {
-                assert_eq!(
-                    tensor,
-                    array![
-                        [(1f64 + 2f64) / 2f64, (3f64 + 4f64) / 2f64],
-                        [(5f64 + 6f64) / 2f64, (7f64 + 8f64) / 2f64]
-                    ]
-                    .into_dyn(),
-                );
-                assert_eq!(access_axis, 2);
+                assert_eq!(access_axis, 0);
+                assert_eq!(tensor, array![1, 2].into_dyn());
             }
             _ => panic!(),
         }
     }
 
     #[test]
-    fn compute_reduce_mean_3() {
+    fn systolic_array() {
         let mut env = Environment::new();
-        env.insert(
-            "t",
-            array![[[1f64, 2f64], [3f64, 4f64]], [[5f64, 6f64], [7f64, 8f64]]].into_dyn(),
-        );
+        env.insert("a", array![[1, 2, 3], [4, 5, 6]].into_dyn());
+        env.insert("b", array![[1, -1], [2, 0], [0, 3]].into_dyn());
 
-        let expr =
-            RecExpr::<Language>::from_str("(compute reduce-mean (access (access-tensor t) 3))")
-                .unwrap();
+        let expr = RecExpr::<Language>::from_str(
+            "(systolic-array 3 2
+              (access (access-tensor a) 1)
+              (access (access-tensor b) 0)
+             )",
+        )
+        .unwrap();
 
         match interpret(&expr, expr.as_ref().len() - 1, &env) {
             Value::Access(Access {
                 tensor,
                 access_axis,
             }) => {
-                assert_eq!(
-                    tensor,
-                    array![[[1f64, 2f64], [3f64, 4f64]], [[5f64, 6f64], [7f64, 8f64]]].into_dyn(),
-                );
-                assert_eq!(access_axis, 3);
+                assert_eq!(access_axis, 2);
+                assert_eq!(tensor, array![[5, 8], [14, 14]].into_dyn());
             }
             _ => panic!(),
         }
     }
 
     #[test]
-    fn access_pad_min_padding() {
+    fn systolic_array_unbatched() {
         let mut env = Environment::new();
-        env.insert("t", array![[1., 2.], [3., 4.]].into_dyn());
+        env.insert("a", array![1, 2, 3].into_dyn());
+        env.insert("b", array![[1, -1], [2, 0], [0, 3]].into_dyn());
+
+        let expr = RecExpr::<Language>::from_str(
+            "(systolic-array 3 2
+              (access (access-tensor a) 0)
+              (access (access-tensor b) 0)
+             )",
+        )
+        .unwrap();
 
-        let expr =
-            RecExpr::<Language>::from_str("(access-pad (access-tensor t) min-padding 0 2 4)")
-                .unwrap();
         match interpret(&expr, expr.as_ref().len() - 1, &env) {
             Value::Access(Access {
                 tensor,
                 access_axis,
             }) => {
-                assert_eq!(
-                    tensor,
-                    array![
-                        [std::f64::MIN, std::f64::MIN],
-                        [std::f64::MIN, std::f64::MIN],
-                        [1., 2.],
-                        [3., 4.],
-                        [std::f64::MIN, std::f64::MIN],
-                        [std::f64::MIN, std::f64::MIN],
-                        [std::f64::MIN, std::f64::MIN],
-                        [std::f64::MIN, std::f64::MIN]
-                    ]
-                    .into_dyn()
-                );
-                assert_eq!(access_axis, 0);
+                assert_eq!(access_axis, 1);
+                assert_eq!(tensor, array![5, 8].into_dyn());
             }
             _ => panic!(),
         }
     }
 
     #[test]
-    fn compute_elementwise_div() {
+    fn systolic_array_with_blocking() {
         let mut env = Environment::new();
         env.insert(
-            "t",
-            array![
-                [[1f32, -2f32], [3f32, 1f32]],
-                [[-5f32, 6f32], [1f32, 8f32]],
-                [[-9f32, 10f32], [11f32, 12f32]],
-            ]
-            .into_dyn(),
+            "a",
+            ndarray::ArrayD::from_shape_vec(vec![3, 4], (0..12).collect()).unwrap(),
+        );
+        env.insert(
+            "b",
+            ndarray::ArrayD::from_shape_vec(vec![4, 6], (0..24).map(|i| i % 5 - 2).collect())
+                .unwrap(),
         );
 
         let expr = RecExpr::<Language>::from_str(
-            "(compute elementwise-div
-              (access (access-tensor t) 0)
+            "(systolic-array-with-blocking 2 3
+              (access (access-tensor a) 1)
+              (access (access-tensor b) 0)
              )",
         )
         .unwrap();
 
+        let a = env["a"].clone().into_shape((3, 4)).unwrap();
+        let b = env["b"].clone().into_shape((4, 6)).unwrap();
+
         match interpret(&expr, expr.as_ref().len() - 1, &env) {
             Value::Access(Access {
                 tensor,
                 access_axis,
             }) => {
-                assert_eq!(access_axis, 0);
-                assert_eq!(
-                    tensor,
-                    array![
-                        [1f32 / -5f32 / -9f32, -2f32 / 6f32 / 10f32],
-                        [3f32 / 1f32 / 11f32, 1f32 / 8f32 / 12f32]
-                    ]
-                    .into_dyn()
-                );
+                assert_eq!(access_axis, 2);
+                assert_eq!(tensor, a.dot(&b).into_dyn());
             }
             _ => panic!(),
         }
     }
 
     #[test]
-    fn literal_0() {
-        let expr = RecExpr::<Language>::from_str("(literal 0.1234)").unwrap();
+    #[should_panic]
+    fn systolic_array_wrong_size_panic() {
+        let mut env = Environment::new();
+        env.insert("a", array![[1, 2, 3], [4, 5, 6]].into_dyn());
+        env.insert("b", array![[1, -1], [2, 0], [0, 3]].into_dyn());
 
-        match interpret::<f64>(&expr, expr.as_ref().len() - 1, &HashMap::default()) {
-            Value::Tensor(t) => {
-                assert_eq!(t, ndarray::arr0(0.1234).into_dyn());
-            }
-            _ => panic!(),
-        }
-    }
+        let expr = RecExpr::<Language>::from_str(
+            "(systolic-array 2 2
+              (access (access-tensor a) 1)
+              (access (access-tensor b) 0)
+             )",
+        )
+        .unwrap();
+
+        interpret(&expr, expr.as_ref().len() - 1, &env);
+    }
+
+    #[test]
+    fn access_reshape() {
+        let mut env = Environment::new();
+        env.insert(
+            "t",
+            ndarray::ArrayD::from_shape_vec(vec![2, 6, 4], (0..2 * 6 * 4).collect()).unwrap(),
+        );
+
+        let expr = RecExpr::<Language>::from_str(
+            "(access-reshape
+              (access (access-tensor t) 1)
+              (access-shape (shape 4 3) (shape 2 2))
+             )",
+        )
+        .unwrap();
+
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(access_axis, 2);
+                assert_eq!(
+                    tensor,
+                    ndarray::ArrayD::from_shape_vec(vec![4, 3, 2, 2], (0..2 * 6 * 4).collect())
+                        .unwrap(),
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn access_reshape_transposed() {
+        let mut env = Environment::new();
+        env.insert("t", array![[1, 2, 3], [4, 5, 6]].into_dyn());
+
+        let expr = RecExpr::<Language>::from_str(
+            "(access-reshape
+              (access-transpose (access (access-tensor t) 1) (list 1 0))
+              (access-shape (shape 6) (shape))
+             )",
+        )
+        .unwrap();
+
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(access_axis, 1);
+                assert_eq!(tensor, array![1, 4, 2, 5, 3, 6].into_dyn());
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn access_shift_right() {
+        let mut env = Environment::new();
+        env.insert(
+            "t",
+            ndarray::ArrayD::from_shape_vec(vec![2, 6, 4], (0..2 * 6 * 4).collect()).unwrap(),
+        );
+
+        let expr =
+            RecExpr::<Language>::from_str("(access-shift-right (access (access-tensor t) 2))")
+                .unwrap();
+
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(access_axis, 1);
+                assert_eq!(
+                    tensor,
+                    ndarray::ArrayD::from_shape_vec(vec![2, 6, 4], (0..2 * 6 * 4).collect())
+                        .unwrap(),
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn access_shift_right_axis_0() {
+        let mut env = Environment::new();
+        env.insert("t", array![1, 2, 3].into_dyn());
+
+        let expr =
+            RecExpr::<Language>::from_str("(access-shift-right (access (access-tensor t) 0))")
+                .unwrap();
+
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(access_axis, 0);
+                assert_eq!(tensor, array![1, 2, 3].into_dyn());
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn compute_reduce_mean_0() {
+        let mut env = Environment::new();
+        env.insert(
+            "t",
+            array![[[1f64, 2f64], [3f64, 4f64]], [[5f64, 6f64], [7f64, 8f64]]].into_dyn(),
+        );
+
+        let expr =
+            RecExpr::<Language>::from_str("(compute reduce-mean (access (access-tensor t) 0))")
+                .unwrap();
+
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(
+                    tensor,
+                    ndarray::arr0((1f64 + 2f64 + 3f64 + 4f64 + 5f64 + 6f64 + 7f64 + 8f64) / 8f64)
+                        .into_dyn(),
+                );
+                assert_eq!(access_axis, 0);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn compute_reduce_mean_1() {
+        let mut env = Environment::new();
+        env.insert(
+            "t",
+            array![[[1f64, 2f64], [3f64, 4f64]], [[5f64, 6f64], [7f64, 8f64]]].into_dyn(),
+        );
+
+        let expr =
+            RecExpr::<Language>::from_str("(compute reduce-mean (access (access-tensor t) 1))")
+                .unwrap();
+
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(
+                    tensor,
+                    array![
+                        (1f64 + 2f64 + 3f64 + 4f64) / 4f64,
+                        (5f64 + 6f64 + 7f64 + 8f64) / 4f64
+                    ]
+                    .into_dyn(),
+                );
+                assert_eq!(access_axis, 1);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn compute_reduce_mean_2() {
+        let mut env = Environment::new();
+        env.insert(
+            "t",
+            array![[[1f64, 2f64], [3f64, 4f64]], [[5f64, 6f64], [7f64, 8f64]]].into_dyn(),
+        );
+
+        let expr =
+            RecExpr::<Language>::from_str("(compute reduce-mean (access (access-tensor t) 2))")
+                .unwrap();
+
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(
+                    tensor,
+                    array![
+                        [(1f64 + 2f64) / 2f64, (3f64 + 4f64) / 2f64],
+                        [(5f64 + 6f64) / 2f64, (7f64 + 8f64) / 2f64]
+                    ]
+                    .into_dyn(),
+                );
+                assert_eq!(access_axis, 2);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn compute_reduce_mean_3() {
+        let mut env = Environment::new();
+        env.insert(
+            "t",
+            array![[[1f64, 2f64], [3f64, 4f64]], [[5f64, 6f64], [7f64, 8f64]]].into_dyn(),
+        );
+
+        let expr =
+            RecExpr::<Language>::from_str("(compute reduce-mean (access (access-tensor t) 3))")
+                .unwrap();
+
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(
+                    tensor,
+                    array![[[1f64, 2f64], [3f64, 4f64]], [[5f64, 6f64], [7f64, 8f64]]].into_dyn(),
+                );
+                assert_eq!(access_axis, 3);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn access_pad_min_padding() {
+        let mut env = Environment::new();
+        env.insert("t", array![[1., 2.], [3., 4.]].into_dyn());
+
+        let expr =
+            RecExpr::<Language>::from_str("(access-pad (access-tensor t) min-padding 0 2 4)")
+                .unwrap();
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(
+                    tensor,
+                    array![
+                        [std::f64::MIN, std::f64::MIN],
+                        [std::f64::MIN, std::f64::MIN],
+                        [1., 2.],
+                        [3., 4.],
+                        [std::f64::MIN, std::f64::MIN],
+                        [std::f64::MIN, std::f64::MIN],
+                        [std::f64::MIN, std::f64::MIN],
+                        [std::f64::MIN, std::f64::MIN]
+                    ]
+                    .into_dyn()
+                );
+                assert_eq!(access_axis, 0);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn compute_elementwise_div() {
+        let mut env = Environment::new();
+        env.insert(
+            "t",
+            array![
+                [[1f32, -2f32], [3f32, 1f32]],
+                [[-5f32, 6f32], [1f32, 8f32]],
+                [[-9f32, 10f32], [11f32, 12f32]],
+            ]
+            .into_dyn(),
+        );
+
+        let expr = RecExpr::<Language>::from_str(
+            "(compute elementwise-div
+              (access (access-tensor t) 0)
+             )",
+        )
+        .unwrap();
+
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(access_axis, 0);
+                assert_eq!(
+                    tensor,
+                    array![
+                        [1f32 / -5f32 / -9f32, -2f32 / 6f32 / 10f32],
+                        [3f32 / 1f32 / 11f32, 1f32 / 8f32 / 12f32]
+                    ]
+                    .into_dyn()
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn literal_0() {
+        let expr = RecExpr::<Language>::from_str("(literal 0.1234)").unwrap();
+
+        match interpret::<f64>(&expr, expr.as_ref().len() - 1, &HashMap::default()) {
+            Value::Tensor(t) => {
+                assert_eq!(t, ndarray::arr0(0.1234).into_dyn());
+            }
+            _ => panic!(),
+        }
+    }
 
     #[test]
     fn access_literal() {
-        let expr = RecExpr::<Language>::from_str("(access-literal (literal 0.1234))").unwrap();
+        let expr =
+            RecExpr::<Language>::from_str("(access-literal (literal 0.1234) float32)").unwrap();
 
         match interpret::<f64>(&expr, expr.as_ref().len() - 1, &HashMap::default()) {
             Value::Access(Access {
@@ -2572,6 +3550,198 @@ mod tests {
         }
     }
 
+    #[test]
+    fn bsg_systolic_array() {
+        let mut env = Environment::new();
+        env.insert(
+            "a",
+            array![[1f64, 2f64], [3f64, 4f64], [5f64, 6f64]].into_dyn(),
+        );
+        env.insert(
+            "b",
+            array![[1f64, 0f64, 2f64], [-1f64, 3f64, 1f64]].into_dyn(),
+        );
+
+        let expr = RecExpr::<Language>::from_str("(bsg-systolic-array 2 3 a b)").unwrap();
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Tensor(t) => assert_eq!(
+                t,
+                array![
+                    [-1f64, 6f64, 4f64],
+                    [-1f64, 12f64, 10f64],
+                    [-1f64, 18f64, 16f64]
+                ]
+                .into_dyn()
+            ),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn bsg_systolic_array_vector() {
+        let mut env = Environment::new();
+        env.insert("a", array![1f64, 2f64].into_dyn());
+        env.insert(
+            "b",
+            array![[1f64, 0f64, 2f64], [-1f64, 3f64, 1f64]].into_dyn(),
+        );
+
+        let expr = RecExpr::<Language>::from_str("(bsg-systolic-array 2 3 a b)").unwrap();
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Tensor(t) => assert_eq!(t, array![-1f64, 6f64, 4f64].into_dyn()),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn move_axis() {
+        let mut env = Environment::new();
+        env.insert(
+            "t",
+            array![[[1f64, 2f64], [3f64, 4f64]], [[5f64, 6f64], [7f64, 8f64]]].into_dyn(),
+        );
+
+        let expr = RecExpr::<Language>::from_str("(move-axis t 0 2)").unwrap();
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Tensor(t) => assert_eq!(
+                t,
+                array![[[1f64, 5f64], [3f64, 7f64]], [[2f64, 6f64], [4f64, 8f64]]].into_dyn()
+            ),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn cartesian_product() {
+        let mut env = Environment::new();
+        env.insert("a", array![[1f64, 2f64], [3f64, 4f64]].into_dyn());
+        env.insert(
+            "b",
+            array![[5f64, 6f64], [7f64, 8f64], [9f64, 10f64]].into_dyn(),
+        );
+
+        let expr = RecExpr::<Language>::from_str("(cartesian-product a b)").unwrap();
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Tensor(t) => assert_eq!(
+                t,
+                array![
+                    [
+                        [[1f64, 2f64], [5f64, 6f64]],
+                        [[1f64, 2f64], [7f64, 8f64]],
+                        [[1f64, 2f64], [9f64, 10f64]]
+                    ],
+                    [
+                        [[3f64, 4f64], [5f64, 6f64]],
+                        [[3f64, 4f64], [7f64, 8f64]],
+                        [[3f64, 4f64], [9f64, 10f64]]
+                    ]
+                ]
+                .into_dyn()
+            ),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn map_dot_product() {
+        let mut env = Environment::new();
+        env.insert(
+            "t",
+            array![[[1f64, 2f64], [3f64, 4f64]], [[5f64, 6f64], [-1f64, 2f64]]].into_dyn(),
+        );
+
+        let expr = RecExpr::<Language>::from_str("(map-dot-product t)").unwrap();
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Tensor(t) => assert_eq!(t, array![11f64, 7f64].into_dyn()),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn matrix_multiplication_with_legacy_constructs() {
+        let mut env = Environment::new();
+        env.insert(
+            "a",
+            array![[1f64, 2f64], [3f64, 4f64], [5f64, 6f64]].into_dyn(),
+        );
+        env.insert(
+            "b",
+            array![[1f64, 0f64, 2f64], [-1f64, 3f64, 1f64]].into_dyn(),
+        );
+
+        let expr = RecExpr::<Language>::from_str(
+            "(map-dot-product (cartesian-product a (move-axis b 1 0)))",
+        )
+        .unwrap();
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Tensor(t) => assert_eq!(
+                t,
+                array![
+                    [-1f64, 6f64, 4f64],
+                    [-1f64, 12f64, 10f64],
+                    [-1f64, 18f64, 16f64]
+                ]
+                .into_dyn()
+            ),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn slice() {
+        let mut env = Environment::new();
+        env.insert(
+            "t",
+            array![[1f64, 2f64, 3f64], [4f64, 5f64, 6f64]].into_dyn(),
+        );
+
+        let expr = RecExpr::<Language>::from_str("(slice t 1 1 3)").unwrap();
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Tensor(t) => assert_eq!(t, array![[2f64, 3f64], [5f64, 6f64]].into_dyn()),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn concatenate() {
+        let mut env = Environment::new();
+        env.insert("a", array![[1f64, 2f64], [3f64, 4f64]].into_dyn());
+        env.insert("b", array![[5f64], [6f64]].into_dyn());
+
+        let expr = RecExpr::<Language>::from_str("(concatenate a b 1)").unwrap();
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Tensor(t) => {
+                assert_eq!(t, array![[1f64, 2f64, 5f64], [3f64, 4f64, 6f64]].into_dyn())
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn elementwise_add() {
+        let mut env = Environment::new();
+        env.insert("a", array![[1f64, 2f64], [3f64, 4f64]].into_dyn());
+        env.insert("b", array![[5f64, -6f64], [7f64, 0f64]].into_dyn());
+
+        let expr = RecExpr::<Language>::from_str("(elementwise-add a b)").unwrap();
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Tensor(t) => assert_eq!(t, array![[6f64, -4f64], [10f64, 4f64]].into_dyn()),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn access_literal_integer() {
+        let expr = RecExpr::<Language>::from_str("(access-literal (literal 3) int8)").unwrap();
+
+        match interpret::<f64>(&expr, expr.as_ref().len() - 1, &HashMap::default()) {
+            Value::Access(Access { tensor, .. }) => {
+                assert_eq!(tensor, ndarray::arr0(3.0).into_dyn());
+            }
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn compute_sqrt() {
         let mut env = Environment::new();
@@ -2652,6 +3822,181 @@ mod tests {
         }
     }
 
+    #[test]
+    fn compute_sigmoid() {
+        let mut env = Environment::new();
+        env.insert("t", array![[-2f64, -0.5f64], [0f64, 3f64]].into_dyn());
+
+        let expr = RecExpr::<Language>::from_str("(compute sigmoid (access (access-tensor t) 1))")
+            .unwrap();
+
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(access_axis, 1);
+                assert!(
+                    tensor.abs_diff_eq(
+                        &array![
+                            [0.11920292202211755, 0.3775406687981454],
+                            [0.5, 0.9525741268224334]
+                        ]
+                        .into_dyn(),
+                        1e-12
+                    ),
+                    "{:?}",
+                    tensor
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn compute_tanh() {
+        let mut env = Environment::new();
+        env.insert("t", array![[-2f64, -0.5f64], [0f64, 3f64]].into_dyn());
+
+        let expr =
+            RecExpr::<Language>::from_str("(compute tanh (access (access-tensor t) 1))").unwrap();
+
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(access_axis, 1);
+                assert!(
+                    tensor.abs_diff_eq(
+                        &array![
+                            [-0.9640275800758169, -0.46211715726000974],
+                            [0.0, 0.9950547536867305]
+                        ]
+                        .into_dyn(),
+                        1e-12
+                    ),
+                    "{:?}",
+                    tensor
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn compute_exp() {
+        let mut env = Environment::new();
+        env.insert("t", array![[-2f64, -0.5f64], [0f64, 3f64]].into_dyn());
+
+        let expr =
+            RecExpr::<Language>::from_str("(compute exp (access (access-tensor t) 1))").unwrap();
+
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(access_axis, 1);
+                assert!(
+                    tensor.abs_diff_eq(
+                        &array![
+                            [0.1353352832366127, 0.6065306597126334],
+                            [1.0, 20.085536923187668]
+                        ]
+                        .into_dyn(),
+                        1e-12
+                    ),
+                    "{:?}",
+                    tensor
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn compute_clip() {
+        let mut env = Environment::new();
+        env.insert("t", array![[-2f64, -0.5f64], [0f64, 3f64]].into_dyn());
+
+        let expr =
+            RecExpr::<Language>::from_str("(compute (clip -1 2.5) (access (access-tensor t) 1))")
+                .unwrap();
+
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(access_axis, 1);
+                assert!(
+                    tensor.abs_diff_eq(&array![[-1.0, -0.5], [0.0, 2.5]].into_dyn(), 1e-12),
+                    "{:?}",
+                    tensor
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn compute_leaky_relu() {
+        let mut env = Environment::new();
+        env.insert("t", array![[-2f64, -0.5f64], [0f64, 3f64]].into_dyn());
+
+        let expr = RecExpr::<Language>::from_str(
+            "(compute (leaky-relu 0.1) (access (access-tensor t) 1))",
+        )
+        .unwrap();
+
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(access_axis, 1);
+                assert!(
+                    tensor.abs_diff_eq(&array![[-0.2, -0.05], [0.0, 3.0]].into_dyn(), 1e-12),
+                    "{:?}",
+                    tensor
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn compute_gelu() {
+        let mut env = Environment::new();
+        env.insert("t", array![[-2f64, -0.5f64], [0f64, 3f64]].into_dyn());
+
+        let expr =
+            RecExpr::<Language>::from_str("(compute gelu (access (access-tensor t) 1))").unwrap();
+
+        match interpret(&expr, expr.as_ref().len() - 1, &env) {
+            Value::Access(Access {
+                tensor,
+                access_axis,
+            }) => {
+                assert_eq!(access_axis, 1);
+                assert!(
+                    tensor.abs_diff_eq(
+                        &array![
+                            [-0.04540230591222494, -0.15428599017485606],
+                            [0.0, 2.996362607918227]
+                        ]
+                        .into_dyn(),
+                        1e-12
+                    ),
+                    "{:?}",
+                    tensor
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
     benchmark_test!(
         access_concatenate_0,
         "(access-concatenate (access (access-tensor t) 0) (access (access-tensor n) 0) 0)",
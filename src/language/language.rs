@@ -1,3 +1,4 @@
+use crate::hw_design_language::DType;
 use egg::{define_language, merge_if_different, EGraph, Id};
 use itertools::{multizip, EitherOrBoth::*, Itertools};
 use log::warn;
@@ -87,6 +88,13 @@ define_language! {
         // AccessWindows is used in other contexts too, i.e. pooling.
         "access-windows" = AccessWindows([Id; 3]),
 
+        // (access-windows-dilated <access> <filters-shape: Shape>
+        //  <stride-shape: Shape> <dilation-shape: Shape>)
+        // Like access-windows, but the elements of each window are spaced
+        // out according to <dilation-shape>. A dilation of 1 in every
+        // dimension is equivalent to access-windows.
+        "access-windows-dilated" = AccessWindowsDilated([Id; 4]),
+
         // (shape-of <tensor>)
         // Returns the shape of the tensor.
         // TODO(@gussmith) Choose between ([Id; 1]) and (Id) and be consistent
@@ -144,6 +152,16 @@ define_language! {
         // shape of the tensors to be dot-producted with one another.
         "compute" = Compute([Id; 2]),
 
+        // (clip <min> <max>)
+        // The compute type which clamps each value to [<min>, <max>]. <min>
+        // and <max> are numbers.
+        "clip" = Clip([Id; 2]),
+
+        // (leaky-relu <alpha>)
+        // The compute type which scales each negative value by the number
+        // <alpha>.
+        "leaky-relu" = LeakyReLU(Id),
+
         // (get-access-shape <access>)
         // Returns the shape of the access.
         // "get-access-shape" = GetAccessShape(Id),
@@ -219,12 +237,12 @@ define_language! {
         // corresponding dimension in <shape> or be 1.
         "access-broadcast" = AccessBroadcast([Id; 2]),
 
-        // (access-literal <literal: Literal>)
-        // Access a literal. This may be able to be folded in to some other
-        // access pattern, later on. It fits in with access-tensor as a "access
-        // pattern constructor"; it takes something that isn't an access pattern
-        // and converts it to an access pattern.
-        "access-literal" = AccessLiteral(Id),
+        // (access-literal <literal: Literal> <dtype: DType>)
+        // Access a literal, as a value of datatype <dtype>. This may be able to
+        // be folded in to some other access pattern, later on. It fits in with
+        // access-tensor as a "access pattern constructor"; it takes something
+        // that isn't an access pattern and converts it to an access pattern.
+        "access-literal" = AccessLiteral([Id; 2]),
 
         // (literal <val: Float64>)
         // A literal value. Can only represent 0-dimensional values for now, but
@@ -244,6 +262,12 @@ define_language! {
 
         ComputeType(ComputeType),
 
+        // A datatype, e.g. float32 or int8.
+        // Important that this go before symbol, so that datatypes aren't
+        // parsed as tensor names. This means tensors can't be named after
+        // datatypes; the analysis reports an error if they are.
+        DataType(DType),
+
         Symbol(String),
     }
 }
@@ -274,6 +298,35 @@ pub enum ComputeType {
     /// For an item shape of `a1 x a2 x ...`, returns an item shape of `1` where
     /// the returned scalar is the mean of the `a1 x a2 x ...`-shaped tensor.
     ReduceMean,
+    /// `1 / (1 + e^-x)`, elementwise.
+    Sigmoid,
+    Tanh,
+    Exp,
+    /// Clamps each value to `[min, max]`. Written `(clip <min> <max>)`.
+    Clip {
+        min: NotNan<f64>,
+        max: NotNan<f64>,
+    },
+    /// `x` if `x >= 0`, otherwise `alpha * x`. Written `(leaky-relu <alpha>)`.
+    LeakyReLU {
+        alpha: NotNan<f64>,
+    },
+    /// The Gaussian error linear unit, using the tanh approximation
+    /// `0.5x(1 + tanh(sqrt(2/pi)(x + 0.044715x^3)))`. The exact, erf-based
+    /// gelu isn't supported.
+    ///
+    /// Gelu can't be imported from Relay, which has no gelu operator.
+    /// Frontends build the exact gelu out of `erf`, which the Relay importer
+    /// rejects, and a tanh-approximated gelu written out in Relay is imported
+    /// as the elementwise operators it's made of, not as `gelu`.
+    Gelu,
+    /// Takes the min across all elements in each item. Reduces any item shape
+    /// to a scalar.
+    ReduceMin,
+    /// Finds the index of the max of each item, as if the item were
+    /// flattened. Ties go to the lowest index. Reduces any item shape to a
+    /// scalar. The indices are int32s, whatever the dtype of the input.
+    ArgMax,
 }
 impl FromStr for ComputeType {
     type Err = ();
@@ -290,16 +343,30 @@ impl FromStr for ComputeType {
             "elementwise-div" => Ok(ComputeType::ElementwiseDiv),
             "softmax" => Ok(ComputeType::Softmax),
             "reduce-mean" => Ok(ComputeType::ReduceMean),
+            "sigmoid" => Ok(ComputeType::Sigmoid),
+            "tanh" => Ok(ComputeType::Tanh),
+            "exp" => Ok(ComputeType::Exp),
+            "gelu" => Ok(ComputeType::Gelu),
+            "reduce-min" => Ok(ComputeType::ReduceMin),
+            "argmax" => Ok(ComputeType::ArgMax),
+            // Clip and leaky-relu take operands, and so are parsed as their
+            // own nodes; see Language::Clip and Language::LeakyReLU.
             _ => Err(()),
         }
     }
 }
 impl Display for ComputeType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ComputeType::Clip { min, max } => return write!(f, "(clip {} {})", min, max),
+            ComputeType::LeakyReLU { alpha } => return write!(f, "(leaky-relu {})", alpha),
+            _ => (),
+        }
         write!(
             f,
             "{}",
             match self {
+                ComputeType::Clip { .. } | ComputeType::LeakyReLU { .. } => unreachable!(),
                 ComputeType::DotProduct => "dot-product",
                 ComputeType::ReduceSum => "reduce-sum",
                 ComputeType::ReduceMax => "reduce-max",
@@ -311,6 +378,12 @@ impl Display for ComputeType {
                 ComputeType::ElementwiseDiv => "elementwise-div",
                 ComputeType::Softmax => "softmax",
                 ComputeType::ReduceMean => "reduce-mean",
+                ComputeType::Sigmoid => "sigmoid",
+                ComputeType::Tanh => "tanh",
+                ComputeType::Exp => "exp",
+                ComputeType::Gelu => "gelu",
+                ComputeType::ReduceMin => "reduce-min",
+                ComputeType::ArgMax => "argmax",
             }
         )
     }
@@ -358,7 +431,9 @@ pub enum MyAnalysisData {
     //Tensor(TensorData),
     ComputeType(ComputeType),
     PadType(PadType),
+    DType(DType),
     List(Vec<usize>),
+    AccessShape(AccessShapeData),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -366,6 +441,14 @@ pub struct ShapeData {
     shape: IxDyn,
 }
 
+/// The shape of an access pattern, given by an `access-shape` literal. Unlike
+/// [`AccessPatternData`], it refers to no data, and so it has no dtype.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessShapeData {
+    pub shape: IxDyn,
+    pub item_shape: IxDyn,
+}
+
 /// New version of rangeset.
 pub trait RangeSet2 {
     type Index;
@@ -734,6 +817,8 @@ mod range_hash_set_tests {
 pub struct AccessPatternData {
     pub shape: IxDyn,
     pub item_shape: IxDyn,
+    /// The datatype of each element of the accessed tensor.
+    pub dtype: DType,
     /// Regions proven to be zero-valued. The outermost map maps a axis index to
     /// a set of usize tuples, indicating the half-open indices [low, high)
     /// which are known to be zero in that axis.
@@ -753,6 +838,7 @@ impl AccessPatternData {
     ///     glenside::language::AccessPatternData {
     ///         shape: ndarray::IxDyn(&[1, 2, 3]),
     ///         item_shape: ndarray::IxDyn(&[4, 5]),
+    ///         dtype: glenside::hw_design_language::DType::Fp32,
     ///         zero_regions: std::collections::HashMap::default()
     ///     }
     ///     .as_vec(),
@@ -784,20 +870,54 @@ pub fn access_windows_resulting_shape(
     access_shape: &IxDyn,
     filters_shape: &IxDyn,
     stride_shape: &IxDyn,
+) -> Vec<usize> {
+    access_windows_dilated_resulting_shape(
+        access_shape,
+        filters_shape,
+        stride_shape,
+        &IxDyn(vec![1; stride_shape.ndim()].as_slice()),
+    )
+}
+
+/// Gives the shape (not including the item shape) of the windows formed by
+/// `access-windows-dilated`. A filter dimension of length `k` with dilation
+/// `d` spans `(k - 1) * d + 1` elements of the access.
+/// ```
+/// use glenside::language::access_windows_dilated_resulting_shape;
+/// use ndarray::IxDyn;
+/// assert_eq!(
+///     access_windows_dilated_resulting_shape(
+///         &IxDyn(&[32, 32]),
+///         &IxDyn(&[3, 3]),
+///         &IxDyn(&[1, 2]),
+///         &IxDyn(&[2, 2]),
+///     ),
+///     vec![28, 14]
+/// );
+/// ```
+pub fn access_windows_dilated_resulting_shape(
+    access_shape: &IxDyn,
+    filters_shape: &IxDyn,
+    stride_shape: &IxDyn,
+    dilation_shape: &IxDyn,
 ) -> Vec<usize> {
     assert_eq!(access_shape.ndim(), stride_shape.ndim());
     assert_eq!(filters_shape.ndim(), stride_shape.ndim());
+    assert_eq!(dilation_shape.ndim(), stride_shape.ndim());
 
     multizip((
         access_shape.slice().iter(),
         filters_shape.slice().iter(),
         stride_shape.slice().iter(),
+        dilation_shape.slice().iter(),
     ))
     .map(
-        |(&dim_len, &kernel_dim_len, &stride): (&usize, &usize, &usize)| {
+        |(&dim_len, &kernel_dim_len, &stride, &dilation): (&usize, &usize, &usize, &usize)| {
+            assert!(dilation >= 1, "Dilation must be at least 1");
             let total_dim_len = dim_len;
-            assert!(total_dim_len >= kernel_dim_len);
-            let num_spots = total_dim_len - (kernel_dim_len - 1);
+            let dilated_kernel_dim_len = (kernel_dim_len - 1) * dilation + 1;
+            assert!(total_dim_len >= dilated_kernel_dim_len);
+            let num_spots = total_dim_len - (dilated_kernel_dim_len - 1);
             (num_spots + stride - 1) / stride
         },
     )
@@ -814,18 +934,85 @@ pub fn access_windows_resulting_shape(
 pub struct MyAnalysisDataLegacyData {
     pub(crate) shape: Option<IxDyn>,
     pub(crate) usize_value: Option<usize>,
+    pub(crate) dtype: Option<DType>,
 }
 #[derive(Default)]
 pub struct MyAnalysis {
     pub name_to_shape: HashMap<String, Vec<usize>>,
+    /// Datatypes of the input tensors. Tensors which don't appear in this map
+    /// are assumed to be float32.
+    pub name_to_dtype: HashMap<String, DType>,
 }
 impl MyAnalysis {
+    /// Get the datatype of the input tensor with the given name.
+    pub fn get_input_dtype(&self, name: &str) -> DType {
+        self.name_to_dtype.get(name).cloned().unwrap_or(DType::Fp32)
+    }
+    /// Get the shape of the input tensor with the given name, if it's known.
+    /// Shapes in `name_to_shape` take precedence over the hardcoded shapes
+    /// below.
+    pub fn get_input_shape(&self, name: &str) -> Option<Vec<usize>> {
+        if let Some(shape) = self.name_to_shape.get(name) {
+            return Some(shape.clone());
+        }
+        match name {
+            "in" => Some(vec![1, 784]),
+            "w1" => Some(vec![784, 512]),
+            "w2" => Some(vec![512, 512]),
+            "w3" => Some(vec![512, 10]),
+            // TODO(@gussmith23) have to figure out a way around this.
+            // Max seems to think the tensors should just go
+            // into the egraph. I was hoping to have some kind
+            // of environment that we could wrap the egraph in
+            // (would have to be accessible from here), but Max
+            // doesn't have that nor does he plan to implement
+            // it.
+            //
+            // Update, Max is implementing something that will
+            // allow for this.
+            "single-matrix-multiply-input-a" => Some(vec![32, 32]),
+            "single-matrix-multiply-input-b" => Some(vec![32, 32]),
+            "v-32" => Some(vec![32]),
+            "t-32-32" => Some(vec![32, 32]),
+            "t-32-64" => Some(vec![32, 64]),
+            "t-64-128" => Some(vec![64, 128]),
+            "t-128-16" => Some(vec![128, 16]),
+            // A 3-channel "image" in CHW format.
+            "t-3-32-32" => Some(vec![3, 32, 32]),
+            // An OIHW set of convolution filters.
+            "t-8-3-3-3" => Some(vec![8, 3, 3, 3]),
+            "t-1024-2-256" => Some(vec![1024, 2, 256]),
+            "t-1-2-3-4" => Some(vec![1, 2, 3, 4]),
+            _ => None,
+        }
+    }
+    /// Get the datatype of a tensor or access pattern.
+    pub fn get_dtype(id: Id, egraph: &EGraph<Language, MyAnalysis>) -> DType {
+        match &egraph[id].data {
+            MyAnalysisData::AccessPattern(a) => a.dtype,
+            MyAnalysisData::Legacy(l) => l
+                .dtype
+                .unwrap_or_else(|| panic!("No dtype for eclass {}", id)),
+            _ => panic!("Expected a tensor or access pattern"),
+        }
+    }
     pub fn get_usize(id: Id, egraph: &EGraph<Language, MyAnalysis>) -> usize {
         match &egraph[id].data {
             MyAnalysisData::Legacy(s) => s.usize_value.unwrap(),
             _ => panic!(),
         }
     }
+    /// Get the value of a number, which may have been parsed as either a
+    /// usize or a float.
+    pub fn get_float(id: Id, egraph: &EGraph<Language, MyAnalysis>) -> NotNan<f64> {
+        match &egraph[id].data {
+            MyAnalysisData::Legacy(s) => NotNan::new(s.usize_value.unwrap() as f64).unwrap(),
+            MyAnalysisData::Literal(t) if t.ndim() == 0 => {
+                NotNan::new(*t.first().unwrap()).unwrap()
+            }
+            _ => panic!(),
+        }
+    }
     pub(crate) fn get_shape(id: Id, egraph: &EGraph<Language, MyAnalysis>) -> &IxDyn {
         match &egraph[id].data {
             MyAnalysisData::Legacy(s) => s.shape.as_ref().unwrap(),
@@ -848,16 +1035,22 @@ impl egg::Analysis<Language> for MyAnalysis {
                 MyAnalysisData::AccessPattern(AccessPatternData {
                     shape: to_shape,
                     item_shape: to_item_shape,
+                    dtype: to_dtype,
                     zero_regions: to_zero_regions,
                 }),
                 MyAnalysisData::AccessPattern(AccessPatternData {
                     shape: from_shape,
                     item_shape: from_item_shape,
+                    dtype: from_dtype,
                     zero_regions: from_zero_regions,
                 }),
             ) => {
                 assert_eq!(to_shape, from_shape);
                 assert_eq!(to_item_shape, from_item_shape);
+                assert_eq!(
+                    to_dtype, from_dtype,
+                    "Attempted to merge access patterns with different dtypes"
+                );
 
                 // Merge zero regions.
                 // TODO(@gussmith23) Make sure merge returns `true` infrequently
@@ -944,8 +1137,11 @@ impl egg::Analysis<Language> for MyAnalysis {
 
     fn make(egraph: &EGraph<Language, Self>, enode: &Language) -> Self::Data {
         use Language::*;
+        if let Err(problem) = super::validate::check(egraph, enode) {
+            panic!("Invalid {}: {}", enode, problem);
+        }
         match enode {
-            &AccessLiteral(id) => match &egraph[id].data {
+            &AccessLiteral([literal_id, dtype_id]) => match &egraph[literal_id].data {
                 MyAnalysisData::Literal(t) => MyAnalysisData::AccessPattern(AccessPatternData {
                     zero_regions: {
                         warn!("Zero regions unimplemented on line {}", std::line!());
@@ -953,12 +1149,20 @@ impl egg::Analysis<Language> for MyAnalysis {
                     },
                     shape: IxDyn(&[]),
                     item_shape: IxDyn(t.shape()),
+                    dtype: match &egraph[dtype_id].data {
+                        MyAnalysisData::DType(dtype) => *dtype,
+                        _ => panic!("Expected a dtype as the second argument to access-literal"),
+                    },
                 }),
                 _ => panic!(),
             },
             &NotNanFloat64(v) => MyAnalysisData::Literal(ndarray::arr0(v.into_inner()).into_dyn()),
             &Literal(id) => match &egraph[id].data {
                 t @ MyAnalysisData::Literal(_) => t.clone(),
+                // Integer-valued literals are parsed as usizes.
+                MyAnalysisData::Legacy(_) => MyAnalysisData::Literal(
+                    ndarray::arr0(Self::get_float(id, egraph).into_inner()).into_dyn(),
+                ),
                 _ => panic!(),
             },
             &AccessTranspose([access_id, list_id]) => {
@@ -971,11 +1175,6 @@ impl egg::Analysis<Language> for MyAnalysis {
                     _ => panic!(),
                 };
 
-                assert_eq!(
-                    access.shape.ndim() + access.item_shape.ndim(),
-                    list.len(),
-                    "Number of items in list should equal the number of axes in the first argument"
-                );
                 let tmp = access
                     .shape
                     .slice()
@@ -995,6 +1194,7 @@ impl egg::Analysis<Language> for MyAnalysis {
                 MyAnalysisData::AccessPattern(AccessPatternData {
                     shape: IxDyn(&new_shape[..access.shape.ndim()]),
                     item_shape: IxDyn(&new_shape[access.shape.ndim()..]),
+                    dtype: access.dtype,
                     zero_regions: new_zero_regions,
                 })
             }
@@ -1011,19 +1211,13 @@ impl egg::Analysis<Language> for MyAnalysis {
                     _ => panic!(),
                 };
                 let shape = match &egraph[shape_id].data {
-                    MyAnalysisData::AccessPattern(a) => a,
+                    MyAnalysisData::AccessShape(s) => s,
                     _ => panic!(
                         "Expected access shape as second argument of access-broadcast, got {:?}",
                         egraph[shape_id]
                     ),
                 };
 
-                assert_eq!(
-                    access.shape.ndim() + access.item_shape.ndim(),
-                    shape.shape.ndim() + shape.item_shape.ndim(),
-                    "Shape we're broadcasting to should have the same number of dimensions as the shape we're broadcasting from"
-                );
-
                 let new_shape = access
                     .shape
                     .slice()
@@ -1036,15 +1230,7 @@ impl egg::Analysis<Language> for MyAnalysis {
                             .iter()
                             .chain(shape.item_shape.slice().iter()),
                     )
-                    .map(|(broadcast_from_dim, broadcast_to_dim): (&usize, &usize)| {
-                        assert!(
-                            *broadcast_from_dim == 1 || broadcast_from_dim == broadcast_to_dim,
-                            "Expected broadcast_from_dim to be 1 or {}, got {}",
-                            *broadcast_to_dim,
-                            *broadcast_from_dim
-                        );
-                        *broadcast_to_dim
-                    })
+                    .map(|(_broadcast_from_dim, broadcast_to_dim)| *broadcast_to_dim)
                     .collect::<Vec<_>>();
 
                 if !access.zero_regions.is_empty() {
@@ -1062,6 +1248,7 @@ impl egg::Analysis<Language> for MyAnalysis {
                 MyAnalysisData::AccessPattern(AccessPatternData {
                     shape: IxDyn(&new_shape[..access.shape.ndim()]),
                     item_shape: IxDyn(&new_shape[access.shape.ndim()..]),
+                    dtype: access.dtype,
                     // TODO(@gussmith23) Implement zero regions
                     // It's harmless (I think) if `zero_regions` defaults to
                     // empty, but for it to be useful, we need to implement it
@@ -1092,8 +1279,6 @@ impl egg::Analysis<Language> for MyAnalysis {
                 }
                 let axis = MyAnalysis::get_usize(axis_id, egraph);
 
-                assert!(axis <= access.shape.ndim() + access.item_shape.ndim());
-
                 if axis <= access.shape.ndim() {
                     access.shape = IxDyn(
                         access.shape.slice()[..axis]
@@ -1135,14 +1320,8 @@ impl egg::Analysis<Language> for MyAnalysis {
                 let axis = MyAnalysis::get_usize(axis_id, egraph);
                 use ndarray::RemoveAxis;
                 if axis < access.shape.ndim() {
-                    assert_eq!(
-                        access.shape[axis], 1,
-                        "Expected axis {} of {:?} to be 1",
-                        axis, access.shape
-                    );
                     access.shape = access.shape.remove_axis(ndarray::Axis(axis));
                 } else {
-                    assert_eq!(access.item_shape[axis - access.shape.ndim()], 1);
                     access.item_shape = access
                         .item_shape
                         .remove_axis(ndarray::Axis(axis - access.shape.ndim()));
@@ -1160,7 +1339,6 @@ impl egg::Analysis<Language> for MyAnalysis {
                     _ => panic!(),
                 };
                 let axis = MyAnalysis::get_usize(axis_id, egraph);
-                assert!(axis < access.shape.ndim() + access.item_shape.ndim());
                 let orig_axis_val = access[axis];
                 let pad_before = MyAnalysis::get_usize(pad_before_id, egraph);
                 let pad_after = MyAnalysis::get_usize(pad_after_id, egraph);
@@ -1239,6 +1417,7 @@ impl egg::Analysis<Language> for MyAnalysis {
                     _ => panic!(),
                 },
                 item_shape: IxDyn(&[]),
+                dtype: MyAnalysis::get_dtype(t_id, egraph),
             }),
             &AccessShiftRight(a_id) => {
                 let a = match &egraph[a_id].data {
@@ -1269,6 +1448,7 @@ impl egg::Analysis<Language> for MyAnalysis {
                     },
                     shape: IxDyn(&combined[..(a.shape.ndim().saturating_sub(1))]),
                     item_shape: IxDyn(&combined[(a.shape.ndim().saturating_sub(1))..]),
+                    dtype: a.dtype,
                 })
             }
             &AccessPair([a0_id, a1_id]) => {
@@ -1279,9 +1459,6 @@ impl egg::Analysis<Language> for MyAnalysis {
                     _ => panic!(),
                 };
 
-                assert_eq!(a0.shape, a1.shape);
-                assert_eq!(a0.item_shape, a1.item_shape);
-
                 MyAnalysisData::AccessPattern(AccessPatternData {
                     // TODO(@gussmith23) Implement zero regions
                     // It's harmless (I think) if `zero_regions` defaults to
@@ -1309,6 +1486,7 @@ impl egg::Analysis<Language> for MyAnalysis {
                             .collect::<Vec<_>>()
                             .as_slice(),
                     ),
+                    dtype: a0.dtype,
                 })
             }
             &AccessSlice([access_id, axis_id, low_id, high_id]) => {
@@ -1321,14 +1499,9 @@ impl egg::Analysis<Language> for MyAnalysis {
                 let high: usize = Self::get_usize(high_id, egraph);
                 let original_axis_value = new_access[axis];
 
-                assert!(new_access.shape.ndim() + new_access.item_shape.ndim() > axis);
                 if axis < new_access.shape.ndim() {
-                    assert!(low < new_access.shape[axis]);
-                    assert!(high <= new_access.shape[axis]);
                     new_access.shape[axis] = high - low;
                 } else {
-                    assert!(low < new_access.item_shape[axis - new_access.shape.ndim()]);
-                    assert!(high <= new_access.item_shape[axis - new_access.shape.ndim()]);
                     new_access.item_shape[axis - new_access.shape.ndim()] = high - low;
                 }
 
@@ -1365,9 +1538,6 @@ impl egg::Analysis<Language> for MyAnalysis {
                         std::line!()
                     );
                 }
-                assert_eq!(new_access.shape.ndim(), a1.shape.ndim(),);
-                assert_eq!(new_access.item_shape.ndim(), a1.item_shape.ndim(),);
-                assert!(axis < a1.shape.ndim() + a1.item_shape.ndim());
                 if axis < new_access.shape.ndim() {
                     new_access.shape[axis] += a1.shape[axis];
                 } else {
@@ -1378,8 +1548,7 @@ impl egg::Analysis<Language> for MyAnalysis {
                 MyAnalysisData::AccessPattern(new_access)
             }
             &AccessShape([shape_id, item_shape_id]) => {
-                MyAnalysisData::AccessPattern(AccessPatternData {
-                    zero_regions: { HashMap::default() },
+                MyAnalysisData::AccessShape(AccessShapeData {
                     shape: match &egraph[shape_id].data {
                         MyAnalysisData::Shape(s) => s.shape.clone(),
                         _ => panic!(),
@@ -1403,30 +1572,22 @@ impl egg::Analysis<Language> for MyAnalysis {
                     MyAnalysisData::AccessPattern(a) => a,
                     _ => panic!("Expected an access as the first argument to access-reshape"),
                 };
-                let mut new_shape = match &egraph[access_shape_id].data {
-                    MyAnalysisData::AccessPattern(a) => a.clone(),
+                let new_shape = match &egraph[access_shape_id].data {
+                    MyAnalysisData::AccessShape(s) => AccessPatternData {
+                        shape: s.shape.clone(),
+                        item_shape: s.item_shape.clone(),
+                        dtype: a.dtype,
+                        // TODO(@gussmith23) Implement zero_regions
+                        zero_regions: HashMap::default(),
+                    },
                     _ => panic!(),
                 };
-                // TODO(@gussmith23) Implement zero_regions
-                new_shape.zero_regions = HashMap::default();
                 if !a.zero_regions.is_empty() {
                     warn!(
                         "Throwing away zero region analysis data on line {}",
                         std::line!()
                     );
                 }
-                assert_eq!(
-                    a.shape.as_array_view().iter().product::<usize>(),
-                    new_shape.shape.as_array_view().iter().product::<usize>(),
-                );
-                assert_eq!(
-                    a.item_shape.as_array_view().iter().product::<usize>(),
-                    new_shape
-                        .item_shape
-                        .as_array_view()
-                        .iter()
-                        .product::<usize>(),
-                );
                 MyAnalysisData::AccessPattern(new_shape)
             }
             &AccessFlatten(access_id) => {
@@ -1450,9 +1611,20 @@ impl egg::Analysis<Language> for MyAnalysis {
                     },
                     shape: IxDyn(&[a.shape.as_array_view().iter().product()]),
                     item_shape: IxDyn(&[a.item_shape.as_array_view().iter().product()]),
+                    dtype: a.dtype,
                 })
             }
             ComputeType(t) => MyAnalysisData::ComputeType(t.clone()),
+            &Clip([min_id, max_id]) => {
+                let (min, max) = (
+                    MyAnalysis::get_float(min_id, egraph),
+                    MyAnalysis::get_float(max_id, egraph),
+                );
+                MyAnalysisData::ComputeType(self::ComputeType::Clip { min, max })
+            }
+            &LeakyReLU(alpha_id) => MyAnalysisData::ComputeType(self::ComputeType::LeakyReLU {
+                alpha: MyAnalysis::get_float(alpha_id, egraph),
+            }),
             &Compute([compute_type_id, access_id]) => {
                 let compute_type = match &egraph[compute_type_id].data {
                     MyAnalysisData::ComputeType(t) => t,
@@ -1488,14 +1660,10 @@ impl egg::Analysis<Language> for MyAnalysis {
                             },
                             shape: a0.shape.clone(),
                             item_shape: ndarray::IxDyn(&[]),
+                            dtype: a0.dtype,
                         })
                     }
                     self::ComputeType::Softmax => {
-                        assert_eq!(
-                            a0.item_shape.ndim(),
-                            1,
-                            "Softmax is only implemented for axis=-1"
-                        );
                         MyAnalysisData::AccessPattern(AccessPatternData {
                             // TODO(@gussmith23) Implement zero regions
                             // It's harmless (I think) if `zero_regions` defaults to
@@ -1512,12 +1680,12 @@ impl egg::Analysis<Language> for MyAnalysis {
                             },
                             shape: a0.shape.clone(),
                             item_shape: a0.item_shape.clone(),
+                            dtype: a0.dtype,
                         })
                     }
                     self::ComputeType::ElementwiseAdd
                     | self::ComputeType::ElementwiseMul
                     | self::ComputeType::ElementwiseDiv => {
-                        assert!(a0.item_shape.ndim() >= 1);
                         MyAnalysisData::AccessPattern(AccessPatternData {
                             // TODO(@gussmith23) Implement zero regions
                             // It's harmless (I think) if `zero_regions` defaults to
@@ -1534,17 +1702,10 @@ impl egg::Analysis<Language> for MyAnalysis {
                             },
                             shape: a0.shape.clone(),
                             item_shape: IxDyn(&a0.item_shape.slice()[1..]),
+                            dtype: a0.dtype,
                         })
                     }
                     self::ComputeType::DotProduct => {
-                        // If it's =1, that's just a "dot product" of scalars,
-                        // which is just a sum.
-                        //
-                        // Honestly, it could also be 0. It doesn't make much
-                        // sense but it's not wrong. Can remove this later if we
-                        // want those semantics.
-                        assert!(a0.item_shape.ndim() >= 1);
-
                         // MyAnalysisData::Tensor(TensorData {
                         //     shape: a0.shape.clone(),
                         // })
@@ -1564,9 +1725,13 @@ impl egg::Analysis<Language> for MyAnalysis {
                             },
                             shape: a0.shape.clone(),
                             item_shape: IxDyn(&[]),
+                            dtype: a0.dtype,
                         })
                     }
-                    self::ComputeType::ReduceSum | self::ComputeType::ReduceMax => {
+                    self::ComputeType::ReduceSum
+                    | self::ComputeType::ReduceMax
+                    | self::ComputeType::ReduceMin
+                    | self::ComputeType::ArgMax => {
                         MyAnalysisData::AccessPattern(AccessPatternData {
                             // TODO(@gussmith23) Implement zero regions
                             // It's harmless (I think) if `zero_regions` defaults to
@@ -1583,11 +1748,22 @@ impl egg::Analysis<Language> for MyAnalysis {
                             },
                             shape: a0.shape.clone(),
                             item_shape: IxDyn(&[]),
+                            // Indices are integers, whatever the dtype of the values.
+                            dtype: match compute_type {
+                                self::ComputeType::ArgMax => DType::Int32,
+                                _ => a0.dtype,
+                            },
                         })
                     }
                     self::ComputeType::ReLU
                     | self::ComputeType::Sqrt
-                    | self::ComputeType::Negative => {
+                    | self::ComputeType::Negative
+                    | self::ComputeType::Sigmoid
+                    | self::ComputeType::Tanh
+                    | self::ComputeType::Exp
+                    | self::ComputeType::Clip { .. }
+                    | self::ComputeType::LeakyReLU { .. }
+                    | self::ComputeType::Gelu => {
                         // TODO(@gussmith23) Implement zero_regions
                         if !a0.zero_regions.is_empty() {
                             warn!(
@@ -1608,10 +1784,6 @@ impl egg::Analysis<Language> for MyAnalysis {
                     }
                     _ => panic!(),
                 };
-                assert_eq!(
-                    a0.item_shape, a1.item_shape,
-                    "Cartesian product argument shapes must match"
-                );
 
                 let new_shape = IxDyn(
                     a0.shape
@@ -1671,6 +1843,7 @@ impl egg::Analysis<Language> for MyAnalysis {
                     },
                     shape: new_shape,
                     item_shape: new_item_shape,
+                    dtype: a0.dtype,
                 })
             }
             &SliceShape([shape_id, dim_id]) => {
@@ -1683,12 +1856,6 @@ impl egg::Analysis<Language> for MyAnalysis {
             &ShapeInsertAxis([shape_id, dim_id]) => {
                 let shape = MyAnalysis::get_shape_of_value(shape_id, egraph);
                 let dim = MyAnalysis::get_usize(dim_id, egraph);
-                assert!(
-                    dim <= shape.ndim(),
-                    "Invalid dimension {} for shape {:?}",
-                    dim,
-                    shape
-                );
                 MyAnalysisData::Shape(ShapeData {
                     shape: IxDyn(
                         shape.slice()[..dim]
@@ -1704,12 +1871,6 @@ impl egg::Analysis<Language> for MyAnalysis {
             &ShapeRemoveAxis([shape_id, dim_id]) => {
                 let shape = MyAnalysis::get_shape_of_value(shape_id, egraph);
                 let dim = MyAnalysis::get_usize(dim_id, egraph);
-                assert!(
-                    dim < shape.ndim(),
-                    "Invalid dimension {} for shape {:?}",
-                    dim,
-                    shape
-                );
                 MyAnalysisData::Shape(ShapeData {
                     shape: IxDyn(
                         shape.slice()[..dim]
@@ -1751,6 +1912,7 @@ impl egg::Analysis<Language> for MyAnalysis {
                     },
                     shape: IxDyn(&shape[..dim]),
                     item_shape: IxDyn(&shape[dim..]),
+                    dtype: access.dtype,
                 })
             }
             &MoveAxis([tensor_id, src_axis_id, dest_axis_id]) => {
@@ -1758,9 +1920,6 @@ impl egg::Analysis<Language> for MyAnalysis {
                 let src_axis = Self::get_usize(src_axis_id, egraph);
                 let dest_axis = Self::get_usize(dest_axis_id, egraph);
 
-                assert!(src_axis < new_shape.as_array_view().len());
-                assert!(dest_axis < new_shape.as_array_view().len());
-
                 let tmp = new_shape[dest_axis];
                 new_shape[dest_axis] = new_shape[src_axis];
                 new_shape[src_axis] = tmp;
@@ -1768,19 +1927,12 @@ impl egg::Analysis<Language> for MyAnalysis {
                 MyAnalysisData::Legacy(MyAnalysisDataLegacyData {
                     shape: Some(new_shape),
                     usize_value: None,
+                    dtype: Some(Self::get_dtype(tensor_id, egraph)),
                 })
             }
             &CartesianProduct([t0_id, t1_id]) => {
                 let initial_shape_left: &IxDyn = Self::get_shape(t0_id, egraph);
-                assert!(initial_shape_left.as_array_view().len() >= 1);
-                assert!(initial_shape_left.as_array_view().len() <= 2);
                 let initial_shape_right: &IxDyn = Self::get_shape(t1_id, egraph);
-                assert!(initial_shape_left.as_array_view().len() >= 1);
-                assert!(initial_shape_left.as_array_view().len() <= 2);
-                assert_eq!(
-                    initial_shape_left[initial_shape_left.as_array_view().len() - 1],
-                    initial_shape_right[initial_shape_right.as_array_view().len() - 1],
-                );
 
                 // New shape is [a1, ..., an, b1, ..., bn, 2, c].
                 let mut new_shape: Vec<usize> = initial_shape_left
@@ -1809,14 +1961,12 @@ impl egg::Analysis<Language> for MyAnalysis {
                 MyAnalysisData::Legacy(MyAnalysisDataLegacyData {
                     shape: Some(new_shape),
                     usize_value: None,
+                    dtype: Some(Self::get_dtype(t0_id, egraph)),
                 })
             }
             &MapDotProduct(tensor_id) => {
                 let shape: &IxDyn = Self::get_shape(tensor_id, egraph);
 
-                assert!(shape.as_array_view().len() >= 3);
-                assert_eq!(shape[shape.as_array_view().len() - 2], 2);
-
                 let new_shape: ndarray::IxDyn = ndarray::IxDyn(
                     &shape
                         .as_array_view()
@@ -1829,24 +1979,12 @@ impl egg::Analysis<Language> for MyAnalysis {
                 MyAnalysisData::Legacy(MyAnalysisDataLegacyData {
                     shape: Some(new_shape),
                     usize_value: None,
+                    dtype: Some(Self::get_dtype(tensor_id, egraph)),
                 })
             }
-            &BsgSystolicArray([rows_id, cols_id, t0_id, t1_id]) => {
-                // Check that the rows and cols are usizes.
-                let _unused = Self::get_usize(rows_id, egraph);
-                let _unused = Self::get_usize(cols_id, egraph);
-
+            &BsgSystolicArray([_rows_id, _cols_id, t0_id, t1_id]) => {
                 let left_shape = Self::get_shape(t0_id, egraph);
                 let right_shape = Self::get_shape(t1_id, egraph);
-                let left_shape_len: usize = left_shape.as_array_view().len();
-                let right_shape_len: usize = right_shape.as_array_view().len();
-
-                // TODO(@gussmith23) check that the rows/cols params sizes are correct
-                // given the input tensor shapes.
-
-                // Assumptions I'm making right now.
-                assert!(left_shape_len == 1 || left_shape_len == 2);
-                assert_eq!(right_shape_len, 2);
 
                 let new_shape: Vec<ndarray::Ix> = left_shape
                     .as_array_view()
@@ -1859,13 +1997,11 @@ impl egg::Analysis<Language> for MyAnalysis {
                 MyAnalysisData::Legacy(MyAnalysisDataLegacyData {
                     shape: Some(ndarray::IxDyn(&new_shape)),
                     usize_value: None,
+                    dtype: Some(Self::get_dtype(t0_id, egraph)),
                 })
             }
-            &SystolicArray([rows_id, cols_id, a0_id, a1_id])
-            | &SystolicArrayWithBlocking([rows_id, cols_id, a0_id, a1_id]) => {
-                let rows = Self::get_usize(rows_id, egraph);
-                let cols = Self::get_usize(cols_id, egraph);
-
+            &SystolicArray([_rows_id, _cols_id, a0_id, a1_id])
+            | &SystolicArrayWithBlocking([_rows_id, _cols_id, a0_id, a1_id]) => {
                 let (a0, a1) = match (&egraph[a0_id].data, &egraph[a1_id].data) {
                     (MyAnalysisData::AccessPattern(a0), MyAnalysisData::AccessPattern(a1)) => {
                         (a0, a1)
@@ -1873,28 +2009,6 @@ impl egg::Analysis<Language> for MyAnalysis {
                     _ => panic!("Expected access patterns as third and fourth arguments"),
                 };
 
-                assert_eq!(a1.shape, IxDyn(&[]));
-                assert!(a0.shape.ndim() == 0 || a0.shape.ndim() == 1);
-
-                match &enode {
-                    &SystolicArray(_) => {
-                        assert_eq!(a1.item_shape, IxDyn(&[rows, cols]));
-                        assert_eq!(a0.item_shape, IxDyn(&[rows]));
-                    }
-                    &SystolicArrayWithBlocking(_) => {
-                        // Scott: The input vector size should be a multiple of
-                        // the systolic array's height and the output vector
-                        // size should be a multiple of the systolic array's
-                        // width.
-                        assert_eq!(a0.item_shape.ndim(), 1);
-                        assert!(a0.item_shape.slice()[0] % rows == 0);
-                        assert_eq!(a1.item_shape.ndim(), 2);
-                        assert_eq!(a0.item_shape.slice()[0], a1.item_shape.slice()[0]);
-                        assert!(a1.item_shape.slice()[1] % cols == 0);
-                    }
-                    _ => unreachable!(),
-                }
-
                 MyAnalysisData::AccessPattern(AccessPatternData {
                     // TODO(@gussmith23) Implement zero regions
                     // It's harmless (I think) if `zero_regions` defaults to
@@ -1925,6 +2039,7 @@ impl egg::Analysis<Language> for MyAnalysis {
                             .as_slice(),
                     ),
                     item_shape: IxDyn(&[]),
+                    dtype: a0.dtype,
                 })
             }
             &Slice([tensor_id, axis_id, low_id, high_id]) => {
@@ -1934,93 +2049,50 @@ impl egg::Analysis<Language> for MyAnalysis {
                 let low: usize = Self::get_usize(low_id, egraph);
                 let high: usize = Self::get_usize(high_id, egraph);
 
-                assert!(new_shape.as_array_view().len() > axis);
-                assert!(low < new_shape[axis]);
-                assert!(high <= new_shape[axis]);
-
                 new_shape[axis] = high - low;
 
                 MyAnalysisData::Legacy(MyAnalysisDataLegacyData {
                     shape: Some(new_shape),
                     usize_value: None,
+                    dtype: Some(Self::get_dtype(tensor_id, egraph)),
                 })
             }
             &Concatenate([t0_id, t1_id, axis_id]) => {
                 let axis = Self::get_usize(axis_id, egraph);
                 let mut new_shape = Self::get_shape(t0_id, egraph).clone();
                 let t1_shape = Self::get_shape(t1_id, egraph).clone();
-                assert_eq!(
-                    new_shape.as_array_view().len(),
-                    t1_shape.as_array_view().len()
-                );
-                assert!(axis < t1_shape.as_array_view().len());
                 new_shape[axis] += t1_shape[axis];
 
                 MyAnalysisData::Legacy(MyAnalysisDataLegacyData {
                     shape: Some(new_shape),
                     usize_value: None,
+                    dtype: Some(Self::get_dtype(t0_id, egraph)),
                 })
             }
-            &ElementwiseAdd([t0_id, t1_id]) => {
-                assert_eq!(
-                    Self::get_shape(t0_id, egraph),
-                    Self::get_shape(t1_id, egraph)
-                );
-
-                MyAnalysisData::Legacy(MyAnalysisDataLegacyData {
-                    shape: Some(Self::get_shape(t0_id, egraph).clone()),
-                    usize_value: None,
-                })
-            }
+            &ElementwiseAdd([t0_id, _t1_id]) => MyAnalysisData::Legacy(MyAnalysisDataLegacyData {
+                shape: Some(Self::get_shape(t0_id, egraph).clone()),
+                usize_value: None,
+                dtype: Some(Self::get_dtype(t0_id, egraph)),
+            }),
             Usize(u) => MyAnalysisData::Legacy(MyAnalysisDataLegacyData {
                 shape: None,
                 usize_value: Some(*u),
+                dtype: None,
+            }),
+            Symbol(name) => MyAnalysisData::Legacy(MyAnalysisDataLegacyData {
+                shape: Some(ndarray::IxDyn(
+                    &egraph
+                        .analysis
+                        .get_input_shape(name)
+                        .unwrap_or_else(|| panic!("No shape defined for {}", name))[..],
+                )),
+                usize_value: None,
+                dtype: Some(egraph.analysis.get_input_dtype(name)),
             }),
-            Symbol(name) => {
-                //println!("Symbol");
-                MyAnalysisData::Legacy(MyAnalysisDataLegacyData {
-                    shape: Some(ndarray::IxDyn(
-                        &(match &name[..] {
-                            "in" => vec![1, 784],
-                            "w1" => vec![784, 512],
-                            "w2" => vec![512, 512],
-                            "w3" => vec![512, 10],
-                            // TODO(@gussmith23) have to figure out a way around this.
-                            // Max seems to think the tensors should just go
-                            // into the egraph. I was hoping to have some kind
-                            // of environment that we could wrap the egraph in
-                            // (would have to be accessible from here), but Max
-                            // doesn't have that nor does he plan to implement
-                            // it.
-                            //
-                            // Update, Max is implementing something that will
-                            // allow for this.
-                            "single-matrix-multiply-input-a" => vec![32, 32],
-                            "single-matrix-multiply-input-b" => vec![32, 32],
-                            "v-32" => vec![32],
-                            "t-32-32" => vec![32, 32],
-                            "t-32-64" => vec![32, 64],
-                            "t-64-128" => vec![64, 128],
-                            "t-128-16" => vec![128, 16],
-                            // A 3-channel "image" in CHW format.
-                            "t-3-32-32" => vec![3, 32, 32],
-                            // An OIHW set of convolution filters.
-                            "t-8-3-3-3" => vec![8, 3, 3, 3],
-                            "t-1024-2-256" => vec![1024, 2, 256],
-                            "t-1-2-3-4" => vec![1, 2, 3, 4],
-                            _ => egraph
-                                .analysis
-                                .name_to_shape
-                                .get(name)
-                                .unwrap_or_else(|| panic!("No shape defined for {}", name))
-                                .clone(),
-                        })[..],
-                    )),
-                    usize_value: None,
-                })
-            }
             PadType(t) => MyAnalysisData::PadType(*t),
-            &AccessWindows([access_id, filters_shape_id, stride_shape_id]) => {
+            DataType(t) => MyAnalysisData::DType(*t),
+            &AccessWindows([access_id, filters_shape_id, stride_shape_id])
+            | &AccessWindowsDilated([access_id, filters_shape_id, stride_shape_id, _]) => {
                 let access = match &egraph[access_id].data {
                     MyAnalysisData::AccessPattern(a) => a,
                     _ => {
@@ -2029,10 +2101,12 @@ impl egg::Analysis<Language> for MyAnalysis {
                 };
                 let filters_shape = MyAnalysis::get_shape_of_value(filters_shape_id, egraph);
                 let stride_shape = MyAnalysis::get_shape_of_value(stride_shape_id, egraph);
-
-                // TODO(@gussmith23) Generalize AccessWindows to other accesses
-                // Right now we expect item shape to be a scalar.
-                assert_eq!(access.item_shape.ndim(), 0);
+                let dilation_shape = match enode {
+                    &AccessWindowsDilated([_, _, _, dilation_shape_id]) => {
+                        MyAnalysis::get_shape_of_value(dilation_shape_id, egraph).clone()
+                    }
+                    _ => IxDyn(vec![1; stride_shape.ndim()].as_slice()),
+                };
 
                 MyAnalysisData::AccessPattern(AccessPatternData {
                     // TODO(@gussmith23) Implement zero regions
@@ -2049,14 +2123,16 @@ impl egg::Analysis<Language> for MyAnalysis {
                         HashMap::default()
                     },
                     shape: IxDyn(
-                        access_windows_resulting_shape(
+                        access_windows_dilated_resulting_shape(
                             &access.shape,
                             &filters_shape,
                             &stride_shape,
+                            &dilation_shape,
                         )
                         .as_slice(),
                     ),
                     item_shape: filters_shape.clone(),
+                    dtype: access.dtype,
                 })
             }
 
@@ -2146,6 +2222,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn access_windows_dilated() {
+        let program = "
+         (access-windows-dilated
+          (access (access-tensor t-3-32-32) 3)
+          (shape 1 3 3)
+          (shape 1 1 1)
+          (shape 1 2 3)
+         )
+         "
+        .parse()
+        .unwrap();
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis::default());
+        let id = egraph.add_expr(&program);
+        match &egraph[id].data {
+            MyAnalysisData::AccessPattern(a) => {
+                assert_eq!(a.shape, IxDyn(&[3, 28, 26]));
+                assert_eq!(a.item_shape, IxDyn(&[1, 3, 3]));
+            }
+            _ => panic!(),
+        }
+
+        let program = "
+         (access-windows-dilated
+          (access (access-tensor t-3-32-32) 3)
+          (shape 1 3 3)
+          (shape 1 2 2)
+          (shape 1 2 2)
+         )
+         "
+        .parse()
+        .unwrap();
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis::default());
+        let id = egraph.add_expr(&program);
+        match &egraph[id].data {
+            MyAnalysisData::AccessPattern(a) => {
+                assert_eq!(a.shape, IxDyn(&[3, 14, 14]));
+                assert_eq!(a.item_shape, IxDyn(&[1, 3, 3]));
+            }
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn shape_of() {
         let program = "
@@ -3408,6 +3527,7 @@ mod tests {
         .unwrap();
         let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
             name_to_shape: HashMap::default(),
+            name_to_dtype: HashMap::default(),
         });
         let id = egraph.add_expr(&program);
         match &egraph[id].data {
@@ -3431,8 +3551,10 @@ mod tests {
          "
         .parse()
         .unwrap();
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         match &egraph[id].data {
             MyAnalysisData::AccessPattern(a) => {
@@ -3457,8 +3579,10 @@ mod tests {
          "
         .parse()
         .unwrap();
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         egraph.add_expr(&program);
     }
 
@@ -3476,8 +3600,10 @@ mod tests {
          "
         .parse()
         .unwrap();
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         egraph.add_expr(&program);
     }
 
@@ -3505,8 +3631,10 @@ mod tests {
         .unwrap();
         let mut map = HashMap::default();
         map.insert("a".to_string(), vec![4, 5, 6]);
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         match &egraph[id].data {
             MyAnalysisData::AccessPattern(a) => {
@@ -3531,8 +3659,10 @@ mod tests {
         .unwrap();
         let mut map = HashMap::new();
         map.insert("t".to_string(), vec![1, 2, 3, 4]);
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         match &egraph[id].data {
             MyAnalysisData::AccessPattern(a) => {
@@ -3555,8 +3685,10 @@ mod tests {
         .unwrap();
         let mut map = HashMap::new();
         map.insert("t".to_string(), vec![1, 2, 3, 4]);
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         match &egraph[id].data {
             MyAnalysisData::AccessPattern(a) => {
@@ -3579,8 +3711,10 @@ mod tests {
         .unwrap();
         let mut map = HashMap::new();
         map.insert("t".to_string(), vec![1, 2, 3, 4]);
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         match &egraph[id].data {
             MyAnalysisData::AccessPattern(a) => {
@@ -3605,8 +3739,10 @@ mod tests {
         .unwrap();
         let mut map = HashMap::default();
         map.insert("a".to_string(), vec![4, 5, 6]);
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         egraph.add_expr(&program);
     }
 
@@ -3620,8 +3756,10 @@ mod tests {
         .unwrap();
         let mut map = HashMap::default();
         map.insert("a".to_string(), vec![4, 6]);
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         egraph.add_expr(&program);
     }
 
@@ -3759,7 +3897,7 @@ mod tests {
     #[test]
     fn access_literal() {
         let program = "
-         (access-literal (literal 0.1234))
+         (access-literal (literal 0.1234) float32)
          "
         .parse()
         .unwrap();
@@ -3769,6 +3907,51 @@ mod tests {
             MyAnalysisData::AccessPattern(a) => {
                 assert_eq!(a.shape, IxDyn(&[]));
                 assert_eq!(a.item_shape, IxDyn(&[]));
+                assert_eq!(a.dtype, DType::Fp32);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn access_literal_int8() {
+        let program = "
+         (access-literal (literal 3) int8)
+         "
+        .parse()
+        .unwrap();
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis::default());
+        let id = egraph.add_expr(&program);
+        match &egraph[id].data {
+            MyAnalysisData::AccessPattern(a) => assert_eq!(a.dtype, DType::Int8),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn access_reshape_dtype() {
+        let program = "
+         (access-reshape
+          (access (access-tensor a) 1)
+          (access-shape (shape 6) (shape 2))
+         )
+         "
+        .parse()
+        .unwrap();
+        let mut map = HashMap::default();
+        map.insert("a".to_string(), vec![2, 3, 2]);
+        let mut dtypes = HashMap::default();
+        dtypes.insert("a".to_string(), DType::Int8);
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: dtypes,
+        });
+        let id = egraph.add_expr(&program);
+        match &egraph[id].data {
+            MyAnalysisData::AccessPattern(a) => {
+                assert_eq!(a.shape, IxDyn(&[6]));
+                assert_eq!(a.item_shape, IxDyn(&[2]));
+                assert_eq!(a.dtype, DType::Int8);
             }
             _ => panic!(),
         }
@@ -3823,8 +4006,10 @@ mod tests {
          "
         .parse()
         .unwrap();
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         match &egraph[id].data {
             MyAnalysisData::AccessPattern(a) => {
@@ -3848,8 +4033,10 @@ mod tests {
          "
         .parse()
         .unwrap();
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         match &egraph[id].data {
             MyAnalysisData::AccessPattern(a) => {
@@ -3873,8 +4060,10 @@ mod tests {
          "
         .parse()
         .unwrap();
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         match &egraph[id].data {
             MyAnalysisData::AccessPattern(a) => {
@@ -3886,7 +4075,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "assertion failed: a1.item_shape.slice()[1] % cols == 0")]
+    #[should_panic(expected = "arguments don't fit a 32x3 systolic array")]
     fn systolic_array_with_blocking_panic() {
         let mut map = HashMap::default();
         map.insert("a".to_string(), vec![32, 64]);
@@ -3899,8 +4088,157 @@ mod tests {
          "
         .parse()
         .unwrap();
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let _id = egraph.add_expr(&program);
     }
+
+    #[test]
+    fn dtype_defaults_to_fp32() {
+        let mut map = HashMap::default();
+        map.insert("a".to_string(), vec![32, 64]);
+        let program = "(access (access-tensor a) 1)".parse().unwrap();
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&program);
+        match &egraph[id].data {
+            MyAnalysisData::AccessPattern(a) => assert_eq!(a.dtype, DType::Fp32),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn dtype_propagation() {
+        let mut map = HashMap::default();
+        map.insert("a".to_string(), vec![32, 64]);
+        map.insert("b".to_string(), vec![64, 32]);
+        let mut dtypes = HashMap::default();
+        dtypes.insert("a".to_string(), DType::Int8);
+        dtypes.insert("b".to_string(), DType::Int8);
+        let program = "
+         (compute relu
+          (access-transpose
+           (systolic-array 64 32
+            (access (access-tensor a) 1)
+            (access (access-tensor b) 0)
+           )
+           (list 1 0)
+          )
+         )
+         "
+        .parse()
+        .unwrap();
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: dtypes,
+        });
+        let id = egraph.add_expr(&program);
+        match &egraph[id].data {
+            MyAnalysisData::AccessPattern(a) => {
+                assert_eq!(a.shape, IxDyn(&[32, 32]));
+                assert_eq!(a.item_shape, IxDyn(&[]));
+                assert_eq!(a.dtype, DType::Int8);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn dtype_argmax() {
+        let mut map = HashMap::default();
+        map.insert("a".to_string(), vec![32, 64]);
+        let mut dtypes = HashMap::default();
+        dtypes.insert("a".to_string(), DType::Bf16);
+        let program = "(compute argmax (access (access-tensor a) 1))"
+            .parse()
+            .unwrap();
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: dtypes,
+        });
+        let id = egraph.add_expr(&program);
+        match &egraph[id].data {
+            MyAnalysisData::AccessPattern(a) => {
+                assert_eq!(a.shape, IxDyn(&[32]));
+                assert_eq!(a.item_shape, IxDyn(&[]));
+                assert_eq!(a.dtype, DType::Int32);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "tensors can't be named after dtypes")]
+    fn dtype_as_tensor_name_panic() {
+        let mut map = HashMap::default();
+        map.insert("float32".to_string(), vec![32, 64]);
+        let program = "(access (access-tensor float32) 1)".parse().unwrap();
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let _id = egraph.add_expr(&program);
+    }
+
+    #[test]
+    fn declared_shapes_override_hardcoded_shapes() {
+        let mut map = HashMap::default();
+        map.insert("v-32".to_string(), vec![16]);
+        let program = "(access-tensor v-32)".parse().unwrap();
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&program);
+        match &egraph[id].data {
+            MyAnalysisData::AccessPattern(a) => assert_eq!(a.shape, IxDyn(&[16])),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "arguments have different dtypes")]
+    fn dtype_mismatch_panic() {
+        let mut map = HashMap::default();
+        map.insert("a".to_string(), vec![32, 64]);
+        map.insert("b".to_string(), vec![16, 64]);
+        let mut dtypes = HashMap::default();
+        dtypes.insert("a".to_string(), DType::Int8);
+        dtypes.insert("b".to_string(), DType::Bf16);
+        let program = "
+         (access-cartesian-product
+          (access (access-tensor a) 1)
+          (access (access-tensor b) 1)
+         )
+         "
+        .parse()
+        .unwrap();
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: dtypes,
+        });
+        let _id = egraph.add_expr(&program);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attempted to merge access patterns with different dtypes")]
+    fn dtype_merge_panic() {
+        let mut map = HashMap::default();
+        map.insert("a".to_string(), vec![32, 64]);
+        map.insert("b".to_string(), vec![32, 64]);
+        let mut dtypes = HashMap::default();
+        dtypes.insert("a".to_string(), DType::Int8);
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: dtypes,
+        });
+        let a_id = egraph.add_expr(&"(access-tensor a)".parse().unwrap());
+        let b_id = egraph.add_expr(&"(access-tensor b)".parse().unwrap());
+        egraph.union(a_id, b_id);
+        egraph.rebuild();
+    }
 }
@@ -7,4 +7,14 @@ pub mod interpreter;
 
 pub mod rewrites;
 
+pub mod rewrite_config;
+
 pub mod from_relay;
+
+pub mod program;
+
+pub mod random_program;
+
+pub mod typecheck;
+
+mod validate;
@@ -0,0 +1,299 @@
+//! Glenside program files: a program in Glenside's s-expression format,
+//! optionally preceded by declarations of its inputs:
+//! ```text
+//! (input input (shape 2) float32)
+//! (input weight (shape 4 2))
+//! (compute dot-product
+//!  (access-cartesian-product
+//!   (access (access-tensor input) 0)
+//!   (access (access-tensor weight) 1)))
+//! ```
+//! Each declaration gives an input's name, its shape and, optionally, its
+//! datatype. A file with declarations for all of its inputs is
+//! self-contained; files without declarations need their shapes given
+//! separately, e.g. in a JSON shapes file.
+
+use super::{Language, MyAnalysis};
+use crate::hw_design_language::DType;
+use egg::RecExpr;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// A parsed program file.
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub expr: RecExpr<Language>,
+    /// The shape of each declared input, by name.
+    pub name_to_shape: HashMap<String, Vec<usize>>,
+    /// The datatype of each declared input which declares one.
+    pub name_to_dtype: HashMap<String, DType>,
+}
+
+impl Program {
+    /// An analysis which knows the shapes and datatypes of the program's
+    /// declared inputs.
+    pub fn analysis(&self) -> MyAnalysis {
+        MyAnalysis {
+            name_to_shape: self.name_to_shape.clone(),
+            name_to_dtype: self.name_to_dtype.clone(),
+        }
+    }
+}
+
+/// An error in a program file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgramError {
+    /// A declaration isn't of the form `(input <name> (shape <dim>...)
+    /// [<dtype>])`.
+    InvalidDeclaration(String),
+    /// The same input is declared more than once.
+    DuplicateDeclaration(String),
+    /// An input is named after a datatype, such as `float32`. Programs parse
+    /// such names as datatypes, so they can't refer to the input.
+    DTypeName(String),
+    /// The program following the declarations can't be parsed.
+    InvalidProgram(String),
+}
+
+impl Display for ProgramError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProgramError::InvalidDeclaration(declaration) => write!(
+                f,
+                "expected a declaration of the form (input <name> (shape <dim>...) [<dtype>]), \
+                 found {}",
+                declaration
+            ),
+            ProgramError::DuplicateDeclaration(name) => {
+                write!(f, "input {} is declared more than once", name)
+            }
+            ProgramError::DTypeName(name) => write!(
+                f,
+                "input {} is named after a dtype, and tensors can't be named after dtypes",
+                name
+            ),
+            ProgramError::InvalidProgram(e) => write!(f, "could not parse program: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProgramError {}
+
+/// Splits an s-expression into parentheses and atoms.
+fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::default();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        if c == '(' || c == ')' || c.is_whitespace() {
+            if let Some(start) = start.take() {
+                tokens.push(&text[start..i]);
+            }
+            if !c.is_whitespace() {
+                tokens.push(&text[i..i + 1]);
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(start) = start {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+/// If `text` starts with an `(input ...)` declaration, returns the
+/// declaration and the text following it.
+fn split_declaration(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_start();
+    let keyword = text.strip_prefix('(')?.trim_start().strip_prefix("input")?;
+    if !keyword.starts_with(|c: char| c == '(' || c == ')' || c.is_whitespace()) {
+        return None;
+    }
+
+    // Find the parenthesis which closes the declaration. If there isn't one,
+    // the whole text is an (unterminated) declaration.
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((&text[..=i], &text[i + 1..]));
+                }
+            }
+            _ => (),
+        }
+    }
+    Some((text, ""))
+}
+
+/// Parses a single `(input <name> (shape <dim>...) [<dtype>])` declaration.
+fn parse_declaration(
+    declaration: &str,
+) -> Result<(String, Vec<usize>, Option<DType>), ProgramError> {
+    let invalid = || ProgramError::InvalidDeclaration(declaration.trim().to_string());
+    let tokens = tokenize(declaration);
+
+    match tokens.as_slice() {
+        ["(", "input", name, "(", "shape", rest @ ..] => {
+            if *name == "(" || *name == ")" {
+                return Err(invalid());
+            }
+            let close = rest
+                .iter()
+                .position(|token| *token == ")")
+                .ok_or_else(invalid)?;
+            let shape = rest[..close]
+                .iter()
+                .map(|dim| dim.parse::<usize>().map_err(|_| invalid()))
+                .collect::<Result<Vec<_>, _>>()?;
+            let dtype = match &rest[close + 1..] {
+                [")"] => None,
+                [dtype, ")"] => Some(DType::from_str(dtype).map_err(|_| invalid())?),
+                _ => return Err(invalid()),
+            };
+            Ok((name.to_string(), shape, dtype))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses a program file: any number of input declarations, followed by the
+/// program itself.
+/// ```
+/// use glenside::hw_design_language::DType;
+/// use glenside::language::program::parse_program;
+///
+/// let program = parse_program(
+///     "(input a (shape 2 3) int8)
+///      (input b (shape 3))
+///      (compute dot-product
+///       (access-cartesian-product
+///        (access (access-tensor a) 1)
+///        (access (access-tensor b) 0)))",
+/// )
+/// .unwrap();
+/// assert_eq!(program.name_to_shape["a"], vec![2, 3]);
+/// assert_eq!(program.name_to_shape["b"], vec![3]);
+/// assert_eq!(program.name_to_dtype["a"], DType::Int8);
+/// assert!(!program.name_to_dtype.contains_key("b"));
+/// ```
+pub fn parse_program(text: &str) -> Result<Program, ProgramError> {
+    let mut name_to_shape = HashMap::default();
+    let mut name_to_dtype = HashMap::default();
+
+    let mut rest = text;
+    while let Some((declaration, after)) = split_declaration(rest) {
+        let (name, shape, dtype) = parse_declaration(declaration)?;
+        if DType::from_str(&name).is_ok() {
+            return Err(ProgramError::DTypeName(name));
+        }
+        if name_to_shape.contains_key(&name) {
+            return Err(ProgramError::DuplicateDeclaration(name));
+        }
+        if let Some(dtype) = dtype {
+            name_to_dtype.insert(name.clone(), dtype);
+        }
+        name_to_shape.insert(name, shape);
+        rest = after;
+    }
+
+    let expr = RecExpr::from_str(rest).map_err(|e| ProgramError::InvalidProgram(e.to_string()))?;
+
+    Ok(Program {
+        expr,
+        name_to_shape,
+        name_to_dtype,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::MyAnalysisData;
+    use egg::EGraph;
+
+    #[test]
+    fn no_declarations() {
+        let program = parse_program("(access (access-tensor t) 0)").unwrap();
+        assert!(program.name_to_shape.is_empty());
+        assert!(program.name_to_dtype.is_empty());
+        assert_eq!(
+            program.expr,
+            RecExpr::from_str("(access (access-tensor t) 0)").unwrap()
+        );
+    }
+
+    #[test]
+    fn declarations_populate_analysis() {
+        let program = parse_program(
+            "(input image (shape 1 3 32 32) float32)
+             (access (access-tensor image) 1)",
+        )
+        .unwrap();
+        assert_eq!(program.name_to_dtype["image"], DType::Fp32);
+
+        let mut egraph = EGraph::new(program.analysis());
+        let id = egraph.add_expr(&program.expr);
+        match &egraph[id].data {
+            MyAnalysisData::AccessPattern(a) => {
+                assert_eq!(a.shape.slice(), &[1]);
+                assert_eq!(a.item_shape.slice(), &[3, 32, 32]);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn scalar_input() {
+        let program = parse_program("(input s (shape)) (access (access-tensor s) 0)").unwrap();
+        assert_eq!(program.name_to_shape["s"], Vec::<usize>::new());
+    }
+
+    #[test]
+    fn duplicate_declaration() {
+        assert_eq!(
+            parse_program("(input t (shape 1)) (input t (shape 2)) (access (access-tensor t) 0)")
+                .unwrap_err(),
+            ProgramError::DuplicateDeclaration("t".to_string())
+        );
+    }
+
+    #[test]
+    fn dtype_name() {
+        assert_eq!(
+            parse_program("(input int8 (shape 1)) (access (access-tensor int8) 0)").unwrap_err(),
+            ProgramError::DTypeName("int8".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_declarations() {
+        for declaration in &[
+            "(input t)",
+            "(input t (shape 1 x))",
+            "(input t (shape 1) float32 int8)",
+            "(input t (shape 1) notadtype)",
+            "(input (shape 1))",
+            "(input t (shape 1)",
+        ] {
+            match parse_program(format!("{} (access (access-tensor t) 0)", declaration).as_str()) {
+                Err(ProgramError::InvalidDeclaration(_)) => (),
+                other => panic!(
+                    "{}: expected an invalid declaration, got {:?}",
+                    declaration, other
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_program() {
+        match parse_program("(input t (shape 1)) (access (access-tensor t) 0") {
+            Err(ProgramError::InvalidProgram(_)) => (),
+            other => panic!("expected an invalid program, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,373 @@
+//! Generates random, well-shaped Glenside programs, for differential testing
+//! of [`MyAnalysis`](super::MyAnalysis), the
+//! [`interpreter`](super::interpreter) and [`codegen`](crate::codegen).
+//!
+//! Programs are built bottom-up out of accesses of randomly-shaped input
+//! tensors. We track the shape of each subprogram as we go, so that every
+//! operator we add is given arguments it accepts.
+
+use super::Language;
+use egg::RecExpr;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// A randomly generated program, along with the shapes of its inputs.
+pub struct RandomProgram {
+    pub expr: RecExpr<Language>,
+    /// The shape of each input tensor, by name.
+    pub shapes: HashMap<String, Vec<usize>>,
+}
+
+/// The largest number of elements any subprogram may have. Operators which
+/// would produce anything larger are not generated, which keeps the programs
+/// quick to interpret and to run.
+const MAX_ELEMENTS: usize = 2048;
+
+/// A subprogram which produces an access pattern.
+#[derive(Clone)]
+struct GeneratedAccess {
+    program: String,
+    /// The shape of the accessed tensor, i.e. the access pattern's shape
+    /// followed by its item shape.
+    dims: Vec<usize>,
+    access_axis: usize,
+}
+
+impl GeneratedAccess {
+    fn num_elements(&self) -> usize {
+        self.dims.iter().product()
+    }
+
+    fn item_dims(&self) -> &[usize] {
+        &self.dims[self.access_axis..]
+    }
+}
+
+struct Generator<'a, R: Rng> {
+    rng: &'a mut R,
+    shapes: HashMap<String, Vec<usize>>,
+}
+
+impl<'a, R: Rng> Generator<'a, R> {
+    /// Declares a new input tensor of shape `dims`, accessed at
+    /// `access_axis`.
+    fn input(&mut self, dims: Vec<usize>, access_axis: usize) -> GeneratedAccess {
+        let name = format!("t{}", self.shapes.len());
+        self.shapes.insert(name.clone(), dims.clone());
+        GeneratedAccess {
+            program: format!("(access (access-tensor {}) {})", name, access_axis),
+            dims,
+            access_axis,
+        }
+    }
+
+    /// Declares a new input tensor with a random shape.
+    fn random_input(&mut self) -> GeneratedAccess {
+        let ndim = self.rng.gen_range(1, 4);
+        let dims = (0..ndim)
+            .map(|_| self.rng.gen_range(1, 5))
+            .collect::<Vec<_>>();
+        let access_axis = self.rng.gen_range(0, ndim + 1);
+        self.input(dims, access_axis)
+    }
+
+    fn generate(&mut self, depth: usize) -> GeneratedAccess {
+        if depth == 0 {
+            return self.random_input();
+        }
+
+        let a = self.generate(depth - 1);
+        // Try operators until we find one which applies to `a`.
+        loop {
+            let generated = match self.rng.gen_range(0, 9) {
+                0 => self.access(&a),
+                1 => self.transpose(&a),
+                2 => self.pad(&a),
+                3 => self.slice(&a),
+                4 => self.concatenate(&a),
+                5 => self.windows(&a),
+                6 => self.cartesian_product(&a),
+                _ => self.compute(&a),
+            };
+            match generated {
+                Some(generated) if generated.num_elements() <= MAX_ELEMENTS => return generated,
+                _ => (),
+            }
+        }
+    }
+
+    fn access(&mut self, a: &GeneratedAccess) -> Option<GeneratedAccess> {
+        let access_axis = self.rng.gen_range(0, a.dims.len() + 1);
+        Some(GeneratedAccess {
+            program: format!("(access {} {})", a.program, access_axis),
+            dims: a.dims.clone(),
+            access_axis,
+        })
+    }
+
+    fn transpose(&mut self, a: &GeneratedAccess) -> Option<GeneratedAccess> {
+        let mut permutation = (0..a.dims.len()).collect::<Vec<_>>();
+        permutation.shuffle(&mut *self.rng);
+        Some(GeneratedAccess {
+            program: format!(
+                "(access-transpose {} (list {}))",
+                a.program,
+                permutation
+                    .iter()
+                    .map(|axis| axis.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            dims: permutation.iter().map(|&axis| a.dims[axis]).collect(),
+            access_axis: a.access_axis,
+        })
+    }
+
+    fn pad(&mut self, a: &GeneratedAccess) -> Option<GeneratedAccess> {
+        let axis = self.rng.gen_range(0, a.dims.len());
+        let pad_before = self.rng.gen_range(0, 3);
+        let pad_after = self.rng.gen_range(0, 3);
+        let mut dims = a.dims.clone();
+        dims[axis] += pad_before + pad_after;
+        Some(GeneratedAccess {
+            program: format!(
+                "(access-pad {} zero-padding {} {} {})",
+                a.program, axis, pad_before, pad_after
+            ),
+            dims,
+            access_axis: a.access_axis,
+        })
+    }
+
+    fn slice(&mut self, a: &GeneratedAccess) -> Option<GeneratedAccess> {
+        let axis = self.rng.gen_range(0, a.dims.len());
+        let low = self.rng.gen_range(0, a.dims[axis]);
+        let high = self.rng.gen_range(low + 1, a.dims[axis] + 1);
+        let mut dims = a.dims.clone();
+        dims[axis] = high - low;
+        Some(GeneratedAccess {
+            program: format!("(access-slice {} {} {} {})", a.program, axis, low, high),
+            dims,
+            access_axis: a.access_axis,
+        })
+    }
+
+    /// Concatenates `a` with either itself or a new input.
+    fn concatenate(&mut self, a: &GeneratedAccess) -> Option<GeneratedAccess> {
+        let axis = self.rng.gen_range(0, a.dims.len());
+        let other = if self.rng.gen() {
+            a.clone()
+        } else {
+            let mut dims = a.dims.clone();
+            dims[axis] = self.rng.gen_range(1, 5);
+            self.input(dims, a.access_axis)
+        };
+        let mut dims = a.dims.clone();
+        dims[axis] += other.dims[axis];
+        Some(GeneratedAccess {
+            program: format!(
+                "(access-concatenate {} {} {})",
+                a.program, other.program, axis
+            ),
+            dims,
+            access_axis: a.access_axis,
+        })
+    }
+
+    /// Forms windows over `a`, which must have a scalar item shape.
+    fn windows(&mut self, a: &GeneratedAccess) -> Option<GeneratedAccess> {
+        if a.access_axis != a.dims.len() {
+            return None;
+        }
+        let filters = a
+            .dims
+            .iter()
+            .map(|&dim| self.rng.gen_range(1, dim + 1))
+            .collect::<Vec<_>>();
+        let strides = a
+            .dims
+            .iter()
+            .map(|_| self.rng.gen_range(1, 3))
+            .collect::<Vec<_>>();
+        let to_string = |list: &[usize]| {
+            list.iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        Some(GeneratedAccess {
+            program: format!(
+                "(access-windows {} (shape {}) (shape {}))",
+                a.program,
+                to_string(&filters),
+                to_string(&strides)
+            ),
+            dims: a
+                .dims
+                .iter()
+                .zip(filters.iter())
+                .zip(strides.iter())
+                .map(|((&dim, &filter), &stride)| (dim - filter) / stride + 1)
+                .chain(filters.iter().cloned())
+                .collect(),
+            access_axis: a.dims.len(),
+        })
+    }
+
+    /// Takes the cartesian product of `a` with a new input with the same item
+    /// shape.
+    fn cartesian_product(&mut self, a: &GeneratedAccess) -> Option<GeneratedAccess> {
+        let other_access_axis = self.rng.gen_range(1, 3);
+        let other_dims = (0..other_access_axis)
+            .map(|_| self.rng.gen_range(1, 4))
+            .chain(a.item_dims().iter().cloned())
+            .collect();
+        let other = self.input(other_dims, other_access_axis);
+        let (left, right) = if self.rng.gen() {
+            (a, &other)
+        } else {
+            (&other, a)
+        };
+        Some(GeneratedAccess {
+            program: format!(
+                "(access-cartesian-product {} {})",
+                left.program, right.program
+            ),
+            dims: left.dims[..left.access_axis]
+                .iter()
+                .chain(right.dims[..right.access_axis].iter())
+                .chain(std::iter::once(&2))
+                .chain(a.item_dims().iter())
+                .cloned()
+                .collect(),
+            access_axis: left.access_axis + right.access_axis,
+        })
+    }
+
+    fn compute(&mut self, a: &GeneratedAccess) -> Option<GeneratedAccess> {
+        let item_ndim = a.item_dims().len();
+        // No exp: on the large values sums can produce, its result is too
+        // large to compare against the interpreter with an absolute
+        // tolerance.
+        let mut compute_types = vec![
+            "relu",
+            "negative",
+            "sigmoid",
+            "tanh",
+            "gelu",
+            "(clip -0.5 0.5)",
+            "(leaky-relu 0.1)",
+        ];
+        if item_ndim >= 1 {
+            compute_types.extend(&[
+                "reduce-sum",
+                "reduce-max",
+                "reduce-mean",
+                "reduce-min",
+                "argmax",
+                "dot-product",
+                "elementwise-add",
+                "elementwise-mul",
+            ]);
+        }
+        if item_ndim == 1 {
+            compute_types.push("softmax");
+        }
+        let compute_type = *compute_types.choose(&mut *self.rng).unwrap();
+
+        let shape_dims = &a.dims[..a.access_axis];
+        let dims = match compute_type {
+            "relu" | "negative" | "sigmoid" | "tanh" | "gelu" | "(clip -0.5 0.5)"
+            | "(leaky-relu 0.1)" | "softmax" => a.dims.clone(),
+            "elementwise-add" | "elementwise-mul" => shape_dims
+                .iter()
+                .chain(a.item_dims()[1..].iter())
+                .cloned()
+                .collect(),
+            _ => shape_dims.to_vec(),
+        };
+        Some(GeneratedAccess {
+            program: format!("(compute {} {})", compute_type, a.program),
+            dims,
+            access_axis: a.access_axis,
+        })
+    }
+}
+
+/// Generates a random program built from `depth` layers of operators:
+/// accesses, transposes, pads, slices, concatenates, windows, cartesian
+/// products and computes. Returns the program along with the shapes of its
+/// inputs, which are named `t0`, `t1`, and so on.
+/// ```
+/// use egg::EGraph;
+/// use glenside::language::random_program::random_program;
+/// use glenside::language::{MyAnalysis, MyAnalysisData};
+/// use rand::{rngs::StdRng, SeedableRng};
+/// use std::collections::HashMap;
+///
+/// let program = random_program(&mut StdRng::seed_from_u64(0), 4);
+///
+/// // The program is well-shaped.
+/// let mut egraph = EGraph::new(MyAnalysis {
+///     name_to_shape: program.shapes.clone(),
+///     name_to_dtype: HashMap::default(),
+/// });
+/// let id = egraph.add_expr(&program.expr);
+/// match &egraph[id].data {
+///     MyAnalysisData::AccessPattern(_) => (),
+///     _ => panic!(),
+/// }
+/// ```
+pub fn random_program<R: Rng>(rng: &mut R, depth: usize) -> RandomProgram {
+    let mut generator = Generator {
+        rng,
+        shapes: HashMap::default(),
+    };
+    let program = generator.generate(depth);
+    RandomProgram {
+        expr: RecExpr::from_str(program.program.as_str()).unwrap(),
+        shapes: generator.shapes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::{MyAnalysis, MyAnalysisData};
+    use egg::EGraph;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// The shapes we track while generating should match the shapes
+    /// [`MyAnalysis`] infers.
+    #[test]
+    fn shapes_match_analysis() {
+        for seed in 0..100 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut generator = Generator {
+                rng: &mut rng,
+                shapes: HashMap::default(),
+            };
+            let generated = generator.generate(5);
+
+            let mut egraph = EGraph::new(MyAnalysis {
+                name_to_shape: generator.shapes,
+                name_to_dtype: HashMap::default(),
+            });
+            let id = egraph.add_expr(&RecExpr::from_str(generated.program.as_str()).unwrap());
+            match &egraph[id].data {
+                MyAnalysisData::AccessPattern(a) => {
+                    assert_eq!(a.as_vec(), generated.dims, "{}", generated.program);
+                    assert_eq!(
+                        a.shape.ndim(),
+                        generated.access_axis,
+                        "{}",
+                        generated.program
+                    );
+                }
+                _ => panic!(),
+            }
+        }
+    }
+}
@@ -0,0 +1,696 @@
+//! Rewrite configurations: JSON files which choose the rewrites to run, and
+//! their parameters.
+//!
+//! A configuration is a list of rewrites. Each rewrite is named by its
+//! constructor in [`rewrites`], and gives the constructor's arguments by
+//! name:
+//! ```json
+//! [
+//!     { "rewrite": "flatten_unflatten_any_access" },
+//!     {
+//!         "rewrite": "pad_slice_accesses",
+//!         "axis": 0,
+//!         "strategy": {
+//!             "type": "pad_to_closest_multiple_of",
+//!             "multiple_of": 64,
+//!             "pad_location": "end",
+//!             "pad_type": "zero-padding"
+//!         }
+//!     },
+//!     {
+//!         "rewrite": "slice_concatenate_accesses",
+//!         "axis": 0,
+//!         "strategy": { "type": "divide_into", "segment_size": 64 }
+//!     },
+//!     { "rewrite": "systolic_array_with_blocking", "rows": 64, "cols": 64 }
+//! ]
+//! ```
+//! Configurations are checked against [`registry()`], which lists every
+//! rewrite which can be configured.
+
+use super::rewrites::{self, PadLocation, PadSliceStrategy, SliceConcatenateStrategy};
+use super::{Language, MyAnalysis, PadType};
+use crate::phases::Phase;
+use egg::Rewrite;
+use serde_json::{Map, Value};
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// An error in a rewrite configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RewriteConfigError {
+    /// The configuration isn't valid JSON.
+    InvalidJson(String),
+    /// The configuration isn't a list of rewrites.
+    NotAList,
+    /// An entry of the configuration isn't an object with a `"rewrite"` name.
+    InvalidEntry(Value),
+    /// The same rewrite, with the same parameters, appears more than once.
+    DuplicateEntry(Value),
+    /// The rewrite isn't in the [`registry()`].
+    UnknownRewrite(String),
+    /// The rewrite doesn't take a parameter with this name.
+    UnknownParameter { rewrite: String, parameter: String },
+    /// The rewrite needs a parameter which wasn't given.
+    MissingParameter { rewrite: String, parameter: String },
+    /// The parameter's value has the wrong type or is out of range.
+    InvalidParameter {
+        rewrite: String,
+        parameter: String,
+        expected: String,
+    },
+}
+
+impl Display for RewriteConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RewriteConfigError::InvalidJson(e) => {
+                write!(f, "rewrite configuration is not valid JSON: {}", e)
+            }
+            RewriteConfigError::NotAList => {
+                write!(f, "rewrite configuration should be a list of rewrites")
+            }
+            RewriteConfigError::InvalidEntry(entry) => write!(
+                f,
+                "expected an object with a \"rewrite\" name, found {}",
+                entry
+            ),
+            RewriteConfigError::DuplicateEntry(entry) => {
+                write!(f, "rewrite {} appears more than once", entry)
+            }
+            RewriteConfigError::UnknownRewrite(name) => write!(
+                f,
+                "unknown rewrite {}; available rewrites are: {}",
+                name,
+                registry()
+                    .iter()
+                    .map(|registered| registered.name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            RewriteConfigError::UnknownParameter { rewrite, parameter } => {
+                write!(f, "rewrite {} has no parameter {}", rewrite, parameter)
+            }
+            RewriteConfigError::MissingParameter { rewrite, parameter } => {
+                write!(f, "rewrite {} needs parameter {}", rewrite, parameter)
+            }
+            RewriteConfigError::InvalidParameter {
+                rewrite,
+                parameter,
+                expected,
+            } => write!(
+                f,
+                "parameter {} of rewrite {} should be {}",
+                parameter, rewrite, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RewriteConfigError {}
+
+/// The parameters given to a rewrite (or to one of its parameters, for
+/// parameters like strategies which are objects themselves).
+pub struct Parameters<'a> {
+    rewrite: &'a str,
+    values: &'a Map<String, Value>,
+}
+
+impl<'a> Parameters<'a> {
+    fn get(&self, parameter: &str) -> Result<&'a Value, RewriteConfigError> {
+        self.values
+            .get(parameter)
+            .ok_or_else(|| RewriteConfigError::MissingParameter {
+                rewrite: self.rewrite.to_string(),
+                parameter: parameter.to_string(),
+            })
+    }
+
+    fn invalid(&self, parameter: &str, expected: &str) -> RewriteConfigError {
+        RewriteConfigError::InvalidParameter {
+            rewrite: self.rewrite.to_string(),
+            parameter: parameter.to_string(),
+            expected: expected.to_string(),
+        }
+    }
+
+    /// Checks that no parameters other than `allowed` were given.
+    fn check_names(&self, allowed: &[&str]) -> Result<(), RewriteConfigError> {
+        match self
+            .values
+            .keys()
+            .find(|name| !allowed.contains(&name.as_str()))
+        {
+            Some(name) => Err(RewriteConfigError::UnknownParameter {
+                rewrite: self.rewrite.to_string(),
+                parameter: name.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    pub fn usize(&self, parameter: &str) -> Result<usize, RewriteConfigError> {
+        self.get(parameter)?
+            .as_u64()
+            .map(|value| value as usize)
+            .ok_or_else(|| self.invalid(parameter, "a nonnegative integer"))
+    }
+
+    /// Like [`Parameters::usize()`], but rejects 0, for parameters which
+    /// are sizes or which are divided by.
+    pub fn positive_usize(&self, parameter: &str) -> Result<usize, RewriteConfigError> {
+        match self.usize(parameter)? {
+            0 => Err(self.invalid(parameter, "a positive integer")),
+            value => Ok(value),
+        }
+    }
+
+    pub fn bool(&self, parameter: &str) -> Result<bool, RewriteConfigError> {
+        self.get(parameter)?
+            .as_bool()
+            .ok_or_else(|| self.invalid(parameter, "true or false"))
+    }
+
+    /// The parameters of an object-valued parameter, and the object's
+    /// `"type"`.
+    fn object(&self, parameter: &str) -> Result<(&'a str, Parameters<'a>), RewriteConfigError> {
+        let values = self
+            .get(parameter)?
+            .as_object()
+            .ok_or_else(|| self.invalid(parameter, "an object with a \"type\""))?;
+        let object_type = values
+            .get("type")
+            .and_then(Value::as_str)
+            .ok_or_else(|| self.invalid(parameter, "an object with a \"type\""))?;
+        Ok((
+            object_type,
+            Parameters {
+                rewrite: self.rewrite,
+                values,
+            },
+        ))
+    }
+
+    pub fn pad_location(&self, parameter: &str) -> Result<PadLocation, RewriteConfigError> {
+        match self.get(parameter)?.as_str() {
+            Some("end") => Ok(PadLocation::End),
+            _ => Err(self.invalid(parameter, "\"end\"")),
+        }
+    }
+
+    pub fn pad_type(&self, parameter: &str) -> Result<PadType, RewriteConfigError> {
+        self.get(parameter)?
+            .as_str()
+            .and_then(|s| PadType::from_str(s).ok())
+            .ok_or_else(|| self.invalid(parameter, "\"zero-padding\" or \"min-padding\""))
+    }
+
+    pub fn pad_slice_strategy(
+        &self,
+        parameter: &str,
+    ) -> Result<PadSliceStrategy, RewriteConfigError> {
+        match self.object(parameter)? {
+            ("pad_to_closest_multiple_of", strategy) => {
+                strategy.check_names(&["type", "multiple_of", "pad_location", "pad_type"])?;
+                Ok(PadSliceStrategy::PadToClosestMultipleOf {
+                    multiple_of: strategy.positive_usize("multiple_of")?,
+                    pad_location: strategy.pad_location("pad_location")?,
+                    pad_type: strategy.pad_type("pad_type")?,
+                })
+            }
+            ("pad_to_multiples_of", strategy) => {
+                strategy.check_names(&[
+                    "type",
+                    "multiples_of",
+                    "limit",
+                    "pad_location",
+                    "pad_type",
+                ])?;
+                Ok(PadSliceStrategy::PadToMultiplesOf {
+                    multiples_of: strategy.positive_usize("multiples_of")?,
+                    limit: strategy.usize("limit")?,
+                    pad_location: strategy.pad_location("pad_location")?,
+                    pad_type: strategy.pad_type("pad_type")?,
+                })
+            }
+            _ => Err(self.invalid(
+                parameter,
+                "of type \"pad_to_closest_multiple_of\" or \"pad_to_multiples_of\"",
+            )),
+        }
+    }
+
+    pub fn slice_concatenate_strategy(
+        &self,
+        parameter: &str,
+    ) -> Result<SliceConcatenateStrategy, RewriteConfigError> {
+        match self.object(parameter)? {
+            ("divide_by", strategy) => {
+                strategy.check_names(&["type", "divisor", "limit"])?;
+                Ok(SliceConcatenateStrategy::DivideBy {
+                    divisor: strategy.positive_usize("divisor")?,
+                    limit: strategy.usize("limit")?,
+                })
+            }
+            ("divide_into", strategy) => {
+                strategy.check_names(&["type", "segment_size"])?;
+                Ok(SliceConcatenateStrategy::DivideInto {
+                    segment_size: strategy.positive_usize("segment_size")?,
+                })
+            }
+            _ => Err(self.invalid(parameter, "of type \"divide_by\" or \"divide_into\"")),
+        }
+    }
+}
+
+/// A rewrite constructor which can be named in a rewrite configuration.
+pub struct RegisteredRewrite {
+    /// The name of the constructor in [`rewrites`].
+    pub name: &'static str,
+    /// The names of the constructor's parameters.
+    pub parameters: &'static [&'static str],
+    construct: fn(&Parameters) -> Result<Rewrite<Language, MyAnalysis>, RewriteConfigError>,
+}
+
+/// Registers a rewrite constructor which takes no parameters.
+macro_rules! no_parameters {
+    ($name:ident) => {
+        RegisteredRewrite {
+            name: stringify!($name),
+            parameters: &[],
+            construct: |_| Ok(rewrites::$name()),
+        }
+    };
+}
+
+/// Every rewrite which can be named in a rewrite configuration.
+pub fn registry() -> Vec<RegisteredRewrite> {
+    vec![
+        RegisteredRewrite {
+            name: "split",
+            parameters: &["axis", "dimension_greater_than", "split_all_nodes"],
+            construct: |p| {
+                Ok(rewrites::split(
+                    p.usize("axis")?,
+                    p.usize("dimension_greater_than")?,
+                    p.bool("split_all_nodes")?,
+                ))
+            },
+        },
+        no_parameters!(collapse_nested_slices),
+        no_parameters!(bubble_concatenate_through_move_axis),
+        no_parameters!(bubble_concatenate_through_cartesian_product_not_last_axis_left),
+        no_parameters!(bubble_concatenate_through_cartesian_product_not_last_axis_right),
+        no_parameters!(bubble_concatenate_through_cartesian_product_last_axis),
+        no_parameters!(bubble_concatenate_through_cartesian_product_axis_0_0),
+        no_parameters!(rewrite_nonmatching_cartesian_product_concatenate),
+        no_parameters!(bubble_concatenate_through_map_dot_product_not_last_axis),
+        no_parameters!(bubble_concatenate_through_map_dot_product_last_axis),
+        no_parameters!(slice_move_axis_composition_commutative),
+        no_parameters!(systolic_array_vector_matrix),
+        no_parameters!(flatten_unflatten_any_access),
+        no_parameters!(bubble_reshape_through_cartesian_product),
+        no_parameters!(bubble_reshape_through_compute_dot_product),
+        RegisteredRewrite {
+            name: "systolic_array_with_blocking",
+            parameters: &["rows", "cols"],
+            construct: |p| {
+                Ok(rewrites::systolic_array_with_blocking(
+                    p.positive_usize("rows")?,
+                    p.positive_usize("cols")?,
+                ))
+            },
+        },
+        no_parameters!(systolic_array),
+        no_parameters!(systolic_array_batch_matmul),
+        RegisteredRewrite {
+            name: "slice_concatenate_accesses",
+            parameters: &["axis", "strategy"],
+            construct: |p| {
+                Ok(rewrites::slice_concatenate_accesses(
+                    p.usize("axis")?,
+                    p.slice_concatenate_strategy("strategy")?,
+                ))
+            },
+        },
+        RegisteredRewrite {
+            name: "slice_concatenate_tensor_accesses",
+            parameters: &["axis", "dimension_greater_than"],
+            construct: |p| {
+                Ok(rewrites::slice_concatenate_tensor_accesses(
+                    p.usize("axis")?,
+                    p.usize("dimension_greater_than")?,
+                ))
+            },
+        },
+        no_parameters!(collapse_nested_access_slices),
+        no_parameters!(access_slice_access_transpose_composition_commutative),
+        no_parameters!(bubble_access_concatenate_through_access_transpose),
+        no_parameters!(
+            bubble_access_concatenate_through_access_cartesian_product_not_item_axis_left
+        ),
+        no_parameters!(
+            bubble_access_concatenate_through_access_cartesian_product_not_item_axis_right
+        ),
+        no_parameters!(bubble_access_concatenate_through_access_cartesian_product_same_item_axis),
+        no_parameters!(bubble_access_concatenate_through_compute_dot_product_not_item_axis),
+        no_parameters!(bubble_access_concatenate_through_compute_dot_product_item_axis),
+        no_parameters!(bubble_access_concatenate_through_access),
+        no_parameters!(bubble_access_concatenate_through_access_slice),
+        no_parameters!(collapse_nested_transposes),
+        no_parameters!(remove_trivial_transpose),
+        no_parameters!(collapse_nested_accesses),
+        RegisteredRewrite {
+            name: "pad_slice_accesses",
+            parameters: &["axis", "strategy"],
+            construct: |p| {
+                Ok(rewrites::pad_slice_accesses(
+                    p.usize("axis")?,
+                    p.pad_slice_strategy("strategy")?,
+                ))
+            },
+        },
+        no_parameters!(bubble_access_slice_through_access_pad_inequal_axes),
+        no_parameters!(bubble_access_slice_through_access_cartesian_product_not_item_axis_left),
+        no_parameters!(bubble_access_slice_through_access_cartesian_product_not_item_axis_right),
+        no_parameters!(bubble_access_slice_through_access_cartesian_product_same_item_axis),
+        no_parameters!(bubble_access_slice_through_compute_dot_product_not_item_axis),
+        no_parameters!(bubble_access_slice_through_compute_dot_product_item_axis_not_tuple_axis),
+    ]
+}
+
+/// Builds the rewrites described by a rewrite configuration, given as a JSON
+/// string.
+pub fn rewrites_from_config(
+    config: &str,
+) -> Result<Vec<Rewrite<Language, MyAnalysis>>, RewriteConfigError> {
+    let config: Value =
+        serde_json::from_str(config).map_err(|e| RewriteConfigError::InvalidJson(e.to_string()))?;
+    rewrites_from_value(&config)
+}
+
+/// Builds the rewrites described by a list of rewrites.
+fn rewrites_from_value(
+    config: &Value,
+) -> Result<Vec<Rewrite<Language, MyAnalysis>>, RewriteConfigError> {
+    let entries = config.as_array().ok_or(RewriteConfigError::NotAList)?;
+
+    let registry = registry();
+    let mut rws = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        if entries[..i].contains(entry) {
+            return Err(RewriteConfigError::DuplicateEntry(entry.clone()));
+        }
+
+        let values = entry
+            .as_object()
+            .ok_or_else(|| RewriteConfigError::InvalidEntry(entry.clone()))?;
+        let name = values
+            .get("rewrite")
+            .and_then(Value::as_str)
+            .ok_or_else(|| RewriteConfigError::InvalidEntry(entry.clone()))?;
+        let registered = registry
+            .iter()
+            .find(|registered| registered.name == name)
+            .ok_or_else(|| RewriteConfigError::UnknownRewrite(name.to_string()))?;
+
+        let parameters = Parameters {
+            rewrite: name,
+            values,
+        };
+        parameters.check_names(
+            &std::iter::once("rewrite")
+                .chain(registered.parameters.iter().cloned())
+                .collect::<Vec<_>>(),
+        )?;
+        rws.push((registered.construct)(&parameters)?);
+    }
+
+    Ok(rws)
+}
+
+/// Builds the phases described by a phase configuration, given as a JSON
+/// string. A phase configuration is a list of phases, each of which gives its
+/// name, its rewrites (in the format of a rewrite configuration), and
+/// optionally its limits and whether to extract at its end:
+/// ```json
+/// [
+///     {
+///         "name": "tensorize",
+///         "rewrites": [ { "rewrite": "systolic_array" } ],
+///         "node_limit": 100000,
+///         "time_limit": 10,
+///         "iter_limit": 20,
+///         "extract": true
+///     }
+/// ]
+/// ```
+/// `time_limit` is in seconds.
+pub fn phases_from_config(config: &str) -> Result<Vec<Phase>, RewriteConfigError> {
+    let config: Value =
+        serde_json::from_str(config).map_err(|e| RewriteConfigError::InvalidJson(e.to_string()))?;
+    let entries = config.as_array().ok_or(RewriteConfigError::NotAList)?;
+
+    let mut phases = Vec::new();
+    for entry in entries.iter() {
+        let values = entry
+            .as_object()
+            .ok_or_else(|| RewriteConfigError::InvalidEntry(entry.clone()))?;
+        let name = values
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| RewriteConfigError::InvalidEntry(entry.clone()))?;
+        let parameters = Parameters {
+            rewrite: name,
+            values,
+        };
+        parameters.check_names(&[
+            "name",
+            "rewrites",
+            "node_limit",
+            "time_limit",
+            "iter_limit",
+            "extract",
+        ])?;
+
+        let mut phase = Phase::new(name, rewrites_from_value(parameters.get("rewrites")?)?);
+        if values.contains_key("node_limit") {
+            phase = phase.with_node_limit(parameters.usize("node_limit")?);
+        }
+        if values.contains_key("time_limit") {
+            phase =
+                phase.with_time_limit(Duration::from_secs(parameters.usize("time_limit")? as u64));
+        }
+        if values.contains_key("iter_limit") {
+            phase = phase.with_iter_limit(parameters.usize("iter_limit")?);
+        }
+        if values.contains_key("extract") && parameters.bool("extract")? {
+            phase = phase.with_extraction();
+        }
+        phases.push(phase);
+    }
+
+    Ok(phases)
+}
+
+/// Reads a phase configuration from a file and builds its phases. Panics if
+/// the file can't be read or the configuration is invalid.
+pub fn read_phases_config(filepath: &str) -> Vec<Phase> {
+    phases_from_config(
+        std::fs::read_to_string(filepath)
+            .unwrap_or_else(|e| panic!("Could not read {}: {}", filepath, e))
+            .as_str(),
+    )
+    .unwrap_or_else(|e| panic!("Invalid phase configuration {}: {}", filepath, e))
+}
+
+/// Reads a rewrite configuration from a file and builds its rewrites. Panics
+/// if the file can't be read or the configuration is invalid.
+pub fn read_rewrite_config(filepath: &str) -> Vec<Rewrite<Language, MyAnalysis>> {
+    rewrites_from_config(
+        std::fs::read_to_string(filepath)
+            .unwrap_or_else(|e| panic!("Could not read {}: {}", filepath, e))
+            .as_str(),
+    )
+    .unwrap_or_else(|e| panic!("Invalid rewrite configuration {}: {}", filepath, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_demo_config() {
+        let rws = read_rewrite_config(
+            format!(
+                "{}/data/rewrite-configs/demo.json",
+                env!("CARGO_MANIFEST_DIR")
+            )
+            .as_str(),
+        );
+        assert_eq!(rws.len(), 22);
+    }
+
+    #[test]
+    fn demo_phases_config() {
+        let phases = read_phases_config(
+            format!(
+                "{}/data/rewrite-configs/demo-phases.json",
+                env!("CARGO_MANIFEST_DIR")
+            )
+            .as_str(),
+        );
+        assert_eq!(
+            phases
+                .iter()
+                .map(|phase| phase.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["normalize", "pad-and-tile", "tensorize"]
+        );
+        assert_eq!(
+            phases
+                .iter()
+                .map(|phase| phase.rewrites.len())
+                .sum::<usize>(),
+            22
+        );
+        assert!(phases[0].extract);
+        assert_eq!(phases[2].iter_limit, Some(10));
+    }
+
+    #[test]
+    fn phase_unknown_field() {
+        assert_eq!(
+            phases_from_config(r#"[{"name": "tensorize", "rewrites": [], "nodes": 10}]"#).err(),
+            Some(RewriteConfigError::UnknownParameter {
+                rewrite: "tensorize".to_string(),
+                parameter: "nodes".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn registry_names_unique() {
+        let registry = registry();
+        for (i, registered) in registry.iter().enumerate() {
+            assert!(registry[..i]
+                .iter()
+                .all(|other| other.name != registered.name));
+        }
+    }
+
+    #[test]
+    fn unknown_rewrite() {
+        assert_eq!(
+            rewrites_from_config(r#"[{"rewrite": "systolic_arary"}]"#).err(),
+            Some(RewriteConfigError::UnknownRewrite(
+                "systolic_arary".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn unknown_parameter() {
+        assert_eq!(
+            rewrites_from_config(r#"[{"rewrite": "systolic_array", "rows": 16}]"#).err(),
+            Some(RewriteConfigError::UnknownParameter {
+                rewrite: "systolic_array".to_string(),
+                parameter: "rows".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn missing_parameter() {
+        assert_eq!(
+            rewrites_from_config(r#"[{"rewrite": "systolic_array_with_blocking", "rows": 16}]"#)
+                .err(),
+            Some(RewriteConfigError::MissingParameter {
+                rewrite: "systolic_array_with_blocking".to_string(),
+                parameter: "cols".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_parameter() {
+        assert_eq!(
+            rewrites_from_config(
+                r#"[{
+                    "rewrite": "slice_concatenate_accesses",
+                    "axis": 0,
+                    "strategy": {"type": "divide_into", "segment_size": -64}
+                }]"#
+            )
+            .err(),
+            Some(RewriteConfigError::InvalidParameter {
+                rewrite: "slice_concatenate_accesses".to_string(),
+                parameter: "segment_size".to_string(),
+                expected: "a nonnegative integer".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn zero_parameter() {
+        assert_eq!(
+            rewrites_from_config(
+                r#"[{"rewrite": "systolic_array_with_blocking", "rows": 0, "cols": 16}]"#
+            )
+            .err(),
+            Some(RewriteConfigError::InvalidParameter {
+                rewrite: "systolic_array_with_blocking".to_string(),
+                parameter: "rows".to_string(),
+                expected: "a positive integer".to_string()
+            })
+        );
+        for strategy in &[
+            r#""slice_concatenate_accesses", "strategy": {"type": "divide_into", "segment_size": 0}"#,
+            r#""slice_concatenate_accesses", "strategy": {"type": "divide_by", "divisor": 0, "limit": 4}"#,
+            r#""pad_slice_accesses", "strategy": {"type": "pad_to_closest_multiple_of", "multiple_of": 0, "pad_location": "end", "pad_type": "zero-padding"}"#,
+            r#""pad_slice_accesses", "strategy": {"type": "pad_to_multiples_of", "multiples_of": 0, "limit": 4, "pad_location": "end", "pad_type": "zero-padding"}"#,
+        ] {
+            match rewrites_from_config(
+                format!(r#"[{{"rewrite": {}, "axis": 0}}]"#, strategy).as_str(),
+            ) {
+                Err(RewriteConfigError::InvalidParameter { expected, .. }) => {
+                    assert_eq!(expected, "a positive integer")
+                }
+                _ => panic!("Expected {} to be rejected", strategy),
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_strategy() {
+        assert!(matches!(
+            rewrites_from_config(
+                r#"[{
+                    "rewrite": "pad_slice_accesses",
+                    "axis": 0,
+                    "strategy": {"type": "pad_to_nearest", "multiple_of": 64}
+                }]"#
+            ),
+            Err(RewriteConfigError::InvalidParameter { .. })
+        ));
+    }
+
+    #[test]
+    fn duplicate_entry() {
+        assert!(matches!(
+            rewrites_from_config(
+                r#"[{"rewrite": "systolic_array"}, {"rewrite": "systolic_array"}]"#
+            ),
+            Err(RewriteConfigError::DuplicateEntry(_))
+        ));
+    }
+
+    #[test]
+    fn not_a_list() {
+        assert_eq!(
+            rewrites_from_config(r#"{"rewrite": "systolic_array"}"#).err(),
+            Some(RewriteConfigError::NotAList)
+        );
+    }
+}
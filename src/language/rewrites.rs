@@ -85,10 +85,15 @@ fn not_item_axis(axis: Var, access: Var) -> impl Fn(&mut EG, egg::Id, &egg::Subs
     }
 }
 
-fn item_axis(axis: Var, access: Var) -> impl Fn(&mut EG, egg::Id, &egg::Subst) -> bool {
+/// Whether the axis is an item axis of the access, other than the first item
+/// axis, which is the tuple axis when computing a dot product over the access.
+fn item_axis_not_tuple_axis(
+    axis: Var,
+    access: Var,
+) -> impl Fn(&mut EG, egg::Id, &egg::Subst) -> bool {
     move |egraph, _id, subst| match &egraph[subst[access]].data {
         MyAnalysisData::AccessPattern(a) => {
-            MyAnalysis::get_usize(subst[axis], egraph) >= a.shape.ndim()
+            MyAnalysis::get_usize(subst[axis], egraph) > a.shape.ndim()
         }
         _ => panic!(),
     }
@@ -160,55 +165,61 @@ pub struct RewriteNonMatchingCartConcatenateApplier {
 impl egg::Applier<Language, MyAnalysis> for RewriteNonMatchingCartConcatenateApplier {
     fn apply_one(
         &self,
-        _egraph: &mut EG,
-        _id: egg::Id,
-        _subst: &egg::Subst,
+        egraph: &mut EG,
+        id: egg::Id,
+        subst: &egg::Subst,
     ) -> std::vec::Vec<egg::Id> {
         // For now, just want to handle these cases.
         assert!(self.a_axis == 0 || self.a_axis == 1);
         assert!(self.b_axis == 0 || self.b_axis == 1);
         assert_ne!(self.a_axis, self.b_axis);
 
-        // We will break up the as into smaller chunks and the bs into
-        // smaller chunks, so that they all match in size.
-        // The goal is to have the innermost concatenates be along axis 0, and
-        // the outermost concatenates to be along axis 1. Additionally, the goal
-        // is that the result should only involve cartesian products of
-        // concatenates, where the left and right concatenate use the same axis.
-        // Then, existing rewrites can be used to bubble the concatenates up
-        // through the cartesian products.
-
-        // Each a needs to be split into 4; each b needs to be split into 4.
-
-        // First we want to construct all of the concatenates along the 1 axis.
-        // These will become our innermost concatenates.
-        // One of these is already concatenateted along the 1 axis!
-
-        // TODO(@gussmith23) left off here, I think I should actually do something
-        // simpler here and just rewrite the two concatenates that are the
-        // children of this cartesian product.
-        // It needs some information from elsewhere in the graph, though,
-        // that's the tough thing.
-
-        // So we're going to slice-and-concatenate all 4 tensors. We'll slice the
-        // as based on the bs size, and slice the bs based on the as size.
-        // TODO(@gussmith23) I could write an even simpler rewrite rule that slices
-        // more indiscriminately, everywhere. Right now I'm using some
-        // context clue (the overarching cartesian product) to only apply
-        // this where needed.
-
-        // All I actually want to do is to rewrite that second concatenate.
+        // The goal is that the result should only involve cartesian products
+        // of concatenates, where the left and right concatenate use the same
+        // axis. Then, existing rewrites can be used to bubble the concatenates
+        // up through the cartesian products.
+        //
+        // So we rewrite the second concatenate: we slice the bs along the as'
+        // concatenate axis, where the as are split, and concatenate the slices
+        // back together along that axis.
         //  (cartesian-product
         //   (concatenate ?a1 ?a2 0)
         //   (concatenate ?b1 ?b2 1)
         //  )
         //  (cartesian-product
         //   (concatenate ?a1 ?a2 0)
-        //   (concatenate (concatenate (slice ?b1) (slice ?b1)  0)
+        //   (concatenate
+        //    (concatenate (slice ?b1 0 0 split) (slice ?b2 0 0 split) 1)
+        //    (concatenate (slice ?b1 0 split len) (slice ?b2 0 split len) 1)
+        //    0)
         //  )
-        //
+        let split = MyAnalysis::get_shape(subst[self.a1], egraph)[self.a_axis];
+        let len = MyAnalysis::get_shape(subst[self.b1], egraph)[self.a_axis];
+        if split == 0 || split >= len {
+            return vec![];
+        }
 
-        vec![]
+        format!(
+            "(cartesian-product
+              (concatenate ?a1 ?a2 {a_axis})
+              (concatenate
+               (concatenate
+                (slice ?b1 {a_axis} 0 {split})
+                (slice ?b2 {a_axis} 0 {split})
+                {b_axis})
+               (concatenate
+                (slice ?b1 {a_axis} {split} {len})
+                (slice ?b2 {a_axis} {split} {len})
+                {b_axis})
+               {a_axis}))",
+            a_axis = self.a_axis,
+            b_axis = self.b_axis,
+            split = split,
+            len = len
+        )
+        .parse::<Pattern<Language>>()
+        .unwrap()
+        .apply_one(egraph, id, subst)
     }
 }
 
@@ -846,6 +857,24 @@ pub fn bubble_reshape_through_compute_dot_product() -> RW {
             _ => false,
         }
     }
+    /// The dot product is only the same before and after the reshape if the
+    /// reshape doesn't move data between items, and doesn't change the size
+    /// of the tuple axis.
+    fn reshape_preserves_items(
+        a: Var,
+        shape: Var,
+    ) -> impl Fn(&mut EG, egg::Id, &egg::Subst) -> bool {
+        move |egraph, _, subst| match (&egraph[subst[a]].data, &egraph[subst[shape]].data) {
+            (MyAnalysisData::AccessPattern(a), MyAnalysisData::AccessPattern(shape)) => {
+                a.item_shape.ndim() > 0
+                    && shape.item_shape.ndim() > 0
+                    && a.item_shape[0] == shape.item_shape[0]
+                    && a.item_shape.slice().iter().product::<usize>()
+                        == shape.item_shape.slice().iter().product::<usize>()
+            }
+            _ => false,
+        }
+    }
     struct ApplierImpl(Var);
     impl Applier<Language, MyAnalysis> for ApplierImpl {
         fn apply_one(&self, egraph: &mut EG, eclass: Id, subst: &Subst) -> Vec<Id> {
@@ -871,7 +900,8 @@ pub fn bubble_reshape_through_compute_dot_product() -> RW {
     rewrite!("bubble-reshape-through-compute";
              "(compute ?op (access-reshape ?a ?shape))" =>
              { ApplierImpl("?shape".parse().unwrap()) }
-             if is_dot_product("?op".parse().unwrap()))
+             if is_dot_product("?op".parse().unwrap())
+             if reshape_preserves_items("?a".parse().unwrap(), "?shape".parse().unwrap()))
 }
 
 /// Tensorizes a computation to an externally-blocked systolic array.
@@ -977,6 +1007,96 @@ pub fn systolic_array() -> Rewrite<Language, MyAnalysis> {
                                  |a| a.shape.ndim() == 1 && a.item_shape.ndim() == 1))
 }
 
+/// Maps a batched matrix multiply, as imported from Relay's `nn.batch_matmul`
+/// (see `from_relay::batch_matmul`), onto
+/// systolic arrays. The multiply is unrolled into one systolic array per
+/// batch, whose results are concatenated. A side with a batch of 1 is
+/// broadcast, i.e. used in every batch.
+pub fn systolic_array_batch_matmul() -> Rewrite<Language, MyAnalysis> {
+    struct ApplierImpl {
+        a: Var,
+        b: Var,
+    }
+    impl Applier<Language, MyAnalysis> for ApplierImpl {
+        fn apply_one(&self, egraph: &mut EG, eclass: Id, subst: &Subst) -> Vec<Id> {
+            let (a, b) = match (&egraph[subst[self.a]].data, &egraph[subst[self.b]].data) {
+                (MyAnalysisData::AccessPattern(a), MyAnalysisData::AccessPattern(b)) => {
+                    (a.as_vec(), b.as_vec())
+                }
+                _ => panic!(),
+            };
+            assert_eq!(a.len(), 3);
+            assert_eq!(b.len(), 3);
+            assert!(a[0] == b[0] || a[0] == 1 || b[0] == 1);
+            assert_eq!(a[2], b[2]);
+            let (batch, rows, cols) = (std::cmp::max(a[0], b[0]), a[2], b[1]);
+            // The batch of `access` to use for batch `i` of the result.
+            let batch_index = |access: &[usize], i: usize| if access[0] == 1 { 0 } else { i };
+
+            // One systolic array per batch.
+            let mut batches = (0..batch).map(|i| {
+                format!(
+                    "(access-insert-axis
+                      (systolic-array {rows} {cols}
+                       (access (access-squeeze (access-slice (access ?a 0) 0 {a_i} {a_j}) 0) 1)
+                       (access
+                        (access-transpose
+                         (access-squeeze (access-slice (access ?b 0) 0 {b_i} {b_j}) 0)
+                         (list 1 0)
+                        )
+                        0
+                       )
+                      )
+                      0
+                     )",
+                    rows = rows,
+                    cols = cols,
+                    a_i = batch_index(&a, i),
+                    a_j = batch_index(&a, i) + 1,
+                    b_i = batch_index(&b, i),
+                    b_j = batch_index(&b, i) + 1,
+                )
+            });
+            let first = batches.next().unwrap();
+            let pattern: Pattern<Language> = batches
+                .fold(first, |concatenated, next| {
+                    format!("(access-concatenate {} {} 0)", concatenated, next)
+                })
+                .parse()
+                .unwrap();
+
+            pattern.apply_one(egraph, eclass, subst)
+        }
+    }
+
+    // The accesses should be [batch, m, k] and [batch, n, k], where either
+    // batch may be 1.
+    let batches_match = constrain_vars(
+        vec!["?a".parse().unwrap(), "?b".parse().unwrap()],
+        |data| match (&data[0], &data[1]) {
+            (MyAnalysisData::AccessPattern(a), MyAnalysisData::AccessPattern(b)) => {
+                let (a, b) = (a.as_vec(), b.as_vec());
+                a.len() == 3
+                    && b.len() == 3
+                    && (a[0] == b[0] || a[0] == 1 || b[0] == 1)
+                    && a[2] == b[2]
+            }
+            _ => false,
+        },
+    );
+
+    rewrite!("systolic-array-batch-matmul";
+             "(compute dot-product
+               (access-pair
+                (access (access-broadcast (access-insert-axis ?a 2) ?a-shape) 3)
+                (access (access-broadcast (access-insert-axis ?b 1) ?b-shape) 3)
+               )
+              )
+             " =>
+             { ApplierImpl{a: "?a".parse().unwrap(), b: "?b".parse().unwrap(),}}
+             if batches_match)
+}
+
 pub enum SliceConcatenateStrategy {
     /// Divides the axis by `divisor`; does not divide anything less than or
     /// equal to `limit`.
@@ -1436,7 +1556,9 @@ pub fn bubble_access_concatenate_through_access_cartesian_product_same_item_axis
 /// whether you're concatenating along an item axis or not. If you are not, it's
 /// easy: you just bubble it straight through. If you are, then you are
 /// concatenating along an axis that's getting reduced in the reduction sum. So
-/// we need to explicitly insert another reduction.
+/// we need to explicitly insert another reduction. We can't bubble through
+/// concatenations along the tuple axis (the first item axis), as the tuple
+/// elements are multiplied together, not summed.
 pub fn bubble_access_concatenate_through_compute_dot_product_not_item_axis(
 ) -> Rewrite<Language, MyAnalysis> {
     rewrite!("bubble-access-concatenate-through-compute-dot-product-not-item-axis";
@@ -1459,7 +1581,7 @@ pub fn bubble_access_concatenate_through_compute_dot_product_item_axis(
                 (compute dot-product ?a1)
                )
               )"
-             if item_axis("?axis".parse().unwrap(), "?a0".parse().unwrap()))
+             if item_axis_not_tuple_axis("?axis".parse().unwrap(), "?a0".parse().unwrap()))
 }
 
 pub fn bubble_access_concatenate_through_access() -> Rewrite<Language, MyAnalysis> {
@@ -1988,7 +2110,7 @@ pub fn bubble_access_slice_through_compute_dot_product_item_axis_not_tuple_axis(
     rewrite!("bubble-access-slice-through-compute-dot-product-item-axis";
              "(compute dot-product (access-slice ?a ?axis ?low ?high))" =>
              "(compute dot-product ?a)"
-             if item_axis("?axis".parse().unwrap(), "?a".parse().unwrap())
+             if item_axis_not_tuple_axis("?axis".parse().unwrap(), "?a".parse().unwrap())
              // This checks that everything outside of the sliced region in
              // this axis is zero.
              if constrain_vars(vec!["?a".parse().unwrap(), "?axis".parse().unwrap(), "?low".parse().unwrap(), "?high".parse().unwrap()],
@@ -2085,6 +2207,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn rewrite_nonmatching_cartesian_product_concatenate() {
+        test_logger::ensure_env_logger_initialized();
+
+        let program = "(cartesian-product (concatenate a1 a2 0) (concatenate b1 b2 1))"
+            .parse()
+            .unwrap();
+
+        let mut map = HashMap::default();
+        map.insert("a1".to_string(), vec![2, 4]);
+        map.insert("a2".to_string(), vec![3, 4]);
+        map.insert("b1".to_string(), vec![5, 1]);
+        map.insert("b2".to_string(), vec![5, 3]);
+        let mut egraph = EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&program);
+
+        let rws = vec![super::rewrite_nonmatching_cartesian_product_concatenate()];
+        let runner = Runner::<_, _, ()>::new(MyAnalysis::default())
+            .with_egraph(egraph)
+            .with_iter_limit(1)
+            .run(&rws);
+
+        let matches = "
+         (cartesian-product
+          (concatenate a1 a2 0)
+          (concatenate
+           (concatenate (slice b1 0 0 2) (slice b2 0 0 2) 1)
+           (concatenate (slice b1 0 2 5) (slice b2 0 2 5) 1)
+           0
+          )
+         )"
+        .parse::<Pattern<_>>()
+        .unwrap()
+        .search_eclass(&runner.egraph, runner.egraph.find(id))
+        .unwrap();
+        assert_eq!(matches.substs.len(), 1);
+    }
+
     #[test]
     fn slice_move_axis() {
         test_logger::ensure_env_logger_initialized();
@@ -2937,7 +3100,9 @@ mod tests {
             .with_egraph(egraph)
             .run(&rws);
 
-        let matches = "
+        // Axis 1 is the tuple axis, whose elements are multiplied together,
+        // so the concatenate can't be bubbled through as a sum.
+        assert!("
              (compute reduce-sum
               (access-pair
                (compute dot-product (access (access-tensor t-3-32-32) 1))
@@ -2948,8 +3113,7 @@ mod tests {
         .parse::<Pattern<_>>()
         .unwrap()
         .search_eclass(&runner.egraph, id)
-        .unwrap();
-        assert_eq!(matches.substs.len(), 1);
+        .is_none());
     }
 
     #[test]
@@ -3213,8 +3377,10 @@ mod tests {
         .unwrap();
         let mut map = HashMap::default();
         map.insert("t".to_string(), vec![1, 2, 3, 4]);
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         let rws = vec![super::collapse_nested_transposes()];
         let runner = Runner::<_, _, ()>::default().with_egraph(egraph).run(&rws);
@@ -3239,8 +3405,10 @@ mod tests {
         .unwrap();
         let mut map = HashMap::default();
         map.insert("t".to_string(), vec![1, 2, 3, 4]);
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         let rws = vec![super::remove_trivial_transpose()];
         let runner = Runner::<_, _, ()>::default().with_egraph(egraph).run(&rws);
@@ -3258,8 +3426,10 @@ mod tests {
         let program = "(access (access (access-tensor t) 0) 1)".parse().unwrap();
         let mut map = HashMap::default();
         map.insert("t".to_string(), vec![1, 2, 3, 4]);
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         let rws = vec![super::collapse_nested_accesses()];
         let runner = Runner::<_, _, ()>::default().with_egraph(egraph).run(&rws);
@@ -3277,8 +3447,10 @@ mod tests {
         let program = "(access (access-tensor t) 0)".parse().unwrap();
         let mut map = HashMap::default();
         map.insert("t".to_string(), vec![1, 2, 3, 4]);
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         let rws = vec![super::pad_slice_accesses(
             0,
@@ -3346,8 +3518,10 @@ mod tests {
         // kernel height, kernel width, in channels, out channels
         map.insert("weights".to_string(), vec![3, 3, 3, 8]);
 
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&expr);
 
         let rws = vec![
@@ -3541,8 +3715,10 @@ mod tests {
         .unwrap();
         let mut map = HashMap::default();
         map.insert("t".to_string(), vec![8, 10]);
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         let rws = vec![super::bubble_access_slice_through_access_pad_inequal_axes()];
         let runner = Runner::<_, _, ()>::default().with_egraph(egraph).run(&rws);
@@ -3568,8 +3744,10 @@ mod tests {
         let program = "(access (access-tensor t) 0)".parse().unwrap();
         let mut map = HashMap::default();
         map.insert("t".to_string(), vec![1, 2, 3, 4]);
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         let rws = vec![
             super::pad_slice_accesses(
@@ -3677,8 +3855,10 @@ mod tests {
         let mut map = HashMap::default();
         map.insert("a".to_string(), vec![4, 3, 3, 4]);
         map.insert("b".to_string(), vec![10, 3, 3, 4]);
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         let rws =
             vec![super::bubble_access_slice_through_access_cartesian_product_not_item_axis_left()];
@@ -3713,8 +3893,10 @@ mod tests {
         let mut map = HashMap::default();
         map.insert("a".to_string(), vec![4, 3, 3, 4]);
         map.insert("b".to_string(), vec![10, 3, 3, 4]);
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         let rws =
             vec![super::bubble_access_slice_through_access_cartesian_product_not_item_axis_right()];
@@ -3749,8 +3931,10 @@ mod tests {
         let mut map = HashMap::default();
         map.insert("a".to_string(), vec![4, 16, 3, 3, 4]);
         map.insert("b".to_string(), vec![10, 3, 3, 4]);
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         let rws =
             vec![super::bubble_access_slice_through_access_cartesian_product_same_item_axis()];
@@ -3786,8 +3970,10 @@ mod tests {
         .unwrap();
         let mut map = HashMap::default();
         map.insert("a".to_string(), vec![4, 16, 3, 3, 4]);
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         let rws = vec![super::bubble_access_slice_through_compute_dot_product_not_item_axis()];
         let runner = Runner::<_, _, ()>::new(MyAnalysis::default())
@@ -3824,8 +4010,10 @@ mod tests {
         .unwrap();
         let mut map = HashMap::default();
         map.insert("a".to_string(), vec![4, 16, 3, 3, 4]);
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
         let rws =
             vec![super::bubble_access_slice_through_compute_dot_product_item_axis_not_tuple_axis()];
@@ -3864,8 +4052,10 @@ mod tests {
         "
         .parse()
         .unwrap();
-        let mut egraph =
-            egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
         let id = egraph.add_expr(&program);
 
         let rws = vec![
@@ -3957,4 +4147,159 @@ mod tests {
         .search_eclass(&runner.egraph, id);
         assert!(matches.is_none());
     }
+
+    #[test]
+    fn systolic_array_batch_matmul() {
+        let mut map = HashMap::default();
+        map.insert("a".to_string(), vec![2, 3, 4]);
+        map.insert("b".to_string(), vec![2, 5, 4]);
+        let program = "
+         (compute dot-product
+          (access-pair
+           (access
+            (access-broadcast
+             (access-insert-axis (access-tensor a) 2)
+             (access-shape (shape 2 3 5 4) (shape))
+            )
+            3
+           )
+           (access
+            (access-broadcast
+             (access-insert-axis (access-tensor b) 1)
+             (access-shape (shape 2 3 5 4) (shape))
+            )
+            3
+           )
+          )
+         )
+        "
+        .parse()
+        .unwrap();
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&program);
+
+        let rws = vec![super::systolic_array_batch_matmul()];
+
+        let runner = Runner::<_, _, ()>::new(MyAnalysis::default())
+            .with_egraph(egraph)
+            .run(&rws);
+
+        let matches = "
+          (access-concatenate
+           (access-insert-axis
+            (systolic-array 4 5
+             (access (access-squeeze (access-slice (access (access-tensor a) 0) 0 0 1) 0) 1)
+             (access
+              (access-transpose
+               (access-squeeze (access-slice (access (access-tensor b) 0) 0 0 1) 0)
+               (list 1 0)
+              )
+              0
+             )
+            )
+            0
+           )
+           (access-insert-axis
+            (systolic-array 4 5
+             (access (access-squeeze (access-slice (access (access-tensor a) 0) 0 1 2) 0) 1)
+             (access
+              (access-transpose
+               (access-squeeze (access-slice (access (access-tensor b) 0) 0 1 2) 0)
+               (list 1 0)
+              )
+              0
+             )
+            )
+            0
+           )
+           0
+          )
+            "
+        .parse::<Pattern<_>>()
+        .unwrap()
+        .search_eclass(&runner.egraph, id)
+        .unwrap();
+        assert_eq!(matches.substs.len(), 1);
+    }
+
+    #[test]
+    fn systolic_array_batch_matmul_broadcast() {
+        let mut map = HashMap::default();
+        map.insert("a".to_string(), vec![2, 3, 4]);
+        map.insert("b".to_string(), vec![1, 5, 4]);
+        let program = "
+         (compute dot-product
+          (access-pair
+           (access
+            (access-broadcast
+             (access-insert-axis (access-tensor a) 2)
+             (access-shape (shape 2 3 5 4) (shape))
+            )
+            3
+           )
+           (access
+            (access-broadcast
+             (access-insert-axis (access-tensor b) 1)
+             (access-shape (shape 2 3 5 4) (shape))
+            )
+            3
+           )
+          )
+         )
+        "
+        .parse()
+        .unwrap();
+        let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+            name_to_shape: map,
+            name_to_dtype: HashMap::default(),
+        });
+        let id = egraph.add_expr(&program);
+
+        let rws = vec![super::systolic_array_batch_matmul()];
+
+        let runner = Runner::<_, _, ()>::new(MyAnalysis::default())
+            .with_egraph(egraph)
+            .run(&rws);
+
+        // The only batch of b is used for both batches of a.
+        let matches = "
+          (access-concatenate
+           (access-insert-axis
+            (systolic-array 4 5
+             (access (access-squeeze (access-slice (access (access-tensor a) 0) 0 0 1) 0) 1)
+             (access
+              (access-transpose
+               (access-squeeze (access-slice (access (access-tensor b) 0) 0 0 1) 0)
+               (list 1 0)
+              )
+              0
+             )
+            )
+            0
+           )
+           (access-insert-axis
+            (systolic-array 4 5
+             (access (access-squeeze (access-slice (access (access-tensor a) 0) 0 1 2) 0) 1)
+             (access
+              (access-transpose
+               (access-squeeze (access-slice (access (access-tensor b) 0) 0 0 1) 0)
+               (list 1 0)
+              )
+              0
+             )
+            )
+            0
+           )
+           0
+          )
+            "
+        .parse::<Pattern<_>>()
+        .unwrap()
+        .search_eclass(&runner.egraph, id)
+        .unwrap();
+        assert_eq!(matches.substs.len(), 1);
+    }
 }
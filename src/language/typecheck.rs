@@ -0,0 +1,257 @@
+//! Checks that a program is well-typed before it goes into an e-graph.
+//!
+//! [`MyAnalysis::make`] panics deep inside [`EGraph::add_expr`] when it's given
+//! a malformed program. [`typecheck`] runs the same checks on each node before
+//! adding it, so that malformed programs can be reported to the user with the
+//! offending node and where it is in the program.
+
+use super::validate::check;
+use super::{Language, MyAnalysis, MyAnalysisData};
+use crate::hw_design_language::DType;
+use egg::{EGraph, Id, Language as LanguageTrait, RecExpr};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+
+/// A node in a program whose arguments don't have the types or shapes it
+/// expects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlensideTypeError {
+    /// The index of the offending node in the program.
+    pub index: usize,
+    /// The offending node's operator, e.g. `access-slice`.
+    pub node: String,
+    /// How to get from the root of the program to the offending node: the
+    /// operator of each node along the way, and which of its arguments to
+    /// follow.
+    pub path: Vec<(String, usize)>,
+    /// What's wrong with the node.
+    pub problem: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl Display for GlensideTypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} in {} (node {}, at {}): expected {}, found {}",
+            self.problem,
+            self.node,
+            self.index,
+            self.path
+                .iter()
+                .map(|(operator, argument)| format!("{} > argument {} > ", operator, argument))
+                .chain(std::iter::once(self.node.clone()))
+                .collect::<String>(),
+            self.expected,
+            self.actual
+        )
+    }
+}
+
+impl std::error::Error for GlensideTypeError {}
+
+/// Finds a shortest path from the root of `expr` to the node at `index`.
+fn path_to(expr: &RecExpr<Language>, index: usize) -> Vec<(String, usize)> {
+    let nodes = expr.as_ref();
+    let root = nodes.len() - 1;
+    let mut parents: Vec<Option<(usize, usize)>> = vec![None; nodes.len()];
+    let mut visited = vec![false; nodes.len()];
+    visited[root] = true;
+    let mut queue = VecDeque::from(vec![root]);
+    while let Some(parent) = queue.pop_front() {
+        for (argument, &child) in nodes[parent].children().iter().enumerate() {
+            let child = usize::from(child);
+            if !visited[child] {
+                visited[child] = true;
+                parents[child] = Some((parent, argument));
+                queue.push_back(child);
+            }
+        }
+    }
+
+    let mut path = Vec::default();
+    let mut index = index;
+    while let Some((parent, argument)) = parents[index] {
+        path.push((nodes[parent].to_string(), argument));
+        index = parent;
+    }
+    path.reverse();
+    path
+}
+
+/// Checks that `expr` is well-typed, given the shapes of its inputs, and
+/// returns the analysis data of its root. Inputs are assumed to be float32.
+/// ```
+/// use egg::RecExpr;
+/// use glenside::language::typecheck::typecheck;
+/// use glenside::language::MyAnalysisData;
+/// use std::collections::HashMap;
+/// use std::str::FromStr;
+///
+/// let mut shapes = HashMap::default();
+/// shapes.insert("a".to_string(), vec![4, 6]);
+///
+/// let expr = RecExpr::from_str("(access-slice (access (access-tensor a) 1) 0 1 3)").unwrap();
+/// match typecheck(&expr, &shapes).unwrap() {
+///     MyAnalysisData::AccessPattern(a) => assert_eq!(a.as_vec(), vec![2, 6]),
+///     _ => panic!(),
+/// }
+///
+/// let expr =
+///     RecExpr::from_str("(compute relu (access-slice (access (access-tensor a) 1) 0 3 5))")
+///         .unwrap();
+/// let error = typecheck(&expr, &shapes).unwrap_err();
+/// assert_eq!(error.node, "access-slice");
+/// assert_eq!(error.path, vec![("compute".to_string(), 1)]);
+/// ```
+pub fn typecheck(
+    expr: &RecExpr<Language>,
+    name_to_shape: &HashMap<String, Vec<usize>>,
+) -> Result<MyAnalysisData, GlensideTypeError> {
+    typecheck_with_dtypes(expr, name_to_shape, &HashMap::default())
+}
+
+/// Like [`typecheck`], but with the dtype of each input which isn't float32.
+pub fn typecheck_with_dtypes(
+    expr: &RecExpr<Language>,
+    name_to_shape: &HashMap<String, Vec<usize>>,
+    name_to_dtype: &HashMap<String, DType>,
+) -> Result<MyAnalysisData, GlensideTypeError> {
+    let mut egraph = EGraph::new(MyAnalysis {
+        name_to_shape: name_to_shape.clone(),
+        name_to_dtype: name_to_dtype.clone(),
+    });
+    let mut ids: Vec<Id> = Vec::default();
+    for (index, node) in expr.as_ref().iter().enumerate() {
+        let node = node.clone().map_children(|id| ids[usize::from(id)]);
+        check(&egraph, &node).map_err(|problem| GlensideTypeError {
+            index,
+            node: node.to_string(),
+            path: path_to(expr, index),
+            problem: problem.problem,
+            expected: problem.expected,
+            actual: problem.actual,
+        })?;
+        ids.push(egraph.add(node));
+    }
+    Ok(egraph[*ids.last().expect("Empty program")].data.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn shapes() -> HashMap<String, Vec<usize>> {
+        let mut shapes = HashMap::default();
+        shapes.insert("a".to_string(), vec![4, 6]);
+        shapes.insert("b".to_string(), vec![3, 5]);
+        shapes
+    }
+
+    fn check_error(program: &str) -> GlensideTypeError {
+        typecheck(&RecExpr::from_str(program).unwrap(), &shapes()).unwrap_err()
+    }
+
+    #[test]
+    fn well_typed() {
+        let expr = RecExpr::from_str(
+            "(compute dot-product
+              (access-cartesian-product
+               (access (access-tensor a) 1)
+               (access (access-transpose (access-tensor b) (list 1 0)) 1)
+              )
+             )",
+        )
+        .unwrap();
+        match typecheck(&expr, &shapes()) {
+            Ok(MyAnalysisData::AccessPattern(a)) => {
+                assert_eq!(a.shape.slice(), &[4, 5]);
+                assert_eq!(a.item_shape.slice(), &[] as &[usize]);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn transpose_list_wrong_length() {
+        let error = check_error("(access-transpose (access (access-tensor a) 1) (list 1 0 2))");
+        assert_eq!(error.node, "access-transpose");
+        assert!(error.path.is_empty());
+        assert_eq!(error.expected, "a permutation of 0..2");
+        assert_eq!(error.actual, "[1, 0, 2]");
+    }
+
+    #[test]
+    fn slice_out_of_range() {
+        let error = check_error(
+            "(access-cartesian-product
+              (access (access-tensor b) 1)
+              (access-slice (access (access-tensor a) 1) 1 2 7)
+             )",
+        );
+        assert_eq!(error.node, "access-slice");
+        assert_eq!(
+            error.path,
+            vec![("access-cartesian-product".to_string(), 1)]
+        );
+        assert_eq!(error.actual, "low 2 and high 7");
+    }
+
+    #[test]
+    fn cartesian_product_item_shapes_differ() {
+        let error = check_error(
+            "(access-cartesian-product (access (access-tensor a) 1) (access (access-tensor b) 1))",
+        );
+        assert_eq!(error.node, "access-cartesian-product");
+        assert_eq!(error.expected, "[6]");
+        assert_eq!(error.actual, "[5]");
+    }
+
+    #[test]
+    fn unknown_tensor() {
+        let error = check_error("(access (access-tensor c) 1)");
+        assert_eq!(error.node, "c");
+        assert_eq!(
+            error.path,
+            vec![("access".to_string(), 0), ("access-tensor".to_string(), 0)]
+        );
+    }
+
+    #[test]
+    fn wrong_kind_of_argument() {
+        let error = check_error("(access (access-tensor a) (list 1))");
+        assert_eq!(error.node, "access");
+        assert_eq!(error.expected, "a usize");
+        assert_eq!(error.actual, "the list [1]");
+    }
+
+    #[test]
+    fn dot_product_of_scalars() {
+        let error = check_error("(compute dot-product (access (access-tensor a) 2))");
+        assert_eq!(error.expected, "an item shape with at least one axis");
+    }
+
+    #[test]
+    fn clip_bounds_out_of_order() {
+        let error = check_error("(compute (clip 6 -1.5) (access (access-tensor a) 1))");
+        assert_eq!(error.node, "clip");
+        assert_eq!(error.path, vec![("compute".to_string(), 0)]);
+        assert_eq!(error.expected, "a max of at least 6");
+        assert_eq!(error.actual, "-1.5");
+    }
+
+    #[test]
+    fn display() {
+        let error = check_error("(compute relu (access-slice (access (access-tensor a) 1) 0 3 5))");
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "slice out of range in access-slice (node {}, at compute > argument 1 > access-slice): \
+                 expected 0 <= low <= high <= 4, with low < 4, found low 3 and high 5",
+                error.index
+            )
+        );
+    }
+}
@@ -0,0 +1,722 @@
+//! Checks that a node's arguments have the kinds, shapes and dtypes it
+//! expects.
+//!
+//! Both [`MyAnalysis::make`] and [`typecheck`](super::typecheck::typecheck)
+//! run [`check`] on each node before computing its analysis data: `make`
+//! panics on a [`Problem`], while `typecheck` reports it with where the node
+//! is in the program.
+
+use super::{
+    AccessPatternData, AccessShapeData, ComputeType, Language, MyAnalysis, MyAnalysisData, PadType,
+};
+use crate::hw_design_language::DType;
+use egg::{EGraph, Id, Language as LanguageTrait};
+use ndarray::{Dimension, IxDyn};
+use std::fmt::Display;
+
+/// What's wrong with a node, before we know where it is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub problem: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: expected {}, found {}",
+            self.problem, self.expected, self.actual
+        )
+    }
+}
+
+fn problem(problem: impl Display, expected: impl Display, actual: impl Display) -> Problem {
+    Problem {
+        problem: problem.to_string(),
+        expected: expected.to_string(),
+        actual: actual.to_string(),
+    }
+}
+
+/// Fails with `problem` unless `condition` holds.
+fn require(condition: bool, problem_fn: impl FnOnce() -> Problem) -> Result<(), Problem> {
+    if condition {
+        Ok(())
+    } else {
+        Err(problem_fn())
+    }
+}
+
+pub fn describe(data: &MyAnalysisData) -> String {
+    match data {
+        MyAnalysisData::AccessPattern(a) => format!(
+            "an access pattern of shape {:?} and item shape {:?}",
+            a.shape.slice(),
+            a.item_shape.slice()
+        ),
+        MyAnalysisData::Legacy(l) => match (l.usize_value, &l.shape) {
+            (Some(u), _) => format!("the usize {}", u),
+            (None, Some(shape)) => format!("a tensor of shape {:?}", shape.slice()),
+            (None, None) => "an untyped value".to_string(),
+        },
+        MyAnalysisData::Shape(_) => "a shape".to_string(),
+        MyAnalysisData::List(l) => format!("the list {:?}", l),
+        MyAnalysisData::ComputeType(t) => format!("the compute type {}", t),
+        MyAnalysisData::PadType(t) => format!("the pad type {}", t),
+        MyAnalysisData::DType(t) => format!("the dtype {}", t),
+        MyAnalysisData::AccessShape(s) => format!(
+            "an access shape of shape {:?} and item shape {:?}",
+            s.shape.slice(),
+            s.item_shape.slice()
+        ),
+        MyAnalysisData::Literal(_) => "a literal".to_string(),
+    }
+}
+
+/// The arguments of a node which has been mapped into an e-graph.
+struct Arguments<'a> {
+    egraph: &'a EGraph<Language, MyAnalysis>,
+    ids: &'a [Id],
+}
+
+impl<'a> Arguments<'a> {
+    fn data(&self, argument: usize) -> &'a MyAnalysisData {
+        &self.egraph[self.ids[argument]].data
+    }
+
+    fn wrong_kind(&self, argument: usize, expected: &str) -> Problem {
+        problem(
+            format!("wrong kind of value for argument {}", argument),
+            expected,
+            describe(self.data(argument)),
+        )
+    }
+
+    fn access(&self, argument: usize) -> Result<&'a AccessPatternData, Problem> {
+        match self.data(argument) {
+            MyAnalysisData::AccessPattern(a) => Ok(a),
+            _ => Err(self.wrong_kind(argument, "an access pattern")),
+        }
+    }
+
+    fn access_shape(&self, argument: usize) -> Result<&'a AccessShapeData, Problem> {
+        match self.data(argument) {
+            MyAnalysisData::AccessShape(s) => Ok(s),
+            _ => Err(self.wrong_kind(argument, "an access shape")),
+        }
+    }
+
+    fn usize(&self, argument: usize) -> Result<usize, Problem> {
+        match self.data(argument) {
+            MyAnalysisData::Legacy(l) if l.usize_value.is_some() => Ok(l.usize_value.unwrap()),
+            _ => Err(self.wrong_kind(argument, "a usize")),
+        }
+    }
+
+    /// A number, which may have been parsed as either a usize or a float.
+    fn number(&self, argument: usize) -> Result<f64, Problem> {
+        match self.data(argument) {
+            MyAnalysisData::Legacy(l) if l.usize_value.is_some() => {
+                Ok(l.usize_value.unwrap() as f64)
+            }
+            MyAnalysisData::Literal(t) if t.ndim() == 0 => Ok(*t.first().unwrap()),
+            _ => Err(self.wrong_kind(argument, "a number")),
+        }
+    }
+
+    fn tensor(&self, argument: usize) -> Result<&'a IxDyn, Problem> {
+        match self.data(argument) {
+            MyAnalysisData::Legacy(l) if l.shape.is_some() => Ok(l.shape.as_ref().unwrap()),
+            // Datatype names are parsed as datatypes, never as symbols.
+            MyAnalysisData::DType(t) => Err(problem(
+                "tensors can't be named after dtypes",
+                "a tensor",
+                format!("the dtype {}", t),
+            )),
+            _ => Err(self.wrong_kind(argument, "a tensor")),
+        }
+    }
+
+    fn shape(&self, argument: usize) -> Result<&'a IxDyn, Problem> {
+        match self.data(argument) {
+            MyAnalysisData::Shape(_) => Ok(MyAnalysis::get_shape_of_value(
+                self.ids[argument],
+                self.egraph,
+            )),
+            _ => Err(self.wrong_kind(argument, "a shape")),
+        }
+    }
+
+    fn list(&self, argument: usize) -> Result<&'a Vec<usize>, Problem> {
+        match self.data(argument) {
+            MyAnalysisData::List(l) => Ok(l),
+            _ => Err(self.wrong_kind(argument, "a list")),
+        }
+    }
+
+    fn compute_type(&self, argument: usize) -> Result<&'a ComputeType, Problem> {
+        match self.data(argument) {
+            MyAnalysisData::ComputeType(t) => Ok(t),
+            _ => Err(self.wrong_kind(argument, "a compute type")),
+        }
+    }
+
+    fn pad_type(&self, argument: usize) -> Result<PadType, Problem> {
+        match self.data(argument) {
+            MyAnalysisData::PadType(t) => Ok(*t),
+            _ => Err(self.wrong_kind(argument, "a pad type")),
+        }
+    }
+
+    fn dtype(&self, argument: usize) -> Result<DType, Problem> {
+        match self.data(argument) {
+            MyAnalysisData::DType(t) => Ok(*t),
+            _ => Err(self.wrong_kind(argument, "a dtype")),
+        }
+    }
+
+    fn literal(&self, argument: usize) -> Result<(), Problem> {
+        match self.data(argument) {
+            MyAnalysisData::Literal(_) => Ok(()),
+            _ => Err(self.wrong_kind(argument, "a literal")),
+        }
+    }
+
+    /// The dtype of a tensor argument. Tensors which come from input symbols
+    /// always have one.
+    fn tensor_dtype(&self, argument: usize) -> Result<DType, Problem> {
+        match self.data(argument) {
+            MyAnalysisData::Legacy(l) if l.dtype.is_some() => Ok(l.dtype.unwrap()),
+            _ => Err(self.wrong_kind(argument, "a tensor with a dtype")),
+        }
+    }
+}
+
+fn ndim(a: &AccessPatternData) -> usize {
+    a.shape.ndim() + a.item_shape.ndim()
+}
+
+fn require_axis(axis: usize, a: &AccessPatternData) -> Result<(), Problem> {
+    require(axis < ndim(a), || {
+        problem(
+            "axis out of range",
+            format!("an axis less than {}", ndim(a)),
+            axis,
+        )
+    })
+}
+
+fn require_same_dtype(a0: DType, a1: DType) -> Result<(), Problem> {
+    require(a0 == a1, || {
+        problem("arguments have different dtypes", a0, a1)
+    })
+}
+
+/// Checks that the node `enode`, whose children are already in `egraph`, can
+/// be added to `egraph`.
+pub fn check(egraph: &EGraph<Language, MyAnalysis>, enode: &Language) -> Result<(), Problem> {
+    use Language::*;
+    let args = Arguments {
+        egraph,
+        ids: enode.children(),
+    };
+    match enode {
+        Usize(_) | NotNanFloat64(_) | PadType(_) | ComputeType(_) | DataType(_) => Ok(()),
+        Symbol(name) => require(egraph.analysis.get_input_shape(name).is_some(), || {
+            problem(
+                format!("unknown tensor {}", name),
+                "a tensor with a declared shape",
+                "no shape",
+            )
+        }),
+        List(_) | Shape(_) => {
+            for argument in 0..args.ids.len() {
+                args.usize(argument)?;
+            }
+            Ok(())
+        }
+        AccessLiteral(_) => {
+            args.literal(0)?;
+            args.dtype(1).map(|_| ())
+        }
+        Literal(_) => args.number(0).map(|_| ()),
+        AccessTensor(_) => {
+            args.tensor(0)?;
+            args.tensor_dtype(0).map(|_| ())
+        }
+        ShapeOf(_) => args.tensor(0).map(|_| ()),
+        AccessShiftRight(_) | AccessFlatten(_) => args.access(0).map(|_| ()),
+        Access(_) => {
+            let access = args.access(0)?;
+            let axis = args.usize(1)?;
+            require(axis <= ndim(access), || {
+                problem(
+                    "access axis out of range",
+                    format!("an axis of at most {}", ndim(access)),
+                    axis,
+                )
+            })
+        }
+        AccessTranspose(_) => {
+            let access = args.access(0)?;
+            let list = args.list(1)?;
+            let mut sorted = list.clone();
+            sorted.sort();
+            require(sorted == (0..ndim(access)).collect::<Vec<_>>(), || {
+                problem(
+                    "transpose is not a permutation of the access's axes",
+                    format!("a permutation of 0..{}", ndim(access)),
+                    format!("{:?}", list),
+                )
+            })
+        }
+        AccessBroadcast(_) => {
+            let access = args.access(0)?;
+            let to = args.access_shape(1)?;
+            let to_dims = to
+                .shape
+                .slice()
+                .iter()
+                .chain(to.item_shape.slice().iter())
+                .cloned()
+                .collect::<Vec<_>>();
+            let from_dims = access.as_vec();
+            require(
+                from_dims.len() == to_dims.len()
+                    && from_dims
+                        .iter()
+                        .zip(to_dims.iter())
+                        .all(|(from, to)| *from == 1 || from == to),
+                || {
+                    problem(
+                        "cannot broadcast",
+                        format!("a shape broadcastable to {:?}", to_dims),
+                        format!("{:?}", from_dims),
+                    )
+                },
+            )
+        }
+        AccessInsertAxis(_) => {
+            let access = args.access(0)?;
+            let axis = args.usize(1)?;
+            require(axis <= ndim(access), || {
+                problem(
+                    "axis out of range",
+                    format!("an axis of at most {}", ndim(access)),
+                    axis,
+                )
+            })
+        }
+        AccessSqueeze(_) => {
+            let access = args.access(0)?;
+            let axis = args.usize(1)?;
+            require_axis(axis, access)?;
+            require(access[axis] == 1, || {
+                problem(
+                    format!("cannot squeeze axis {}", axis),
+                    "an axis of length 1",
+                    format!("an axis of length {}", access[axis]),
+                )
+            })
+        }
+        AccessPad(_) => {
+            let access = args.access(0)?;
+            args.pad_type(1)?;
+            let axis = args.usize(2)?;
+            args.usize(3)?;
+            args.usize(4)?;
+            require_axis(axis, access)
+        }
+        AccessSlice(_) => {
+            let access = args.access(0)?;
+            let axis = args.usize(1)?;
+            let low = args.usize(2)?;
+            let high = args.usize(3)?;
+            require_axis(axis, access)?;
+            require(
+                low < access[axis] && low <= high && high <= access[axis],
+                || {
+                    problem(
+                        "slice out of range",
+                        format!(
+                            "0 <= low <= high <= {}, with low < {}",
+                            access[axis], access[axis]
+                        ),
+                        format!("low {} and high {}", low, high),
+                    )
+                },
+            )
+        }
+        AccessConcatenate(_) => {
+            let a0 = args.access(0)?;
+            let a1 = args.access(1)?;
+            let axis = args.usize(2)?;
+            require(a0.shape.ndim() == a1.shape.ndim(), || {
+                problem(
+                    "arguments are accessed at different axes",
+                    a0.shape.ndim(),
+                    a1.shape.ndim(),
+                )
+            })?;
+            require_axis(axis, a0)?;
+            let (mut dims0, mut dims1) = (a0.as_vec(), a1.as_vec());
+            require(dims0.len() == dims1.len(), || {
+                problem(
+                    "cannot concatenate",
+                    format!("a shape matching {:?} except on axis {}", dims0, axis),
+                    format!("{:?}", dims1),
+                )
+            })?;
+            dims0.remove(axis);
+            dims1.remove(axis);
+            require(dims0 == dims1, || {
+                problem(
+                    "cannot concatenate",
+                    format!("a shape matching {:?} except on axis {}", a0.as_vec(), axis),
+                    format!("{:?}", a1.as_vec()),
+                )
+            })?;
+            require_same_dtype(a0.dtype, a1.dtype)
+        }
+        AccessPair(_) => {
+            let a0 = args.access(0)?;
+            let a1 = args.access(1)?;
+            require(
+                a0.shape == a1.shape && a0.item_shape == a1.item_shape,
+                || {
+                    problem(
+                        "cannot pair",
+                        describe(args.data(0)),
+                        describe(args.data(1)),
+                    )
+                },
+            )?;
+            require_same_dtype(a0.dtype, a1.dtype)
+        }
+        AccessCartesianProduct(_) => {
+            let a0 = args.access(0)?;
+            let a1 = args.access(1)?;
+            require(a0.item_shape == a1.item_shape, || {
+                problem(
+                    "item shapes differ",
+                    format!("{:?}", a0.item_shape.slice()),
+                    format!("{:?}", a1.item_shape.slice()),
+                )
+            })?;
+            require_same_dtype(a0.dtype, a1.dtype)
+        }
+        AccessShape(_) => {
+            args.shape(0)?;
+            args.shape(1).map(|_| ())
+        }
+        AccessReshape(_) => {
+            let access = args.access(0)?;
+            let new_shape = args.access_shape(1)?;
+            let size = |shape: &IxDyn| shape.slice().iter().product::<usize>();
+            require(
+                size(&access.shape) == size(&new_shape.shape)
+                    && size(&access.item_shape) == size(&new_shape.item_shape),
+                || {
+                    problem(
+                        "reshape changes the number of items or the item size",
+                        format!(
+                            "a shape with {} items of size {}",
+                            size(&access.shape),
+                            size(&access.item_shape)
+                        ),
+                        format!(
+                            "shape {:?} and item shape {:?}",
+                            new_shape.shape.slice(),
+                            new_shape.item_shape.slice()
+                        ),
+                    )
+                },
+            )
+        }
+        Clip(_) => {
+            let min = args.number(0)?;
+            let max = args.number(1)?;
+            require(min <= max, || {
+                problem(
+                    "clip's bounds are out of order",
+                    format!("a max of at least {}", min),
+                    max,
+                )
+            })
+        }
+        LeakyReLU(_) => args.number(0).map(|_| ()),
+        Compute(_) => {
+            let compute_type = args.compute_type(0)?;
+            let access = args.access(1)?;
+            match compute_type {
+                self::ComputeType::Softmax => require(access.item_shape.ndim() == 1, || {
+                    problem(
+                        "softmax is only implemented over the last axis",
+                        "an item shape with one axis",
+                        format!("{:?}", access.item_shape.slice()),
+                    )
+                }),
+                // A dot product over an item shape with one axis is a "dot
+                // product" of scalars, which is just a sum.
+                self::ComputeType::ElementwiseAdd
+                | self::ComputeType::ElementwiseMul
+                | self::ComputeType::ElementwiseDiv
+                | self::ComputeType::DotProduct => require(access.item_shape.ndim() >= 1, || {
+                    problem(
+                        format!("{} needs operands to combine", compute_type),
+                        "an item shape with at least one axis",
+                        format!("{:?}", access.item_shape.slice()),
+                    )
+                }),
+                _ => Ok(()),
+            }
+        }
+        AccessWindows(_) | AccessWindowsDilated(_) => {
+            let access = args.access(0)?;
+            let filters = args.shape(1)?;
+            let strides = args.shape(2)?;
+            let dilations = match enode {
+                AccessWindowsDilated(_) => args.shape(3)?.clone(),
+                _ => IxDyn(vec![1; strides.ndim()].as_slice()),
+            };
+            // TODO(@gussmith23) Generalize AccessWindows to other accesses
+            require(access.item_shape.ndim() == 0, || {
+                problem(
+                    "windows can only be formed over scalar items",
+                    "an item shape of []",
+                    format!("{:?}", access.item_shape.slice()),
+                )
+            })?;
+            for (name, shape) in &[
+                ("filters", filters),
+                ("strides", strides),
+                ("dilations", &dilations),
+            ] {
+                require(shape.ndim() == access.shape.ndim(), || {
+                    problem(
+                        format!("{} have the wrong number of axes", name),
+                        access.shape.ndim(),
+                        shape.ndim(),
+                    )
+                })?;
+            }
+            require(
+                strides.slice().iter().all(|&stride| stride >= 1)
+                    && dilations.slice().iter().all(|&dilation| dilation >= 1),
+                || {
+                    problem(
+                        "strides and dilations must be positive",
+                        "positive strides and dilations",
+                        format!("{:?} and {:?}", strides.slice(), dilations.slice()),
+                    )
+                },
+            )?;
+            let dilated_filters = filters
+                .slice()
+                .iter()
+                .zip(dilations.slice().iter())
+                .map(|(&filter, &dilation)| filter.saturating_sub(1) * dilation + 1)
+                .collect::<Vec<_>>();
+            require(
+                access
+                    .shape
+                    .slice()
+                    .iter()
+                    .zip(dilated_filters.iter())
+                    .all(|(dim, filter)| dim >= filter),
+                || {
+                    problem(
+                        "windows are larger than the access",
+                        format!("windows no larger than {:?}", access.shape.slice()),
+                        format!("{:?}", dilated_filters),
+                    )
+                },
+            )
+        }
+        SliceShape(_) | ShapeInsertAxis(_) | ShapeRemoveAxis(_) => {
+            let shape = args.shape(0)?;
+            let axis = args.usize(1)?;
+            let in_range = match enode {
+                ShapeRemoveAxis(_) => axis < shape.ndim(),
+                _ => axis <= shape.ndim(),
+            };
+            require(in_range, || {
+                problem(
+                    "axis out of range",
+                    format!("an axis of a shape with {} axes", shape.ndim()),
+                    axis,
+                )
+            })
+        }
+        SystolicArray(_) | SystolicArrayWithBlocking(_) => {
+            let rows = args.usize(0)?;
+            let cols = args.usize(1)?;
+            let a0 = args.access(2)?;
+            let a1 = args.access(3)?;
+            require(a1.shape.ndim() == 0 && a0.shape.ndim() <= 1, || {
+                problem(
+                    "systolic array arguments are accessed at the wrong axes",
+                    "a vector or a batch of vectors, and a matrix",
+                    format!("{} and {}", describe(args.data(2)), describe(args.data(3))),
+                )
+            })?;
+            require_same_dtype(a0.dtype, a1.dtype)?;
+            let fits = match enode {
+                SystolicArray(_) => {
+                    a0.item_shape.slice() == [rows] && a1.item_shape.slice() == [rows, cols]
+                }
+                // Scott: The input vector size should be a multiple of the
+                // systolic array's height and the output vector size should be
+                // a multiple of the systolic array's width.
+                _ => {
+                    a0.item_shape.ndim() == 1
+                        && a1.item_shape.ndim() == 2
+                        && rows > 0
+                        && cols > 0
+                        && a0.item_shape[0] % rows == 0
+                        && a0.item_shape[0] == a1.item_shape[0]
+                        && a1.item_shape[1] % cols == 0
+                }
+            };
+            require(fits, || {
+                problem(
+                    format!("arguments don't fit a {}x{} systolic array", rows, cols),
+                    match enode {
+                        SystolicArray(_) => format!(
+                            "item shapes [{}] and [{}, {}]",
+                            rows, rows, cols
+                        ),
+                        _ => format!(
+                            "item shapes [n] and [n, m], with n a multiple of {} and m a multiple of {}",
+                            rows, cols
+                        ),
+                    },
+                    format!(
+                        "item shapes {:?} and {:?}",
+                        a0.item_shape.slice(),
+                        a1.item_shape.slice()
+                    ),
+                )
+            })
+        }
+        MoveAxis(_) => {
+            let shape = args.tensor(0)?;
+            args.tensor_dtype(0)?;
+            for argument in 1..3 {
+                let axis = args.usize(argument)?;
+                require(axis < shape.ndim(), || {
+                    problem(
+                        "axis out of range",
+                        format!("an axis less than {}", shape.ndim()),
+                        axis,
+                    )
+                })?;
+            }
+            Ok(())
+        }
+        CartesianProduct(_) => {
+            let left = args.tensor(0)?;
+            let right = args.tensor(1)?;
+            args.tensor_dtype(0)?;
+            require(
+                (1..=2).contains(&left.ndim())
+                    && right.ndim() >= 1
+                    && left[left.ndim() - 1] == right[right.ndim() - 1],
+                || {
+                    problem(
+                        "cannot take the cartesian product",
+                        "tensors of 1 or 2 dimensions, with matching last dimensions",
+                        format!("shapes {:?} and {:?}", left.slice(), right.slice()),
+                    )
+                },
+            )
+        }
+        MapDotProduct(_) => {
+            let shape = args.tensor(0)?;
+            args.tensor_dtype(0)?;
+            require(shape.ndim() >= 3 && shape[shape.ndim() - 2] == 2, || {
+                problem(
+                    "cannot map a dot product",
+                    "a shape of [..., 2, n]",
+                    format!("{:?}", shape.slice()),
+                )
+            })
+        }
+        BsgSystolicArray(_) => {
+            args.usize(0)?;
+            args.usize(1)?;
+            let left = args.tensor(2)?;
+            let right = args.tensor(3)?;
+            args.tensor_dtype(2)?;
+            require((1..=2).contains(&left.ndim()) && right.ndim() == 2, || {
+                problem(
+                    "wrong number of dimensions",
+                    "a vector or matrix, and a matrix",
+                    format!("shapes {:?} and {:?}", left.slice(), right.slice()),
+                )
+            })
+        }
+        Slice(_) => {
+            let shape = args.tensor(0)?;
+            args.tensor_dtype(0)?;
+            let axis = args.usize(1)?;
+            let low = args.usize(2)?;
+            let high = args.usize(3)?;
+            require(axis < shape.ndim(), || {
+                problem(
+                    "axis out of range",
+                    format!("an axis less than {}", shape.ndim()),
+                    axis,
+                )
+            })?;
+            require(
+                low < shape[axis] && low <= high && high <= shape[axis],
+                || {
+                    problem(
+                        "slice out of range",
+                        format!(
+                            "0 <= low <= high <= {}, with low < {}",
+                            shape[axis], shape[axis]
+                        ),
+                        format!("low {} and high {}", low, high),
+                    )
+                },
+            )
+        }
+        Concatenate(_) => {
+            let t0 = args.tensor(0)?;
+            let t1 = args.tensor(1)?;
+            args.tensor_dtype(0)?;
+            let axis = args.usize(2)?;
+            require(t0.ndim() == t1.ndim() && axis < t1.ndim(), || {
+                problem(
+                    "cannot concatenate",
+                    format!(
+                        "tensors with the same number of dimensions, and an axis less than {}",
+                        t0.ndim()
+                    ),
+                    format!(
+                        "shapes {:?} and {:?}, and axis {}",
+                        t0.slice(),
+                        t1.slice(),
+                        axis
+                    ),
+                )
+            })
+        }
+        ElementwiseAdd(_) => {
+            let t0 = args.tensor(0)?;
+            let t1 = args.tensor(1)?;
+            args.tensor_dtype(0)?;
+            require(t0 == t1, || {
+                problem(
+                    "shapes differ",
+                    format!("{:?}", t0.slice()),
+                    format!("{:?}", t1.slice()),
+                )
+            })
+        }
+    }
+}
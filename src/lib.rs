@@ -4,3 +4,5 @@ pub mod codegen;
 pub mod extraction;
 pub mod hw_design_language;
 pub mod language;
+pub mod phases;
+pub mod report;
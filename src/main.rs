@@ -1,5 +1,6 @@
-use clap::{App, Arg, SubCommand};
-use egg::{EGraph, RecExpr, Runner};
+use clap::{App, AppSettings, Arg, SubCommand};
+use egg::{EGraph, Id, RecExpr, Runner};
+use glenside::hw_design_language::DType;
 use glenside::language::rewrites::{PadLocation, PadSliceStrategy, SliceConcatenateStrategy};
 use glenside::language::{Language, MyAnalysis, PadType};
 use serde_json::Value;
@@ -8,17 +9,28 @@ use std::io::Write;
 use std::str::FromStr;
 
 fn main() {
-    let matches = App::new("glenside")
+    let app = App::new("glenside")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
         .subcommand(
             SubCommand::with_name("demo")
                 .arg(Arg::with_name("NAME").required(true).index(1))
                 .arg(Arg::with_name("PROGRAM").required(true).index(2))
-                .arg(Arg::with_name("SHAPES").required(true).index(3))
-                .arg(Arg::with_name("OUT_CODE_FILEPATH").required(true).index(4))
+                // clap can only leave out the second-to-last positional
+                // argument, so SHAPES and the output files are taken together.
                 .arg(
-                    Arg::with_name("OUT_DESIGN_FILEPATH")
+                    Arg::with_name("FILES")
+                        .help(
+                            "[SHAPES] OUT_CODE_FILEPATH OUT_DESIGN_FILEPATH: \
+                             a JSON file giving the shapes of the program's \
+                             inputs, which can be left out if the program \
+                             declares its inputs itself, followed by where to \
+                             write the generated code and hardware design.",
+                        )
                         .required(true)
-                        .index(5),
+                        .multiple(true)
+                        .min_values(2)
+                        .max_values(3)
+                        .index(3),
                 )
                 .arg(
                     Arg::with_name("allocate-for-manycore")
@@ -45,6 +57,44 @@ fn main() {
                         .min_values(1)
                         .default_value("glenside"),
                 )
+                .arg(
+                    Arg::with_name("rewrite-config")
+                        .help(
+                            "A JSON file listing the rewrites to run and \
+                             their parameters; see \
+                             data/rewrite-configs/demo.json for an example. \
+                             Replaces Glenside's default rewrites, including \
+                             those chosen by --blocking.",
+                        )
+                        .long("rewrite-config")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("phases")
+                        .help(
+                            "A JSON file describing phases of rewriting to \
+                             run in order, each with its own rewrites and \
+                             limits; see data/rewrite-configs/demo-phases.json \
+                             for an example. Phases which don't set a limit \
+                             use --node-limit, --time-limit, and --iter-limit. \
+                             Replaces Glenside's default rewrites, including \
+                             those chosen by --blocking.",
+                        )
+                        .long("phases")
+                        .takes_value(true)
+                        .conflicts_with("rewrite-config"),
+                )
+                .arg(
+                    Arg::with_name("report")
+                        .help(
+                            "Writes a JSON report to this file, listing which \
+                             rewrites fired in each iteration, how the e-graph \
+                             grew, and which systolic array configurations \
+                             were found.",
+                        )
+                        .long("report")
+                        .takes_value(true),
+                )
                 .arg(
                     Arg::with_name("prefer-bsg-blocking")
                         .help(
@@ -53,6 +103,63 @@ fn main() {
                         )
                         .long("prefer-bsg-blocking"),
                 )
+                .arg(
+                    Arg::with_name("software-compute-cost")
+                        .help(
+                            "Allows compute statements which aren't mapped to \
+                             hardware to be extracted and compiled to plain C \
+                             loops, at the given cost per compute statement. \
+                             Systolic arrays cost 1, so use a large value to \
+                             prefer hardware wherever possible. By default, \
+                             compute statements can't be extracted. Can't be \
+                             used with --find-monolithic-designs, whose cost \
+                             function decides which compute statements stay \
+                             in software.",
+                        )
+                        .long("software-compute-cost")
+                        .takes_value(true)
+                        .conflicts_with("find-monolithic-designs"),
+                )
+                .arg(
+                    Arg::with_name("test-harness")
+                        .help(
+                            "Also writes a C test harness to the given file. \
+                             The harness #includes OUT_CODE_FILEPATH, runs \
+                             the generated function on the tensors given by \
+                             --input, and checks the result against \
+                             --expected. If no expected output is given, it \
+                             is computed by Glenside's interpreter.",
+                        )
+                        .long("test-harness")
+                        .takes_value(true)
+                        .requires("input"),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .help(
+                            "An input for the test harness, of the form \
+                             <name>=<path to .npy file>. Pass once per input. \
+                             The .npy file should hold the input's dtype; \
+                             bfloat16 inputs are given as float32s.",
+                        )
+                        .long("input")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("expected")
+                        .help("The expected output for the test harness, as a .npy file.")
+                        .long("expected")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("tolerance")
+                        .help("The tolerance used by the test harness when comparing values.")
+                        .long("tolerance")
+                        .takes_value(true)
+                        .default_value("1e-5"),
+                )
                 .arg(
                     Arg::with_name("node-limit")
                         .long("node-limit")
@@ -81,166 +188,286 @@ fn main() {
                         .takes_value(true),
                 ),
         )
-        .get_matches();
+        .subcommand(
+            SubCommand::with_name("interpret")
+                .about("Runs a Glenside program on .npy inputs with Glenside's interpreter")
+                // SHAPES can be left out.
+                .setting(AppSettings::AllowMissingPositional)
+                .arg(Arg::with_name("PROGRAM").required(true).index(1))
+                .arg(
+                    Arg::with_name("SHAPES")
+                        .help(
+                            "A JSON file giving the shapes of the program's \
+                             inputs. Can be left out if the program declares \
+                             its inputs itself.",
+                        )
+                        .index(2),
+                )
+                .arg(Arg::with_name("OUT_FILEPATH").required(true).index(3))
+                .arg(
+                    Arg::with_name("input")
+                        .help(
+                            "An input to the program, of the form \
+                             <name>=<path to .npy file>. Pass once per input. \
+                             Its datatype must match --dtype.",
+                        )
+                        .long("input")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("dtype")
+                        .help("The datatype to interpret the program in.")
+                        .long("dtype")
+                        .takes_value(true)
+                        .possible_values(&["float32", "float64"])
+                        .default_value("float32"),
+                ),
+        );
 
-    if let Some(matches) = matches.subcommand_matches("demo") {
-        // Read in shapes as JSON dict; convert to HashMap<String, Vec<usize>>
-        let shapes_json: Value = serde_json::from_str(
-            std::fs::read_to_string(matches.value_of("SHAPES").unwrap())
-                .unwrap()
-                .as_str(),
-        )
-        .unwrap();
-        let mut shapes_map = HashMap::new();
-        for (name, value) in shapes_json.as_object().unwrap().iter() {
-            shapes_map.insert(
-                name.clone(),
-                value
-                    .as_array()
-                    .unwrap()
-                    .iter()
-                    .map(|value| value.as_u64().unwrap() as usize)
-                    .collect::<Vec<_>>(),
-            );
+    #[cfg(feature = "tvm")]
+    let app = app.subcommand(
+        SubCommand::with_name("from-relay")
+            .about("Converts a Relay program to a Glenside program and shapes file")
+            .arg(
+                Arg::with_name("RELAY")
+                    .help("A Relay program, in Relay's text format")
+                    .required(true)
+                    .index(1),
+            )
+            .arg(
+                Arg::with_name("OUT_PROGRAM_FILEPATH")
+                    .required(true)
+                    .index(2),
+            )
+            .arg(
+                Arg::with_name("OUT_SHAPES_FILEPATH")
+                    .required(true)
+                    .index(3),
+            ),
+    );
+
+    let matches = app.get_matches();
+
+    #[cfg(feature = "tvm")]
+    {
+        if let Some(matches) = matches.subcommand_matches("from-relay") {
+            from_relay(matches);
+            return;
         }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("demo") {
+        let (shapes_filepath, out_code_filepath, out_design_filepath) =
+            match matches.values_of("FILES").unwrap().collect::<Vec<_>>()[..] {
+                [out_code_filepath, out_design_filepath] => {
+                    (None, out_code_filepath, out_design_filepath)
+                }
+                [shapes_filepath, out_code_filepath, out_design_filepath] => (
+                    Some(shapes_filepath),
+                    out_code_filepath,
+                    out_design_filepath,
+                ),
+                _ => unreachable!("clap requires two or three files"),
+            };
 
         // Read in program into egraph
-        let glenside_expr = RecExpr::<Language>::from_str(
-            std::fs::read_to_string(matches.value_of("PROGRAM").unwrap())
-                .unwrap()
-                .as_str(),
-        )
-        .unwrap();
+        let (glenside_expr, shapes_map, dtypes_map) =
+            read_program(matches.value_of("PROGRAM").unwrap(), shapes_filepath);
 
         let mut egraph = EGraph::new(MyAnalysis {
             name_to_shape: shapes_map.clone(),
+            name_to_dtype: dtypes_map.clone(),
         });
         let id = egraph.add_expr(&glenside_expr);
 
-        let mut runner = Runner::default().with_egraph(egraph);
-
-        if let Some(m) = matches.value_of("node-limit") {
-            runner = runner.with_node_limit(m.parse().expect("node-limit should be an integer"));
-        }
-        if let Some(m) = matches.value_of("iter-limit") {
-            runner = runner.with_iter_limit(m.parse().expect("iter-limit should be an integer"));
-        }
-        if let Some(m) = matches.value_of("time-limit") {
-            runner = runner.with_time_limit(std::time::Duration::from_secs(
-                m.parse().expect("time-limit should be an integer"),
-            ));
-        }
-
-        // TODO(@gussmith23) Add flags to control different rewrites
-        let mut rws = vec![
-            glenside::language::rewrites::flatten_unflatten_any_access(),
-            glenside::language::rewrites::bubble_reshape_through_cartesian_product(),
-            glenside::language::rewrites::bubble_reshape_through_compute_dot_product(),
-            glenside::language::rewrites::bubble_access_concatenate_through_access_cartesian_product_not_item_axis_left(),
-            glenside::language::rewrites::bubble_access_concatenate_through_access_cartesian_product_not_item_axis_right(),
-            glenside::language::rewrites::bubble_access_concatenate_through_access_cartesian_product_same_item_axis(),
-            glenside::language::rewrites::bubble_access_concatenate_through_compute_dot_product_item_axis(),
-            glenside::language::rewrites::bubble_access_concatenate_through_compute_dot_product_not_item_axis(),
-            glenside::language::rewrites::bubble_access_slice_through_access_pad_inequal_axes(),
-            glenside::language::rewrites::pad_slice_accesses(
-                0,
-                PadSliceStrategy::PadToClosestMultipleOf {
-                    multiple_of: 64,
-                    pad_location: PadLocation::End,
-                    pad_type: PadType::ZeroPadding,
-                },
-            ),
-            glenside::language::rewrites::pad_slice_accesses(
-                1,
-                PadSliceStrategy::PadToClosestMultipleOf {
-                    multiple_of: 64,
-                    pad_location: PadLocation::End,
-                    pad_type: PadType::ZeroPadding,
-                },
-            ),
-            glenside::language::rewrites::slice_concatenate_accesses(
-                0,
-                SliceConcatenateStrategy::DivideInto { segment_size: 64 },
-            ),
-            glenside::language::rewrites::slice_concatenate_accesses(
-                1,
-                SliceConcatenateStrategy::DivideInto { segment_size: 64 },
-            ),
-            glenside::language::rewrites::bubble_access_slice_through_access_cartesian_product_not_item_axis_left(),
-            glenside::language::rewrites::bubble_access_slice_through_access_cartesian_product_not_item_axis_right(),
-            glenside::language::rewrites::bubble_access_slice_through_access_cartesian_product_same_item_axis(),
-            glenside::language::rewrites::bubble_access_slice_through_compute_dot_product_not_item_axis(),
-            glenside::language::rewrites::bubble_access_slice_through_compute_dot_product_item_axis_not_tuple_axis(),
-            glenside::language::rewrites::collapse_nested_accesses(),
-            glenside::language::rewrites::collapse_nested_transposes(),
-            glenside::language::rewrites::remove_trivial_transpose(),
-        ];
-
-        // Ensure that each rewrite is just added once.
-        let mut added = std::collections::HashSet::new();
-        for value in matches.values_of("blocking").unwrap() {
-            if added.contains(value) {
-                continue;
-            }
-            if value == "glenside" {
-                rws.push(glenside::language::rewrites::systolic_array());
-            } else {
-                let parsed = value
+        // TODO(@gussmith23) Explain difference between extraction and hw gen
+        // Why do we "extract a monolithic design" and then "create a monolithic
+        // design"? Why are these two separate steps? Well, extracting a
+        // monolithic design doesn't actually mean creating a design; it means
+        // extracting an expression which /could/ be monolithic. What comes out
+        // at extraction time doesn't actually have hardware assigned yet; that
+        // happens in design creation, a few lines later.
+        let extract = |egraph: &EGraph<Language, MyAnalysis>, id: Id| -> RecExpr<Language> {
+            if let Some(val) = matches.value_of("find-monolithic-designs") {
+                let parsed = val
                     .chars()
                     .skip(1)
-                    .take(value.len() - 2)
+                    .take(val.len() - 2)
                     .collect::<String>()
                     .split(",")
                     .map(|s| s.parse::<usize>().unwrap())
                     .collect::<Vec<_>>();
                 assert_eq!(parsed.len(), 2);
-                rws.push(glenside::language::rewrites::systolic_array_with_blocking(
-                    parsed[0], parsed[1],
-                ));
+                egg::Extractor::new(
+                    egraph,
+                    glenside::extraction::MonolithicCostFunction {
+                        egraph,
+                        systolic_array_configuration: (parsed[0], parsed[1]),
+                        prefer_systolic_arrays_with_blocking: matches
+                            .is_present("prefer-bsg-blocking"),
+                    },
+                )
+                .find_best(id)
+                .1
+            } else {
+                egg::Extractor::new(
+                    egraph,
+                    glenside::extraction::SimpleCostFunction {
+                        prefer_systolic_arrays_with_blocking: matches
+                            .is_present("prefer-bsg-blocking"),
+                        compute_cost: matches
+                            .value_of("software-compute-cost")
+                            .map(|s| s.parse::<usize>().unwrap())
+                            .unwrap_or(std::usize::MAX),
+                    },
+                )
+                .find_best(id)
+                .1
             }
-            added.insert(value);
-        }
+        };
 
-        runner = runner.run(&rws);
+        // --blocking has a default value, so clap can't check that it isn't
+        // given alongside a rewrite config.
+        if matches.occurrences_of("blocking") > 0
+            && (matches.is_present("rewrite-config") || matches.is_present("phases"))
+        {
+            eprintln!("error: --blocking cannot be used with --rewrite-config or --phases");
+            std::process::exit(1);
+        }
 
-        // TODO(@gussmith23) Explain difference between extraction and hw gen
-        // Why do we "extract a monolithic design" and then "create a monolithic
-        // design"? Why are these two separate steps? Well, extracting a
-        // monolithic design doesn't actually mean creating a design; it means
-        // extracting an expression which /could/ be monolithic. What comes out
-        // at extraction time doesn't actually have hardware assigned yet; that
-        // happens in design creation, a few lines later.
-        let (_, extracted_expr) = if let Some(val) = matches.value_of("find-monolithic-designs") {
-            let parsed = val
-                .chars()
-                .skip(1)
-                .take(val.len() - 2)
-                .collect::<String>()
-                .split(",")
-                .map(|s| s.parse::<usize>().unwrap())
-                .collect::<Vec<_>>();
-            assert_eq!(parsed.len(), 2);
-            egg::Extractor::new(
-                &runner.egraph,
-                glenside::extraction::MonolithicCostFunction {
-                    egraph: &runner.egraph,
-                    systolic_array_configuration: (parsed[0], parsed[1]),
-                    prefer_systolic_arrays_with_blocking: matches.is_present("prefer-bsg-blocking"),
-                },
-            )
-            .find_best(id)
+        let (egraph, id, runs) = if let Some(filepath) = matches.value_of("phases") {
+            let mut phases = glenside::language::rewrite_config::read_phases_config(filepath);
+            // Limits given on the command line apply to any phase which doesn't
+            // set its own.
+            for phase in phases.iter_mut() {
+                if let Some(m) = matches.value_of("node-limit") {
+                    phase.node_limit = phase
+                        .node_limit
+                        .or(Some(m.parse().expect("node-limit should be an integer")));
+                }
+                if let Some(m) = matches.value_of("iter-limit") {
+                    phase.iter_limit = phase
+                        .iter_limit
+                        .or(Some(m.parse().expect("iter-limit should be an integer")));
+                }
+                if let Some(m) = matches.value_of("time-limit") {
+                    phase.time_limit = phase.time_limit.or(Some(std::time::Duration::from_secs(
+                        m.parse().expect("time-limit should be an integer"),
+                    )));
+                }
+            }
+            let run = glenside::phases::run_phases(egraph, id, &phases, &extract);
+            let runs = run.reports.into_iter().map(|report| report.run).collect();
+            (run.egraph, run.id, runs)
         } else {
-            egg::Extractor::new(
-                &runner.egraph,
-                glenside::extraction::SimpleCostFunction {
-                    prefer_systolic_arrays_with_blocking: matches.is_present("prefer-bsg-blocking"),
-                },
-            )
-            .find_best(id)
+            let mut runner = Runner::default().with_egraph(egraph);
+
+            if let Some(m) = matches.value_of("node-limit") {
+                runner =
+                    runner.with_node_limit(m.parse().expect("node-limit should be an integer"));
+            }
+            if let Some(m) = matches.value_of("iter-limit") {
+                runner =
+                    runner.with_iter_limit(m.parse().expect("iter-limit should be an integer"));
+            }
+            if let Some(m) = matches.value_of("time-limit") {
+                runner = runner.with_time_limit(std::time::Duration::from_secs(
+                    m.parse().expect("time-limit should be an integer"),
+                ));
+            }
+
+            let rws = if let Some(filepath) = matches.value_of("rewrite-config") {
+                glenside::language::rewrite_config::read_rewrite_config(filepath)
+            } else {
+                let mut rws = vec![
+                    glenside::language::rewrites::flatten_unflatten_any_access(),
+                    glenside::language::rewrites::bubble_reshape_through_cartesian_product(),
+                    glenside::language::rewrites::bubble_reshape_through_compute_dot_product(),
+                    glenside::language::rewrites::bubble_access_concatenate_through_access_cartesian_product_not_item_axis_left(),
+                    glenside::language::rewrites::bubble_access_concatenate_through_access_cartesian_product_not_item_axis_right(),
+                    glenside::language::rewrites::bubble_access_concatenate_through_access_cartesian_product_same_item_axis(),
+                    glenside::language::rewrites::bubble_access_concatenate_through_compute_dot_product_item_axis(),
+                    glenside::language::rewrites::bubble_access_concatenate_through_compute_dot_product_not_item_axis(),
+                    glenside::language::rewrites::bubble_access_slice_through_access_pad_inequal_axes(),
+                    glenside::language::rewrites::pad_slice_accesses(
+                        0,
+                        PadSliceStrategy::PadToClosestMultipleOf {
+                            multiple_of: 64,
+                            pad_location: PadLocation::End,
+                            pad_type: PadType::ZeroPadding,
+                        },
+                    ),
+                    glenside::language::rewrites::pad_slice_accesses(
+                        1,
+                        PadSliceStrategy::PadToClosestMultipleOf {
+                            multiple_of: 64,
+                            pad_location: PadLocation::End,
+                            pad_type: PadType::ZeroPadding,
+                        },
+                    ),
+                    glenside::language::rewrites::slice_concatenate_accesses(
+                        0,
+                        SliceConcatenateStrategy::DivideInto { segment_size: 64 },
+                    ),
+                    glenside::language::rewrites::slice_concatenate_accesses(
+                        1,
+                        SliceConcatenateStrategy::DivideInto { segment_size: 64 },
+                    ),
+                    glenside::language::rewrites::bubble_access_slice_through_access_cartesian_product_not_item_axis_left(),
+                    glenside::language::rewrites::bubble_access_slice_through_access_cartesian_product_not_item_axis_right(),
+                    glenside::language::rewrites::bubble_access_slice_through_access_cartesian_product_same_item_axis(),
+                    glenside::language::rewrites::bubble_access_slice_through_compute_dot_product_not_item_axis(),
+                    glenside::language::rewrites::bubble_access_slice_through_compute_dot_product_item_axis_not_tuple_axis(),
+                    glenside::language::rewrites::collapse_nested_accesses(),
+                    glenside::language::rewrites::collapse_nested_transposes(),
+                    glenside::language::rewrites::remove_trivial_transpose(),
+                ];
+
+                // Ensure that each rewrite is just added once.
+                let mut added = std::collections::HashSet::new();
+                for value in matches.values_of("blocking").unwrap() {
+                    if added.contains(value) {
+                        continue;
+                    }
+                    if value == "glenside" {
+                        rws.push(glenside::language::rewrites::systolic_array());
+                        rws.push(glenside::language::rewrites::systolic_array_batch_matmul());
+                    } else {
+                        let parsed = value
+                            .chars()
+                            .skip(1)
+                            .take(value.len() - 2)
+                            .collect::<String>()
+                            .split(",")
+                            .map(|s| s.parse::<usize>().unwrap())
+                            .collect::<Vec<_>>();
+                        assert_eq!(parsed.len(), 2);
+                        rws.push(glenside::language::rewrites::systolic_array_with_blocking(
+                            parsed[0], parsed[1],
+                        ));
+                    }
+                    added.insert(value);
+                }
+
+                rws
+            };
+
+            let (runner, run) = glenside::report::run_with_report(runner, "rewrites", &rws);
+            (runner.egraph, id, vec![run])
         };
 
+        if let Some(filepath) = matches.value_of("report") {
+            let report = glenside::report::ExplorationReport::new(runs, &egraph);
+            std::fs::write(filepath, report.to_json()).unwrap();
+        }
+
+        let extracted_expr = extract(&egraph, id);
+
         let mut egraph = EGraph::new(MyAnalysis {
             name_to_shape: shapes_map,
+            name_to_dtype: dtypes_map,
         });
         let id = egraph.add_expr(&extracted_expr);
         let (hw_id_map, hw_atoms) = if let Some(val) = matches.value_of("find-monolithic-designs") {
@@ -279,15 +506,388 @@ fn main() {
             &glenside::hw_design_language::HardwareDesign { atoms: hw_atoms },
         );
 
-        std::fs::File::create(matches.value_of("OUT_CODE_FILEPATH").unwrap())
+        std::fs::File::create(out_code_filepath)
             .unwrap()
             .write_all(code.as_bytes())
             .unwrap();
-        std::fs::File::create(matches.value_of("OUT_DESIGN_FILEPATH").unwrap())
+        std::fs::File::create(out_design_filepath)
             .unwrap()
             .write_all(serde_json::to_string_pretty(&json).unwrap().as_bytes())
             .unwrap();
+
+        if let Some(harness_filepath) = matches.value_of("test-harness") {
+            let mut inputs = HashMap::new();
+            for input in matches.values_of("input").unwrap() {
+                let (name, filepath) = parse_input_arg(input);
+                let value = read_harness_npy(filepath, egraph.analysis.get_input_dtype(name));
+                inputs.insert(name.to_string(), value);
+            }
+            for var in found_vars.iter() {
+                assert!(
+                    inputs.contains_key(var),
+                    "No --input given for {}, which the program uses",
+                    var
+                );
+            }
+
+            let expected = match matches.value_of("expected") {
+                Some(filepath) => read_harness_npy(filepath, MyAnalysis::get_dtype(id, &egraph)),
+                None => {
+                    let env = inputs
+                        .iter()
+                        .map(|(name, value)| (name.as_str(), value.clone()))
+                        .collect::<HashMap<_, _>>();
+                    match glenside::language::interpreter::interpret(
+                        &glenside_expr,
+                        glenside_expr.as_ref().len() - 1,
+                        &env,
+                    ) {
+                        glenside::language::interpreter::Value::Access(a) => a.tensor,
+                        glenside::language::interpreter::Value::Tensor(t) => t,
+                        _ => panic!("Program did not produce a tensor"),
+                    }
+                }
+            };
+
+            // The harness may be written to a different directory than the
+            // code, so it includes the code by its absolute path.
+            let code_filepath = std::fs::canonicalize(out_code_filepath)
+                .unwrap()
+                .to_string_lossy()
+                .to_string();
+            let harness = glenside::codegen::c_test_harness(
+                code_filepath.as_str(),
+                matches.value_of("NAME").unwrap(),
+                &found_vars
+                    .iter()
+                    .map(|var| {
+                        (
+                            var.as_str(),
+                            egraph.analysis.get_input_dtype(var),
+                            inputs[var].view(),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+                MyAnalysis::get_dtype(id, &egraph),
+                &expected.view(),
+                matches
+                    .value_of("tolerance")
+                    .unwrap()
+                    .parse()
+                    .expect("tolerance should be a number"),
+            );
+            std::fs::File::create(harness_filepath)
+                .unwrap()
+                .write_all(harness.as_bytes())
+                .unwrap();
+        }
+    } else if let Some(matches) = matches.subcommand_matches("interpret") {
+        let (expr, shapes_map, dtypes_map) = read_program(
+            matches.value_of("PROGRAM").unwrap(),
+            matches.value_of("SHAPES"),
+        );
+
+        let inputs = matches
+            .values_of("input")
+            .map(|values| values.map(parse_input_arg).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let out_filepath = matches.value_of("OUT_FILEPATH").unwrap();
+
+        match matches.value_of("dtype").unwrap() {
+            "float32" => interpret_to_npy::<f32>(&expr, &shapes_map, &inputs, out_filepath),
+            "float64" => interpret_to_npy::<f64>(&expr, &shapes_map, &inputs, out_filepath),
+            _ => unreachable!(),
+        }
     } else {
-        todo!()
+        unreachable!("clap requires a subcommand")
     }
 }
+
+/// Reads the program at `program_filepath`, along with the shapes file at
+/// `shapes_filepath`, if there is one. Inputs may be declared in either, but
+/// not differently in both. Exits with an error message if the program is
+/// malformed.
+fn read_program(
+    program_filepath: &str,
+    shapes_filepath: Option<&str>,
+) -> (
+    RecExpr<Language>,
+    HashMap<String, Vec<usize>>,
+    HashMap<String, DType>,
+) {
+    let program = glenside::language::program::parse_program(
+        std::fs::read_to_string(program_filepath)
+            .unwrap_or_else(|e| panic!("Could not read {}: {}", program_filepath, e))
+            .as_str(),
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("error: {}: {}", program_filepath, e);
+        std::process::exit(1);
+    });
+    let mut shapes_map = program.name_to_shape;
+    let mut dtypes_map = program.name_to_dtype;
+
+    if let Some(shapes_filepath) = shapes_filepath {
+        let (file_shapes_map, file_dtypes_map) = read_shapes(shapes_filepath);
+        for (name, shape) in file_shapes_map {
+            match shapes_map.get(&name) {
+                Some(declared) if *declared != shape => {
+                    eprintln!(
+                        "error: {} declares {} with shape {:?}, but {} gives {:?}",
+                        program_filepath, name, declared, shapes_filepath, shape
+                    );
+                    std::process::exit(1);
+                }
+                _ => {
+                    shapes_map.insert(name, shape);
+                }
+            }
+        }
+        for (name, dtype) in file_dtypes_map {
+            match dtypes_map.get(&name) {
+                Some(declared) if *declared != dtype => {
+                    eprintln!(
+                        "error: {} declares {} with dtype {}, but {} gives {}",
+                        program_filepath, name, declared, shapes_filepath, dtype
+                    );
+                    std::process::exit(1);
+                }
+                _ => {
+                    dtypes_map.insert(name, dtype);
+                }
+            }
+        }
+    }
+
+    check_types(program_filepath, &program.expr, &shapes_map, &dtypes_map);
+
+    (program.expr, shapes_map, dtypes_map)
+}
+
+/// Reads a shapes file: a JSON dict mapping each input to its declaration.
+/// Returns the shape of each input, and the dtype of each input which
+/// declares one.
+fn read_shapes(filepath: &str) -> (HashMap<String, Vec<usize>>, HashMap<String, DType>) {
+    // Read in shapes as JSON dict; convert to HashMap<String, Vec<usize>>
+    // Each input is either declared as a list (its shape, in which case
+    // it's assumed to be float32) or as a dict of the form
+    // {"shape": [...], "dtype": "int8"}.
+    let shapes_json: Value =
+        serde_json::from_str(std::fs::read_to_string(filepath).unwrap().as_str()).unwrap();
+    let mut shapes_map = HashMap::new();
+    let mut dtypes_map = HashMap::new();
+    for (name, value) in shapes_json.as_object().unwrap().iter() {
+        let shape = match value {
+            Value::Array(shape) => shape,
+            Value::Object(declaration) => {
+                if let Some(dtype) = declaration.get("dtype") {
+                    let dtype = dtype
+                        .as_str()
+                        .unwrap_or_else(|| panic!("dtype of {} should be a string", name));
+                    dtypes_map.insert(
+                        name.clone(),
+                        DType::from_str(dtype).unwrap_or_else(|_| {
+                            panic!("Unrecognized dtype {} for {}", dtype, name)
+                        }),
+                    );
+                }
+                declaration
+                    .get("shape")
+                    .and_then(Value::as_array)
+                    .unwrap_or_else(|| panic!("No shape given for {}", name))
+            }
+            _ => panic!("Could not parse shape declaration for {}", name),
+        };
+        shapes_map.insert(
+            name.clone(),
+            shape
+                .iter()
+                .map(|value| value.as_u64().unwrap() as usize)
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    (shapes_map, dtypes_map)
+}
+
+/// Typechecks the program read from `program_filepath`, exiting with an
+/// error message if it's malformed.
+fn check_types(
+    program_filepath: &str,
+    expr: &RecExpr<Language>,
+    shapes: &HashMap<String, Vec<usize>>,
+    dtypes: &HashMap<String, DType>,
+) {
+    if let Err(e) = glenside::language::typecheck::typecheck_with_dtypes(expr, shapes, dtypes) {
+        eprintln!("error: {} is not well-typed: {}", program_filepath, e);
+        std::process::exit(1);
+    }
+}
+
+/// Parses an argument of the form `<name>=<path>`.
+fn parse_input_arg(arg: &str) -> (&str, &str) {
+    let mut split = arg.splitn(2, '=');
+    match (split.next(), split.next()) {
+        (Some(name), Some(filepath)) => (name, filepath),
+        _ => panic!("--input should be of the form <name>=<path>, got {}", arg),
+    }
+}
+
+/// Reads a .npy file for the test harness, which holds values of type `dtype`.
+/// The harness takes all values as float32s, and converts them back to
+/// `dtype` when it writes them out. Exits with an error if the file doesn't
+/// hold `dtype`s, or if its values can't be passed through exactly.
+fn read_harness_npy(filepath: &str, dtype: DType) -> ndarray::ArrayD<f32> {
+    fn read<T>(filepath: &str, dtype: DType) -> ndarray::ArrayD<f32>
+    where
+        T: ndarray_npy::ReadableElement
+            + num_traits::cast::AsPrimitive<f32>
+            + num_traits::cast::AsPrimitive<f64>,
+    {
+        let value: ndarray::ArrayD<T> = ndarray_npy::read_npy(filepath).unwrap_or_else(|e| {
+            eprintln!("error: could not read {} as {}: {:?}", filepath, dtype, e);
+            std::process::exit(1)
+        });
+        if value.iter().any(|v| {
+            let as_f32: f32 = v.as_();
+            let as_f64: f64 = v.as_();
+            as_f32 as f64 != as_f64
+        }) {
+            eprintln!(
+                "error: {} holds values which can't be represented exactly as float32s, \
+                 which the test harness passes values as",
+                filepath
+            );
+            std::process::exit(1)
+        }
+        value.mapv(|v| v.as_())
+    }
+
+    match dtype {
+        DType::Int8 => read::<i8>(filepath, dtype),
+        DType::Int16 => read::<i16>(filepath, dtype),
+        DType::Int32 => read::<i32>(filepath, dtype),
+        DType::Uint8 => read::<u8>(filepath, dtype),
+        DType::Uint16 => read::<u16>(filepath, dtype),
+        DType::Uint32 => read::<u32>(filepath, dtype),
+        // NumPy has no bfloat16 type, so bfloat16 values are given as
+        // float32s, which the harness truncates.
+        DType::Fp32 | DType::Bf16 => read::<f32>(filepath, dtype),
+        DType::Fp64 => read::<f64>(filepath, dtype),
+        DType::Fp16 => {
+            eprintln!(
+                "error: the test harness can't read {}: float16 .npy files aren't supported",
+                filepath
+            );
+            std::process::exit(1)
+        }
+    }
+}
+
+/// Interprets `expr` on the tensors given by `inputs`, and writes the result
+/// to `out_filepath`.
+fn interpret_to_npy<DataType: 'static>(
+    expr: &RecExpr<Language>,
+    shapes: &HashMap<String, Vec<usize>>,
+    inputs: &[(&str, &str)],
+    out_filepath: &str,
+) where
+    DataType: Copy
+        + std::ops::Mul<Output = DataType>
+        + std::ops::Div<Output = DataType>
+        + std::ops::Neg<Output = DataType>
+        + std::iter::Sum
+        + num_traits::identities::One
+        + num_traits::identities::Zero
+        + std::cmp::PartialOrd
+        + num_traits::Bounded
+        + glenside::language::interpreter::Exp
+        + glenside::language::interpreter::Sqrt
+        + glenside::language::interpreter::Tanh
+        + glenside::language::interpreter::FromNotNanFloat64Literal
+        + ndarray::ScalarOperand
+        + ndarray_npy::ReadableElement
+        + ndarray_npy::WritableElement,
+    usize: num_traits::cast::AsPrimitive<DataType>,
+{
+    let mut env = HashMap::new();
+    for (name, filepath) in inputs.iter() {
+        let value: ndarray::ArrayD<DataType> = ndarray_npy::read_npy(filepath)
+            .unwrap_or_else(|e| panic!("Could not read {}: {:?}", filepath, e));
+        if let Some(shape) = shapes.get(*name) {
+            assert_eq!(
+                value.shape(),
+                shape.as_slice(),
+                "Shape of {} doesn't match the shapes file",
+                name
+            );
+        }
+        env.insert(*name, value);
+    }
+
+    let result =
+        match glenside::language::interpreter::interpret(expr, expr.as_ref().len() - 1, &env) {
+            glenside::language::interpreter::Value::Access(a) => a.tensor,
+            glenside::language::interpreter::Value::Tensor(t) => t,
+            _ => panic!("Program did not produce a tensor"),
+        };
+
+    ndarray_npy::write_npy(out_filepath, &result)
+        .unwrap_or_else(|e| panic!("Could not write {}: {:?}", out_filepath, e));
+}
+
+/// Runs the from-relay subcommand.
+#[cfg(feature = "tvm")]
+fn from_relay(matches: &clap::ArgMatches) {
+    use std::convert::TryFrom;
+
+    let relay_filepath = matches.value_of("RELAY").unwrap();
+    let relay = std::fs::read_to_string(relay_filepath)
+        .unwrap_or_else(|e| panic!("Could not read {}: {}", relay_filepath, e));
+    // IRModule::parse panics on malformed Relay, so call the parser directly
+    // to get at its error.
+    let module = tvm::runtime::Function::get("parser.ParseModule")
+        .unwrap()
+        .invoke(vec![
+            tvm::runtime::String::from(relay_filepath.to_string()).into(),
+            tvm::runtime::String::from(relay).into(),
+        ])
+        .and_then(tvm::ir::module::IRModule::try_from);
+    let module = match module {
+        Ok(module) => module,
+        Err(e) => {
+            eprintln!("error: could not parse {}: {}", relay_filepath, e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(unsupported) = glenside::language::from_relay::find_unsupported_operator(&module) {
+        eprintln!(
+            "error: {} uses {}, which Glenside can't yet convert from Relay",
+            relay_filepath, unsupported
+        );
+        std::process::exit(1);
+    }
+
+    let (expr, shapes) = glenside::language::from_relay::from_relay(&module);
+
+    let shapes_json = Value::Object(
+        shapes
+            .into_iter()
+            .map(|(name, shape)| (name, serde_json::json!(shape)))
+            .collect(),
+    );
+
+    std::fs::File::create(matches.value_of("OUT_PROGRAM_FILEPATH").unwrap())
+        .unwrap()
+        .write_all(expr.pretty(80).as_bytes())
+        .unwrap();
+    std::fs::File::create(matches.value_of("OUT_SHAPES_FILEPATH").unwrap())
+        .unwrap()
+        .write_all(
+            serde_json::to_string_pretty(&shapes_json)
+                .unwrap()
+                .as_bytes(),
+        )
+        .unwrap();
+}
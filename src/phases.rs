@@ -0,0 +1,209 @@
+//! Phased rewriting: running rewrites in a sequence of phases, rather than
+//! all at once.
+//!
+//! Running every rewrite at once can blow up the e-graph before the rewrites
+//! we care about (e.g. tensorizing to systolic arrays) get a chance to fire.
+//! Instead, we can run e.g. a phase which normalizes reshapes, then a phase
+//! which pads and tiles, then a phase which tensorizes. Each phase has its own
+//! rewrites and limits, and can optionally extract the best program at its
+//! end, so that the next phase starts from a small e-graph.
+
+use crate::language::{Language, MyAnalysis};
+use crate::report::{run_with_report, RunReport};
+use egg::{EGraph, Id, RecExpr, Rewrite, Runner, StopReason};
+use std::time::Duration;
+
+/// One phase of rewriting.
+pub struct Phase {
+    /// The name of the phase, used in reports.
+    pub name: String,
+    /// The rewrites to run in this phase.
+    pub rewrites: Vec<Rewrite<Language, MyAnalysis>>,
+    /// The node limit for this phase. Uses egg's default if `None`.
+    pub node_limit: Option<usize>,
+    /// The time limit for this phase. Uses egg's default if `None`.
+    pub time_limit: Option<Duration>,
+    /// The iteration limit for this phase. Uses egg's default if `None`.
+    pub iter_limit: Option<usize>,
+    /// Whether to extract the best program at the end of this phase, and
+    /// start the next phase from an e-graph containing only that program.
+    pub extract: bool,
+}
+
+impl Phase {
+    pub fn new(name: &str, rewrites: Vec<Rewrite<Language, MyAnalysis>>) -> Self {
+        Phase {
+            name: name.to_string(),
+            rewrites,
+            node_limit: None,
+            time_limit: None,
+            iter_limit: None,
+            extract: false,
+        }
+    }
+
+    pub fn with_node_limit(self, node_limit: usize) -> Self {
+        Phase {
+            node_limit: Some(node_limit),
+            ..self
+        }
+    }
+
+    pub fn with_time_limit(self, time_limit: Duration) -> Self {
+        Phase {
+            time_limit: Some(time_limit),
+            ..self
+        }
+    }
+
+    pub fn with_iter_limit(self, iter_limit: usize) -> Self {
+        Phase {
+            iter_limit: Some(iter_limit),
+            ..self
+        }
+    }
+
+    pub fn with_extraction(self) -> Self {
+        Phase {
+            extract: true,
+            ..self
+        }
+    }
+}
+
+/// What happened during one phase.
+#[derive(Debug, Clone)]
+pub struct PhaseReport {
+    pub name: String,
+    /// The number of iterations the phase ran for.
+    pub iterations: usize,
+    /// Why the phase stopped.
+    pub stop_reason: Option<StopReason>,
+    /// The number of e-nodes in the e-graph at the end of the phase, before
+    /// any extraction.
+    pub egraph_nodes: usize,
+    /// The number of e-classes in the e-graph at the end of the phase, before
+    /// any extraction.
+    pub egraph_classes: usize,
+    /// The number of e-nodes in the extracted program, if the phase extracted.
+    pub extracted_nodes: Option<usize>,
+    /// Which rewrites fired in each iteration, and how the e-graph grew.
+    pub run: RunReport,
+}
+
+/// The result of [`run_phases`].
+pub struct PhasedRun {
+    /// The e-graph at the end of the last phase.
+    pub egraph: EGraph<Language, MyAnalysis>,
+    /// The e-class of the program in `egraph`.
+    pub id: Id,
+    pub reports: Vec<PhaseReport>,
+}
+
+/// Runs each of `phases` in order, starting from the program at `id` in
+/// `egraph`. `extract` is used by phases which extract the best program at
+/// their end; it's given the e-graph and the e-class of the program.
+/// ```
+/// use egg::{EGraph, Extractor, RecExpr};
+/// use glenside::extraction::SimpleCostFunction;
+/// use glenside::language::rewrites::*;
+/// use glenside::language::MyAnalysis;
+/// use glenside::phases::{run_phases, Phase};
+/// use std::collections::HashMap;
+/// use std::str::FromStr;
+///
+/// let mut map = HashMap::default();
+/// map.insert("a".to_string(), vec![32, 16]);
+/// map.insert("b".to_string(), vec![16, 64]);
+/// let mut egraph = EGraph::new(MyAnalysis {
+///     name_to_shape: map,
+///     name_to_dtype: HashMap::default(),
+/// });
+/// let id = egraph.add_expr(
+///     &RecExpr::from_str(
+///         "(compute dot-product
+///           (access-cartesian-product
+///            (access (access (access-tensor a) 0) 1)
+///            (access (access-transpose (access-tensor b) (list 1 0)) 1)
+///           )
+///          )",
+///     )
+///     .unwrap(),
+/// );
+///
+/// let run = run_phases(
+///     egraph,
+///     id,
+///     &[
+///         Phase::new(
+///             "normalize",
+///             vec![collapse_nested_accesses(), collapse_nested_transposes()],
+///         )
+///         .with_extraction(),
+///         Phase::new("tensorize", vec![systolic_array()]).with_iter_limit(5),
+///     ],
+///     |egraph, id| Extractor::new(egraph, SimpleCostFunction::default()).find_best(id).1,
+/// );
+///
+/// assert_eq!(run.reports.len(), 2);
+/// assert_eq!(run.reports[0].name, "normalize");
+/// assert!(run.reports[0].extracted_nodes.is_some());
+/// assert_eq!(run.reports[1].extracted_nodes, None);
+/// assert_eq!(run.reports[1].run.iterations.len(), run.reports[1].iterations);
+/// ```
+pub fn run_phases(
+    egraph: EGraph<Language, MyAnalysis>,
+    id: Id,
+    phases: &[Phase],
+    extract: impl Fn(&EGraph<Language, MyAnalysis>, Id) -> RecExpr<Language>,
+) -> PhasedRun {
+    let mut egraph = egraph;
+    let mut id = id;
+    let mut reports = Vec::default();
+
+    for phase in phases.iter() {
+        let mut runner = Runner::default().with_egraph(egraph);
+        if let Some(node_limit) = phase.node_limit {
+            runner = runner.with_node_limit(node_limit);
+        }
+        if let Some(time_limit) = phase.time_limit {
+            runner = runner.with_time_limit(time_limit);
+        }
+        if let Some(iter_limit) = phase.iter_limit {
+            runner = runner.with_iter_limit(iter_limit);
+        }
+        let (runner, run) = run_with_report(runner, &phase.name, &phase.rewrites);
+
+        egraph = runner.egraph;
+        id = egraph.find(id);
+
+        let mut report = PhaseReport {
+            name: phase.name.clone(),
+            iterations: runner.iterations.len(),
+            stop_reason: runner.stop_reason,
+            egraph_nodes: egraph.total_number_of_nodes(),
+            egraph_classes: egraph.number_of_classes(),
+            extracted_nodes: None,
+            run,
+        };
+
+        if phase.extract {
+            let expr = extract(&egraph, id);
+            report.extracted_nodes = Some(expr.as_ref().len());
+            let mut extracted_egraph = EGraph::new(MyAnalysis {
+                name_to_shape: egraph.analysis.name_to_shape.clone(),
+                name_to_dtype: egraph.analysis.name_to_dtype.clone(),
+            });
+            id = extracted_egraph.add_expr(&expr);
+            egraph = extracted_egraph;
+        }
+
+        reports.push(report);
+    }
+
+    PhasedRun {
+        egraph,
+        id,
+        reports,
+    }
+}
@@ -0,0 +1,208 @@
+//! Reports on what happened while running rewrites: which rewrites fired and
+//! how often, and how the e-graph grew, iteration by iteration.
+//!
+//! When exploration is slow or doesn't find what we expect (e.g. no systolic
+//! arrays), these reports let us see which rewrites are doing the work and
+//! which are blowing up the e-graph.
+
+use crate::extraction::find_all_systolic_array_configurations;
+use crate::language::{Language, MyAnalysis};
+use egg::{BackoffScheduler, EGraph, Rewrite, RewriteScheduler, Runner, SearchMatches};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// How often a single rewrite fired during a single iteration.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RewriteCounts {
+    /// The number of matches the rewrite's searcher found.
+    pub matches: usize,
+    /// The number of times the rewrite was applied, i.e. the number of
+    /// matches which actually changed the e-graph.
+    pub applications: usize,
+}
+
+/// What happened during a single iteration of a [`Runner`].
+#[derive(Serialize, Debug, Clone)]
+pub struct IterationReport {
+    /// The number of e-nodes at the start of the iteration.
+    pub egraph_nodes: usize,
+    /// The number of e-classes at the start of the iteration.
+    pub egraph_classes: usize,
+    /// Counts for each rewrite which was searched this iteration, by name.
+    /// Rewrites which the scheduler skipped this iteration are left out.
+    pub rewrites: BTreeMap<String, RewriteCounts>,
+    /// Seconds spent searching for matches.
+    pub search_time: f64,
+    /// Seconds spent applying matches.
+    pub apply_time: f64,
+    /// Seconds spent rebuilding the e-graph.
+    pub rebuild_time: f64,
+}
+
+/// What happened during a single run of a [`Runner`], e.g. one phase.
+#[derive(Serialize, Debug, Clone)]
+pub struct RunReport {
+    pub name: String,
+    pub iterations: Vec<IterationReport>,
+    /// Why the run stopped, if it did.
+    pub stop_reason: Option<String>,
+    /// The number of e-nodes at the end of the run.
+    pub egraph_nodes: usize,
+    /// The number of e-classes at the end of the run.
+    pub egraph_classes: usize,
+}
+
+/// A report on an entire exploration, which may be made up of multiple runs.
+#[derive(Serialize, Debug, Clone)]
+pub struct ExplorationReport {
+    pub runs: Vec<RunReport>,
+    /// The systolic array configurations (rows, cols) in the final e-graph,
+    /// sorted.
+    pub systolic_array_configurations: Vec<(usize, usize)>,
+}
+
+impl ExplorationReport {
+    /// Creates a report from `runs`, looking for systolic arrays in the final
+    /// e-graph, `egraph`.
+    pub fn new(runs: Vec<RunReport>, egraph: &EGraph<Language, MyAnalysis>) -> Self {
+        let mut systolic_array_configurations = find_all_systolic_array_configurations(egraph)
+            .into_iter()
+            .collect::<Vec<_>>();
+        systolic_array_configurations.sort();
+        ExplorationReport {
+            runs,
+            systolic_array_configurations,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+/// Wraps egg's default scheduler, recording the number of matches and
+/// applications of each rewrite in each iteration.
+struct CountingScheduler {
+    scheduler: BackoffScheduler,
+    counts: Rc<RefCell<Vec<BTreeMap<String, RewriteCounts>>>>,
+}
+
+impl CountingScheduler {
+    fn counts_for(&self, iteration: usize, name: &str) -> std::cell::RefMut<RewriteCounts> {
+        std::cell::RefMut::map(self.counts.borrow_mut(), |counts| {
+            if counts.len() <= iteration {
+                counts.resize(iteration + 1, BTreeMap::default());
+            }
+            counts[iteration].entry(name.to_string()).or_default()
+        })
+    }
+}
+
+impl RewriteScheduler<Language, MyAnalysis> for CountingScheduler {
+    fn can_stop(&mut self, iteration: usize) -> bool {
+        self.scheduler.can_stop(iteration)
+    }
+
+    fn search_rewrite(
+        &mut self,
+        iteration: usize,
+        egraph: &EGraph<Language, MyAnalysis>,
+        rewrite: &Rewrite<Language, MyAnalysis>,
+    ) -> Vec<SearchMatches> {
+        let matches = self.scheduler.search_rewrite(iteration, egraph, rewrite);
+        self.counts_for(iteration, rewrite.name()).matches += matches
+            .iter()
+            .map(|search_matches| search_matches.substs.len())
+            .sum::<usize>();
+        matches
+    }
+
+    fn apply_rewrite(
+        &mut self,
+        iteration: usize,
+        egraph: &mut EGraph<Language, MyAnalysis>,
+        rewrite: &Rewrite<Language, MyAnalysis>,
+        matches: Vec<SearchMatches>,
+    ) -> usize {
+        let applications = self
+            .scheduler
+            .apply_rewrite(iteration, egraph, rewrite, matches);
+        self.counts_for(iteration, rewrite.name()).applications += applications;
+        applications
+    }
+}
+
+/// Runs `rewrites` with `runner`, recording a report of the run under `name`.
+/// Uses egg's default scheduler, so the run behaves the same as
+/// `runner.run(rewrites)`.
+/// ```
+/// use egg::{EGraph, RecExpr, Runner};
+/// use glenside::language::rewrites::*;
+/// use glenside::language::MyAnalysis;
+/// use glenside::report::{run_with_report, ExplorationReport};
+/// use std::collections::HashMap;
+/// use std::str::FromStr;
+///
+/// let mut map = HashMap::default();
+/// map.insert("a".to_string(), vec![32, 16]);
+/// let mut egraph = EGraph::new(MyAnalysis {
+///     name_to_shape: map,
+///     name_to_dtype: HashMap::default(),
+/// });
+/// egraph.add_expr(&RecExpr::from_str("(access (access (access-tensor a) 0) 1)").unwrap());
+///
+/// let runner = Runner::default().with_egraph(egraph).with_iter_limit(5);
+/// let (runner, report) = run_with_report(runner, "collapse", &[collapse_nested_accesses()]);
+///
+/// assert_eq!(report.iterations.len(), runner.iterations.len());
+/// assert_eq!(
+///     report.iterations[0].rewrites["collapse-nested-accesses"].applications,
+///     1
+/// );
+///
+/// let report = ExplorationReport::new(vec![report], &runner.egraph);
+/// assert!(report.systolic_array_configurations.is_empty());
+/// ```
+pub fn run_with_report(
+    runner: Runner<Language, MyAnalysis>,
+    name: &str,
+    rewrites: &[Rewrite<Language, MyAnalysis>],
+) -> (Runner<Language, MyAnalysis>, RunReport) {
+    let counts = Rc::new(RefCell::new(Vec::default()));
+    let runner = runner
+        .with_scheduler(CountingScheduler {
+            scheduler: BackoffScheduler::default(),
+            counts: counts.clone(),
+        })
+        .run(rewrites);
+
+    let counts = counts.borrow();
+    let iterations = runner
+        .iterations
+        .iter()
+        .enumerate()
+        .map(|(i, iteration)| IterationReport {
+            egraph_nodes: iteration.egraph_nodes,
+            egraph_classes: iteration.egraph_classes,
+            rewrites: counts.get(i).cloned().unwrap_or_default(),
+            search_time: iteration.search_time,
+            apply_time: iteration.apply_time,
+            rebuild_time: iteration.rebuild_time,
+        })
+        .collect();
+
+    let report = RunReport {
+        name: name.to_string(),
+        iterations,
+        stop_reason: runner
+            .stop_reason
+            .as_ref()
+            .map(|reason| format!("{:?}", reason)),
+        egraph_nodes: runner.egraph.total_number_of_nodes(),
+        egraph_classes: runner.egraph.number_of_classes(),
+    };
+
+    (runner, report)
+}
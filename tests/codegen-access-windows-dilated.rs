@@ -0,0 +1,142 @@
+use egg::EGraph;
+use egg::RecExpr;
+use glenside::codegen::*;
+use glenside::hw_design_language::DType;
+use glenside::language::interpreter::{interpret, Value};
+use glenside::language::MyAnalysis;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::prelude::*;
+use std::process::Command;
+use std::str::FromStr;
+
+/// Generates C for a dilated access-windows, then compiles and runs it with
+/// gcc, checking it against the interpreter.
+#[test]
+fn codegen_access_windows_dilated() {
+    let shape = vec![2, 3, 17, 15];
+    let filters_shape = vec![1, 3, 3, 2];
+    let stride = vec![1, 1, 2, 3];
+    let dilation = vec![1, 1, 3, 2];
+
+    let input = ndarray::ArrayD::from_shape_vec(
+        shape.clone(),
+        (0i64..shape.iter().map(|v| *v as i64).product::<i64>()).collect(),
+    )
+    .unwrap();
+
+    let join = |v: &Vec<usize>| {
+        v.iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let expr = RecExpr::from_str(
+        format!(
+            "
+(access-windows-dilated
+ (access (access-tensor t) {access_axis})
+ (shape {filter_shapes})
+ (shape {strides})
+ (shape {dilations})
+)",
+            access_axis = shape.len(),
+            filter_shapes = join(&filters_shape),
+            strides = join(&stride),
+            dilations = join(&dilation),
+        )
+        .as_str(),
+    )
+    .unwrap();
+
+    let mut env = HashMap::default();
+    env.insert("t", input.clone());
+    let out = match interpret(&expr, expr.as_ref().len() - 1, &env) {
+        Value::Access(a) => a,
+        _ => panic!(),
+    };
+
+    let mut map = HashMap::default();
+    map.insert("t".to_string(), shape.clone());
+    let mut egraph = EGraph::new(MyAnalysis {
+        name_to_shape: map,
+        name_to_dtype: HashMap::default(),
+    });
+    let id = egraph.add_expr(&expr);
+
+    let code = codegen(
+        &egraph,
+        id,
+        &HashMap::default(),
+        "access_windows_dilated",
+        "",
+        &vec!["t"],
+    );
+
+    let main_code = format!(
+        "
+#include <assert.h>
+
+{}
+{}
+{}
+{}
+
+int main() {{
+  access_windows_dilated(out, a);
+
+  for (int i = 0; i < {}; i++) {{
+    assert(((float*)a_windows)[i] == ((float*)out)[i]);
+  }}
+}}
+",
+        c_assignment_string("", "a", DType::Fp32, &input.view()),
+        c_assignment_string("", "a_windows", DType::Fp32, &out.tensor.view()),
+        c_assignment_string(
+            "",
+            "out",
+            DType::Fp32,
+            &ndarray::ArrayD::<f32>::zeros(out.tensor.shape()).view()
+        ),
+        code,
+        out.tensor.len()
+    );
+
+    let main_c_filepath = std::env::temp_dir().with_file_name(format!(
+        "codegen-access-windows-dilated-test-{}.c",
+        std::time::SystemTime::now().elapsed().unwrap().as_nanos()
+    ));
+
+    let binary_filepath = std::env::temp_dir().with_file_name(format!(
+        "codegen-access-windows-dilated-test-{}",
+        std::time::SystemTime::now().elapsed().unwrap().as_nanos()
+    ));
+
+    File::create(&main_c_filepath)
+        .unwrap()
+        .write_all(main_code.as_bytes())
+        .unwrap();
+
+    let result = Command::new("gcc")
+        .arg("-Werror")
+        .arg("-g")
+        .arg("-o")
+        .arg(&binary_filepath)
+        .arg(&main_c_filepath)
+        .output()
+        .unwrap();
+
+    assert!(
+        result.status.success(),
+        "{}",
+        std::str::from_utf8(result.stderr.as_slice()).expect("Could not convert stderr to UTF8")
+    );
+
+    let result = Command::new(&binary_filepath).output().unwrap();
+
+    assert!(
+        result.status.success(),
+        "{}",
+        std::str::from_utf8(result.stderr.as_slice()).expect("Could not convert stderr to UTF8")
+    );
+}
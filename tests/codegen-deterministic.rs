@@ -0,0 +1,88 @@
+use std::process::Command;
+
+/// Runs the glenside binary's demo on `program_filepath`, returning the
+/// generated code and hardware design. Each call is a separate process, so
+/// anything which varies between runs (e.g. hash map iteration order) shows
+/// up as a difference between calls.
+fn run_demo(
+    name: &str,
+    run: usize,
+    program_filepath: &str,
+    shapes_filepath: &str,
+    extra_args: &[&str],
+) -> (String, String) {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+
+    let mut out_code_filepath = std::env::temp_dir();
+    out_code_filepath.push(format!("deterministic-{}-{}-{}.c", name, run, nanos));
+    let mut out_design_filepath = std::env::temp_dir();
+    out_design_filepath.push(format!(
+        "deterministic-{}-design-{}-{}.json",
+        name, run, nanos
+    ));
+
+    let output = Command::new("target/debug/glenside")
+        .arg("demo")
+        .arg(name)
+        .arg(program_filepath)
+        .arg(shapes_filepath)
+        .arg(&out_code_filepath)
+        .arg(&out_design_filepath)
+        .args(extra_args)
+        .output()
+        .expect("Failed to run glenside");
+    assert!(
+        output.status.success(),
+        "Glenside binary failed with code {:?}. stderr:\n{}",
+        output.status.code(),
+        std::str::from_utf8(output.stderr.as_slice()).expect("Could not convert stderr to UTF8")
+    );
+
+    (
+        std::fs::read_to_string(&out_code_filepath).unwrap(),
+        std::fs::read_to_string(&out_design_filepath).unwrap(),
+    )
+}
+
+#[test]
+fn mlp_codegen_is_deterministic() {
+    let program_filepath = format!(
+        "{}/data/asplos-demo/mlp.glenside",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let shapes_filepath = format!(
+        "{}/data/asplos-demo/mlp-shapes.json",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    // Limit the search by iterations, not time, so both runs explore the same
+    // e-graph.
+    let args = ["--iter-limit", "5"];
+
+    let first = run_demo("mlp", 0, &program_filepath, &shapes_filepath, &args);
+    let second = run_demo("mlp", 1, &program_filepath, &shapes_filepath, &args);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn conv2d_codegen_is_deterministic() {
+    let program_filepath = format!("{}/data/conv2d/conv2d.glenside", env!("CARGO_MANIFEST_DIR"));
+    let shapes_filepath = format!(
+        "{}/data/conv2d/conv2d-shapes.json",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    // Limit the search by iterations, not time, so both runs explore the same
+    // e-graph.
+    let args = ["--iter-limit", "1"];
+
+    let first = run_demo("conv2d", 0, &program_filepath, &shapes_filepath, &args);
+    let second = run_demo("conv2d", 1, &program_filepath, &shapes_filepath, &args);
+    assert_eq!(first, second);
+
+    // Intermediates are named after the construct and e-class which produce
+    // them.
+    assert!(first.0.contains("access_windows_eclass_"));
+    assert!(first.0.contains("compute_eclass_"));
+}
@@ -0,0 +1,95 @@
+#![cfg(feature = "tvm")]
+
+use egg::RecExpr;
+use glenside::language::Language;
+use serde_json::Value;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::str::FromStr;
+
+fn temp_filepath(name: &str) -> PathBuf {
+    let mut filepath = std::env::temp_dir();
+    filepath.push(format!(
+        "from-relay-{}-{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos(),
+        name
+    ));
+    filepath
+}
+
+/// Runs `glenside from-relay` on `relay`, returning the output of the command
+/// and the paths of the program and shapes files.
+fn from_relay(relay: &str) -> (Output, PathBuf, PathBuf) {
+    let relay_filepath = temp_filepath("model.relay");
+    std::fs::File::create(&relay_filepath)
+        .unwrap()
+        .write_all(relay.as_bytes())
+        .unwrap();
+    let program_filepath = temp_filepath("model.glenside");
+    let shapes_filepath = temp_filepath("model-shapes.json");
+
+    let output = Command::new("target/debug/glenside")
+        .arg("from-relay")
+        .arg(&relay_filepath)
+        .arg(&program_filepath)
+        .arg(&shapes_filepath)
+        .output()
+        .expect("Failed to run glenside");
+
+    (output, program_filepath, shapes_filepath)
+}
+
+#[test]
+fn from_relay_dense_relu() {
+    let (output, program_filepath, shapes_filepath) = from_relay(
+        r#"
+#[version = "0.0.5"]
+def @main(%data: Tensor[(1, 16), float32], %weight: Tensor[(8, 16), float32]) -> Tensor[(1, 8), float32] {
+  %0 = nn.dense(%data, %weight, units=8);
+  nn.relu(%0)
+}
+"#,
+    );
+    assert!(
+        output.status.success(),
+        "Glenside binary failed with code {:?}. stderr:\n{}",
+        output.status.code(),
+        std::str::from_utf8(output.stderr.as_slice()).expect("Could not convert stderr to UTF8")
+    );
+
+    // The program should parse.
+    RecExpr::<Language>::from_str(std::fs::read_to_string(&program_filepath).unwrap().as_str())
+        .unwrap();
+
+    let shapes: Value =
+        serde_json::from_str(std::fs::read_to_string(&shapes_filepath).unwrap().as_str()).unwrap();
+    assert_eq!(
+        shapes,
+        serde_json::json!({"data": [1, 16], "weight": [8, 16]})
+    );
+}
+
+#[test]
+fn from_relay_unsupported_operator() {
+    let (output, _, _) = from_relay(
+        r#"
+#[version = "0.0.5"]
+def @main(%x: Tensor[(1, 3), float32]) -> Tensor[(1, 3), float32] {
+  %0 = nn.relu(%x);
+  log(%0)
+}
+"#,
+    );
+    assert!(!output.status.success());
+    let stderr = std::str::from_utf8(output.stderr.as_slice()).unwrap();
+    assert!(stderr.contains("uses log,"), "stderr:\n{}", stderr);
+    assert!(
+        !stderr.contains("not yet implemented"),
+        "stderr:\n{}",
+        stderr
+    );
+}
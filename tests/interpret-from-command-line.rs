@@ -0,0 +1,121 @@
+use approx::AbsDiffEq;
+use ndarray::{ArrayD, Ix1, Ix2, LinalgScalar};
+use ndarray_npy::{ReadableElement, WritableElement};
+use ndarray_rand::{rand_distr::Uniform, RandomExt};
+use num_traits::Float;
+use rand::distributions::uniform::SampleUniform;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Runs `glenside interpret` on the MLP demo program with the given inputs,
+/// returning the path of the output .npy file.
+fn interpret_mlp(dtype: &str, inputs: &[(&str, PathBuf)]) -> PathBuf {
+    let program_filepath = format!(
+        "{}/data/asplos-demo/mlp.glenside",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let shapes_filepath = format!(
+        "{}/data/asplos-demo/mlp-shapes.json",
+        env!("CARGO_MANIFEST_DIR")
+    );
+
+    let mut out_filepath = std::env::temp_dir();
+    out_filepath.push(format!(
+        "interpret-mlp-{}-out-{}.npy",
+        dtype,
+        std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+
+    let mut command = Command::new("target/debug/glenside");
+    command
+        .arg("interpret")
+        .arg(program_filepath)
+        .arg(shapes_filepath)
+        .arg(&out_filepath)
+        .arg("--dtype")
+        .arg(dtype);
+    for (name, filepath) in inputs.iter() {
+        command
+            .arg("--input")
+            .arg(format!("{}={}", name, filepath.to_string_lossy()));
+    }
+
+    let output = command.output().expect("Failed to run glenside");
+    assert!(
+        output.status.success(),
+        "Glenside binary failed with code {:?}. stderr:\n{}",
+        output.status.code(),
+        std::str::from_utf8(output.stderr.as_slice()).expect("Could not convert stderr to UTF8")
+    );
+
+    out_filepath
+}
+
+fn temp_npy_filepath(name: &str) -> PathBuf {
+    let mut filepath = std::env::temp_dir();
+    filepath.push(format!(
+        "interpret-mlp-{}-{}.npy",
+        name,
+        std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    filepath
+}
+
+/// Interprets the MLP demo program in the datatype `dtype` (whose Rust type is
+/// `T`) on random inputs, checking the result against ndarray.
+fn check_interpret_mlp<T>(dtype: &str, tolerance: T)
+where
+    T: LinalgScalar
+        + Float
+        + SampleUniform
+        + WritableElement
+        + ReadableElement
+        + AbsDiffEq<Epsilon = T>,
+{
+    let uniform = || Uniform::new(-T::one(), T::one());
+    let input = ArrayD::random(vec![2], uniform());
+    let weight0 = ArrayD::random(vec![2, 4], uniform());
+    let weight1 = ArrayD::random(vec![4, 6], uniform());
+    let weight2 = ArrayD::random(vec![6, 2], uniform());
+
+    let mut inputs = Vec::new();
+    for (name, value) in [
+        ("input", &input),
+        ("weight0", &weight0),
+        ("weight1", &weight1),
+        ("weight2", &weight2),
+    ]
+    .iter()
+    {
+        let filepath = temp_npy_filepath(name);
+        ndarray_npy::write_npy(&filepath, *value).unwrap();
+        inputs.push((*name, filepath));
+    }
+
+    let result: ArrayD<T> = ndarray_npy::read_npy(interpret_mlp(dtype, &inputs)).unwrap();
+
+    let expected = input
+        .into_dimensionality::<Ix1>()
+        .unwrap()
+        .dot(&weight0.into_dimensionality::<Ix2>().unwrap())
+        .dot(&weight1.into_dimensionality::<Ix2>().unwrap())
+        .dot(&weight2.into_dimensionality::<Ix2>().unwrap())
+        .into_dyn();
+    assert!(result.abs_diff_eq(&expected, tolerance));
+}
+
+#[test]
+fn interpret_mlp_float32() {
+    check_interpret_mlp::<f32>("float32", 1e-5);
+}
+
+#[test]
+fn interpret_mlp_float64() {
+    check_interpret_mlp::<f64>("float64", 1e-10);
+}
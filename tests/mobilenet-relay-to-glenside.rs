@@ -393,6 +393,7 @@ def @main(%data: Tensor[(1, 3, 224, 224), float32], %conv_block_1_conv_weight: T
     // from_relay.py. It can be simpler (e.g. collapsing accesses).
     let mut egraph = EGraph::new(MyAnalysis {
         name_to_shape: env.clone(),
+        name_to_dtype: HashMap::default(),
     });
     egraph.add_expr(&expr);
 }
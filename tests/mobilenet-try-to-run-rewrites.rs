@@ -1,17 +1,21 @@
 #![cfg(feature = "tvm")]
 
 use egg::EGraph;
-use egg::Extractor;
 use egg::Pattern;
-use egg::Runner;
 use egg::Searcher;
 use glenside::extraction::MonolithicCostFunction;
+use glenside::hw_design_language::DType;
+use glenside::language::interpreter::{interpret, Value};
 use glenside::language::rewrites::PadLocation;
 use glenside::language::rewrites::PadSliceStrategy;
 use glenside::language::rewrites::SliceConcatenateStrategy;
 use glenside::language::MyAnalysis;
 use glenside::language::PadType;
+use glenside::phases::{run_phases, Phase};
+use ndarray::ArrayD;
+use ndarray_rand::{rand_distr::Uniform, RandomExt};
 use std::collections::HashMap;
+use std::process::Command;
 
 // Mobilenet, simplified for inference (so batch norms are removed).
 // Generate with:
@@ -24,12 +28,13 @@ use std::collections::HashMap;
 // mod = relay.transform.SimplifyInference()(mod)
 // print(mod.astext())
 // ```
-//
-// TODO(@gussmith23) Shouldn't always panic.
-// Panics at the moment because we can't actually handle the size of mobilenet.
-#[should_panic]
 #[test]
 fn mobilenet_try_to_run_rewrites() {
+    #[cfg(not(feature = "run-on-github-actions"))]
+    pub const EGG_SEARCH_TIME_SECS: u64 = 60;
+    #[cfg(feature = "run-on-github-actions")]
+    pub const EGG_SEARCH_TIME_SECS: u64 = 180;
+
     let relay = r#"
 #[version = "0.0.5"]
 def @main(%data: Tensor[(1, 3, 224, 224), float32], %conv_block_1_conv_weight: Tensor[(32, 3, 3, 3), float32], %conv_block_1_bn_gamma: Tensor[(32), float32], %conv_block_1_bn_beta: Tensor[(32), float32], %conv_block_1_bn_moving_mean: Tensor[(32), float32], %conv_block_1_bn_moving_var: Tensor[(32), float32], %separable_conv_block_1_weight: Tensor[(32, 1, 3, 3), float32], %separable_conv_block_1_bn1_gamma: Tensor[(32), float32], %separable_conv_block_1_bn1_beta: Tensor[(32), float32], %separable_conv_block_1_bn1_moving_mean: Tensor[(32), float32], %separable_conv_block_1_bn1_moving_var: Tensor[(32), float32], %separable_conv_block_1_conv2_weight: Tensor[(64, 32, 1, 1), float32], %separable_conv_block_1_bn2_gamma: Tensor[(64), float32], %separable_conv_block_1_bn2_beta: Tensor[(64), float32], %separable_conv_block_1_bn2_moving_mean: Tensor[(64), float32], %separable_conv_block_1_bn2_moving_var: Tensor[(64), float32], %separable_conv_block_2_weight: Tensor[(64, 1, 3, 3), float32], %separable_conv_block_2_bn1_gamma: Tensor[(64), float32], %separable_conv_block_2_bn1_beta: Tensor[(64), float32], %separable_conv_block_2_bn1_moving_mean: Tensor[(64), float32], %separable_conv_block_2_bn1_moving_var: Tensor[(64), float32], %separable_conv_block_2_conv2_weight: Tensor[(128, 64, 1, 1), float32], %separable_conv_block_2_bn2_gamma: Tensor[(128), float32], %separable_conv_block_2_bn2_beta: Tensor[(128), float32], %separable_conv_block_2_bn2_moving_mean: Tensor[(128), float32], %separable_conv_block_2_bn2_moving_var: Tensor[(128), float32], %separable_conv_block_3_weight: Tensor[(128, 1, 3, 3), float32], %separable_conv_block_3_bn1_gamma: Tensor[(128), float32], %separable_conv_block_3_bn1_beta: Tensor[(128), float32], %separable_conv_block_3_bn1_moving_mean: Tensor[(128), float32], %separable_conv_block_3_bn1_moving_var: Tensor[(128), float32], %separable_conv_block_3_conv2_weight: Tensor[(128, 128, 1, 1), float32], %separable_conv_block_3_bn2_gamma: Tensor[(128), float32], %separable_conv_block_3_bn2_beta: Tensor[(128), float32], %separable_conv_block_3_bn2_moving_mean: Tensor[(128), float32], %separable_conv_block_3_bn2_moving_var: Tensor[(128), float32], %separable_conv_block_4_weight: Tensor[(128, 1, 3, 3), float32], %separable_conv_block_4_bn1_gamma: Tensor[(128), float32], %separable_conv_block_4_bn1_beta: Tensor[(128), float32], %separable_conv_block_4_bn1_moving_mean: Tensor[(128), float32], %separable_conv_block_4_bn1_moving_var: Tensor[(128), float32], %separable_conv_block_4_conv2_weight: Tensor[(256, 128, 1, 1), float32], %separable_conv_block_4_bn2_gamma: Tensor[(256), float32], %separable_conv_block_4_bn2_beta: Tensor[(256), float32], %separable_conv_block_4_bn2_moving_mean: Tensor[(256), float32], %separable_conv_block_4_bn2_moving_var: Tensor[(256), float32], %separable_conv_block_5_weight: Tensor[(256, 1, 3, 3), float32], %separable_conv_block_5_bn1_gamma: Tensor[(256), float32], %separable_conv_block_5_bn1_beta: Tensor[(256), float32], %separable_conv_block_5_bn1_moving_mean: Tensor[(256), float32], %separable_conv_block_5_bn1_moving_var: Tensor[(256), float32], %separable_conv_block_5_conv2_weight: Tensor[(256, 256, 1, 1), float32], %separable_conv_block_5_bn2_gamma: Tensor[(256), float32], %separable_conv_block_5_bn2_beta: Tensor[(256), float32], %separable_conv_block_5_bn2_moving_mean: Tensor[(256), float32], %separable_conv_block_5_bn2_moving_var: Tensor[(256), float32], %separable_conv_block_6_weight: Tensor[(256, 1, 3, 3), float32], %separable_conv_block_6_bn1_gamma: Tensor[(256), float32], %separable_conv_block_6_bn1_beta: Tensor[(256), float32], %separable_conv_block_6_bn1_moving_mean: Tensor[(256), float32], %separable_conv_block_6_bn1_moving_var: Tensor[(256), float32], %separable_conv_block_6_conv2_weight: Tensor[(512, 256, 1, 1), float32], %separable_conv_block_6_bn2_gamma: Tensor[(512), float32], %separable_conv_block_6_bn2_beta: Tensor[(512), float32], %separable_conv_block_6_bn2_moving_mean: Tensor[(512), float32], %separable_conv_block_6_bn2_moving_var: Tensor[(512), float32], %separable_conv_block_7_weight: Tensor[(512, 1, 3, 3), float32], %separable_conv_block_7_bn1_gamma: Tensor[(512), float32], %separable_conv_block_7_bn1_beta: Tensor[(512), float32], %separable_conv_block_7_bn1_moving_mean: Tensor[(512), float32], %separable_conv_block_7_bn1_moving_var: Tensor[(512), float32], %separable_conv_block_7_conv2_weight: Tensor[(512, 512, 1, 1), float32], %separable_conv_block_7_bn2_gamma: Tensor[(512), float32], %separable_conv_block_7_bn2_beta: Tensor[(512), float32], %separable_conv_block_7_bn2_moving_mean: Tensor[(512), float32], %separable_conv_block_7_bn2_moving_var: Tensor[(512), float32], %separable_conv_block_8_weight: Tensor[(512, 1, 3, 3), float32], %separable_conv_block_8_bn1_gamma: Tensor[(512), float32], %separable_conv_block_8_bn1_beta: Tensor[(512), float32], %separable_conv_block_8_bn1_moving_mean: Tensor[(512), float32], %separable_conv_block_8_bn1_moving_var: Tensor[(512), float32], %separable_conv_block_8_conv2_weight: Tensor[(512, 512, 1, 1), float32], %separable_conv_block_8_bn2_gamma: Tensor[(512), float32], %separable_conv_block_8_bn2_beta: Tensor[(512), float32], %separable_conv_block_8_bn2_moving_mean: Tensor[(512), float32], %separable_conv_block_8_bn2_moving_var: Tensor[(512), float32], %separable_conv_block_9_weight: Tensor[(512, 1, 3, 3), float32], %separable_conv_block_9_bn1_gamma: Tensor[(512), float32], %separable_conv_block_9_bn1_beta: Tensor[(512), float32], %separable_conv_block_9_bn1_moving_mean: Tensor[(512), float32], %separable_conv_block_9_bn1_moving_var: Tensor[(512), float32], %separable_conv_block_9_conv2_weight: Tensor[(512, 512, 1, 1), float32], %separable_conv_block_9_bn2_gamma: Tensor[(512), float32], %separable_conv_block_9_bn2_beta: Tensor[(512), float32], %separable_conv_block_9_bn2_moving_mean: Tensor[(512), float32], %separable_conv_block_9_bn2_moving_var: Tensor[(512), float32], %separable_conv_block_10_weight: Tensor[(512, 1, 3, 3), float32], %separable_conv_block_10_bn1_gamma: Tensor[(512), float32], %separable_conv_block_10_bn1_beta: Tensor[(512), float32], %separable_conv_block_10_bn1_moving_mean: Tensor[(512), float32], %separable_conv_block_10_bn1_moving_var: Tensor[(512), float32], %separable_conv_block_10_conv2_weight: Tensor[(512, 512, 1, 1), float32], %separable_conv_block_10_bn2_gamma: Tensor[(512), float32], %separable_conv_block_10_bn2_beta: Tensor[(512), float32], %separable_conv_block_10_bn2_moving_mean: Tensor[(512), float32], %separable_conv_block_10_bn2_moving_var: Tensor[(512), float32], %separable_conv_block_11_weight: Tensor[(512, 1, 3, 3), float32], %separable_conv_block_11_bn1_gamma: Tensor[(512), float32], %separable_conv_block_11_bn1_beta: Tensor[(512), float32], %separable_conv_block_11_bn1_moving_mean: Tensor[(512), float32], %separable_conv_block_11_bn1_moving_var: Tensor[(512), float32], %separable_conv_block_11_conv2_weight: Tensor[(512, 512, 1, 1), float32], %separable_conv_block_11_bn2_gamma: Tensor[(512), float32], %separable_conv_block_11_bn2_beta: Tensor[(512), float32], %separable_conv_block_11_bn2_moving_mean: Tensor[(512), float32], %separable_conv_block_11_bn2_moving_var: Tensor[(512), float32], %separable_conv_block_12_weight: Tensor[(512, 1, 3, 3), float32], %separable_conv_block_12_bn1_gamma: Tensor[(512), float32], %separable_conv_block_12_bn1_beta: Tensor[(512), float32], %separable_conv_block_12_bn1_moving_mean: Tensor[(512), float32], %separable_conv_block_12_bn1_moving_var: Tensor[(512), float32], %separable_conv_block_12_conv2_weight: Tensor[(1024, 512, 1, 1), float32], %separable_conv_block_12_bn2_gamma: Tensor[(1024), float32], %separable_conv_block_12_bn2_beta: Tensor[(1024), float32], %separable_conv_block_12_bn2_moving_mean: Tensor[(1024), float32], %separable_conv_block_12_bn2_moving_var: Tensor[(1024), float32], %separable_conv_block_13_weight: Tensor[(1024, 1, 3, 3), float32], %separable_conv_block_13_bn1_gamma: Tensor[(1024), float32], %separable_conv_block_13_bn1_beta: Tensor[(1024), float32], %separable_conv_block_13_bn1_moving_mean: Tensor[(1024), float32], %separable_conv_block_13_bn1_moving_var: Tensor[(1024), float32], %separable_conv_block_13_conv2_weight: Tensor[(1024, 1024, 1, 1), float32], %separable_conv_block_13_bn2_gamma: Tensor[(1024), float32], %separable_conv_block_13_bn2_beta: Tensor[(1024), float32], %separable_conv_block_13_bn2_moving_mean: Tensor[(1024), float32], %separable_conv_block_13_bn2_moving_var: Tensor[(1024), float32], %fc_weight: Tensor[(1000, 1024), float32], %fc_bias: Tensor[(1000), float32]) -> Tensor[(1, 1000), float32] {
@@ -400,74 +405,87 @@ def @main(%data: Tensor[(1, 3, 224, 224), float32], %conv_block_1_conv_weight: T
         env.insert(k.clone(), v.clone());
     }
 
-    // TODO(@gussmith23) Include some simple simplifying rewrites
-    // If we add some very basic rewrites here, then $glenside_str
-    // won't need to exactly match what's actually produced by
-    // from_relay.py. It can be simpler (e.g. collapsing accesses).
     let mut egraph = EGraph::new(MyAnalysis {
         name_to_shape: env.clone(),
+        name_to_dtype: HashMap::default(),
     });
-    let _id = egraph.add_expr(&expr);
-
-    let rws = vec![
-        glenside::language::rewrites::flatten_unflatten_any_access(),
-        glenside::language::rewrites::bubble_reshape_through_cartesian_product(),
-        glenside::language::rewrites::bubble_reshape_through_compute_dot_product(),
-        glenside::language::rewrites::bubble_access_concatenate_through_access_cartesian_product_not_item_axis_left(),
-        glenside::language::rewrites::bubble_access_concatenate_through_access_cartesian_product_not_item_axis_right(),
-        glenside::language::rewrites::bubble_access_concatenate_through_access_cartesian_product_same_item_axis(),
-        glenside::language::rewrites::bubble_access_concatenate_through_compute_dot_product_item_axis(),
-        glenside::language::rewrites::bubble_access_concatenate_through_compute_dot_product_not_item_axis(),
-        glenside::language::rewrites::bubble_access_slice_through_access_pad_inequal_axes(),
-        glenside::language::rewrites::systolic_array(),
-        glenside::language::rewrites::pad_slice_accesses(
-            0,
-            PadSliceStrategy::PadToClosestMultipleOf {
-                multiple_of: 64,
-                pad_location: PadLocation::End,
-                pad_type: PadType::ZeroPadding,
-            },
-        ),
-        glenside::language::rewrites::pad_slice_accesses(
-            1,
-            PadSliceStrategy::PadToClosestMultipleOf {
-                multiple_of: 64,
-                pad_location: PadLocation::End,
-                pad_type: PadType::ZeroPadding,
-            },
-        ),
-        glenside::language::rewrites::slice_concatenate_accesses(
-            0,
-            SliceConcatenateStrategy::DivideInto { segment_size: 64 },
-        ),
-        glenside::language::rewrites::slice_concatenate_accesses(
-            1,
-            SliceConcatenateStrategy::DivideInto { segment_size: 64 },
-        ),
-        glenside::language::rewrites::bubble_access_slice_through_access_cartesian_product_not_item_axis_left(),
-        glenside::language::rewrites::bubble_access_slice_through_access_cartesian_product_not_item_axis_right(),
-        glenside::language::rewrites::bubble_access_slice_through_access_cartesian_product_same_item_axis(),
-        glenside::language::rewrites::bubble_access_slice_through_compute_dot_product_not_item_axis(),
-        glenside::language::rewrites::bubble_access_slice_through_compute_dot_product_item_axis_not_tuple_axis(),
-    ];
+    let id = egraph.add_expr(&expr);
 
-    // TODO(@gussmith23) This is starting to become a flaky test...
-    // I know the correct program can be found, but it takes time.
-    let runner = Runner::<_, _, ()>::new(MyAnalysis::default())
-        .with_egraph(egraph)
-        .with_time_limit(std::time::Duration::from_secs(10))
-        .with_node_limit(500000)
-        .with_iter_limit(40)
-        .run(&rws);
-
-    runner.print_report();
+    // First, flatten the convolutions and dense layers into matrix
+    // multiplications. Only then do we pad and slice them to fit 64x64
+    // systolic arrays, so that padding and slicing isn't wasted on accesses
+    // which will never be tensorized.
+    let run = run_phases(
+        egraph,
+        id,
+        &[
+            Phase::new(
+                "flatten",
+                vec![
+                    glenside::language::rewrites::flatten_unflatten_any_access(),
+                    glenside::language::rewrites::bubble_reshape_through_cartesian_product(),
+                    glenside::language::rewrites::bubble_reshape_through_compute_dot_product(),
+                ],
+            )
+            .with_iter_limit(10)
+            .with_node_limit(500000),
+            Phase::new(
+                "pad-slice-and-tensorize",
+                vec![
+                    glenside::language::rewrites::flatten_unflatten_any_access(),
+                    glenside::language::rewrites::bubble_reshape_through_cartesian_product(),
+                    glenside::language::rewrites::bubble_reshape_through_compute_dot_product(),
+                    glenside::language::rewrites::bubble_access_concatenate_through_access_cartesian_product_not_item_axis_left(),
+                    glenside::language::rewrites::bubble_access_concatenate_through_access_cartesian_product_not_item_axis_right(),
+                    glenside::language::rewrites::bubble_access_concatenate_through_access_cartesian_product_same_item_axis(),
+                    glenside::language::rewrites::bubble_access_concatenate_through_compute_dot_product_item_axis(),
+                    glenside::language::rewrites::bubble_access_concatenate_through_compute_dot_product_not_item_axis(),
+                    glenside::language::rewrites::bubble_access_slice_through_access_pad_inequal_axes(),
+                    glenside::language::rewrites::systolic_array(),
+                    glenside::language::rewrites::pad_slice_accesses(
+                        0,
+                        PadSliceStrategy::PadToClosestMultipleOf {
+                            multiple_of: 64,
+                            pad_location: PadLocation::End,
+                            pad_type: PadType::ZeroPadding,
+                        },
+                    ),
+                    glenside::language::rewrites::pad_slice_accesses(
+                        1,
+                        PadSliceStrategy::PadToClosestMultipleOf {
+                            multiple_of: 64,
+                            pad_location: PadLocation::End,
+                            pad_type: PadType::ZeroPadding,
+                        },
+                    ),
+                    glenside::language::rewrites::slice_concatenate_accesses(
+                        0,
+                        SliceConcatenateStrategy::DivideInto { segment_size: 64 },
+                    ),
+                    glenside::language::rewrites::slice_concatenate_accesses(
+                        1,
+                        SliceConcatenateStrategy::DivideInto { segment_size: 64 },
+                    ),
+                    glenside::language::rewrites::bubble_access_slice_through_access_cartesian_product_not_item_axis_left(),
+                    glenside::language::rewrites::bubble_access_slice_through_access_cartesian_product_not_item_axis_right(),
+                    glenside::language::rewrites::bubble_access_slice_through_access_cartesian_product_same_item_axis(),
+                    glenside::language::rewrites::bubble_access_slice_through_compute_dot_product_not_item_axis(),
+                    glenside::language::rewrites::bubble_access_slice_through_compute_dot_product_item_axis_not_tuple_axis(),
+                ],
+            )
+            .with_time_limit(std::time::Duration::from_secs(EGG_SEARCH_TIME_SECS))
+            .with_node_limit(1000000)
+            .with_iter_limit(40),
+        ],
+        |_, _| unreachable!("No phase extracts"),
+    );
 
     // Did any tensorization happen?
     assert!(
         "(systolic-array ?a ?b ?c ?d)"
             .parse::<Pattern<_>>()
             .unwrap()
-            .search(&runner.egraph)
+            .search(&run.egraph)
             .len()
             > 0
     );
@@ -478,22 +496,122 @@ def @main(%data: Tensor[(1, 3, 224, 224), float32], %conv_block_1_conv_weight: T
         "(systolic-array 64 64 ?c ?d)"
             .parse::<Pattern<_>>()
             .unwrap()
-            .search(&runner.egraph)
+            .search(&run.egraph)
             .len()
             > 0
     );
 
     // Can we extract something that can be turned into a hardware design?
-    let _ex = Extractor::new(
-        &runner.egraph,
+    // Mobilenet is too deep to extract with egg's (recursive) extractor.
+    let (cost, extracted_expr) = glenside::extraction::find_best(
+        &run.egraph,
+        run.id,
         MonolithicCostFunction {
-            systolic_array_configuration: (16, 128),
-            egraph: &runner.egraph,
+            systolic_array_configuration: (64, 64),
+            egraph: &run.egraph,
             prefer_systolic_arrays_with_blocking: false,
         },
     );
-    // TODO(@gussmith23) This is overflowing the stack
-    // See https://github.com/egraphs-good/egg/issues/50
-    // let (cost, _) = ex.find_best(id);
-    // assert!(cost < usize::MAX);
+    assert!(cost < MonolithicCostFunction::INFINITY_VALUE);
+
+    let mut egraph = EGraph::new(MyAnalysis {
+        name_to_shape: env.clone(),
+        name_to_dtype: HashMap::default(),
+    });
+    let id = glenside::extraction::add_expr(&mut egraph, &extracted_expr);
+
+    let (hw_map, _hw_atoms) =
+        glenside::codegen::create_hardware_design_monolithic(&egraph, (64, 64));
+
+    let mut found_vars = glenside::codegen::find_vars(&egraph, id);
+    found_vars.sort();
+
+    let code = glenside::codegen::codegen(
+        &egraph,
+        id,
+        &hw_map,
+        "mobilenet",
+        "",
+        &found_vars.iter().map(AsRef::as_ref).collect(),
+    );
+
+    // Run the generated code on random inputs, and check it against the
+    // interpreter running the imported program. The inputs are kept small so
+    // that activations don't blow up over the network's many layers, and
+    // variances are kept positive, as batch norms take their square roots.
+    let inputs = shapes_vec
+        .iter()
+        .map(|(name, shape)| {
+            let distribution = if name.ends_with("moving_var") {
+                Uniform::new(0.5f32, 1.5f32)
+            } else if name == "data" {
+                Uniform::new(-1f32, 1f32)
+            } else {
+                Uniform::new(-0.1f32, 0.1f32)
+            };
+            (name.clone(), ArrayD::random(shape.clone(), distribution))
+        })
+        .collect::<HashMap<_, _>>();
+    let interpreter_env = inputs
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.clone()))
+        .collect::<HashMap<_, _>>();
+    let expected = match interpret(&expr, expr.as_ref().len() - 1, &interpreter_env) {
+        Value::Access(a) => a.tensor,
+        _ => panic!(),
+    };
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let mut code_filepath = std::env::temp_dir();
+    code_filepath.push(format!("mobilenet-{}.c", nanos));
+    std::fs::write(&code_filepath, code).unwrap();
+    let mut harness_filepath = std::env::temp_dir();
+    harness_filepath.push(format!("mobilenet-harness-{}.c", nanos));
+    std::fs::write(
+        &harness_filepath,
+        glenside::codegen::c_test_harness(
+            code_filepath.to_str().unwrap(),
+            "mobilenet",
+            &found_vars
+                .iter()
+                .map(|name| (name.as_str(), DType::Fp32, inputs[name].view()))
+                .collect::<Vec<_>>(),
+            DType::Fp32,
+            &expected.view(),
+            1e-5,
+        ),
+    )
+    .unwrap();
+    let mut binary_filepath = std::env::temp_dir();
+    binary_filepath.push(format!("mobilenet-harness-{}", nanos));
+
+    let output = Command::new("gcc")
+        .arg("-Werror")
+        .arg(&harness_filepath)
+        .arg(format!(
+            "{}/data/codegen-mlp/rtml_systolic_array_weight_stationary.c",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .arg("-o")
+        .arg(&binary_filepath)
+        .arg("-lm")
+        .output()
+        .expect("Failed to compile with gcc");
+    assert!(
+        output.status.success(),
+        "Compilation failed. stderr:\n{}",
+        std::str::from_utf8(output.stderr.as_slice()).expect("Could not convert stderr to UTF8")
+    );
+
+    let output = Command::new(&binary_filepath)
+        .output()
+        .expect("Failed to run the test harness");
+    assert!(
+        output.status.success(),
+        "Generated code did not match the interpreter. stderr:\n{}",
+        std::str::from_utf8(output.stderr.as_slice()).expect("Could not convert stderr to UTF8")
+    );
 }
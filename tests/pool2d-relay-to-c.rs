@@ -0,0 +1,149 @@
+#![cfg(feature = "tvm")]
+
+use egg::EGraph;
+use glenside::hw_design_language::DType;
+use glenside::language::interpreter::{interpret, Value};
+use glenside::language::MyAnalysis;
+use ndarray::ArrayD;
+use ndarray_rand::{rand_distr::Uniform, RandomExt};
+use std::collections::HashMap;
+use std::process::Command;
+
+/// Imports `relay`, a Relay program with a single input `data`, generates C
+/// for it, and checks the C against Glenside's interpreter on a random input.
+fn check_codegen(name: &str, relay: &str) {
+    let module = tvm::ir::module::IRModule::parse("", relay);
+    let (expr, shapes_vec) = glenside::language::from_relay::from_relay(&module);
+    let env = shapes_vec.iter().cloned().collect::<HashMap<_, _>>();
+
+    let mut egraph = EGraph::new(MyAnalysis {
+        name_to_shape: env.clone(),
+        name_to_dtype: HashMap::default(),
+    });
+    let id = egraph.add_expr(&expr);
+    let code =
+        glenside::codegen::codegen(&egraph, id, &HashMap::default(), name, "", &vec!["data"]);
+
+    let data = ArrayD::<f32>::random(env["data"].clone(), Uniform::new(-1f32, 1f32));
+    let mut interpreter_env = HashMap::default();
+    interpreter_env.insert("data", data.clone());
+    let expected = match interpret(&expr, expr.as_ref().len() - 1, &interpreter_env) {
+        Value::Access(a) => a.tensor,
+        _ => panic!(),
+    };
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let mut code_filepath = std::env::temp_dir();
+    code_filepath.push(format!("{}-{}.c", name, nanos));
+    std::fs::write(&code_filepath, code).unwrap();
+    let mut harness_filepath = std::env::temp_dir();
+    harness_filepath.push(format!("{}-harness-{}.c", name, nanos));
+    std::fs::write(
+        &harness_filepath,
+        glenside::codegen::c_test_harness(
+            code_filepath.to_str().unwrap(),
+            name,
+            &[("data", DType::Fp32, data.view())],
+            DType::Fp32,
+            &expected.view(),
+            1e-6,
+        ),
+    )
+    .unwrap();
+    let mut binary_filepath = std::env::temp_dir();
+    binary_filepath.push(format!("{}-harness-{}", name, nanos));
+
+    let output = Command::new("gcc")
+        .arg("-Werror")
+        .arg(&harness_filepath)
+        .arg("-o")
+        .arg(&binary_filepath)
+        .arg("-lm")
+        .output()
+        .expect("Failed to compile with gcc");
+    assert!(
+        output.status.success(),
+        "Compilation failed. stderr:\n{}",
+        std::str::from_utf8(output.stderr.as_slice()).expect("Could not convert stderr to UTF8")
+    );
+
+    let output = Command::new(&binary_filepath)
+        .output()
+        .expect("Failed to run the test harness");
+    assert!(
+        output.status.success(),
+        "Generated code did not match the interpreter. stderr:\n{}",
+        std::str::from_utf8(output.stderr.as_slice()).expect("Could not convert stderr to UTF8")
+    );
+}
+
+/// Max pooling pads with the smallest float, so that padding is never the max.
+#[test]
+fn max_pool2d() {
+    check_codegen(
+        "max_pool2d",
+        r#"
+#[version = "0.0.5"]
+def @main(%data: Tensor[(1, 3, 8, 8), float32]) -> Tensor[(1, 3, 4, 4), float32] {
+  nn.max_pool2d(%data, pool_size=[3, 3], strides=[2, 2], padding=[1, 1, 1, 1]) /* ty=Tensor[(1, 3, 4, 4), float32] */
+}
+"#,
+    );
+}
+
+#[test]
+fn max_pool2d_nhwc() {
+    check_codegen(
+        "max_pool2d_nhwc",
+        r#"
+#[version = "0.0.5"]
+def @main(%data: Tensor[(1, 8, 8, 3), float32]) -> Tensor[(1, 4, 4, 3), float32] {
+  nn.max_pool2d(%data, pool_size=[3, 3], strides=[2, 2], padding=[1, 1, 1, 1], layout="NHWC") /* ty=Tensor[(1, 4, 4, 3), float32] */
+}
+"#,
+    );
+}
+
+#[test]
+fn avg_pool2d_include_pad() {
+    check_codegen(
+        "avg_pool2d_include_pad",
+        r#"
+#[version = "0.0.5"]
+def @main(%data: Tensor[(1, 3, 8, 8), float32]) -> Tensor[(1, 3, 4, 4), float32] {
+  nn.avg_pool2d(%data, pool_size=[3, 3], strides=[2, 2], padding=[1, 1, 1, 1], count_include_pad=True) /* ty=Tensor[(1, 3, 4, 4), float32] */
+}
+"#,
+    );
+}
+
+/// Averaging without counting the padding divides each window by the number
+/// of values in it, which is counted by pooling over a tensor of ones.
+#[test]
+fn avg_pool2d_exclude_pad() {
+    check_codegen(
+        "avg_pool2d_exclude_pad",
+        r#"
+#[version = "0.0.5"]
+def @main(%data: Tensor[(1, 3, 8, 8), float32]) -> Tensor[(1, 3, 4, 4), float32] {
+  nn.avg_pool2d(%data, pool_size=[3, 3], strides=[2, 2], padding=[1, 1, 1, 1]) /* ty=Tensor[(1, 3, 4, 4), float32] */
+}
+"#,
+    );
+}
+
+#[test]
+fn avg_pool2d_nhwc() {
+    check_codegen(
+        "avg_pool2d_nhwc",
+        r#"
+#[version = "0.0.5"]
+def @main(%data: Tensor[(1, 8, 8, 3), float32]) -> Tensor[(1, 4, 4, 3), float32] {
+  nn.avg_pool2d(%data, pool_size=[2, 2], strides=[2, 2], padding=[0, 0, 0, 0], layout="NHWC") /* ty=Tensor[(1, 4, 4, 3), float32] */
+}
+"#,
+    );
+}
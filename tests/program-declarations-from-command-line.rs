@@ -0,0 +1,212 @@
+use std::process::Command;
+
+/// Inputs declared in the program and in the shapes file must agree.
+#[test]
+fn conflicting_declarations() {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+
+    let mut program_filepath = std::env::temp_dir();
+    program_filepath.push(format!("conflicting-declarations-{}.glenside", nanos));
+    std::fs::write(
+        &program_filepath,
+        "(input input (shape 2 4))
+         (compute relu (access (access-tensor input) 1))",
+    )
+    .unwrap();
+    let mut shapes_filepath = std::env::temp_dir();
+    shapes_filepath.push(format!("conflicting-declarations-shapes-{}.json", nanos));
+    std::fs::write(&shapes_filepath, r#"{ "input": [4, 2] }"#).unwrap();
+
+    let mut out_code_filepath = std::env::temp_dir();
+    out_code_filepath.push(format!("conflicting-declarations-{}.c", nanos));
+    let mut out_design_filepath = std::env::temp_dir();
+    out_design_filepath.push(format!("conflicting-declarations-{}.json", nanos));
+
+    let output = Command::new("target/debug/glenside")
+        .arg("demo")
+        .arg("conflicting")
+        .arg(&program_filepath)
+        .arg(&shapes_filepath)
+        .arg(&out_code_filepath)
+        .arg(&out_design_filepath)
+        .output()
+        .expect("Failed to run glenside");
+
+    let stderr =
+        std::str::from_utf8(output.stderr.as_slice()).expect("Could not convert stderr to UTF8");
+    assert_eq!(output.status.code(), Some(1), "stderr:\n{}", stderr);
+    assert!(!stderr.contains("panicked"), "stderr:\n{}", stderr);
+    assert!(
+        stderr.contains("declares input with shape [2, 4]"),
+        "stderr:\n{}",
+        stderr
+    );
+}
+
+/// A malformed declaration should be reported as an error, not a panic.
+#[test]
+fn malformed_declaration() {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+
+    let mut program_filepath = std::env::temp_dir();
+    program_filepath.push(format!("malformed-declaration-{}.glenside", nanos));
+    std::fs::write(
+        &program_filepath,
+        "(input input (shape 2 four))
+         (compute relu (access (access-tensor input) 1))",
+    )
+    .unwrap();
+
+    let mut out_code_filepath = std::env::temp_dir();
+    out_code_filepath.push(format!("malformed-declaration-{}.c", nanos));
+    let mut out_design_filepath = std::env::temp_dir();
+    out_design_filepath.push(format!("malformed-declaration-{}.json", nanos));
+
+    let output = Command::new("target/debug/glenside")
+        .arg("demo")
+        .arg("malformed")
+        .arg(&program_filepath)
+        .arg(&out_code_filepath)
+        .arg(&out_design_filepath)
+        .output()
+        .expect("Failed to run glenside");
+
+    let stderr =
+        std::str::from_utf8(output.stderr.as_slice()).expect("Could not convert stderr to UTF8");
+    assert_eq!(output.status.code(), Some(1), "stderr:\n{}", stderr);
+    assert!(!stderr.contains("panicked"), "stderr:\n{}", stderr);
+    assert!(
+        stderr.contains("found (input input (shape 2 four))"),
+        "stderr:\n{}",
+        stderr
+    );
+}
+
+/// Runs the glenside binary's demo on the MLP program, passing `shapes_filepath`
+/// if there is one. Returns the generated code and hardware design.
+fn run_mlp_demo(
+    run: &str,
+    program_filepath: &str,
+    shapes_filepath: Option<&str>,
+) -> (String, String) {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+
+    let mut out_code_filepath = std::env::temp_dir();
+    out_code_filepath.push(format!("declarations-mlp-{}-{}.c", run, nanos));
+    let mut out_design_filepath = std::env::temp_dir();
+    out_design_filepath.push(format!("declarations-mlp-design-{}-{}.json", run, nanos));
+
+    let mut command = Command::new("target/debug/glenside");
+    command.arg("demo").arg("mlp").arg(program_filepath);
+    if let Some(shapes_filepath) = shapes_filepath {
+        command.arg(shapes_filepath);
+    }
+    let output = command
+        .arg(&out_code_filepath)
+        .arg(&out_design_filepath)
+        // Limit the search by iterations, not time, so both runs explore the
+        // same e-graph.
+        .arg("--iter-limit")
+        .arg("5")
+        .output()
+        .expect("Failed to run glenside");
+    assert!(
+        output.status.success(),
+        "Glenside binary failed with code {:?}. stderr:\n{}",
+        output.status.code(),
+        std::str::from_utf8(output.stderr.as_slice()).expect("Could not convert stderr to UTF8")
+    );
+
+    (
+        std::fs::read_to_string(&out_code_filepath).unwrap(),
+        std::fs::read_to_string(&out_design_filepath).unwrap(),
+    )
+}
+
+/// A program which declares its inputs needs no shapes file, and compiles the
+/// same as the program with its shapes given in a shapes file.
+#[test]
+fn demo_with_declarations() {
+    let with_declarations = run_mlp_demo(
+        "declarations",
+        format!(
+            "{}/data/asplos-demo/mlp-with-declarations.glenside",
+            env!("CARGO_MANIFEST_DIR")
+        )
+        .as_str(),
+        None,
+    );
+    let with_shapes_file = run_mlp_demo(
+        "shapes-file",
+        format!(
+            "{}/data/asplos-demo/mlp.glenside",
+            env!("CARGO_MANIFEST_DIR")
+        )
+        .as_str(),
+        Some(
+            format!(
+                "{}/data/asplos-demo/mlp-shapes.json",
+                env!("CARGO_MANIFEST_DIR")
+            )
+            .as_str(),
+        ),
+    );
+    assert_eq!(with_declarations, with_shapes_file);
+}
+
+/// The interpret subcommand also takes its shapes from the program's
+/// declarations when no shapes file is given.
+#[test]
+fn interpret_with_declarations() {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+
+    let mut program_filepath = std::env::temp_dir();
+    program_filepath.push(format!("interpret-declarations-{}.glenside", nanos));
+    std::fs::write(
+        &program_filepath,
+        "(input t (shape 2 2))
+         (compute relu (access (access-tensor t) 1))",
+    )
+    .unwrap();
+
+    let mut input_filepath = std::env::temp_dir();
+    input_filepath.push(format!("interpret-declarations-t-{}.npy", nanos));
+    ndarray_npy::write_npy(
+        &input_filepath,
+        &ndarray::arr2(&[[-1f32, 2.], [3., -4.]]).into_dyn(),
+    )
+    .unwrap();
+
+    let mut out_filepath = std::env::temp_dir();
+    out_filepath.push(format!("interpret-declarations-out-{}.npy", nanos));
+
+    let output = Command::new("target/debug/glenside")
+        .arg("interpret")
+        .arg(&program_filepath)
+        .arg(&out_filepath)
+        .arg("--input")
+        .arg(format!("t={}", input_filepath.to_string_lossy()))
+        .output()
+        .expect("Failed to run glenside");
+    assert!(
+        output.status.success(),
+        "Glenside binary failed with code {:?}. stderr:\n{}",
+        output.status.code(),
+        std::str::from_utf8(output.stderr.as_slice()).expect("Could not convert stderr to UTF8")
+    );
+
+    let out: ndarray::ArrayD<f32> = ndarray_npy::read_npy(&out_filepath).unwrap();
+    assert_eq!(out, ndarray::arr2(&[[0f32, 2.], [3., 0.]]).into_dyn());
+}
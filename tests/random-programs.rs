@@ -0,0 +1,145 @@
+//! Differential testing of the interpreter against code generation, on random
+//! well-shaped programs.
+//!
+//! Each program is interpreted in Rust, and also compiled to C, which we build
+//! with gcc and run on the same inputs. The generated C checks its output
+//! against the interpreter's.
+
+use egg::EGraph;
+use glenside::codegen::{c_test_harness, codegen};
+use glenside::language::interpreter::{interpret, Value};
+use glenside::language::random_program::random_program;
+use glenside::language::{MyAnalysis, MyAnalysisData};
+use ndarray::ArrayD;
+use ndarray_rand::{rand_distr::Uniform, RandomExt};
+use rand::{rngs::StdRng, SeedableRng};
+use std::collections::HashMap;
+use std::process::Command;
+
+/// The number of random programs to test.
+const NUM_PROGRAMS: u64 = 100;
+
+/// The deepest program to generate.
+const MAX_DEPTH: usize = 5;
+
+fn check_program(seed: u64) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let program = random_program(&mut rng, (seed as usize) % MAX_DEPTH + 1);
+    let pretty = program.expr.pretty(80);
+
+    let mut names = program
+        .shapes
+        .keys()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    names.sort();
+    let inputs = names
+        .iter()
+        .map(|name| {
+            (
+                *name,
+                ArrayD::<f32>::random(program.shapes[*name].clone(), Uniform::new(-1f32, 1f32)),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+
+    let expected = match interpret(&program.expr, program.expr.as_ref().len() - 1, &inputs) {
+        Value::Access(a) => a.tensor,
+        _ => panic!("Program did not produce an access:\n{}", pretty),
+    };
+
+    let mut egraph = EGraph::new(MyAnalysis {
+        name_to_shape: program.shapes.clone(),
+        name_to_dtype: HashMap::default(),
+    });
+    let id = egraph.add_expr(&program.expr);
+    match &egraph[id].data {
+        MyAnalysisData::AccessPattern(a) => {
+            assert_eq!(
+                a.as_vec(),
+                expected.shape(),
+                "Analysis and interpreter disagree on the shape of:\n{}",
+                pretty
+            )
+        }
+        _ => panic!(),
+    }
+
+    let code = codegen(
+        &egraph,
+        id,
+        &HashMap::default(),
+        "random_program",
+        "",
+        &names,
+    );
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let mut code_filepath = std::env::temp_dir();
+    code_filepath.push(format!("random-program-{}-{}.c", seed, nanos));
+    let mut harness_filepath = std::env::temp_dir();
+    harness_filepath.push(format!("random-program-{}-main-{}.c", seed, nanos));
+    let mut binary_filepath = std::env::temp_dir();
+    binary_filepath.push(format!("random-program-{}-{}", seed, nanos));
+
+    std::fs::write(&code_filepath, code).unwrap();
+    std::fs::write(
+        &harness_filepath,
+        c_test_harness(
+            code_filepath.to_str().unwrap(),
+            "random_program",
+            &names
+                .iter()
+                .map(|name| {
+                    (
+                        *name,
+                        egraph.analysis.get_input_dtype(name),
+                        inputs[name].view(),
+                    )
+                })
+                .collect::<Vec<_>>(),
+            MyAnalysis::get_dtype(id, &egraph),
+            &expected.view(),
+            1e-4,
+        ),
+    )
+    .unwrap();
+
+    let output = Command::new("gcc")
+        .arg("-g")
+        .arg("-Werror")
+        .arg(&harness_filepath)
+        .arg("-o")
+        .arg(&binary_filepath)
+        .arg("-lm")
+        .output()
+        .expect("Failed to run gcc");
+    assert!(
+        output.status.success(),
+        "Compilation failed for seed {}:\n{}\nstderr:\n{}",
+        seed,
+        pretty,
+        std::str::from_utf8(output.stderr.as_slice()).expect("Could not convert stderr to UTF8")
+    );
+
+    let output = Command::new(&binary_filepath)
+        .output()
+        .expect("Failed to run compiled program");
+    assert!(
+        output.status.success(),
+        "Generated code and interpreter disagree for seed {}:\n{}\nstderr:\n{}",
+        seed,
+        pretty,
+        std::str::from_utf8(output.stderr.as_slice()).expect("Could not convert stderr to UTF8")
+    );
+}
+
+#[test]
+fn interpreter_matches_codegen() {
+    for seed in 0..NUM_PROGRAMS {
+        check_program(seed);
+    }
+}
@@ -0,0 +1,125 @@
+use serde_json::Value;
+use std::process::Command;
+
+/// Runs the MLP demo with `--report`, plus `extra_args`, returning the parsed
+/// report.
+fn mlp_report(name: &str, extra_args: &[&str]) -> Value {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+
+    let program_filepath = format!(
+        "{}/data/asplos-demo/mlp.glenside",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let shapes_filepath = format!(
+        "{}/data/asplos-demo/mlp-shapes.json",
+        env!("CARGO_MANIFEST_DIR")
+    );
+
+    let mut out_code_filepath = std::env::temp_dir();
+    out_code_filepath.push(format!("report-{}-{}.c", name, nanos));
+    let mut out_design_filepath = std::env::temp_dir();
+    out_design_filepath.push(format!("report-{}-design-{}.json", name, nanos));
+    let mut out_report_filepath = std::env::temp_dir();
+    out_report_filepath.push(format!("report-{}-{}.json", name, nanos));
+
+    let output = Command::new("target/debug/glenside")
+        .arg("demo")
+        .arg("mlp")
+        .arg(program_filepath)
+        .arg(shapes_filepath)
+        .arg(&out_code_filepath)
+        .arg(&out_design_filepath)
+        .arg("--report")
+        .arg(&out_report_filepath)
+        .args(extra_args)
+        .output()
+        .expect("Failed to run glenside");
+    assert!(
+        output.status.success(),
+        "Glenside binary failed with code {:?}. stderr:\n{}",
+        output.status.code(),
+        std::str::from_utf8(output.stderr.as_slice()).expect("Could not convert stderr to UTF8")
+    );
+
+    serde_json::from_str(
+        std::fs::read_to_string(&out_report_filepath)
+            .unwrap()
+            .as_str(),
+    )
+    .unwrap()
+}
+
+/// The total number of applications of the rewrite `name` across all
+/// iterations of a run.
+fn applications(run: &Value, name: &str) -> u64 {
+    run["iterations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|iteration| iteration["rewrites"][name]["applications"].as_u64())
+        .sum()
+}
+
+#[test]
+fn report_mlp() {
+    let report = mlp_report("mlp", &[]);
+
+    let runs = report["runs"].as_array().unwrap();
+    assert_eq!(runs.len(), 1);
+    let iterations = runs[0]["iterations"].as_array().unwrap();
+    assert!(!iterations.is_empty());
+    for iteration in iterations {
+        assert!(iteration["egraph_nodes"].as_u64().unwrap() > 0);
+        assert!(iteration["egraph_classes"].as_u64().unwrap() > 0);
+        assert!(iteration["search_time"].as_f64().unwrap() >= 0.0);
+        assert!(iteration["apply_time"].as_f64().unwrap() >= 0.0);
+        assert!(iteration["rebuild_time"].as_f64().unwrap() >= 0.0);
+        for counts in iteration["rewrites"].as_object().unwrap().values() {
+            assert!(
+                counts["applications"].as_u64().unwrap() <= counts["matches"].as_u64().unwrap()
+            );
+        }
+    }
+    assert!(applications(&runs[0], "systolic-array") > 0);
+
+    let configurations = report["systolic_array_configurations"].as_array().unwrap();
+    assert!(!configurations.is_empty());
+    for configuration in configurations {
+        let configuration = configuration.as_array().unwrap();
+        assert_eq!(configuration.len(), 2);
+        assert!(configuration.iter().all(|dim| dim.as_u64().unwrap() > 0));
+    }
+}
+
+#[test]
+fn report_mlp_phases() {
+    let phases_filepath = format!(
+        "{}/data/rewrite-configs/demo-phases.json",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let report = mlp_report(
+        "mlp-phases",
+        &[
+            "--phases",
+            phases_filepath.as_str(),
+            "--software-compute-cost",
+            "1000000",
+        ],
+    );
+
+    let runs = report["runs"].as_array().unwrap();
+    assert_eq!(
+        runs.iter()
+            .map(|run| run["name"].as_str().unwrap())
+            .collect::<Vec<_>>(),
+        vec!["normalize", "pad-and-tile", "tensorize"]
+    );
+    assert!(applications(&runs[2], "systolic-array") > 0);
+    assert!(!report["systolic_array_configurations"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+}
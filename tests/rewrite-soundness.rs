@@ -0,0 +1,638 @@
+//! Checks that rewrites are sound: that every term a rewrite adds to an e-class
+//! computes the same value as the terms already there.
+//!
+//! For each rewrite, we start from a seed program on which the rewrite fires,
+//! run just that rewrite, and then extract several different terms from the
+//! root e-class. Each term is interpreted on the same random inputs, and the
+//! results are compared.
+
+use approx::AbsDiffEq;
+use egg::{AstSize, EGraph, Extractor, Id, Language as LanguageTrait, RecExpr, Rewrite, Runner};
+use glenside::language::interpreter::{interpret, Value};
+use glenside::language::rewrites::{self, PadLocation, PadSliceStrategy, SliceConcatenateStrategy};
+use glenside::language::{Language, MyAnalysis, PadType};
+use ndarray::ArrayD;
+use ndarray_rand::{rand_distr::Uniform, RandomExt};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// The number of random inputs to compare the terms on.
+const SAMPLES: usize = 3;
+
+/// The maximum number of terms to extract from the root e-class.
+const MAX_TERMS: usize = 8;
+
+/// Adds `sub` to `expr`, returning the id of its root.
+fn append(expr: &mut RecExpr<Language>, sub: &RecExpr<Language>) -> Id {
+    let mut ids: Vec<Id> = Vec::default();
+    for node in sub.as_ref() {
+        let node = node.clone().map_children(|id| ids[usize::from(id)]);
+        ids.push(expr.add(node));
+    }
+    *ids.last().unwrap()
+}
+
+/// Extracts up to [`MAX_TERMS`] different terms from the e-class `id`: one for
+/// each e-node in the class, with the smallest terms for its children.
+fn terms(egraph: &EGraph<Language, MyAnalysis>, id: Id) -> Vec<RecExpr<Language>> {
+    let extractor = Extractor::new(egraph, AstSize);
+    let mut terms: Vec<RecExpr<Language>> = Vec::default();
+    for node in egraph[id].nodes.iter() {
+        let mut expr = RecExpr::default();
+        let node = node.clone().map_children(|child| {
+            let (_, child_expr) = extractor.find_best(child);
+            append(&mut expr, &child_expr)
+        });
+        expr.add(node);
+        if !terms.iter().any(|term| term.pretty(80) == expr.pretty(80)) {
+            terms.push(expr);
+        }
+        if terms.len() == MAX_TERMS {
+            break;
+        }
+    }
+    terms
+}
+
+/// Asserts that two interpreted values are equal, within tolerance.
+fn assert_values_equal(a: &Value<f64>, b: &Value<f64>, message: &str) {
+    match (a, b) {
+        (Value::Access(a), Value::Access(b)) => {
+            assert_eq!(a.access_axis, b.access_axis, "{}", message);
+            assert_eq!(a.tensor.shape(), b.tensor.shape(), "{}", message);
+            assert!(a.tensor.abs_diff_eq(&b.tensor, 1e-9), "{}", message);
+        }
+        (Value::Tensor(a), Value::Tensor(b)) => {
+            assert_eq!(a.shape(), b.shape(), "{}", message);
+            assert!(a.abs_diff_eq(b, 1e-9), "{}", message);
+        }
+        _ => panic!("Expected two accesses or two tensors: {}", message),
+    }
+}
+
+/// Runs `rewrite` on `program`, whose tensors have the given shapes, and checks
+/// that all of the terms extracted from the root e-class compute the same
+/// values on random inputs.
+fn check_rewrite(
+    rewrite: Rewrite<Language, MyAnalysis>,
+    program: &str,
+    shapes: &[(&str, &[usize])],
+) {
+    let program = RecExpr::from_str(program).unwrap();
+
+    let mut egraph = EGraph::new(MyAnalysis {
+        name_to_shape: shapes
+            .iter()
+            .map(|(name, shape)| (name.to_string(), shape.to_vec()))
+            .collect(),
+        name_to_dtype: HashMap::default(),
+    });
+    let id = egraph.add_expr(&program);
+
+    let name = rewrite.name().to_string();
+    let runner = Runner::default()
+        .with_egraph(egraph)
+        .with_iter_limit(3)
+        .with_node_limit(10000);
+    let (runner, report) = glenside::report::run_with_report(runner, "soundness", &[rewrite]);
+    assert!(
+        report
+            .iterations
+            .iter()
+            .filter_map(|iteration| iteration.rewrites.get(&name))
+            .map(|counts| counts.applications)
+            .sum::<usize>()
+            > 0,
+        "{} never fired",
+        name
+    );
+
+    let id = runner.egraph.find(id);
+    let terms = terms(&runner.egraph, id);
+    assert!(
+        terms.len() > 1,
+        "{} didn't add any new terms to the root e-class",
+        name
+    );
+
+    for _ in 0..SAMPLES {
+        let mut env = HashMap::default();
+        for (name, shape) in shapes.iter() {
+            env.insert(
+                *name,
+                ArrayD::random(shape.to_vec(), Uniform::new(-1f64, 1f64)),
+            );
+        }
+
+        let expected = interpret(&terms[0], terms[0].as_ref().len() - 1, &env);
+        for term in terms[1..].iter() {
+            let result = interpret(term, term.as_ref().len() - 1, &env);
+            assert_values_equal(
+                &expected,
+                &result,
+                format!(
+                    "{} is not sound:\n{}\ncomputes something different from\n{}",
+                    name,
+                    term.pretty(80),
+                    terms[0].pretty(80)
+                )
+                .as_str(),
+            );
+        }
+    }
+}
+
+/// Defines a test which checks the rewrite `$rewrite` for soundness on the seed
+/// program `$program`, with tensors of the given shapes.
+macro_rules! test {
+    ($test_name:ident, $rewrite:expr, $program:literal, [$(($name:literal, $shape:expr)),*]) => {
+        #[test]
+        fn $test_name() {
+            check_rewrite($rewrite, $program, &[$(($name, &$shape)),*]);
+        }
+    };
+}
+
+/// The rewrites checked in this file, by constructor name. Each should have at
+/// least one test below.
+const CHECKED: &[&str] = &[
+    "flatten_unflatten_any_access",
+    "bubble_reshape_through_cartesian_product",
+    "bubble_reshape_through_compute_dot_product",
+    "systolic_array_with_blocking",
+    "systolic_array",
+    "systolic_array_batch_matmul",
+    "slice_concatenate_accesses",
+    "slice_concatenate_tensor_accesses",
+    "collapse_nested_access_slices",
+    "access_slice_access_transpose_composition_commutative",
+    "bubble_access_concatenate_through_access_transpose",
+    "bubble_access_concatenate_through_access_cartesian_product_not_item_axis_left",
+    "bubble_access_concatenate_through_access_cartesian_product_not_item_axis_right",
+    "bubble_access_concatenate_through_access_cartesian_product_same_item_axis",
+    "bubble_access_concatenate_through_compute_dot_product_not_item_axis",
+    "bubble_access_concatenate_through_compute_dot_product_item_axis",
+    "bubble_access_concatenate_through_access",
+    "bubble_access_concatenate_through_access_slice",
+    "collapse_nested_transposes",
+    "remove_trivial_transpose",
+    "collapse_nested_accesses",
+    "pad_slice_accesses",
+    "bubble_access_slice_through_access_pad_inequal_axes",
+    "bubble_access_slice_through_access_cartesian_product_not_item_axis_left",
+    "bubble_access_slice_through_access_cartesian_product_not_item_axis_right",
+    "bubble_access_slice_through_access_cartesian_product_same_item_axis",
+    "bubble_access_slice_through_compute_dot_product_not_item_axis",
+    "bubble_access_slice_through_compute_dot_product_item_axis_not_tuple_axis",
+    "split",
+    "collapse_nested_slices",
+    "bubble_concatenate_through_move_axis",
+    "bubble_concatenate_through_cartesian_product_not_last_axis_left",
+    "bubble_concatenate_through_cartesian_product_not_last_axis_right",
+    "bubble_concatenate_through_cartesian_product_last_axis",
+    "bubble_concatenate_through_cartesian_product_axis_0_0",
+    "rewrite_nonmatching_cartesian_product_concatenate",
+    "bubble_concatenate_through_map_dot_product_not_last_axis",
+    "bubble_concatenate_through_map_dot_product_last_axis",
+    "slice_move_axis_composition_commutative",
+    "systolic_array_vector_matrix",
+];
+
+/// Makes sure that new rewrites get a soundness test.
+#[test]
+fn every_rewrite_is_checked() {
+    for registered in glenside::language::rewrite_config::registry() {
+        assert!(
+            CHECKED.contains(&registered.name),
+            "{} has no soundness test",
+            registered.name
+        );
+    }
+}
+
+test!(
+    flatten_unflatten_any_access,
+    rewrites::flatten_unflatten_any_access(),
+    "(access (access-tensor a) 1)",
+    [("a", [4, 6])]
+);
+
+test!(
+    bubble_reshape_through_cartesian_product,
+    rewrites::bubble_reshape_through_cartesian_product(),
+    "(access-cartesian-product
+      (access-reshape (access (access-tensor a) 1) (access-shape (shape 2 2) (shape 6)))
+      (access-reshape (access (access-tensor b) 1) (access-shape (shape 3) (shape 6)))
+     )",
+    [("a", [4, 6]), ("b", [3, 6])]
+);
+
+test!(
+    bubble_reshape_through_compute_dot_product,
+    rewrites::bubble_reshape_through_compute_dot_product(),
+    "(compute dot-product
+      (access-reshape (access (access-tensor a) 1) (access-shape (shape 2 2) (shape 2 3 2)))
+     )",
+    [("a", [4, 2, 6])]
+);
+
+test!(
+    systolic_array_with_blocking,
+    rewrites::systolic_array_with_blocking(2, 5),
+    "(compute dot-product
+      (access-cartesian-product (access (access-tensor a) 1) (access (access-tensor b) 1))
+     )",
+    [("a", [3, 4]), ("b", [5, 4])]
+);
+
+test!(
+    systolic_array,
+    rewrites::systolic_array(),
+    "(compute dot-product
+      (access-cartesian-product (access (access-tensor a) 1) (access (access-tensor b) 1))
+     )",
+    [("a", [3, 4]), ("b", [5, 4])]
+);
+
+test!(
+    systolic_array_batch_matmul,
+    rewrites::systolic_array_batch_matmul(),
+    "(compute dot-product
+      (access-pair
+       (access
+        (access-broadcast
+         (access-insert-axis (access-tensor a) 2)
+         (access-shape (shape 2 3 5 4) (shape))
+        )
+        3
+       )
+       (access
+        (access-broadcast
+         (access-insert-axis (access-tensor b) 1)
+         (access-shape (shape 2 3 5 4) (shape))
+        )
+        3
+       )
+      )
+     )",
+    [("a", [2, 3, 4]), ("b", [2, 5, 4])]
+);
+
+test!(
+    systolic_array_batch_matmul_broadcast,
+    rewrites::systolic_array_batch_matmul(),
+    "(compute dot-product
+      (access-pair
+       (access
+        (access-broadcast
+         (access-insert-axis (access-tensor a) 2)
+         (access-shape (shape 2 3 5 4) (shape))
+        )
+        3
+       )
+       (access
+        (access-broadcast
+         (access-insert-axis (access-tensor b) 1)
+         (access-shape (shape 2 3 5 4) (shape))
+        )
+        3
+       )
+      )
+     )",
+    [("a", [2, 3, 4]), ("b", [1, 5, 4])]
+);
+
+test!(
+    slice_concatenate_accesses_divide_into,
+    rewrites::slice_concatenate_accesses(
+        0,
+        SliceConcatenateStrategy::DivideInto { segment_size: 2 }
+    ),
+    "(access (access-tensor a) 1)",
+    [("a", [4, 6])]
+);
+
+test!(
+    slice_concatenate_accesses_divide_by,
+    rewrites::slice_concatenate_accesses(
+        1,
+        SliceConcatenateStrategy::DivideBy {
+            divisor: 2,
+            limit: 2
+        }
+    ),
+    "(access (access-tensor a) 1)",
+    [("a", [4, 6])]
+);
+
+test!(
+    slice_concatenate_tensor_accesses,
+    rewrites::slice_concatenate_tensor_accesses(0, 1),
+    "(access-tensor a)",
+    [("a", [4, 6])]
+);
+
+test!(
+    collapse_nested_access_slices,
+    rewrites::collapse_nested_access_slices(),
+    "(access-slice (access-slice (access (access-tensor a) 1) 0 1 4) 0 1 3)",
+    [("a", [4, 6])]
+);
+
+test!(
+    access_slice_access_transpose_composition_commutative,
+    rewrites::access_slice_access_transpose_composition_commutative(),
+    "(access-slice (access-transpose (access (access-tensor a) 1) (list 1 0)) 0 1 3)",
+    [("a", [4, 6])]
+);
+
+test!(
+    bubble_access_concatenate_through_access_transpose,
+    rewrites::bubble_access_concatenate_through_access_transpose(),
+    "(access-transpose
+      (access-concatenate (access (access-tensor a) 1) (access (access-tensor b) 1) 0)
+      (list 1 0)
+     )",
+    [("a", [4, 6]), ("b", [2, 6])]
+);
+
+test!(
+    bubble_access_concatenate_through_access_cartesian_product_not_item_axis_left,
+    rewrites::bubble_access_concatenate_through_access_cartesian_product_not_item_axis_left(),
+    "(access-cartesian-product
+      (access-concatenate (access (access-tensor a) 1) (access (access-tensor b) 1) 0)
+      (access (access-tensor c) 1)
+     )",
+    [("a", [4, 6]), ("b", [2, 6]), ("c", [3, 6])]
+);
+
+test!(
+    bubble_access_concatenate_through_access_cartesian_product_not_item_axis_right,
+    rewrites::bubble_access_concatenate_through_access_cartesian_product_not_item_axis_right(),
+    "(access-cartesian-product
+      (access (access-tensor c) 1)
+      (access-concatenate (access (access-tensor a) 1) (access (access-tensor b) 1) 0)
+     )",
+    [("a", [4, 6]), ("b", [2, 6]), ("c", [3, 6])]
+);
+
+test!(
+    bubble_access_concatenate_through_access_cartesian_product_same_item_axis,
+    rewrites::bubble_access_concatenate_through_access_cartesian_product_same_item_axis(),
+    "(access-cartesian-product
+      (access-concatenate (access (access-tensor a) 1) (access (access-tensor b) 1) 1)
+      (access-concatenate (access (access-tensor c) 1) (access (access-tensor d) 1) 1)
+     )",
+    [("a", [4, 6]), ("b", [4, 2]), ("c", [3, 6]), ("d", [3, 2])]
+);
+
+test!(
+    bubble_access_concatenate_through_compute_dot_product_not_item_axis,
+    rewrites::bubble_access_concatenate_through_compute_dot_product_not_item_axis(),
+    "(compute dot-product
+      (access-concatenate (access (access-tensor a) 1) (access (access-tensor b) 1) 0)
+     )",
+    [("a", [4, 2, 3]), ("b", [2, 2, 3])]
+);
+
+test!(
+    bubble_access_concatenate_through_compute_dot_product_item_axis,
+    rewrites::bubble_access_concatenate_through_compute_dot_product_item_axis(),
+    "(compute dot-product
+      (access-concatenate (access (access-tensor a) 1) (access (access-tensor b) 1) 2)
+     )",
+    [("a", [4, 2, 3]), ("b", [4, 2, 5])]
+);
+
+test!(
+    bubble_access_concatenate_through_access,
+    rewrites::bubble_access_concatenate_through_access(),
+    "(access
+      (access-concatenate (access (access-tensor a) 1) (access (access-tensor b) 1) 0)
+      0
+     )",
+    [("a", [4, 6]), ("b", [2, 6])]
+);
+
+test!(
+    bubble_access_concatenate_through_access_slice_same_axis,
+    rewrites::bubble_access_concatenate_through_access_slice(),
+    "(access-slice
+      (access-concatenate (access (access-tensor a) 1) (access (access-tensor b) 1) 0)
+      0 2 5
+     )",
+    [("a", [4, 6]), ("b", [2, 6])]
+);
+
+test!(
+    bubble_access_concatenate_through_access_slice_different_axis,
+    rewrites::bubble_access_concatenate_through_access_slice(),
+    "(access-slice
+      (access-concatenate (access (access-tensor a) 1) (access (access-tensor b) 1) 0)
+      1 1 4
+     )",
+    [("a", [4, 6]), ("b", [2, 6])]
+);
+
+test!(
+    collapse_nested_transposes,
+    rewrites::collapse_nested_transposes(),
+    "(access-transpose
+      (access-transpose (access (access-tensor a) 1) (list 1 2 0))
+      (list 1 0 2)
+     )",
+    [("a", [2, 3, 4])]
+);
+
+test!(
+    remove_trivial_transpose,
+    rewrites::remove_trivial_transpose(),
+    "(access-transpose (access (access-tensor a) 1) (list 0 1))",
+    [("a", [4, 6])]
+);
+
+test!(
+    collapse_nested_accesses,
+    rewrites::collapse_nested_accesses(),
+    "(access (access (access-tensor a) 0) 1)",
+    [("a", [4, 6])]
+);
+
+test!(
+    pad_slice_accesses_pad_to_closest_multiple_of,
+    rewrites::pad_slice_accesses(
+        0,
+        PadSliceStrategy::PadToClosestMultipleOf {
+            multiple_of: 4,
+            pad_location: PadLocation::End,
+            pad_type: PadType::ZeroPadding,
+        }
+    ),
+    "(access (access-tensor a) 1)",
+    [("a", [3, 6])]
+);
+
+test!(
+    pad_slice_accesses_pad_to_multiples_of,
+    rewrites::pad_slice_accesses(
+        1,
+        PadSliceStrategy::PadToMultiplesOf {
+            multiples_of: 4,
+            limit: 12,
+            pad_location: PadLocation::End,
+            pad_type: PadType::MinPadding,
+        }
+    ),
+    "(access (access-tensor a) 1)",
+    [("a", [3, 6])]
+);
+
+test!(
+    bubble_access_slice_through_access_pad_inequal_axes,
+    rewrites::bubble_access_slice_through_access_pad_inequal_axes(),
+    "(access-pad (access-slice (access (access-tensor a) 1) 0 1 3) zero-padding 1 2 1)",
+    [("a", [4, 6])]
+);
+
+test!(
+    bubble_access_slice_through_access_cartesian_product_not_item_axis_left,
+    rewrites::bubble_access_slice_through_access_cartesian_product_not_item_axis_left(),
+    "(access-cartesian-product
+      (access-slice (access (access-tensor a) 1) 0 1 3)
+      (access (access-tensor c) 1)
+     )",
+    [("a", [4, 6]), ("c", [3, 6])]
+);
+
+test!(
+    bubble_access_slice_through_access_cartesian_product_not_item_axis_right,
+    rewrites::bubble_access_slice_through_access_cartesian_product_not_item_axis_right(),
+    "(access-cartesian-product
+      (access (access-tensor c) 1)
+      (access-slice (access (access-tensor a) 1) 0 1 3)
+     )",
+    [("a", [4, 6]), ("c", [3, 6])]
+);
+
+test!(
+    bubble_access_slice_through_access_cartesian_product_same_item_axis,
+    rewrites::bubble_access_slice_through_access_cartesian_product_same_item_axis(),
+    "(access-cartesian-product
+      (access-slice (access (access-tensor a) 1) 1 1 4)
+      (access-slice (access (access-tensor c) 1) 1 1 4)
+     )",
+    [("a", [4, 6]), ("c", [3, 6])]
+);
+
+test!(
+    bubble_access_slice_through_compute_dot_product_not_item_axis,
+    rewrites::bubble_access_slice_through_compute_dot_product_not_item_axis(),
+    "(compute dot-product (access-slice (access (access-tensor a) 1) 0 1 3))",
+    [("a", [4, 2, 3])]
+);
+
+test!(
+    bubble_access_slice_through_compute_dot_product_item_axis_not_tuple_axis,
+    rewrites::bubble_access_slice_through_compute_dot_product_item_axis_not_tuple_axis(),
+    "(compute dot-product
+      (access-slice
+       (access-pad (access (access-tensor a) 1) zero-padding 2 1 2)
+       2 1 4
+      )
+     )",
+    [("a", [4, 2, 3])]
+);
+
+test!(split, rewrites::split(0, 2, false), "a", [("a", [4, 3])]);
+
+test!(
+    collapse_nested_slices,
+    rewrites::collapse_nested_slices(),
+    "(slice (slice a 0 1 5) 0 1 3)",
+    [("a", [6, 4])]
+);
+
+test!(
+    bubble_concatenate_through_move_axis,
+    rewrites::bubble_concatenate_through_move_axis(),
+    "(move-axis (concatenate a b 0) 0 1)",
+    [("a", [2, 3]), ("b", [4, 3])]
+);
+
+test!(
+    bubble_concatenate_through_cartesian_product_not_last_axis_left,
+    rewrites::bubble_concatenate_through_cartesian_product_not_last_axis_left(),
+    "(cartesian-product (concatenate a b 0) c)",
+    [("a", [2, 4]), ("b", [3, 4]), ("c", [5, 4])]
+);
+
+test!(
+    bubble_concatenate_through_cartesian_product_not_last_axis_right,
+    rewrites::bubble_concatenate_through_cartesian_product_not_last_axis_right(),
+    "(cartesian-product c (concatenate a b 0))",
+    [("a", [2, 4]), ("b", [3, 4]), ("c", [5, 4])]
+);
+
+test!(
+    bubble_concatenate_through_cartesian_product_last_axis,
+    rewrites::bubble_concatenate_through_cartesian_product_last_axis(),
+    "(cartesian-product (concatenate a1 a2 1) (concatenate b1 b2 1))",
+    [
+        ("a1", [2, 3]),
+        ("a2", [2, 1]),
+        ("b1", [5, 3]),
+        ("b2", [5, 1])
+    ]
+);
+
+test!(
+    bubble_concatenate_through_cartesian_product_axis_0_0,
+    rewrites::bubble_concatenate_through_cartesian_product_axis_0_0(),
+    "(cartesian-product (concatenate a1 a2 0) (concatenate b1 b2 0))",
+    [
+        ("a1", [2, 4]),
+        ("a2", [3, 4]),
+        ("b1", [5, 4]),
+        ("b2", [1, 4])
+    ]
+);
+
+test!(
+    rewrite_nonmatching_cartesian_product_concatenate,
+    rewrites::rewrite_nonmatching_cartesian_product_concatenate(),
+    "(cartesian-product (concatenate a1 a2 0) (concatenate b1 b2 1))",
+    [
+        ("a1", [2, 4]),
+        ("a2", [3, 4]),
+        ("b1", [5, 1]),
+        ("b2", [5, 3])
+    ]
+);
+
+test!(
+    bubble_concatenate_through_map_dot_product_not_last_axis,
+    rewrites::bubble_concatenate_through_map_dot_product_not_last_axis(),
+    "(map-dot-product (concatenate a b 0))",
+    [("a", [2, 2, 4]), ("b", [3, 2, 4])]
+);
+
+test!(
+    bubble_concatenate_through_map_dot_product_last_axis,
+    rewrites::bubble_concatenate_through_map_dot_product_last_axis(),
+    "(map-dot-product (concatenate a b 2))",
+    [("a", [3, 2, 4]), ("b", [3, 2, 1])]
+);
+
+test!(
+    slice_move_axis_composition_commutative,
+    rewrites::slice_move_axis_composition_commutative(),
+    "(slice (move-axis a 0 1) 1 1 3)",
+    [("a", [4, 5])]
+);
+
+test!(
+    systolic_array_vector_matrix,
+    rewrites::systolic_array_vector_matrix(),
+    "(map-dot-product (cartesian-product a (move-axis b 1 0)))",
+    [("a", [4]), ("b", [4, 5])]
+);
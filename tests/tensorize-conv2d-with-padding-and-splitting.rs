@@ -44,7 +44,10 @@ fn conv2d_im2col_tensorize_to_smaller_array_with_padding_and_slicing() {
     // kernel height, kernel width, in channels, out channels
     map.insert("weights".to_string(), vec![3, 3, 3, 8]);
 
-    let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis { name_to_shape: map });
+    let mut egraph = egg::EGraph::<Language, MyAnalysis>::new(MyAnalysis {
+        name_to_shape: map,
+        name_to_dtype: HashMap::default(),
+    });
     let id = egraph.add_expr(&expr);
 
     let rws = vec![
@@ -0,0 +1,103 @@
+use ndarray_rand::{rand_distr::Uniform, RandomExt};
+use std::process::Command;
+
+/// Runs the MLP demo with `--test-harness`, which has Glenside generate a
+/// self-checking `main()` for the generated code. The expected output is
+/// computed by Glenside's interpreter.
+#[test]
+fn test_harness_mlp() {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+
+    let program_filepath = format!(
+        "{}/data/asplos-demo/mlp.glenside",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let shapes_filepath = format!(
+        "{}/data/asplos-demo/mlp-shapes.json",
+        env!("CARGO_MANIFEST_DIR")
+    );
+
+    // Write random inputs to .npy files.
+    let mut input_args = Vec::new();
+    for (name, shape) in [
+        ("input", vec![2]),
+        ("weight0", vec![2, 4]),
+        ("weight1", vec![4, 6]),
+        ("weight2", vec![6, 2]),
+    ]
+    .iter()
+    {
+        let mut filepath = std::env::temp_dir();
+        filepath.push(format!("test-harness-mlp-{}-{}.npy", name, nanos));
+        ndarray_npy::write_npy(
+            &filepath,
+            &ndarray::ArrayD::<f32>::random(shape.clone(), Uniform::new(-1f32, 1f32)),
+        )
+        .unwrap();
+        input_args.push(format!("{}={}", name, filepath.to_string_lossy()));
+    }
+
+    let mut out_code_filepath = std::env::temp_dir();
+    out_code_filepath.push(format!("test-harness-mlp-{}.c", nanos));
+    let mut out_design_filepath = std::env::temp_dir();
+    out_design_filepath.push(format!("test-harness-mlp-{}.json", nanos));
+    let mut out_harness_filepath = std::env::temp_dir();
+    out_harness_filepath.push(format!("test-harness-mlp-main-{}.c", nanos));
+    let mut out_filepath = std::env::temp_dir();
+    out_filepath.push(format!("test-harness-mlp-{}", nanos));
+
+    let mut command = Command::new("target/debug/glenside");
+    command
+        .arg("demo")
+        .arg("mlp")
+        .arg(program_filepath)
+        .arg(shapes_filepath)
+        .arg(&out_code_filepath)
+        .arg(&out_design_filepath)
+        .arg("--test-harness")
+        .arg(&out_harness_filepath);
+    for input_arg in input_args.iter() {
+        command.arg("--input").arg(input_arg);
+    }
+    let output = command.output().expect("Failed to run glenside");
+    assert!(
+        output.status.success(),
+        "Glenside binary failed with code {:?}. stderr:\n{}",
+        output.status.code(),
+        std::str::from_utf8(output.stderr.as_slice()).expect("Could not convert stderr to UTF8")
+    );
+
+    let systolic_array_impl_filepath = format!(
+        "{}/data/codegen-mlp/{}",
+        env!("CARGO_MANIFEST_DIR"),
+        "rtml_systolic_array_weight_stationary.c"
+    );
+
+    let output = Command::new("gcc")
+        .arg("-g")
+        .arg("-Werror")
+        .arg(&out_harness_filepath)
+        .arg(&systolic_array_impl_filepath)
+        .arg("-o")
+        .arg(&out_filepath)
+        .output()
+        .expect("Failed to compile main file with gcc");
+    assert!(
+        output.status.success(),
+        "Compilation failed. stderr:\n{}",
+        std::str::from_utf8(output.stderr.as_slice()).expect("Could not convert stderr to UTF8")
+    );
+
+    let output = Command::new(&out_filepath)
+        .output()
+        .expect("Failed to run result");
+    assert!(
+        output.status.success(),
+        "Test harness failed with code {:?}. stderr:\n{}",
+        output.status.code(),
+        std::str::from_utf8(output.stderr.as_slice()).expect("Could not convert stderr to UTF8")
+    );
+}
@@ -0,0 +1,50 @@
+use std::process::Command;
+
+/// A malformed program should be reported as an error, not a panic.
+#[test]
+fn typecheck_malformed_program() {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+
+    let mut program_filepath = std::env::temp_dir();
+    program_filepath.push(format!("typecheck-malformed-{}.glenside", nanos));
+    std::fs::write(
+        &program_filepath,
+        "(compute relu
+          (access-transpose (access (access-tensor input) 1) (list 1 0 2))
+         )",
+    )
+    .unwrap();
+    let mut shapes_filepath = std::env::temp_dir();
+    shapes_filepath.push(format!("typecheck-malformed-shapes-{}.json", nanos));
+    std::fs::write(&shapes_filepath, r#"{ "input": [2, 4] }"#).unwrap();
+
+    let mut out_code_filepath = std::env::temp_dir();
+    out_code_filepath.push(format!("typecheck-malformed-{}.c", nanos));
+    let mut out_design_filepath = std::env::temp_dir();
+    out_design_filepath.push(format!("typecheck-malformed-{}.json", nanos));
+
+    let output = Command::new("target/debug/glenside")
+        .arg("demo")
+        .arg("malformed")
+        .arg(&program_filepath)
+        .arg(&shapes_filepath)
+        .arg(&out_code_filepath)
+        .arg(&out_design_filepath)
+        .output()
+        .expect("Failed to run glenside");
+
+    let stderr =
+        std::str::from_utf8(output.stderr.as_slice()).expect("Could not convert stderr to UTF8");
+    assert_eq!(output.status.code(), Some(1), "stderr:\n{}", stderr);
+    assert!(!stderr.contains("panicked"), "stderr:\n{}", stderr);
+    assert!(stderr.contains("is not well-typed"), "stderr:\n{}", stderr);
+    assert!(
+        stderr.contains("compute > argument 1 > access-transpose"),
+        "stderr:\n{}",
+        stderr
+    );
+    assert!(stderr.contains("found [1, 0, 2]"), "stderr:\n{}", stderr);
+}